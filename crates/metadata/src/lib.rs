@@ -12,6 +12,7 @@ mod metadata_error;
 mod project;
 mod pubfile;
 mod publish;
+mod simulate;
 mod test;
 #[cfg(test)]
 mod tests;
@@ -29,6 +30,7 @@ pub use project::Project;
 pub use pubfile::{Pubfile, Release};
 pub use publish::Publish;
 pub use semver;
+pub use simulate::Simulate;
 pub use test::{SimType, Test, WaveFormFormat, WaveFormTarget};
 
 include!(concat!(env!("OUT_DIR"), "/veryl_version.rs"));
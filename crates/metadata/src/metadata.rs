@@ -9,6 +9,7 @@ use crate::lockfile::Lockfile;
 use crate::project::Project;
 use crate::pubfile::{Pubfile, Release};
 use crate::publish::Publish;
+use crate::simulate::Simulate;
 use crate::test::Test;
 use crate::{FilelistType, MetadataError, SourceMapTarget};
 use log::{debug, info, warn};
@@ -50,6 +51,8 @@ pub struct Metadata {
     #[serde(default)]
     pub test: Test,
     #[serde(default)]
+    pub simulate: Simulate,
+    #[serde(default)]
     pub dependencies: HashMap<String, Dependency>,
     #[serde(skip)]
     pub metadata_path: PathBuf,
@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Simulate {
+    /// Top module
+    #[serde(default)]
+    pub top: Option<String>,
+    /// Simulation time, e.g. "100ns", "10us"
+    #[serde(default)]
+    pub time: Option<String>,
+    /// Clock periods in nanoseconds
+    #[serde(default)]
+    pub clocks: HashMap<String, u64>,
+    /// Period in nanoseconds of the clocks not listed in `clocks`
+    #[serde(default = "default_period")]
+    pub period: u64,
+    /// Cycles of the first clock the reset is held at the start, 0 resets instantaneously
+    #[serde(default)]
+    pub reset_cycles: usize,
+    /// Stimulus file relative to the project root
+    #[serde(default)]
+    pub stimulus: Option<PathBuf>,
+    /// VCD file relative to the project root
+    #[serde(default)]
+    pub waveform: Option<PathBuf>,
+    /// Glob patterns of the signals dumped to the waveform, e.g. ["top.u_core.*", "!*_debug"]
+    #[serde(default)]
+    pub signals: Vec<String>,
+    /// Options passed to the simulation like plusargs
+    #[serde(default)]
+    pub plusargs: HashMap<String, String>,
+}
+
+impl Default for Simulate {
+    fn default() -> Self {
+        toml::from_str("").unwrap()
+    }
+}
+
+fn default_period() -> u64 {
+    10
+}
@@ -2,7 +2,7 @@ use crate::git::Git;
 use crate::*;
 use semver::Version;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use tempfile::TempDir;
 
 const GIT_IGNORE: &'static str = r#"
@@ -23,6 +23,16 @@ target = {type = "source"}
 
 [format]
 indent_width = 4
+
+[simulate]
+top = "Top"
+time = "10us"
+clocks = {clk = 10, clk_fast = 4}
+reset_cycles = 2
+stimulus = "tb/stimulus.csv"
+waveform = "sim.vcd"
+signals = ["Top.*", "!*_debug"]
+plusargs = {seed = "5"}
 "#;
 
 const MAIN_TOML: &'static str = r#"
@@ -180,6 +190,16 @@ fn check_toml() {
     assert!(metadata.build.reset_low_prefix.is_none());
     assert_eq!(metadata.build.reset_low_suffix.unwrap(), "_n");
     assert_eq!(metadata.format.indent_width, 4);
+    assert_eq!(metadata.simulate.top.unwrap(), "Top");
+    assert_eq!(metadata.simulate.clocks["clk_fast"], 4);
+    assert_eq!(metadata.simulate.period, 10);
+    assert_eq!(metadata.simulate.reset_cycles, 2);
+    assert_eq!(
+        metadata.simulate.stimulus.unwrap(),
+        PathBuf::from("tb/stimulus.csv")
+    );
+    assert_eq!(metadata.simulate.signals, ["Top.*", "!*_debug"]);
+    assert_eq!(metadata.simulate.plusargs["seed"], "5");
 }
 
 #[test]
@@ -0,0 +1,127 @@
+use criterion::{BenchmarkId, Criterion, Throughput, black_box, criterion_group, criterion_main};
+use std::collections::HashMap;
+use std::fmt::Write;
+use veryl_analyzer::{Analyzer, symbol_table};
+use veryl_metadata::Metadata;
+use veryl_parser::Parser;
+use veryl_simulator::{Model, Simulator};
+
+// Rising edges simulated in a single iteration
+const CYCLES: usize = 1000;
+
+// Chain of adders fed back from the register, every term is re-evaluated at each cycle
+fn wide_adder(terms: usize) -> String {
+    let mut code = String::from(
+        "module Bench (\n    clk: input clock,\n    rst: input reset,\n    a  : input logic<64>,\n    y  : output logic<64>,\n) {\n",
+    );
+    for i in 0..terms {
+        let _ = writeln!(code, "    var s{i}: logic<64>;");
+    }
+    code.push_str("    assign s0 = y + a;\n");
+    for i in 1..terms {
+        let _ = writeln!(code, "    assign s{i} = s{} + a;", i - 1);
+    }
+    let _ = writeln!(
+        code,
+        "    always_ff {{\n        if_reset {{\n            y = 0;\n        }} else {{\n            y = s{};\n        }}\n    }}\n}}",
+        terms - 1
+    );
+    code
+}
+
+// Registers shifting a counter through the stages
+fn pipeline(stages: usize) -> String {
+    let mut code = String::from(
+        "module Bench (\n    clk: input clock,\n    rst: input reset,\n    y  : output logic<32>,\n) {\n",
+    );
+    for i in 0..stages {
+        let _ = writeln!(code, "    var p{i}: logic<32>;");
+    }
+    code.push_str("    always_ff {\n        if_reset {\n");
+    for i in 0..stages {
+        let _ = writeln!(code, "            p{i} = 0;");
+    }
+    code.push_str("        } else {\n            p0 = p0 + 1;\n");
+    for i in 1..stages {
+        let _ = writeln!(code, "            p{i} = p{} + 1;", i - 1);
+    }
+    let _ = writeln!(
+        code,
+        "        }}\n    }}\n    assign y = p{};\n}}",
+        stages - 1
+    );
+    code
+}
+
+// Independent counters updated at every cycle
+fn registers(count: usize) -> String {
+    let mut code = String::from(
+        "module Bench (\n    clk: input clock,\n    rst: input reset,\n    y  : output logic<16>,\n) {\n",
+    );
+    for i in 0..count {
+        let _ = writeln!(code, "    var r{i}: logic<16>;");
+    }
+    code.push_str("    always_ff {\n        if_reset {\n");
+    for i in 0..count {
+        let _ = writeln!(code, "            r{i} = 0;");
+    }
+    code.push_str("        } else {\n");
+    for i in 0..count {
+        let _ = writeln!(code, "            r{i} = r{i} + {};", i + 1);
+    }
+    let _ = writeln!(
+        code,
+        "        }}\n    }}\n    assign y = r{};\n}}",
+        count - 1
+    );
+    code
+}
+
+fn analyze(code: &str) {
+    symbol_table::clear();
+
+    let metadata = Metadata::create_default("prj").unwrap();
+    let parser = Parser::parse(code, &"").unwrap();
+    let analyzer = Analyzer::new(&metadata);
+
+    let mut errors = vec![];
+    errors.append(&mut analyzer.analyze_pass1("prj", "", &parser.veryl));
+    errors.append(&mut Analyzer::analyze_post_pass1());
+    errors.append(&mut analyzer.analyze_pass2("prj", "", &parser.veryl));
+    let info = Analyzer::analyze_post_pass2();
+    errors.append(&mut analyzer.analyze_pass3("prj", "", &parser.veryl, &info));
+    assert!(errors.is_empty(), "{errors:?}");
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let designs = [
+        ("wide_adder", 256, wide_adder(256)),
+        ("pipeline", 256, pipeline(256)),
+        ("registers", 1024, registers(1024)),
+    ];
+
+    for (name, size, code) in designs {
+        // Model::new refers to the symbol table of the analyzed design
+        analyze(&code);
+
+        let mut group = c.benchmark_group("elaborate");
+        group.bench_with_input(BenchmarkId::new(name, size), &size, |b, _| {
+            b.iter_with_large_drop(|| Model::new(black_box("Bench"), HashMap::new()).unwrap())
+        });
+        group.finish();
+
+        // simulated cycles per second
+        let model = Model::new("Bench", HashMap::new()).unwrap();
+        let mut simulator = Simulator::builder(model).clock("clk", 10).build().unwrap();
+        simulator.reset();
+        let mut group = c.benchmark_group("simulate");
+        group.throughput(Throughput::Elements(CYCLES as u64));
+        group.bench_with_input(BenchmarkId::new(name, size), &size, |b, _| {
+            b.iter(|| simulator.run_cycles("clk", CYCLES).unwrap())
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,261 @@
+use crate::{ClockEdge, ModelError, Simulator, StepEvent};
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+type Task = Pin<Box<dyn Future<Output = Result<(), ModelError>>>>;
+
+// State shared by the executor and the tasks
+struct Shared {
+    simulator: Simulator,
+    last_event: Option<StepEvent>, // event processed by the last step
+    seq: u64,                      // number of processed events
+    deadlines: Vec<u64>,           // times waited by the delays
+    progress: u64,                 // number of resolved waits
+}
+
+/// Executor of concurrent testbench tasks
+///
+/// Tasks are `async` blocks awaiting clock edges, delays and signal values through `TbHandle`.
+/// All tasks are polled after every event, and the simulator is advanced only while all
+/// tasks are waiting.
+pub struct AsyncTestBench {
+    shared: Rc<RefCell<Shared>>,
+    tasks: Vec<Task>,
+}
+
+impl AsyncTestBench {
+    pub fn new(simulator: Simulator) -> Self {
+        AsyncTestBench {
+            shared: Rc::new(RefCell::new(Shared {
+                simulator,
+                last_event: None,
+                seq: 0,
+                deadlines: Vec::new(),
+                progress: 0,
+            })),
+            tasks: Vec::new(),
+        }
+    }
+
+    /// Handle to access the simulator from tasks
+    pub fn handle(&self) -> TbHandle {
+        TbHandle {
+            shared: self.shared.clone(),
+        }
+    }
+
+    /// Add a task, it starts at the next `run`
+    pub fn spawn<F, T>(&mut self, task: F)
+    where
+        F: FnOnce(TbHandle) -> T,
+        T: Future<Output = Result<(), ModelError>> + 'static,
+    {
+        self.tasks.push(Box::pin(task(self.handle())));
+    }
+
+    /// Access the simulator outside of tasks
+    pub fn with_simulator<R>(&self, f: impl FnOnce(&mut Simulator) -> R) -> R {
+        f(&mut self.shared.borrow_mut().simulator)
+    }
+
+    /// Run until all tasks complete, or `timeout_ns` nanoseconds have passed
+    /// the first error returned by a task stops the run
+    pub fn run(&mut self, timeout_ns: u64) -> Result<(), ModelError> {
+        let end_time = self.shared.borrow().simulator.time() + timeout_ns;
+        loop {
+            self.poll_tasks()?;
+            if self.tasks.is_empty() {
+                return Ok(());
+            }
+
+            let mut shared = self.shared.borrow_mut();
+            let time = shared.simulator.time();
+            let next = shared.simulator.next_time();
+            // 遅延の期限までイベントがなければ時間だけ進める
+            let deadline = shared
+                .deadlines
+                .iter()
+                .copied()
+                .min()
+                .filter(|x| next.is_none_or(|y| *x < y));
+            let timeout = || ModelError::Timeout {
+                condition: "testbench tasks".to_string(),
+                time,
+            };
+
+            if let Some(deadline) = deadline {
+                if deadline > end_time {
+                    return Err(timeout());
+                }
+                shared.simulator.advance_to(deadline);
+            } else if let Some(next) = next {
+                if next > end_time {
+                    return Err(timeout());
+                }
+                let Some(event) = shared.simulator.step() else {
+                    return Err(ModelError::SimulationStopped(time));
+                };
+                shared.last_event = Some(event);
+                shared.seq += 1;
+            } else {
+                return Err(ModelError::SimulationStopped(time));
+            }
+        }
+    }
+
+    // 待ちが解消されなくなるまでタスクを繰り返し実行する
+    fn poll_tasks(&mut self) -> Result<(), ModelError> {
+        let mut cx = Context::from_waker(Waker::noop());
+        loop {
+            let progress = {
+                let mut shared = self.shared.borrow_mut();
+                shared.deadlines.clear();
+                shared.progress
+            };
+
+            let mut completed = false;
+            let mut i = 0;
+            while i < self.tasks.len() {
+                match self.tasks[i].as_mut().poll(&mut cx) {
+                    Poll::Ready(result) => {
+                        drop(self.tasks.remove(i));
+                        completed = true;
+                        result?;
+                    }
+                    Poll::Pending => i += 1,
+                }
+            }
+
+            if !completed && self.shared.borrow().progress == progress {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Handle of a testbench task
+#[derive(Clone)]
+pub struct TbHandle {
+    shared: Rc<RefCell<Shared>>,
+}
+
+impl TbHandle {
+    /// Current simulation time in nanoseconds
+    pub fn time(&self) -> u64 {
+        self.shared.borrow().simulator.time()
+    }
+
+    /// Drive the input port
+    pub fn drive(&self, port: &str, value: usize) -> Result<(), ModelError> {
+        self.shared
+            .borrow_mut()
+            .simulator
+            .model_mut()
+            .try_input(port, value)
+    }
+
+    /// Read any signal including internal signals
+    pub fn read(&self, path: &str) -> Result<usize, ModelError> {
+        self.shared
+            .borrow()
+            .simulator
+            .model()
+            .peek(path)
+            .ok_or_else(|| ModelError::SignalNotFound(path.to_string()))
+    }
+
+    /// Wait for the next rising edge of the clock
+    pub fn posedge(&self, clock: &str) -> Wait {
+        self.edge(clock, ClockEdge::Rising)
+    }
+
+    /// Wait for the next falling edge of the clock
+    pub fn negedge(&self, clock: &str) -> Wait {
+        self.edge(clock, ClockEdge::Falling)
+    }
+
+    /// Wait for the nanoseconds
+    pub fn delay(&self, duration_ns: u64) -> Wait {
+        let time = self.time() + duration_ns;
+        self.wait(Trigger::Time(time))
+    }
+
+    /// Wait until the signal has the value
+    pub fn wait_until(&self, path: &str, value: usize) -> Wait {
+        self.wait(Trigger::Value(path.to_string(), value))
+    }
+
+    fn edge(&self, clock: &str, edge: ClockEdge) -> Wait {
+        let checked = self.shared.borrow().simulator.check_clock(clock);
+        match checked {
+            Ok(()) => self.wait(Trigger::Edge(clock.to_string(), edge)),
+            Err(x) => self.wait(Trigger::Failed(x)),
+        }
+    }
+
+    fn wait(&self, trigger: Trigger) -> Wait {
+        Wait {
+            shared: self.shared.clone(),
+            trigger,
+            seq: None,
+        }
+    }
+}
+
+enum Trigger {
+    Edge(String, ClockEdge),
+    Time(u64),
+    Value(String, usize),
+    Failed(ModelError),
+}
+
+/// Future resolved when the awaited event occurs
+pub struct Wait {
+    shared: Rc<RefCell<Shared>>,
+    trigger: Trigger,
+    seq: Option<u64>, // number of processed events at the first poll
+}
+
+impl Future for Wait {
+    type Output = Result<(), ModelError>;
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let mut shared = this.shared.borrow_mut();
+        let ret = match &this.trigger {
+            Trigger::Edge(clock, edge) => {
+                // 待ち始めた後のイベントのみ対象にする
+                let seq = *this.seq.get_or_insert(shared.seq);
+                let matched = shared.last_event.as_ref().is_some_and(|x| {
+                    x.clock.as_deref() == Some(clock.as_str()) && x.edge == Some(*edge)
+                });
+                if shared.seq > seq && matched {
+                    Poll::Ready(Ok(()))
+                } else {
+                    Poll::Pending
+                }
+            }
+            Trigger::Time(time) => {
+                if shared.simulator.time() >= *time {
+                    Poll::Ready(Ok(()))
+                } else {
+                    shared.deadlines.push(*time);
+                    Poll::Pending
+                }
+            }
+            Trigger::Value(path, value) => match shared.simulator.model().peek(path) {
+                Some(x) if x == *value => Poll::Ready(Ok(())),
+                Some(_) => Poll::Pending,
+                None => Poll::Ready(Err(ModelError::SignalNotFound(path.clone()))),
+            },
+            Trigger::Failed(x) => Poll::Ready(Err(x.clone())),
+        };
+        if ret.is_ready() {
+            shared.progress += 1;
+        }
+        ret
+    }
+}
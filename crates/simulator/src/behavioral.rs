@@ -0,0 +1,117 @@
+/// Rust implementation of a module instantiated in the design
+///
+/// Instances of modules without a definition, e.g. `$sv::` modules or proto modules,
+/// are simulated by the implementation registered by `Model::register_module`.
+pub trait BehavioralModule: Send {
+    /// Update the outputs from the inputs, called whenever the inputs may have changed
+    fn eval(&mut self, ports: &mut Ports);
+
+    /// Active edge of a clock connected to the instance
+    /// the inputs are the values before the edge, and the outputs are reflected after all
+    /// always_ff blocks like non-blocking assignments
+    fn clock(&mut self, ports: &mut Ports) {
+        let _ = ports;
+    }
+
+    /// Called by `Model::reset`
+    fn reset(&mut self, ports: &mut Ports) {
+        let _ = ports;
+    }
+}
+
+/// Port values of an instance
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Ports {
+    inputs: Vec<(String, usize)>,
+    outputs: Vec<(String, usize)>,
+}
+
+impl Ports {
+    /// Value of the input port, or the last value set to the output port
+    pub fn get(&self, name: &str) -> Option<usize> {
+        self.inputs
+            .iter()
+            .chain(&self.outputs)
+            .find(|(x, _)| x == name)
+            .map(|(_, x)| *x)
+    }
+
+    /// Set the output port, returns false if the output port doesn't exist
+    pub fn set(&mut self, name: &str, value: usize) -> bool {
+        match self.outputs.iter_mut().find(|(x, _)| x == name) {
+            Some((_, x)) => {
+                *x = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn inputs(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.inputs.iter().map(|(x, y)| (x.as_str(), *y))
+    }
+
+    pub fn outputs(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.outputs.iter().map(|(x, y)| (x.as_str(), *y))
+    }
+}
+
+// 振る舞いモデルで置き換えるインスタンス
+// ポートの値は前回の評価の結果を保持する（クロックエッジではエッジ前の入力の値になる）
+pub(crate) struct Instance {
+    pub(crate) name: String,
+    pub(crate) module: String,
+    pub(crate) clocks: Vec<String>, // 入力ポートに接続された親のクロック
+    pub(crate) ports: Ports,
+    pub(crate) model: Option<Box<dyn BehavioralModule>>,
+}
+
+impl Instance {
+    pub(crate) fn new(
+        name: String,
+        module: String,
+        inputs: Vec<String>,
+        outputs: Vec<String>,
+        clocks: Vec<String>,
+    ) -> Self {
+        Instance {
+            name,
+            module,
+            clocks,
+            ports: Ports {
+                inputs: inputs.into_iter().map(|x| (x, 0)).collect(),
+                outputs: outputs.into_iter().map(|x| (x, 0)).collect(),
+            },
+            model: None,
+        }
+    }
+
+    // 入力の値を設定して評価し、出力の値を返す（モデルが未登録なら None）
+    pub(crate) fn eval(&mut self, inputs: &[usize]) -> Option<Vec<usize>> {
+        for ((_, x), y) in self.ports.inputs.iter_mut().zip(inputs) {
+            *x = *y;
+        }
+        self.model.as_mut()?.eval(&mut self.ports);
+        Some(self.ports.outputs.iter().map(|(_, x)| *x).collect())
+    }
+
+    // clock が None なら全てのクロックのエッジ
+    pub(crate) fn is_clocked_by(&self, clock: Option<&str>) -> bool {
+        match clock {
+            Some(clock) => self.clocks.iter().any(|x| x == clock),
+            None => !self.clocks.is_empty(),
+        }
+    }
+
+    pub(crate) fn clock(&mut self) {
+        if let Some(model) = &mut self.model {
+            model.clock(&mut self.ports);
+        }
+    }
+
+    pub(crate) fn reset(&mut self) {
+        if let Some(model) = &mut self.model {
+            model.reset(&mut self.ports);
+        }
+    }
+}
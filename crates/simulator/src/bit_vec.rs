@@ -0,0 +1,93 @@
+use std::fmt;
+
+/// Fixed-width bit vector
+/// bit 0 is the LSB, and bits above the width are always 0
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct BitVec {
+    width: usize,
+    words: Vec<u64>,
+}
+
+impl BitVec {
+    /// All-zero bit vector
+    pub fn new(width: usize) -> Self {
+        BitVec {
+            width,
+            words: vec![0; width.div_ceil(64)],
+        }
+    }
+
+    /// Bit vector holding the lower `width` bits of `value`
+    pub fn from_u64(width: usize, value: u64) -> Self {
+        let mut ret = Self::new(width);
+        if let Some(word) = ret.words.first_mut() {
+            *word = value;
+        }
+        ret.normalize();
+        ret
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Value of the bit, bits outside the width are 0
+    pub fn bit(&self, index: usize) -> bool {
+        index < self.width && (self.words[index / 64] >> (index % 64)) & 1 == 1
+    }
+
+    /// Set the bit, bits outside the width are ignored
+    pub fn set_bit(&mut self, index: usize, value: bool) {
+        if index >= self.width {
+            return;
+        }
+        let mask = 1 << (index % 64);
+        if value {
+            self.words[index / 64] |= mask;
+        } else {
+            self.words[index / 64] &= !mask;
+        }
+    }
+
+    /// Lower 64 bits of the value
+    pub fn to_u64(&self) -> u64 {
+        self.words.first().copied().unwrap_or(0)
+    }
+
+    /// Value as u64, or None if it doesn't fit
+    pub fn try_to_u64(&self) -> Option<u64> {
+        if self.words.iter().skip(1).all(|x| *x == 0) {
+            Some(self.to_u64())
+        } else {
+            None
+        }
+    }
+
+    /// Bits from the LSB
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        (0..self.width).map(|i| self.bit(i))
+    }
+
+    // Clear the bits above the width
+    fn normalize(&mut self) {
+        let rem = self.width % 64;
+        if rem != 0
+            && let Some(word) = self.words.last_mut()
+        {
+            *word &= (1 << rem) - 1;
+        }
+    }
+}
+
+impl fmt::Display for BitVec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}'b", self.width)?;
+        if self.width == 0 {
+            return write!(f, "0");
+        }
+        for i in (0..self.width).rev() {
+            write!(f, "{}", if self.bit(i) { '1' } else { '0' })?;
+        }
+        Ok(())
+    }
+}
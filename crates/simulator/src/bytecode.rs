@@ -0,0 +1,629 @@
+#[cfg(feature = "jit")]
+use crate::jit::NativeFn;
+use crate::model::{
+    AssertStatement, CaseCheck, CaseLabel, Direction, Expr, Functions, Span, Statement, Target,
+    bit_mask, element_name, merge_bits, select_bits, shift_left, shift_right,
+};
+use std::collections::HashMap;
+
+// 信号名と状態ベクタ上の位置の対応（位置は信号名の順）
+#[derive(Debug, Default)]
+pub(crate) struct Layout {
+    slots: HashMap<String, usize>,
+    signals: Vec<(String, Direction)>,
+}
+
+impl Layout {
+    // レイアウトと初期値の状態ベクタを作る
+    pub(crate) fn new(mut signals: Vec<(String, Direction, usize)>) -> (Self, Vec<usize>) {
+        signals.sort_by(|a, b| a.0.cmp(&b.0));
+        let slots = signals
+            .iter()
+            .enumerate()
+            .map(|(i, (name, _, _))| (name.clone(), i))
+            .collect();
+        let values = signals.iter().map(|x| x.2).collect();
+        let signals = signals.into_iter().map(|(x, y, _)| (x, y)).collect();
+        (Layout { slots, signals }, values)
+    }
+
+    pub(crate) fn slot(&self, name: &str) -> Option<usize> {
+        self.slots.get(name).copied()
+    }
+
+    pub(crate) fn direction(&self, slot: usize) -> Direction {
+        self.signals[slot].1
+    }
+
+    pub(crate) fn signal(&self, slot: usize) -> (&str, Direction) {
+        let (name, direction) = &self.signals[slot];
+        (name, *direction)
+    }
+
+    // 信号の位置と名前、方向を位置の順に列挙する
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (usize, &str, Direction)> {
+        self.signals
+            .iter()
+            .enumerate()
+            .map(|(i, (name, direction))| (i, name.as_str(), *direction))
+    }
+
+    // 代入できる信号の位置（入力ポートは代入先にならない）
+    fn writable(&self, name: &str) -> Option<usize> {
+        self.slot(name)
+            .filter(|x| self.direction(*x) != Direction::Input)
+    }
+
+    // 信号の位置（配列の場合は全要素の位置）
+    pub(crate) fn slots(&self, name: &str) -> Vec<usize> {
+        match self.slot(name) {
+            Some(x) => vec![x],
+            None => self.elements(name),
+        }
+    }
+
+    // 配列要素の位置を要素番号の順に並べる
+    fn elements(&self, name: &str) -> Vec<usize> {
+        (0..)
+            .map_while(|i| self.slot(&element_name(name, i)))
+            .collect()
+    }
+}
+
+// バイトコードの命令
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Op {
+    Const(usize),     // 定数を積む
+    Load(usize),      // 信号の値を積む
+    Unknown(usize),   // 未知の信号を通知して 0 を積む（信号名の番号）
+    LoadIndex(usize), // 添字を取り出して配列要素の値を積む（配列の番号）
+    Select,           // 対象, MSB, LSB を取り出してビット選択の結果を積む
+    Add,
+    Sub,
+    SatSub,
+    Mul,
+    Div,
+    Rem,
+    And,
+    Or,
+    Xor,
+    Shl,
+    Shr,
+    Not,
+    Eq(usize),     // 左辺, 右辺を取り出してマスクのビットが一致するかを積む（マスク）
+    Inside(usize), // 値とラベルの値を取り出していずれかのラベルに一致するかを積む（inside 式の番号）
+    Call(usize),   // 引数を取り出して外部関数の結果を積む（関数の番号）
+}
+
+// inside 式のラベル（比較する値は命令列で積む）
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Member {
+    Value,                  // 値 1 つと一致
+    Wildcard(usize, usize), // x / z を含む数値と一致 (値, 比較するビットのマスク)
+    Range(bool),            // 下限と上限の 2 つの値の範囲 (上限を含むか)
+}
+
+impl Member {
+    pub(crate) fn operands(&self) -> usize {
+        match self {
+            Member::Value => 1,
+            Member::Wildcard(_, _) => 0,
+            Member::Range(_) => 2,
+        }
+    }
+}
+
+// 式を逆ポーランド記法の命令列に変換したもの
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Program {
+    ops: Vec<Op>,
+    names: Vec<String>,                // 未知の信号名
+    arrays: Vec<(String, Vec<usize>)>, // 配列名と要素の位置
+    calls: Vec<(String, usize)>,       // 外部関数名と引数の数
+    insides: Vec<Vec<Member>>,         // inside 式のラベル
+    #[cfg(feature = "jit")]
+    native: Option<NativeFn>, // JIT で変換したネイティブ関数
+}
+
+impl Program {
+    pub(crate) fn compile(expr: &Expr, layout: &Layout) -> Self {
+        let mut program = Program::default();
+        program.push(expr, layout);
+        program
+    }
+
+    fn push(&mut self, expr: &Expr, layout: &Layout) {
+        // 変数を含まない式は畳み込む
+        if expr.is_const() {
+            self.ops.push(Op::Const(expr.eval(&HashMap::new())));
+            return;
+        }
+        match expr {
+            Expr::Const(x) => self.ops.push(Op::Const(*x)),
+            Expr::Var(name) => match layout.slot(name) {
+                Some(slot) => self.ops.push(Op::Load(slot)),
+                None => {
+                    self.names.push(name.clone());
+                    self.ops.push(Op::Unknown(self.names.len() - 1));
+                }
+            },
+            Expr::Index(name, index) => {
+                self.push(index, layout);
+                self.arrays.push((name.clone(), layout.elements(name)));
+                self.ops.push(Op::LoadIndex(self.arrays.len() - 1));
+            }
+            Expr::Select(expr, msb, lsb) => {
+                self.push(expr, layout);
+                self.push(msb, layout);
+                self.push(lsb, layout);
+                self.ops.push(Op::Select);
+            }
+            Expr::Add(left, right) => self.push_binary(left, right, Op::Add, layout),
+            Expr::Sub(left, right) => self.push_binary(left, right, Op::Sub, layout),
+            Expr::SatSub(left, right) => self.push_binary(left, right, Op::SatSub, layout),
+            Expr::Mul(left, right) => self.push_binary(left, right, Op::Mul, layout),
+            Expr::Div(left, right) => self.push_binary(left, right, Op::Div, layout),
+            Expr::Rem(left, right) => self.push_binary(left, right, Op::Rem, layout),
+            Expr::And(left, right) => self.push_binary(left, right, Op::And, layout),
+            Expr::Or(left, right) => self.push_binary(left, right, Op::Or, layout),
+            Expr::Xor(left, right) => self.push_binary(left, right, Op::Xor, layout),
+            Expr::Shl(left, right) => self.push_binary(left, right, Op::Shl, layout),
+            Expr::Shr(left, right) => self.push_binary(left, right, Op::Shr, layout),
+            Expr::Not(expr) => {
+                self.push(expr, layout);
+                self.ops.push(Op::Not);
+            }
+            Expr::Eq(left, right, mask) => self.push_binary(left, right, Op::Eq(*mask), layout),
+            Expr::Inside(expr, labels) => {
+                self.push(expr, layout);
+                let mut members = Vec::new();
+                for label in labels {
+                    match label {
+                        CaseLabel::Value(x) => {
+                            self.push(x, layout);
+                            members.push(Member::Value);
+                        }
+                        CaseLabel::Wildcard(x, mask) => members.push(Member::Wildcard(*x, *mask)),
+                        CaseLabel::Range(lo, hi, inclusive) => {
+                            self.push(lo, layout);
+                            self.push(hi, layout);
+                            members.push(Member::Range(*inclusive));
+                        }
+                    }
+                }
+                self.insides.push(members);
+                self.ops.push(Op::Inside(self.insides.len() - 1));
+            }
+            Expr::Call(name, args) => {
+                for arg in args {
+                    self.push(arg, layout);
+                }
+                self.calls.push((name.clone(), args.len()));
+                self.ops.push(Op::Call(self.calls.len() - 1));
+            }
+        }
+    }
+
+    #[cfg(feature = "jit")]
+    pub(crate) fn ops(&self) -> &[Op] {
+        &self.ops
+    }
+
+    #[cfg(feature = "jit")]
+    pub(crate) fn members(&self, inside: usize) -> &[Member] {
+        &self.insides[inside]
+    }
+
+    // ネイティブ関数を設定すると、以降の評価は命令列の代わりにネイティブ関数を呼び出す
+    // 関数は Program の評価に使われる間は有効でなければならない
+    #[cfg(feature = "jit")]
+    pub(crate) fn set_native(&mut self, native: Option<NativeFn>) {
+        self.native = native;
+    }
+
+    fn push_binary(&mut self, left: &Expr, right: &Expr, op: Op, layout: &Layout) {
+        self.push(left, layout);
+        self.push(right, layout);
+        self.ops.push(op);
+    }
+
+    // 状態ベクタに対して評価する（結果は Expr::eval_with と同じ）
+    // 未知の信号や登録されていない関数を参照した場合は unknown にその名前を通知する
+    pub(crate) fn eval(
+        &self,
+        values: &[usize],
+        stack: &mut Vec<usize>,
+        functions: &Functions,
+        unknown: &mut dyn FnMut(&str),
+    ) -> usize {
+        #[cfg(feature = "jit")]
+        if let Some(native) = self.native {
+            // SAFETY: ネイティブ関数は状態ベクタの範囲内の位置だけを読み出す
+            return unsafe { native(values.as_ptr()) };
+        }
+
+        // 定数と信号の参照だけの式はスタックを使わない
+        match self.ops.as_slice() {
+            [Op::Const(x)] => return *x,
+            [Op::Load(x)] => return values[*x],
+            _ => {}
+        }
+
+        stack.clear();
+        for op in &self.ops {
+            let value = match *op {
+                Op::Const(x) => x,
+                Op::Load(x) => values[x],
+                Op::Unknown(x) => {
+                    unknown(&self.names[x]);
+                    0
+                }
+                Op::LoadIndex(x) => {
+                    let index = pop(stack);
+                    let (name, elements) = &self.arrays[x];
+                    match elements.get(index) {
+                        Some(slot) => values[*slot],
+                        None => {
+                            unknown(&element_name(name, index));
+                            0
+                        }
+                    }
+                }
+                Op::Select => {
+                    let lsb = pop(stack);
+                    let msb = pop(stack);
+                    select_bits(pop(stack), msb, lsb)
+                }
+                Op::Add
+                | Op::Sub
+                | Op::SatSub
+                | Op::Mul
+                | Op::Div
+                | Op::Rem
+                | Op::And
+                | Op::Or
+                | Op::Xor
+                | Op::Shl
+                | Op::Shr => {
+                    let right = pop(stack);
+                    let left = pop(stack);
+                    match op {
+                        Op::Add => left.wrapping_add(right),
+                        Op::Sub => left.wrapping_sub(right),
+                        Op::SatSub => left.saturating_sub(right),
+                        Op::Mul => left.wrapping_mul(right),
+                        Op::Rem => left.checked_rem(right).unwrap_or(0),
+                        Op::And => left & right,
+                        Op::Or => left | right,
+                        Op::Xor => left ^ right,
+                        Op::Shl => shift_left(left, right),
+                        Op::Shr => shift_right(left, right),
+                        _ => left.checked_div(right).unwrap_or(0),
+                    }
+                }
+                Op::Not => (pop(stack) == 0) as usize,
+                Op::Eq(mask) => {
+                    let right = pop(stack);
+                    let left = pop(stack);
+                    ((left ^ right) & mask == 0) as usize
+                }
+                Op::Inside(x) => {
+                    let members = &self.insides[x];
+                    let count = members.iter().map(Member::operands).sum::<usize>();
+                    let operands = stack.split_off(stack.len().saturating_sub(count));
+                    let value = pop(stack);
+                    let mut operands = operands.into_iter();
+                    let mut next = || operands.next().unwrap_or(0);
+                    let mut matched = false;
+                    for member in members {
+                        matched |= match *member {
+                            Member::Value => next() == value,
+                            Member::Wildcard(x, mask) => (x ^ value) & mask == 0,
+                            Member::Range(inclusive) => {
+                                let lo = next();
+                                let hi = next();
+                                lo <= value && (value < hi || inclusive && value == hi)
+                            }
+                        };
+                    }
+                    matched as usize
+                }
+                Op::Call(x) => {
+                    let (name, count) = &self.calls[x];
+                    let args = stack.split_off(stack.len().saturating_sub(*count));
+                    functions.call(name, &args).unwrap_or_else(|| {
+                        unknown(name);
+                        0
+                    })
+                }
+            };
+            stack.push(value);
+        }
+        pop(stack)
+    }
+}
+
+fn pop(stack: &mut Vec<usize>) -> usize {
+    stack.pop().unwrap_or(0)
+}
+
+// 文を変換したもの
+#[derive(Debug, Clone)]
+pub(crate) enum Code {
+    Assign(Vec<TargetCode>, Program),
+    If(Vec<(Program, Vec<Code>)>, Vec<Code>),
+    Case(CaseCode),
+    Assert(AssertStatement),
+    Cover(usize),
+    Instance(usize, Vec<Program>, Vec<TargetCode>), // インスタンスの番号、入力の式、出力の代入先
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct CaseCode {
+    pub(crate) expression: Program,
+    pub(crate) arms: Vec<(Vec<LabelCode>, Vec<Code>)>,
+    pub(crate) spans: Vec<Span>,
+    pub(crate) otherwise: Vec<Code>,
+    pub(crate) has_default: bool,
+    pub(crate) check: Option<CaseCheck>,
+    pub(crate) span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum LabelCode {
+    Value(Program),
+    Wildcard(usize, usize),
+    Range(Program, Program, bool),
+}
+
+impl LabelCode {
+    pub(crate) fn matches(
+        &self,
+        value: usize,
+        values: &[usize],
+        stack: &mut Vec<usize>,
+        functions: &Functions,
+        unknown: &mut dyn FnMut(&str),
+    ) -> bool {
+        match self {
+            LabelCode::Value(x) => x.eval(values, stack, functions, unknown) == value,
+            LabelCode::Wildcard(x, mask) => (x ^ value) & mask == 0,
+            LabelCode::Range(lo, hi, inclusive) => {
+                let lo = lo.eval(values, stack, functions, unknown);
+                let hi = hi.eval(values, stack, functions, unknown);
+                if *inclusive {
+                    lo <= value && value <= hi
+                } else {
+                    lo <= value && value < hi
+                }
+            }
+        }
+    }
+}
+
+// 代入先を変換したもの
+#[derive(Debug, Clone)]
+pub(crate) struct TargetCode {
+    slot: TargetSlot,
+    width: usize,
+    select: Option<(Program, Program)>,
+}
+
+#[derive(Debug, Clone)]
+enum TargetSlot {
+    Fixed(Option<usize>),         // 代入できない信号は None
+    Indexed(Program, Vec<usize>), // 添字と配列要素の位置
+}
+
+// 添字とビット選択を評価した代入先
+struct Resolved {
+    slot: Option<usize>,
+    width: usize,
+    select: Option<(usize, usize)>,
+}
+
+impl TargetCode {
+    fn compile(target: &Target, layout: &Layout) -> Self {
+        let slot = match &target.index {
+            Some(index) => TargetSlot::Indexed(
+                Program::compile(index, layout),
+                layout.elements(&target.name),
+            ),
+            None => TargetSlot::Fixed(layout.writable(&target.name)),
+        };
+        let select = target
+            .select
+            .as_ref()
+            .map(|(msb, lsb)| (Program::compile(msb, layout), Program::compile(lsb, layout)));
+        TargetCode {
+            slot,
+            width: target.width,
+            select,
+        }
+    }
+
+    // 代入先の添字とビット選択は未知の信号を通知せず、外部関数を呼び出さない
+    fn resolve(&self, values: &[usize], stack: &mut Vec<usize>) -> Resolved {
+        let functions = Functions::default();
+        let slot = match &self.slot {
+            TargetSlot::Fixed(x) => *x,
+            TargetSlot::Indexed(index, elements) => {
+                let index = index.eval(values, stack, &functions, &mut |_| {});
+                elements.get(index).copied()
+            }
+        };
+        let select = self.select.as_ref().map(|(msb, lsb)| {
+            (
+                msb.eval(values, stack, &functions, &mut |_| {}),
+                lsb.eval(values, stack, &functions, &mut |_| {}),
+            )
+        });
+        let width = match select {
+            Some((msb, lsb)) => (msb + 1).saturating_sub(lsb),
+            None => self.width,
+        };
+        Resolved {
+            slot,
+            width,
+            select,
+        }
+    }
+}
+
+impl Resolved {
+    fn write(&self, values: &mut [usize], value: usize) {
+        if let Some(slot) = self.slot {
+            values[slot] = match self.select {
+                Some((msb, lsb)) => merge_bits(values[slot], value, msb, lsb),
+                None => value & bit_mask(self.width),
+            };
+        }
+    }
+}
+
+// 代入先に値を書き込む
+// 連接の場合も代入先の添字とビット選択は書き込み前の値で評価する
+pub(crate) fn store(
+    targets: &[TargetCode],
+    value: usize,
+    values: &mut [usize],
+    stack: &mut Vec<usize>,
+) {
+    if let [target] = targets {
+        target.resolve(values, stack).write(values, value);
+        return;
+    }
+
+    let resolved: Vec<_> = targets.iter().map(|x| x.resolve(values, stack)).collect();
+    let mut offset = 0;
+    for target in resolved.iter().rev() {
+        let part = value.checked_shr(offset as u32).unwrap_or(0) & bit_mask(target.width);
+        target.write(values, part);
+        offset += target.width;
+    }
+}
+
+// 文の列を変換する
+pub(crate) fn compile(statements: &[Statement], layout: &Layout) -> Vec<Code> {
+    statements
+        .iter()
+        .map(|x| compile_statement(x, layout))
+        .collect()
+}
+
+fn compile_statement(statement: &Statement, layout: &Layout) -> Code {
+    match statement {
+        Statement::Assign(x) => Code::Assign(
+            x.targets
+                .iter()
+                .map(|x| TargetCode::compile(x, layout))
+                .collect(),
+            Program::compile(&x.expression, layout),
+        ),
+        Statement::If(branches, otherwise) => Code::If(
+            branches
+                .iter()
+                .map(|(x, y)| (Program::compile(x, layout), compile(y, layout)))
+                .collect(),
+            compile(otherwise, layout),
+        ),
+        Statement::Case(x) => Code::Case(CaseCode {
+            expression: Program::compile(&x.expression, layout),
+            arms: x
+                .arms
+                .iter()
+                .map(|(labels, statements)| {
+                    let labels = labels.iter().map(|x| compile_label(x, layout)).collect();
+                    (labels, compile(statements, layout))
+                })
+                .collect(),
+            spans: x.spans.clone(),
+            otherwise: compile(&x.otherwise, layout),
+            has_default: x.has_default,
+            check: x.check,
+            span: x.span,
+        }),
+        Statement::Assert(x) => Code::Assert(x.clone()),
+        Statement::Cover(x) => Code::Cover(*x),
+        Statement::Instance(x) => Code::Instance(
+            x.index,
+            x.inputs
+                .iter()
+                .map(|x| Program::compile(x, layout))
+                .collect(),
+            x.outputs
+                .iter()
+                .map(|x| TargetCode::compile(x, layout))
+                .collect(),
+        ),
+    }
+}
+
+fn compile_label(label: &CaseLabel, layout: &Layout) -> LabelCode {
+    match label {
+        CaseLabel::Value(x) => LabelCode::Value(Program::compile(x, layout)),
+        CaseLabel::Wildcard(x, mask) => LabelCode::Wildcard(*x, *mask),
+        CaseLabel::Range(lo, hi, inclusive) => LabelCode::Range(
+            Program::compile(lo, layout),
+            Program::compile(hi, layout),
+            *inclusive,
+        ),
+    }
+}
+
+// 文の列に含まれる全ての Program を順に訪れる
+#[cfg(feature = "jit")]
+pub(crate) fn for_each_program(code: &mut [Code], f: &mut dyn FnMut(&mut Program)) {
+    for code in code {
+        match code {
+            Code::Assign(targets, expression) => {
+                for_each_target(targets, f);
+                f(expression);
+            }
+            Code::Instance(_, inputs, outputs) => {
+                for input in inputs {
+                    f(input);
+                }
+                for_each_target(outputs, f);
+            }
+            Code::If(branches, otherwise) => {
+                for (condition, code) in branches {
+                    f(condition);
+                    for_each_program(code, f);
+                }
+                for_each_program(otherwise, f);
+            }
+            Code::Case(case) => {
+                f(&mut case.expression);
+                for (labels, code) in &mut case.arms {
+                    for label in labels {
+                        match label {
+                            LabelCode::Value(x) => f(x),
+                            LabelCode::Wildcard(_, _) => {}
+                            LabelCode::Range(lo, hi, _) => {
+                                f(lo);
+                                f(hi);
+                            }
+                        }
+                    }
+                    for_each_program(code, f);
+                }
+                for_each_program(&mut case.otherwise, f);
+            }
+            Code::Assert(_) | Code::Cover(_) => {}
+        }
+    }
+}
+
+#[cfg(feature = "jit")]
+fn for_each_target(targets: &mut [TargetCode], f: &mut dyn FnMut(&mut Program)) {
+    for target in targets {
+        if let TargetSlot::Indexed(index, _) = &mut target.slot {
+            f(index);
+        }
+        if let Some((msb, lsb)) = &mut target.select {
+            f(msb);
+            f(lsb);
+        }
+    }
+}
@@ -0,0 +1,293 @@
+use crate::model::{ModelWarning, Span};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Support level of an operator or a construct in the simulator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Support {
+    /// Simulated as the language defines
+    Supported,
+    /// Simulated, but the result may differ from the language definition
+    Approximated,
+    /// Ignored or rejected
+    Unsupported,
+}
+
+impl fmt::Display for Support {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Support::Supported => write!(f, "supported"),
+            Support::Approximated => write!(f, "approximated"),
+            Support::Unsupported => write!(f, "unsupported"),
+        }
+    }
+}
+
+/// Entry of the capability matrix
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capability {
+    pub construct: &'static str,
+    pub support: Support,
+    pub note: &'static str,
+}
+
+const fn entry(construct: &'static str, support: Support, note: &'static str) -> Capability {
+    Capability {
+        construct,
+        support,
+        note,
+    }
+}
+
+use Support::*;
+
+// The construct names are the same as the ones in ModelWarning
+const CAPABILITIES: &[Capability] = &[
+    // Declarations
+    entry("assign", Supported, ""),
+    entry("always_comb", Supported, "evaluated until settled"),
+    entry(
+        "always_ff",
+        Supported,
+        "evaluated at the active edge of the clock, falling for clock_negedge",
+    ),
+    entry(
+        "var",
+        Unsupported,
+        "variables in statement blocks are ignored",
+    ),
+    entry("let", Unsupported, "ignored"),
+    entry(
+        "const",
+        Unsupported,
+        "references are read as unknown signals",
+    ),
+    entry("function", Unsupported, "ignored"),
+    entry("initial", Unsupported, "ignored"),
+    entry("final", Unsupported, "ignored"),
+    entry(
+        "inst",
+        Unsupported,
+        "rejected, except modules without a definition replaced by Model::register_module",
+    ),
+    entry("if (generate)", Unsupported, "rejected"),
+    entry("for (generate)", Unsupported, "rejected"),
+    // Statements
+    entry("if", Supported, "unassigned variables hold their values"),
+    entry("if_reset", Supported, "only at the top level of always_ff"),
+    entry("case", Supported, "including ranges and x / z wildcards"),
+    entry("for", Unsupported, "rejected"),
+    entry("switch", Unsupported, "rejected"),
+    entry("return", Unsupported, "rejected"),
+    entry("break", Unsupported, "rejected"),
+    entry(
+        "function call",
+        Supported,
+        "calls the Rust function registered by Model::register_function",
+    ),
+    entry(
+        "system function `$warning`",
+        Supported,
+        "recorded as an assertion failure",
+    ),
+    entry(
+        "system function `$error`",
+        Supported,
+        "recorded as an assertion failure",
+    ),
+    entry(
+        "system function `$fatal`",
+        Supported,
+        "recorded as an assertion failure and finishes the simulation",
+    ),
+    entry(
+        "system function",
+        Unsupported,
+        "ignored as statement, or read as 0",
+    ),
+    // Expressions
+    entry("bit select", Supported, ""),
+    entry("array element", Supported, ""),
+    entry("concatenation assignment", Supported, ""),
+    entry("msb / lsb", Supported, ""),
+    entry("$bits", Supported, ""),
+    entry("$size", Supported, ""),
+    entry("if expression", Unsupported, "the else value is used"),
+    entry("struct constructor", Unsupported, "read as a signal"),
+    entry("number", Supported, ""),
+    entry("all-bit literal", Unsupported, "read as 0"),
+    entry("real number", Unsupported, "read as 0"),
+    entry("boolean literal", Unsupported, "read as 0"),
+    entry("parenthesized expression", Unsupported, "read as 0"),
+    entry("concatenation", Unsupported, "read as 0"),
+    entry("array literal", Unsupported, "read as 0"),
+    entry("case expression", Unsupported, "read as 0"),
+    entry("switch expression", Unsupported, "read as 0"),
+    entry("string literal", Unsupported, "read as 0"),
+    entry("inside expression", Supported, ""),
+    entry("outside expression", Supported, ""),
+    entry("type expression", Unsupported, "read as 0"),
+    entry("type", Unsupported, "read as 0"),
+    // Operators
+    entry(
+        "operator `+`",
+        Approximated,
+        "intermediate results are not truncated",
+    ),
+    entry(
+        "operator `-`",
+        Approximated,
+        "intermediate results are not truncated",
+    ),
+    entry(
+        "operator `*`",
+        Approximated,
+        "intermediate results are not truncated",
+    ),
+    entry("operator `/`", Approximated, "division by 0 results in 0"),
+    entry(
+        "operator `~`",
+        Approximated,
+        "logical negation instead of bitwise",
+    ),
+    entry("unary operator", Unsupported, "the operand is used"),
+    entry("operator `%`", Approximated, "modulo by 0 results in 0"),
+    entry("operator `**`", Unsupported, "the left operand is used"),
+    entry("operator `&`", Supported, ""),
+    entry("operator `|`", Supported, ""),
+    entry("operator `^`", Supported, ""),
+    entry("operator `~^`", Unsupported, "the left operand is used"),
+    entry("operator `&&`", Unsupported, "the left operand is used"),
+    entry("operator `||`", Unsupported, "the left operand is used"),
+    entry("operator `==`", Supported, ""),
+    entry("operator `!=`", Supported, ""),
+    entry("operator `==?`", Supported, ""),
+    entry("operator `!=?`", Supported, ""),
+    entry("operator `<:`", Unsupported, "the left operand is used"),
+    entry("operator `>:`", Unsupported, "the left operand is used"),
+    entry("operator `<=`", Unsupported, "the left operand is used"),
+    entry("operator `>=`", Unsupported, "the left operand is used"),
+    entry(
+        "operator `<<`",
+        Approximated,
+        "intermediate results are not truncated",
+    ),
+    entry("operator `>>`", Supported, ""),
+    entry(
+        "operator `<<<`",
+        Approximated,
+        "intermediate results are not truncated",
+    ),
+    entry("operator `>>>`", Unsupported, "the left operand is used"),
+    entry(
+        "operator `as`",
+        Approximated,
+        "not truncated to the casting type",
+    ),
+    entry("operator `=`", Supported, ""),
+    entry("operator `+=`", Supported, ""),
+    entry("operator `-=`", Supported, ""),
+    entry("operator `*=`", Supported, ""),
+    entry("operator `/=`", Approximated, "division by 0 results in 0"),
+    entry("operator `%=`", Approximated, "modulo by 0 results in 0"),
+    entry("operator `&=`", Supported, ""),
+    entry("operator `|=`", Supported, ""),
+    entry("operator `^=`", Supported, ""),
+    entry("operator `<<=`", Supported, ""),
+    entry("operator `>>=`", Supported, ""),
+    entry("operator `<<<=`", Supported, ""),
+    entry("operator `>>>=`", Unsupported, "rejected"),
+];
+
+/// All entries of the capability matrix
+pub fn capabilities() -> &'static [Capability] {
+    CAPABILITIES
+}
+
+/// Capability of the construct
+/// constructs with a name suffix like "system function `$display`" fall back to the generic entry
+pub fn capability(construct: &str) -> Option<&'static Capability> {
+    let generic = construct.split(" `").next().unwrap_or(construct);
+    CAPABILITIES
+        .iter()
+        .find(|x| x.construct == construct)
+        .or_else(|| CAPABILITIES.iter().find(|x| x.construct == generic))
+}
+
+/// Support level of the construct, unknown constructs are unsupported
+pub fn support(construct: &str) -> Support {
+    capability(construct)
+        .map(|x| x.support)
+        .unwrap_or(Support::Unsupported)
+}
+
+/// Constructs used in a design which are approximated or unsupported
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintEntry {
+    pub construct: String,
+    pub support: Support,
+    pub note: &'static str,
+    pub locations: Vec<Span>,
+}
+
+/// Report of `Model::lint()`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Lint {
+    /// Unsupported constructs come first
+    pub entries: Vec<LintEntry>,
+}
+
+impl Lint {
+    pub(crate) fn new<'a>(warnings: impl Iterator<Item = &'a ModelWarning>) -> Self {
+        let mut entries: BTreeMap<(Support, String), LintEntry> = BTreeMap::new();
+        for warning in warnings {
+            let capability = capability(&warning.construct);
+            let support = capability
+                .map(|x| x.support)
+                .unwrap_or(Support::Unsupported);
+            entries
+                .entry((support, warning.construct.clone()))
+                .or_insert_with(|| LintEntry {
+                    construct: warning.construct.clone(),
+                    support,
+                    note: capability.map(|x| x.note).unwrap_or(""),
+                    locations: Vec::new(),
+                })
+                .locations
+                .push(warning.span);
+        }
+
+        let mut entries: Vec<_> = entries.into_values().collect();
+        entries.sort_by_key(|x| std::cmp::Reverse(x.support));
+        for entry in &mut entries {
+            entry.locations.sort();
+        }
+        Lint { entries }
+    }
+
+    /// Whether the design is inside the supported subset
+    /// approximated constructs are allowed
+    pub fn is_supported(&self) -> bool {
+        self.entries
+            .iter()
+            .all(|x| x.support != Support::Unsupported)
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for entry in &self.entries {
+            let locations: Vec<_> = entry.locations.iter().map(|x| x.to_string()).collect();
+            write!(f, "{}: {}", entry.support, entry.construct)?;
+            if !entry.note.is_empty() {
+                write!(f, " ({})", entry.note)?;
+            }
+            writeln!(f, " at {}", locations.join(", "))?;
+        }
+        if self.is_supported() {
+            writeln!(f, "the design is inside the supported subset")
+        } else {
+            writeln!(f, "the design is outside the supported subset")
+        }
+    }
+}
@@ -0,0 +1,563 @@
+use crate::bytecode::Layout;
+use crate::model::{
+    CaseLabel, Expr, MAX_SETTLE_ITERATIONS, SequentialBlock, Statement, Target, element_name,
+    reset_level,
+};
+use crate::{ClockType, Direction, ResetType};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+// Rust ソースとして出力するモデルの内容
+pub(crate) struct RustModel<'a> {
+    pub(crate) module: &'a str,
+    pub(crate) layout: &'a Layout,
+    pub(crate) values: &'a [usize],
+    pub(crate) combinational: &'a [Statement],
+    pub(crate) levelized: bool,
+    pub(crate) sequential: &'a [SequentialBlock],
+    pub(crate) clocks: &'a [(String, ClockType)],
+    pub(crate) resets: &'a [(String, ResetType)],
+}
+
+impl RustModel<'_> {
+    // モデルと同じ結果になる構造体と関数を出力する
+    // 信号の値は状態ベクタ values に layout の位置で格納する
+    pub(crate) fn emit(&self) -> String {
+        let mut emitter = Emitter {
+            layout: self.layout,
+            code: String::new(),
+            indent: 1,
+            temps: 0,
+            tables: Vec::new(),
+            table_ids: HashMap::new(),
+            calls: false,
+        };
+
+        emitter.function("comb", |x| x.statements(self.combinational));
+        for (i, block) in self.sequential.iter().enumerate() {
+            emitter.function(&format!("reset_{i}"), |x| x.statements(&block.reset));
+            emitter.function(&format!("clock_{i}"), |x| x.statements(&block.clock));
+        }
+        let functions = std::mem::take(&mut emitter.code);
+
+        let n = self.layout.iter().count();
+        let name = self.module;
+        let mut code = String::new();
+        let _ = writeln!(
+            code,
+            "// Generated by veryl-simulator from module `{name}`, do not edit\n"
+        );
+        let _ = writeln!(code, "#[derive(Debug, Clone)]\npub struct {name} {{");
+        let _ = writeln!(
+            code,
+            "    /// Values of all signals, in the order of `SIGNALS`"
+        );
+        let _ = writeln!(code, "    pub values: [usize; {n}],");
+        if emitter.calls {
+            let _ = writeln!(
+                code,
+                "    /// Foreign functions called from the design, unregistered functions return 0"
+            );
+            let _ = writeln!(code, "    pub functions: {FUNCTIONS},");
+        }
+        code.push_str("}\n\n");
+        let _ = writeln!(
+            code,
+            "#[allow(clippy::all, unused, unused_comparisons)]\nimpl {name} {{"
+        );
+
+        let signals: Vec<_> = self
+            .layout
+            .iter()
+            .map(|(_, x, _)| format!("{x:?}"))
+            .collect();
+        let _ = writeln!(
+            code,
+            "    pub const SIGNALS: [&'static str; {n}] = [{}];",
+            signals.join(", ")
+        );
+        for (i, table) in emitter.tables.iter().enumerate() {
+            let slots: Vec<_> = table.iter().map(|x| x.to_string()).collect();
+            let _ = writeln!(
+                code,
+                "    const TABLE_{i}: [usize; {}] = [{}];",
+                table.len(),
+                slots.join(", ")
+            );
+        }
+
+        let values: Vec<_> = self.values.iter().map(|x| x.to_string()).collect();
+        let table = if emitter.calls {
+            "            functions: std::collections::HashMap::new(),\n"
+        } else {
+            ""
+        };
+        let _ = writeln!(
+            code,
+            "\n    pub fn new() -> Self {{\n        {name} {{\n            values: [{}],\n{table}        }}\n    }}\n",
+            values.join(", ")
+        );
+        code.push_str(
+            "    pub fn get(&self, name: &str) -> Option<usize> {\n        Self::SIGNALS\n            .iter()\n            .position(|x| *x == name)\n            .map(|x| self.values[x])\n    }\n\n",
+        );
+        self.emit_set(&mut code);
+        self.emit_clock(&mut code, "clock_rise", true);
+        self.emit_clock(&mut code, "clock_fall", false);
+        self.emit_reset(&mut code);
+        self.emit_eval(&mut code);
+        code.push_str(&functions);
+        code.push_str(HELPERS);
+        if emitter.calls {
+            let _ = writeln!(
+                code,
+                "\n    fn call(functions: &{FUNCTIONS}, name: &str, args: &[usize]) -> usize {{\n        functions.get(name).map_or(0, |x| x(args))\n    }}"
+            );
+        }
+        code.push_str("}\n");
+        code
+    }
+
+    // 入力ポートを設定する（非同期リセットのアサートはクロックを待たずに反映する）
+    fn emit_set(&self, code: &mut String) {
+        code.push_str("    /// Set the input port, returns false if the port doesn't exist\n");
+        code.push_str("    pub fn set(&mut self, name: &str, value: usize) -> bool {\n");
+        code.push_str("        match name {\n");
+        for (slot, name, direction) in self.layout.iter() {
+            if direction != Direction::Input {
+                continue;
+            }
+            let _ = write!(
+                code,
+                "            {name:?} => {{\n                self.values[{slot}] = value;\n"
+            );
+            for (i, block) in self.sequential.iter().enumerate() {
+                let Some((_, reset_type)) = self
+                    .resets
+                    .iter()
+                    .find(|(x, _)| x == name && block.reset_signal.as_deref() == Some(x))
+                else {
+                    continue;
+                };
+                if matches!(reset_type, ResetType::AsyncHigh | ResetType::AsyncLow) {
+                    let _ = writeln!(
+                        code,
+                        "                if value == {} {{\n                    self.reset_{i}();\n                }}",
+                        reset_level(*reset_type, true)
+                    );
+                }
+            }
+            code.push_str("            }\n");
+        }
+        code.push_str("            _ => return false,\n        }\n        self.eval();\n        true\n    }\n\n");
+    }
+
+    // クロックの有効エッジでそのクロックの順序回路ブロックを評価する
+    fn emit_clock(&self, code: &mut String, function: &str, rising: bool) {
+        let _ = writeln!(code, "    pub fn {function}(&mut self, clock: &str) {{");
+        code.push_str("        match clock {\n");
+        for (clock, clock_type) in self.clocks {
+            let _ = writeln!(code, "            {clock:?} => {{");
+            if let Some(slot) = self.input_slot(clock) {
+                let _ = writeln!(
+                    code,
+                    "                self.values[{slot}] = {};",
+                    rising as usize
+                );
+            }
+            if rising == matches!(clock_type, ClockType::PosEdge) {
+                for (i, block) in self.sequential.iter().enumerate() {
+                    if block.clock_signal.as_deref() != Some(clock) {
+                        continue;
+                    }
+                    match self.reset_condition(block) {
+                        Some(condition) => {
+                            let _ = writeln!(
+                                code,
+                                "                if {condition} {{\n                    self.reset_{i}();\n                }} else {{\n                    self.clock_{i}();\n                }}"
+                            );
+                        }
+                        None => {
+                            let _ = writeln!(code, "                self.clock_{i}();");
+                        }
+                    }
+                }
+            }
+            code.push_str("            }\n");
+        }
+        code.push_str("            _ => {}\n        }\n        self.eval();\n    }\n\n");
+    }
+
+    // リセット入力をアサートして全ての順序回路ブロックのリセット処理を実行し、解除する
+    fn emit_reset(&self, code: &mut String) {
+        code.push_str("    pub fn reset(&mut self) {\n");
+        self.drive_resets(code, true);
+        for i in 0..self.sequential.len() {
+            let _ = writeln!(code, "        self.reset_{i}();");
+        }
+        self.drive_resets(code, false);
+        code.push_str("        self.eval();\n    }\n\n");
+    }
+
+    fn drive_resets(&self, code: &mut String, active: bool) {
+        for (name, reset_type) in self.resets {
+            if let Some(slot) = self.input_slot(name) {
+                let _ = writeln!(
+                    code,
+                    "        self.values[{slot}] = {};",
+                    reset_level(*reset_type, active)
+                );
+            }
+        }
+    }
+
+    // 組み合わせ回路を評価する（ループがある場合は値が変化しなくなるまで繰り返す）
+    fn emit_eval(&self, code: &mut String) {
+        code.push_str("    pub fn eval(&mut self) {\n");
+        if self.levelized {
+            code.push_str("        self.comb();\n");
+        } else {
+            let _ = writeln!(
+                code,
+                "        for _ in 0..{MAX_SETTLE_ITERATIONS} {{\n            let previous = self.values;\n            self.comb();\n            if self.values == previous {{\n                break;\n            }}\n        }}"
+            );
+        }
+        code.push_str("    }\n\n");
+    }
+
+    fn input_slot(&self, name: &str) -> Option<usize> {
+        self.layout
+            .slot(name)
+            .filter(|x| self.layout.direction(*x) == Direction::Input)
+    }
+
+    // ブロックのリセットがアサートされている条件
+    fn reset_condition(&self, block: &SequentialBlock) -> Option<String> {
+        let signal = block.reset_signal.as_ref()?;
+        let (_, reset_type) = self.resets.iter().find(|(x, _)| x == signal)?;
+        let slot = self.layout.slot(signal)?;
+        Some(format!(
+            "self.values[{slot}] == {}",
+            reset_level(*reset_type, true)
+        ))
+    }
+}
+
+// 文を Rust のコードに変換する
+struct Emitter<'a> {
+    layout: &'a Layout,
+    code: String,
+    indent: usize,
+    temps: usize,                              // 一時変数の数
+    tables: Vec<Vec<usize>>, // 配列要素の位置の表（範囲外・代入できない要素は usize::MAX）
+    table_ids: HashMap<(String, bool), usize>, // (配列名, 代入先か) と表の番号
+    calls: bool,             // 外部関数を呼び出すか
+}
+
+impl Emitter<'_> {
+    fn function(&mut self, name: &str, body: impl FnOnce(&mut Self)) {
+        self.line(&format!("fn {name}(&mut self) {{"));
+        self.indent += 1;
+        self.line("let v = &mut self.values;");
+        body(self);
+        self.indent -= 1;
+        self.line("}\n");
+    }
+
+    fn line(&mut self, line: &str) {
+        let _ = writeln!(self.code, "{}{line}", "    ".repeat(self.indent));
+    }
+
+    fn temp(&mut self) -> String {
+        self.temps += 1;
+        format!("t{}", self.temps)
+    }
+
+    // 配列要素の位置の表の番号
+    fn table(&mut self, name: &str, write: bool) -> usize {
+        if let Some(x) = self.table_ids.get(&(name.to_string(), write)) {
+            return *x;
+        }
+        let slots = (0..)
+            .map_while(|i| self.layout.slot(&element_name(name, i)))
+            .map(|x| {
+                if write && self.layout.direction(x) == Direction::Input {
+                    usize::MAX
+                } else {
+                    x
+                }
+            })
+            .collect();
+        self.tables.push(slots);
+        let id = self.tables.len() - 1;
+        self.table_ids.insert((name.to_string(), write), id);
+        id
+    }
+
+    fn statements(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            self.statement(statement);
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Assign(x) => {
+                self.line("{");
+                self.indent += 1;
+                let value = self.temp();
+                let expr = self.expr(&x.expression);
+                // 代入先のない文は外部関数の呼び出し
+                if x.targets.is_empty() {
+                    self.line(&format!("{expr};"));
+                } else {
+                    self.line(&format!("let {value} = {expr};"));
+                    self.store(&x.targets, &value);
+                }
+                self.indent -= 1;
+                self.line("}");
+            }
+            Statement::If(branches, otherwise) => {
+                for (i, (condition, statements)) in branches.iter().enumerate() {
+                    let condition = self.expr(condition);
+                    let keyword = if i == 0 { "if" } else { "} else if" };
+                    self.line(&format!("{keyword} {condition} != 0 {{"));
+                    self.indent += 1;
+                    self.statements(statements);
+                    self.indent -= 1;
+                }
+                self.line("} else {");
+                self.indent += 1;
+                self.statements(otherwise);
+                self.indent -= 1;
+                self.line("}");
+            }
+            Statement::Case(x) => {
+                self.line("{");
+                self.indent += 1;
+                let value = self.temp();
+                let expr = self.expr(&x.expression);
+                self.line(&format!("let {value} = {expr};"));
+                for (i, (labels, statements)) in x.arms.iter().enumerate() {
+                    let labels: Vec<_> = labels.iter().map(|x| self.label(x, &value)).collect();
+                    let condition = if labels.is_empty() {
+                        "false".to_string()
+                    } else {
+                        labels.join(" || ")
+                    };
+                    let keyword = if i == 0 { "if" } else { "} else if" };
+                    self.line(&format!("{keyword} {condition} {{"));
+                    self.indent += 1;
+                    self.statements(statements);
+                    self.indent -= 1;
+                }
+                if x.arms.is_empty() {
+                    self.statements(&x.otherwise);
+                } else {
+                    self.line("} else {");
+                    self.indent += 1;
+                    self.statements(&x.otherwise);
+                    self.indent -= 1;
+                    self.line("}");
+                }
+                self.indent -= 1;
+                self.line("}");
+            }
+            // カバレッジとアサーション、振る舞いモデルのインスタンスは出力しない
+            Statement::Assert(_) | Statement::Cover(_) | Statement::Instance(_) => {}
+        }
+    }
+
+    fn label(&mut self, label: &CaseLabel, value: &str) -> String {
+        match label {
+            CaseLabel::Value(x) => format!("{value} == {}", self.expr(x)),
+            CaseLabel::Wildcard(x, mask) => format!("({value} ^ {x}) & {mask} == 0"),
+            CaseLabel::Range(lo, hi, inclusive) => {
+                let lo = self.expr(lo);
+                let hi = self.expr(hi);
+                let op = if *inclusive { "<=" } else { "<" };
+                format!("({lo} <= {value} && {value} {op} {hi})")
+            }
+        }
+    }
+
+    // 代入先の添字とビット選択は代入前の値で評価し、連接は LSB 側の代入先から値を切り出す
+    fn store(&mut self, targets: &[Target], value: &str) {
+        let mut resolved = Vec::new();
+        for target in targets {
+            let slot = match &target.index {
+                Some(index) => {
+                    let table = self.table(&target.name, true);
+                    let index = self.expr(index);
+                    let slot = self.temp();
+                    self.line(&format!(
+                        "let {slot} = Self::TABLE_{table}.get({index}).copied().unwrap_or(usize::MAX);"
+                    ));
+                    Some(slot)
+                }
+                None => self
+                    .layout
+                    .slot(&target.name)
+                    .filter(|x| self.layout.direction(*x) != Direction::Input)
+                    .map(|x| x.to_string()),
+            };
+            let select = match &target.select {
+                Some((msb, lsb)) => {
+                    let (m, l) = (self.temp(), self.temp());
+                    let msb = self.expr(msb);
+                    let lsb = self.expr(lsb);
+                    self.line(&format!("let {m} = {msb};"));
+                    self.line(&format!("let {l} = {lsb};"));
+                    Some((m, l))
+                }
+                None => None,
+            };
+            resolved.push((slot, select, target.width, target.index.is_some()));
+        }
+
+        let single = resolved.len() == 1;
+        let offset = self.temp();
+        if !single {
+            self.line(&format!("let mut {offset}: usize = 0;"));
+        }
+        for (slot, select, width, indexed) in resolved.into_iter().rev() {
+            let width = match &select {
+                Some((m, l)) => format!("({m} + 1).saturating_sub({l})"),
+                None => width.to_string(),
+            };
+            let part = if single {
+                value.to_string()
+            } else {
+                let part = self.temp();
+                self.line(&format!(
+                    "let {part} = {value}.checked_shr({offset} as u32).unwrap_or(0) & Self::bit_mask({width});"
+                ));
+                part
+            };
+            if let Some(slot) = slot {
+                let merged = match &select {
+                    Some((m, l)) => format!("Self::merge_bits(v[{slot}], {part}, {m}, {l})"),
+                    None => format!("{part} & Self::bit_mask({width})"),
+                };
+                if indexed {
+                    self.line(&format!("if {slot} != usize::MAX {{"));
+                    self.line(&format!("    v[{slot}] = {merged};"));
+                    self.line("}");
+                } else {
+                    self.line(&format!("v[{slot}] = {merged};"));
+                }
+            }
+            if !single {
+                self.line(&format!("{offset} += {width};"));
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) -> String {
+        match expr {
+            Expr::Const(x) => format!("{x}usize"),
+            Expr::Var(name) => match self.layout.slot(name) {
+                Some(slot) => format!("v[{slot}]"),
+                // 未知の信号は 0 とする
+                None => "0usize".to_string(),
+            },
+            Expr::Index(name, index) => {
+                let table = self.table(name, false);
+                let index = self.expr(index);
+                format!("Self::TABLE_{table}.get({index}).map(|x| v[*x]).unwrap_or(0)")
+            }
+            Expr::Select(expr, msb, lsb) => format!(
+                "Self::select_bits({}, {}, {})",
+                self.expr(expr),
+                self.expr(msb),
+                self.expr(lsb)
+            ),
+            Expr::Add(left, right) => {
+                format!("{}.wrapping_add({})", self.expr(left), self.expr(right))
+            }
+            Expr::Sub(left, right) => {
+                format!("{}.wrapping_sub({})", self.expr(left), self.expr(right))
+            }
+            Expr::SatSub(left, right) => {
+                format!("{}.saturating_sub({})", self.expr(left), self.expr(right))
+            }
+            Expr::Mul(left, right) => {
+                format!("{}.wrapping_mul({})", self.expr(left), self.expr(right))
+            }
+            Expr::Div(left, right) => format!(
+                "{}.checked_div({}).unwrap_or(0)",
+                self.expr(left),
+                self.expr(right)
+            ),
+            Expr::Rem(left, right) => format!(
+                "{}.checked_rem({}).unwrap_or(0)",
+                self.expr(left),
+                self.expr(right)
+            ),
+            Expr::And(left, right) => format!("({} & {})", self.expr(left), self.expr(right)),
+            Expr::Or(left, right) => format!("({} | {})", self.expr(left), self.expr(right)),
+            Expr::Xor(left, right) => format!("({} ^ {})", self.expr(left), self.expr(right)),
+            Expr::Shl(left, right) => format!(
+                "u32::try_from({}).ok().and_then(|x| {}.checked_shl(x)).unwrap_or(0)",
+                self.expr(right),
+                self.expr(left)
+            ),
+            Expr::Shr(left, right) => format!(
+                "u32::try_from({}).ok().and_then(|x| {}.checked_shr(x)).unwrap_or(0)",
+                self.expr(right),
+                self.expr(left)
+            ),
+            Expr::Not(expr) => format!("(({} == 0) as usize)", self.expr(expr)),
+            Expr::Eq(left, right, mask) => format!(
+                "((({} ^ {}) & {mask}usize == 0) as usize)",
+                self.expr(left),
+                self.expr(right)
+            ),
+            Expr::Inside(expr, labels) => {
+                let value = self.temp();
+                let expr = self.expr(expr);
+                let labels: Vec<_> = labels.iter().map(|x| self.label(x, &value)).collect();
+                format!(
+                    "{{ let {value} = {expr}; ({}) as usize }}",
+                    labels.join(" || ")
+                )
+            }
+            Expr::Call(name, args) => {
+                self.calls = true;
+                let args: Vec<_> = args.iter().map(|x| self.expr(x)).collect();
+                format!(
+                    "Self::call(&self.functions, {name:?}, &[{}])",
+                    args.join(", ")
+                )
+            }
+        }
+    }
+}
+
+// 外部関数の表の型
+const FUNCTIONS: &str = "std::collections::HashMap<&'static str, fn(&[usize]) -> usize>";
+
+// model.rs の merge_bits / select_bits / bit_mask と同じ
+const HELPERS: &str =
+    "    fn merge_bits(current: usize, value: usize, msb: usize, lsb: usize) -> usize {
+        if msb < lsb || lsb >= usize::BITS as usize {
+            return current;
+        }
+        let mask = Self::bit_mask(msb - lsb + 1);
+        (current & !(mask << lsb)) | ((value & mask) << lsb)
+    }
+
+    fn select_bits(value: usize, msb: usize, lsb: usize) -> usize {
+        if msb < lsb || lsb >= usize::BITS as usize {
+            return 0;
+        }
+        (value >> lsb) & Self::bit_mask(msb - lsb + 1)
+    }
+
+    fn bit_mask(width: usize) -> usize {
+        if width >= usize::BITS as usize {
+            usize::MAX
+        } else {
+            (1 << width) - 1
+        }
+    }
+";
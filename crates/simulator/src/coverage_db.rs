@@ -0,0 +1,115 @@
+use crate::ModelError;
+use crate::hooks::{CoverGroupReport, CoverageReport};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+
+/// Coverage results of one or more simulation runs
+/// runs are aggregated by `merge`, and saved as JSON or exported as lcov
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoverageDb {
+    /// Names of the merged runs
+    pub tests: Vec<String>,
+    /// Statement, branch and toggle coverage
+    pub code: CoverageReport,
+    /// Functional coverage
+    pub groups: Vec<CoverGroupReport>,
+}
+
+impl CoverageDb {
+    pub fn new(test: &str) -> Self {
+        CoverageDb {
+            tests: vec![test.to_string()],
+            ..Default::default()
+        }
+    }
+
+    /// Add the report of `CoverageHook`
+    pub fn add_code(&mut self, report: &CoverageReport) {
+        self.code.merge(report);
+    }
+
+    /// Add the report of `CoverGroup`, covergroups with the same name are merged
+    pub fn add_group(&mut self, report: &CoverGroupReport) {
+        match self.groups.iter_mut().find(|x| x.name == report.name) {
+            Some(x) => x.merge(report),
+            None => self.groups.push(report.clone()),
+        }
+    }
+
+    /// Aggregate the coverage of another run
+    pub fn merge(&mut self, other: &CoverageDb) {
+        self.tests.extend(other.tests.iter().cloned());
+        self.add_code(&other.code);
+        for x in &other.groups {
+            self.add_group(x);
+        }
+    }
+
+    /// Load and merge the databases saved by `save`
+    pub fn merge_files<T: AsRef<Path>>(paths: &[T]) -> Result<Self, ModelError> {
+        let mut ret = CoverageDb::default();
+        for path in paths {
+            ret.merge(&Self::load(path)?);
+        }
+        Ok(ret)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    pub fn save<T: AsRef<Path>>(&self, path: T) -> std::io::Result<()> {
+        std::fs::write(path, self.to_json())
+    }
+
+    pub fn load<T: AsRef<Path>>(path: T) -> Result<Self, ModelError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|x| ModelError::ReadFailed {
+            path: path.to_string_lossy().to_string(),
+            cause: x.to_string(),
+        })?;
+        serde_json::from_str(&text).map_err(|x| ModelError::ParseFailed {
+            path: path.to_string_lossy().to_string(),
+            cause: x.to_string(),
+        })
+    }
+
+    /// Statement and branch coverage in the lcov tracefile format
+    /// `source` is written as the source file of the design
+    pub fn to_lcov(&self, source: &str) -> String {
+        // 行ごとに文の実行回数の最大値と分岐の実行回数をまとめる
+        let mut lines: BTreeMap<u32, u64> = BTreeMap::new();
+        let mut branches: BTreeMap<u32, Vec<u64>> = BTreeMap::new();
+        for (point, hits) in &self.code.points {
+            let line = point.location.line;
+            if point.kind.is_branch() {
+                branches.entry(line).or_default().push(*hits);
+            } else {
+                let x = lines.entry(line).or_default();
+                *x = (*x).max(*hits);
+            }
+        }
+
+        let mut ret = String::new();
+        let _ = writeln!(ret, "TN:{}", self.tests.join(","));
+        let _ = writeln!(ret, "SF:{source}");
+        for (line, hits) in &branches {
+            for (i, x) in hits.iter().enumerate() {
+                let _ = writeln!(ret, "BRDA:{line},0,{i},{x}");
+            }
+        }
+        let found = branches.values().map(|x| x.len()).sum::<usize>();
+        let hit = branches.values().flatten().filter(|x| **x > 0).count();
+        let _ = writeln!(ret, "BRF:{found}");
+        let _ = writeln!(ret, "BRH:{hit}");
+        for (line, hits) in &lines {
+            let _ = writeln!(ret, "DA:{line},{hits}");
+        }
+        let _ = writeln!(ret, "LF:{}", lines.len());
+        let _ = writeln!(ret, "LH:{}", lines.values().filter(|x| **x > 0).count());
+        ret.push_str("end_of_record\n");
+        ret
+    }
+}
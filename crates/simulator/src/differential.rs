@@ -0,0 +1,355 @@
+use crate::elaborate::{self, PROJECT_NAME};
+use crate::model::reset_level;
+use crate::random::Random;
+use crate::{Direction, Model, ModelError};
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use veryl_emitter::Emitter;
+
+// 作業ディレクトリを区別する番号（同じプロセスから並行して実行する場合）
+static WORK_DIRS: AtomicUsize = AtomicUsize::new(0);
+
+/// SystemVerilog simulator used as the reference
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SvSimulator {
+    /// Icarus Verilog (`iverilog` and `vvp`)
+    #[default]
+    Icarus,
+    /// Verilator (`verilator --binary`)
+    Verilator,
+}
+
+/// Output which differs between the model and the reference simulator
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffMismatch {
+    pub cycle: usize,
+    pub signal: String,
+    pub expected: usize,
+    pub actual: usize,
+}
+
+impl fmt::Display for DiffMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "mismatch at cycle {}: {} expected {:#x} but got {:#x}",
+            self.cycle, self.signal, self.expected, self.actual
+        )
+    }
+}
+
+/// Result of a differential run
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiffReport {
+    pub cycles: usize,
+    /// Outputs compared over all cycles
+    pub compared: usize,
+    /// Outputs skipped because the reference had unknown bits (x, z)
+    pub unknowns: usize,
+    pub mismatches: Vec<DiffMismatch>,
+}
+
+impl DiffReport {
+    pub fn is_match(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+/// SystemVerilog sources of a differential run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffSources {
+    /// Design emitted by the Veryl transpiler
+    pub design: String,
+    /// Testbench applying the stimuli and printing the outputs after every cycle
+    pub testbench: String,
+}
+
+// 1 サイクル分の入力と、入力を与えてクロックを進めた後のモデルの出力
+struct Cycle {
+    inputs: Vec<(String, usize)>,
+    outputs: Vec<(String, usize)>,
+}
+
+// This harness runs a design on the model and on an external SystemVerilog simulator,
+// and compares the outputs cycle by cycle
+// both sides are reset by asserting the resets over one edge of every clock,
+// then random values are applied to the other inputs before every cycle
+// outputs which are unknown (x, z) in the reference are not compared, like VcdCompareHook
+pub struct Differential {
+    paths: Vec<PathBuf>,
+    top: String,
+    simulator: SvSimulator,
+    cycles: usize,
+    seed: u64,
+}
+
+impl Differential {
+    pub fn new<T: AsRef<Path>>(paths: &[T], top: &str) -> Self {
+        Differential {
+            paths: paths.iter().map(|x| x.as_ref().to_path_buf()).collect(),
+            top: top.to_string(),
+            simulator: SvSimulator::default(),
+            cycles: 100,
+            seed: 0,
+        }
+    }
+
+    pub fn simulator(mut self, simulator: SvSimulator) -> Self {
+        self.simulator = simulator;
+        self
+    }
+
+    pub fn cycles(mut self, cycles: usize) -> Self {
+        self.cycles = cycles;
+        self
+    }
+
+    /// Seed of the random input values
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Emitted design and testbench, without running the reference simulator
+    pub fn sources(&self) -> Result<DiffSources, ModelError> {
+        self.prepare().map(|(sources, _)| sources)
+    }
+
+    pub fn run(&self) -> Result<DiffReport, ModelError> {
+        let (sources, cycles) = self.prepare()?;
+        let output = self.simulate(&sources)?;
+
+        // 出力は "<サイクル> <信号> <16 進数の値>" の行
+        let mut reference: HashMap<(usize, &str), &str> = HashMap::new();
+        for line in output.lines() {
+            let mut fields = line.split_whitespace();
+            if let (Some(cycle), Some(signal), Some(value), None) =
+                (fields.next(), fields.next(), fields.next(), fields.next())
+                && let Ok(cycle) = cycle.parse()
+            {
+                reference.insert((cycle, signal), value);
+            }
+        }
+
+        let mut report = DiffReport {
+            cycles: cycles.len(),
+            ..Default::default()
+        };
+        for (i, cycle) in cycles.iter().enumerate() {
+            for (signal, actual) in &cycle.outputs {
+                let value = reference.get(&(i, signal.as_str())).ok_or_else(|| {
+                    ModelError::ReferenceFailed(format!("{signal} is missing at cycle {i}"))
+                })?;
+                let Some(expected) = parse_hex(value) else {
+                    report.unknowns += 1;
+                    continue;
+                };
+                report.compared += 1;
+                if expected != *actual {
+                    report.mismatches.push(DiffMismatch {
+                        cycle: i,
+                        signal: signal.clone(),
+                        expected,
+                        actual: *actual,
+                    });
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    // 設計を解析してモデルを実行し、同じ入力を与えるテストベンチを作る
+    fn prepare(&self) -> Result<(DiffSources, Vec<Cycle>), ModelError> {
+        let (mut model, design, module) = elaborate::isolated(|| {
+            let (metadata, parsers) = elaborate::parse_files(&self.paths)?;
+            let model = Model::with_build(&self.top, HashMap::new(), &metadata.build)?;
+            let mut design = String::new();
+            for (path, parser) in &parsers {
+                let mut emitter = Emitter::new(
+                    &metadata,
+                    path,
+                    &path.with_extension("sv"),
+                    &path.with_extension("sv.map"),
+                );
+                emitter.emit(PROJECT_NAME, &parser.veryl);
+                design.push_str(emitter.as_str());
+            }
+            let module = if metadata.build.omit_project_prefix {
+                self.top.clone()
+            } else {
+                format!("{PROJECT_NAME}_{}", self.top)
+            };
+            Ok::<_, ModelError>((model, design, module))
+        })?;
+
+        let clocks: Vec<_> = model.clocks().iter().map(|x| x.0.clone()).collect();
+        let resets: Vec<_> = model.resets().to_vec();
+        // 配列のポートは扱わない
+        let ports: Vec<_> = model
+            .signals()
+            .filter(|x| x.direction != Direction::Internal && !x.name.contains('['))
+            .collect();
+        let inputs: Vec<_> = ports
+            .iter()
+            .filter(|x| x.direction == Direction::Input)
+            .filter(|x| !clocks.contains(&x.name) && !resets.iter().any(|(y, _)| *y == x.name))
+            .map(|x| (x.name.clone(), x.width))
+            .collect();
+        let outputs: Vec<_> = ports
+            .iter()
+            .filter(|x| x.direction == Direction::Output)
+            .map(|x| (x.name.clone(), x.width))
+            .collect();
+
+        // モデル側: リセットをアサートしたまま全クロックのエッジを 1 回与える
+        model.set_reset(true);
+        for clock in &clocks {
+            model.clock_rise(clock);
+            model.clock_fall(clock);
+        }
+        model.set_reset(false);
+
+        let mut random = Random::new(self.seed);
+        let mut cycles = Vec::new();
+        for _ in 0..self.cycles {
+            let inputs: Vec<_> = inputs
+                .iter()
+                .map(|(name, width)| (name.clone(), random.next_u64() as usize & mask(*width)))
+                .collect();
+            for (name, value) in &inputs {
+                model.input(name, *value);
+            }
+            for clock in &clocks {
+                model.clock_rise(clock);
+                model.clock_fall(clock);
+            }
+            let outputs = outputs
+                .iter()
+                .map(|(name, width)| (name.clone(), model.get(name).unwrap_or(0) & mask(*width)))
+                .collect();
+            cycles.push(Cycle { inputs, outputs });
+        }
+
+        // テストベンチ側: 各操作を 1 時間単位ずつずらして同じ順序で与える
+        let mut testbench = String::from("module veryl_differential_tb;\n");
+        for port in &ports {
+            let range = if port.width > 1 {
+                format!(" [{}:0]", port.width - 1)
+            } else {
+                String::new()
+            };
+            let init = match resets.iter().find(|(name, _)| *name == port.name) {
+                Some((_, x)) => format!(" = {}", reset_level(*x, false)),
+                None if port.direction == Direction::Input => " = 0".to_string(),
+                None => String::new(),
+            };
+            let _ = writeln!(testbench, "    logic{range} {}{init};", port.name);
+        }
+        let connections: Vec<_> = ports
+            .iter()
+            .map(|x| format!("        .{0}({0})", x.name))
+            .collect();
+        let _ = writeln!(
+            testbench,
+            "    {module} dut (\n{}\n    );",
+            connections.join(",\n")
+        );
+        testbench.push_str("    initial begin\n");
+        for (name, x) in &resets {
+            let _ = writeln!(testbench, "        #1 {name} = {};", reset_level(*x, true));
+        }
+        let edges: String = clocks
+            .iter()
+            .map(|x| format!("        #1 {x} = 1;\n        #1 {x} = 0;\n"))
+            .collect();
+        testbench.push_str(&edges);
+        for (name, x) in &resets {
+            let _ = writeln!(testbench, "        #1 {name} = {};", reset_level(*x, false));
+        }
+        for (i, cycle) in cycles.iter().enumerate() {
+            for (name, value) in &cycle.inputs {
+                let width = inputs.iter().find(|x| x.0 == *name).map(|x| x.1);
+                let _ = writeln!(
+                    testbench,
+                    "        #1 {name} = {}'h{value:x};",
+                    width.unwrap_or(1)
+                );
+            }
+            testbench.push_str(&edges);
+            testbench.push_str("        #1;\n");
+            for (name, _) in &cycle.outputs {
+                let _ = writeln!(testbench, "        $display(\"{i} {name} %h\", {name});");
+            }
+        }
+        testbench.push_str("        $finish;\n    end\nendmodule\n");
+
+        Ok((DiffSources { design, testbench }, cycles))
+    }
+
+    // 作業ディレクトリでリファレンスのシミュレータを実行し、標準出力を返す
+    fn simulate(&self, sources: &DiffSources) -> Result<String, ModelError> {
+        let dir = std::env::temp_dir().join(format!(
+            "veryl_differential_{}_{}",
+            std::process::id(),
+            WORK_DIRS.fetch_add(1, Ordering::Relaxed)
+        ));
+        let failed = |x: std::io::Error| ModelError::ReferenceFailed(x.to_string());
+        std::fs::create_dir_all(&dir).map_err(failed)?;
+        let ret = (|| {
+            std::fs::write(dir.join("design.sv"), &sources.design).map_err(failed)?;
+            std::fs::write(dir.join("testbench.sv"), &sources.testbench).map_err(failed)?;
+            let files = ["design.sv", "testbench.sv"];
+            let top = "veryl_differential_tb";
+            match self.simulator {
+                SvSimulator::Icarus => {
+                    run(Command::new("iverilog")
+                        .args(["-g2012", "-s", top, "-o", "sim"])
+                        .args(files)
+                        .current_dir(&dir))?;
+                    run(Command::new("vvp").args(["-n", "sim"]).current_dir(&dir))
+                }
+                SvSimulator::Verilator => {
+                    run(Command::new("verilator")
+                        .args(["--binary", "-Wno-fatal", "--top-module", top])
+                        .args(files)
+                        .current_dir(&dir))?;
+                    run(Command::new(dir.join("obj_dir").join(format!("V{top}"))).current_dir(&dir))
+                }
+            }
+        })();
+        let _ = std::fs::remove_dir_all(&dir);
+        ret
+    }
+}
+
+fn run(command: &mut Command) -> Result<String, ModelError> {
+    let program = command.get_program().to_string_lossy().to_string();
+    let output = command
+        .output()
+        .map_err(|x| ModelError::ReferenceFailed(format!("{program}: {x}")))?;
+    if !output.status.success() {
+        return Err(ModelError::ReferenceFailed(format!(
+            "{program}: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn mask(width: usize) -> usize {
+    if width >= usize::BITS as usize {
+        usize::MAX
+    } else {
+        (1 << width) - 1
+    }
+}
+
+// %h の出力の下位ビット、x や z を含めば None
+fn parse_hex(value: &str) -> Option<usize> {
+    let digits = usize::BITS as usize / 4;
+    let value = &value[value.len().saturating_sub(digits)..];
+    usize::from_str_radix(value, 16).ok()
+}
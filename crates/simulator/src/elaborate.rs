@@ -0,0 +1,93 @@
+use crate::model_error::ModelError;
+use std::path::{Path, PathBuf};
+use veryl_analyzer::{Analyzer, AnalyzerError};
+use veryl_metadata::Metadata;
+use veryl_parser::Parser;
+
+// Project name used to analyze source files outside of a Veryl project
+pub(crate) const PROJECT_NAME: &str = "prj";
+
+// Run `f` with a fresh set of analyzer tables
+// the analyzer keeps its tables in thread-local storage, so elaborating on a dedicated thread
+// isolates the design from the tables of the calling thread and from the other designs
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub(crate) fn isolated<R: Send>(f: impl FnOnce() -> R + Send) -> R {
+    std::thread::scope(|s| match s.spawn(f).join() {
+        Ok(x) => x,
+        Err(x) => std::panic::resume_unwind(x),
+    })
+}
+
+// wasm32-unknown-unknown has no threads, so the tables of the calling thread are cleared instead
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub(crate) fn isolated<R: Send>(f: impl FnOnce() -> R + Send) -> R {
+    use veryl_analyzer::{attribute_table, msb_table, namespace_table, symbol_table, type_dag};
+
+    // Analyzer::clear と同じテーブルを消去する
+    attribute_table::clear();
+    msb_table::clear();
+    namespace_table::clear();
+    symbol_table::clear();
+    type_dag::clear();
+    f()
+}
+
+// Parse and analyze source files, and register them to the symbol table of the current thread
+pub(crate) fn analyze_files<T: AsRef<Path>>(paths: &[T]) -> Result<Metadata, ModelError> {
+    parse_files(paths).map(|(metadata, _)| metadata)
+}
+
+// Same as analyze_files, and returns the parsed sources for the emitter
+pub(crate) fn parse_files<T: AsRef<Path>>(
+    paths: &[T],
+) -> Result<(Metadata, Vec<(PathBuf, Parser)>), ModelError> {
+    let mut sources = Vec::new();
+    for path in paths {
+        let path = path.as_ref();
+        let code = std::fs::read_to_string(path).map_err(|x| ModelError::ReadFailed {
+            path: path.to_string_lossy().to_string(),
+            cause: x.to_string(),
+        })?;
+        sources.push((path.to_path_buf(), code));
+    }
+    parse_sources(&sources)
+}
+
+// Parse and analyze source texts, the paths are used in the error messages and the emitted code
+pub(crate) fn parse_sources(
+    sources: &[(PathBuf, String)],
+) -> Result<(Metadata, Vec<(PathBuf, Parser)>), ModelError> {
+    let metadata = Metadata::create_default(PROJECT_NAME)
+        .map_err(|x| ModelError::AnalyzeFailed(x.to_string()))?;
+    let analyzer = Analyzer::new(&metadata);
+
+    let mut parsers = Vec::new();
+    for (path, code) in sources {
+        let parser = Parser::parse(code, path).map_err(|x| ModelError::ParseFailed {
+            path: path.to_string_lossy().to_string(),
+            cause: x.to_string(),
+        })?;
+        check(analyzer.analyze_pass1(PROJECT_NAME, path, &parser.veryl))?;
+        parsers.push((path.clone(), parser));
+    }
+    check(Analyzer::analyze_post_pass1())?;
+
+    for (path, parser) in &parsers {
+        check(analyzer.analyze_pass2(PROJECT_NAME, path, &parser.veryl))?;
+    }
+    let info = Analyzer::analyze_post_pass2();
+
+    for (path, parser) in &parsers {
+        check(analyzer.analyze_pass3(PROJECT_NAME, path, &parser.veryl, &info))?;
+    }
+
+    Ok((metadata, parsers))
+}
+
+// Warnings are ignored, and the first error is reported
+fn check(errors: Vec<AnalyzerError>) -> Result<(), ModelError> {
+    match errors.into_iter().find(|x| x.is_error()) {
+        Some(x) => Err(ModelError::AnalyzeFailed(x.to_string())),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,97 @@
+use crate::Model;
+use std::collections::BTreeMap;
+
+pub(crate) type StimulusFn = Box<dyn FnMut(&mut Model) + Send>;
+
+// シミュレーションのイベント
+pub(crate) enum Event {
+    // 予約された入力操作と繰り返し周期 [ns]
+    Stimulus(StimulusFn, Option<u64>),
+    // 遅延を指定した入力
+    Input(String, usize),
+    // クロックの次のエッジ
+    Clock(String),
+}
+
+impl Event {
+    // 同時刻のイベントは入力、クロックの順に処理する
+    fn rank(&self) -> u8 {
+        match self {
+            Event::Stimulus(_, _) | Event::Input(_, _) => 0,
+            Event::Clock(_) => 1,
+        }
+    }
+}
+
+// 時刻の順に並べたイベント
+// 同じ時刻・同じ種類のイベントは、クロックはクロックの順序、それ以外は追加した順に並べる
+#[derive(Default)]
+pub(crate) struct EventQueue {
+    events: BTreeMap<(u64, u8, u64), Event>,
+    sequence: u64,
+}
+
+impl EventQueue {
+    pub(crate) fn push(&mut self, time: u64, event: Event) {
+        self.sequence += 1;
+        self.events
+            .insert((time, event.rank(), self.sequence), event);
+    }
+
+    // order はクロックの順序における位置
+    pub(crate) fn push_clock(&mut self, time: u64, clock: String, order: usize) {
+        let event = Event::Clock(clock);
+        self.events
+            .insert((time, event.rank(), order as u64), event);
+    }
+
+    // 次のイベントの時刻
+    pub(crate) fn next_time(&self) -> Option<u64> {
+        self.events.keys().next().map(|(time, _, _)| *time)
+    }
+
+    // 次のイベントが入力操作であればその時刻
+    pub(crate) fn next_stimulus(&self) -> Option<u64> {
+        self.events
+            .iter()
+            .next()
+            .filter(|(_, x)| matches!(x, Event::Stimulus(_, _) | Event::Input(_, _)))
+            .map(|((time, _, _), _)| *time)
+    }
+
+    // クロックの次のエッジの時刻
+    pub(crate) fn clock_time(&self, clock: &str) -> Option<u64> {
+        self.events
+            .iter()
+            .find(|(_, x)| matches!(x, Event::Clock(name) if name == clock))
+            .map(|((time, _, _), _)| *time)
+    }
+
+    // 時刻 time 以前の入力操作を取り出す
+    pub(crate) fn pop_stimulus(&mut self, time: u64) -> Option<Event> {
+        let key = self
+            .events
+            .iter()
+            .take_while(|((x, _, _), _)| *x <= time)
+            .find(|(_, x)| matches!(x, Event::Stimulus(_, _) | Event::Input(_, _)))
+            .map(|(key, _)| *key)?;
+        self.events.remove(&key)
+    }
+
+    // 次のクロックエッジを取り出す
+    pub(crate) fn pop_clock(&mut self) -> Option<(u64, String)> {
+        let key = self
+            .events
+            .iter()
+            .find(|(_, x)| matches!(x, Event::Clock(_)))
+            .map(|(key, _)| *key)?;
+        match self.events.remove(&key) {
+            Some(Event::Clock(x)) => Some((key.0, x)),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn clear_clocks(&mut self) {
+        self.events.retain(|_, x| !matches!(x, Event::Clock(_)));
+    }
+}
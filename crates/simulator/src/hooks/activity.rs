@@ -0,0 +1,314 @@
+use super::{Hook, HookAction, HookError, SignalFilter};
+use crate::Model;
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::sync::{Arc, Mutex};
+
+/// Time at 0 / 1 and the number of toggles of a bit, like T0 / T1 / TC of SAIF
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BitActivity {
+    pub t0: u64,
+    pub t1: u64,
+    pub tc: u64,
+}
+
+/// Switching activity of a signal
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SignalActivity {
+    pub name: String,
+    pub width: usize,
+    /// Number of value changes
+    pub changes: u64,
+    /// Number of bit toggles, a change of a wide signal counts each flipped bit
+    pub toggles: u64,
+    /// Indexed by the bit position
+    pub bits: Vec<BitActivity>,
+}
+
+impl SignalActivity {
+    /// Average toggles per bit per cycle
+    pub fn toggle_rate(&self, cycles: u64) -> f64 {
+        if self.width == 0 || cycles == 0 {
+            return 0.0;
+        }
+        self.toggles as f64 / (self.width as f64 * cycles as f64)
+    }
+}
+
+/// Switching activity of the signals, a proxy of the dynamic power
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ActivityReport {
+    pub module: String,
+    /// Duration of the measurement in ns
+    pub duration: u64,
+    /// Number of rising edges of the clock
+    pub cycles: u64,
+    /// Signals in the order of the names
+    pub signals: Vec<SignalActivity>,
+}
+
+impl ActivityReport {
+    pub fn total_toggles(&self) -> u64 {
+        self.signals.iter().map(|x| x.toggles).sum()
+    }
+
+    pub fn signal(&self, name: &str) -> Option<&SignalActivity> {
+        self.signals.iter().find(|x| x.name == name)
+    }
+
+    /// Report in a SAIF-like format with T0 / T1 / TC of each bit
+    pub fn to_saif(&self) -> String {
+        let mut ret = String::new();
+        let _ = writeln!(ret, "(SAIFILE");
+        let _ = writeln!(ret, "  (SAIFVERSION \"2.0\")");
+        let _ = writeln!(ret, "  (DIRECTION \"backward\")");
+        let _ = writeln!(ret, "  (DESIGN \"{}\")", self.module);
+        let _ = writeln!(ret, "  (PROGRAM_NAME \"Veryl Simulator\")");
+        let _ = writeln!(ret, "  (TIMESCALE 1 ns)");
+        let _ = writeln!(ret, "  (DURATION {})", self.duration);
+        let _ = writeln!(ret, "  (INSTANCE {}", self.module);
+        let _ = writeln!(ret, "    (NET");
+        for signal in &self.signals {
+            for (i, x) in signal.bits.iter().enumerate() {
+                let name = if signal.width == 1 {
+                    signal.name.clone()
+                } else {
+                    format!("{}\\[{i}\\]", signal.name)
+                };
+                let _ = writeln!(
+                    ret,
+                    "      ({name} (T0 {}) (T1 {}) (TC {}))",
+                    x.t0, x.t1, x.tc
+                );
+            }
+        }
+        let _ = writeln!(ret, "    )");
+        let _ = writeln!(ret, "  )");
+        let _ = writeln!(ret, ")");
+        ret
+    }
+}
+
+impl fmt::Display for ActivityReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} toggles in {}ns ({} cycles)",
+            self.total_toggles(),
+            self.duration,
+            self.cycles
+        )?;
+        // 活性の高い順に並べる
+        let mut signals: Vec<_> = self.signals.iter().collect();
+        signals.sort_by(|x, y| y.toggles.cmp(&x.toggles).then(x.name.cmp(&y.name)));
+        for x in signals {
+            write!(
+                f,
+                "\n  {}: {} toggles, {} changes, {:.3} per bit per cycle",
+                x.name,
+                x.toggles,
+                x.changes,
+                x.toggle_rate(self.cycles)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+struct Tracked {
+    activity: SignalActivity,
+    value: usize,
+    since: u64, // 現在の値になった時刻
+}
+
+impl Tracked {
+    // 現在の値の期間を T0 / T1 に加える
+    fn close(&mut self, time: u64) {
+        let duration = time.saturating_sub(self.since);
+        for (i, bit) in self.activity.bits.iter_mut().enumerate() {
+            if (self.value >> i) & 1 == 1 {
+                bit.t1 += duration;
+            } else {
+                bit.t0 += duration;
+            }
+        }
+        self.since = time;
+    }
+}
+
+// This hook accumulates the switching activity of the signals except clocks
+// cycles are counted at rising edges of the clock, the first clock of the model by default
+pub struct ActivityHook {
+    filter: SignalFilter,
+    clock: Option<String>,
+    signals: BTreeMap<String, Tracked>,
+    start: Option<u64>,
+    cycles: u64,
+    report: Arc<Mutex<ActivityReport>>,
+}
+
+impl Default for ActivityHook {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ActivityHook {
+    pub fn new() -> Self {
+        ActivityHook {
+            filter: SignalFilter::default(),
+            clock: None,
+            signals: BTreeMap::new(),
+            start: None,
+            cycles: 0,
+            report: Arc::new(Mutex::new(ActivityReport::default())),
+        }
+    }
+
+    /// Record only the signals selected by the glob patterns
+    /// see `SignalFilter` for the syntax
+    pub fn filter(mut self, patterns: &[&str]) -> Self {
+        self.filter = SignalFilter::new(patterns);
+        self
+    }
+
+    /// Clock whose rising edges are counted as cycles
+    pub fn clock(mut self, clock: &str) -> Self {
+        self.clock = Some(clock.to_string());
+        self
+    }
+
+    /// Shared report updated at every cycle and at the end of the simulation
+    /// it can be read while the hook is owned by the simulator
+    pub fn report(&self) -> Arc<Mutex<ActivityReport>> {
+        self.report.clone()
+    }
+
+    // 最初に通知された時刻から計測する
+    fn begin(&mut self, time: u64) -> u64 {
+        if self.start.is_none() {
+            self.start = Some(time);
+            for x in self.signals.values_mut() {
+                x.since = time;
+            }
+        }
+        self.start.unwrap_or(time)
+    }
+
+    fn change(&mut self, time: u64, name: &str, old: usize, new: usize) {
+        self.begin(time);
+        let Some(x) = self.signals.get_mut(name) else {
+            return;
+        };
+        x.close(time);
+        let flipped = old ^ new;
+        for (i, bit) in x.activity.bits.iter_mut().enumerate() {
+            if (flipped >> i) & 1 == 1 {
+                bit.tc += 1;
+                x.activity.toggles += 1;
+            }
+        }
+        x.activity.changes += 1;
+        x.value = new;
+    }
+
+    fn update(&mut self, time: u64) {
+        let start = self.begin(time);
+        let mut report = self.report.lock().unwrap();
+        report.duration = time.saturating_sub(start);
+        report.cycles = self.cycles;
+        report.signals = self
+            .signals
+            .values()
+            .map(|x| {
+                let mut x = Tracked {
+                    activity: x.activity.clone(),
+                    value: x.value,
+                    since: x.since,
+                };
+                x.close(time);
+                x.activity
+            })
+            .collect();
+    }
+}
+
+impl Hook for ActivityHook {
+    fn on_start(&mut self, model: &Model) -> Result<(), HookError> {
+        // 再スタート時は集計を保持する
+        if !self.signals.is_empty() {
+            return Ok(());
+        }
+        if self.clock.is_none() {
+            self.clock = model.clocks().first().map(|(x, _)| x.clone());
+        }
+        let clocks: Vec<_> = model.clocks().iter().map(|(x, _)| x.clone()).collect();
+        for x in model.signals() {
+            if clocks.contains(&x.name) || !self.filter.matches(model.module_name(), &x.name) {
+                continue;
+            }
+            let width = x.width.min(usize::BITS as usize);
+            let activity = SignalActivity {
+                name: x.name.clone(),
+                width,
+                bits: vec![BitActivity::default(); width],
+                ..Default::default()
+            };
+            self.signals.insert(
+                x.name,
+                Tracked {
+                    activity,
+                    value: x.value,
+                    since: 0,
+                },
+            );
+        }
+        self.report.lock().unwrap().module = model.module_name().to_string();
+        Ok(())
+    }
+
+    fn on_input_change(
+        &mut self,
+        time: u64,
+        name: &str,
+        old: usize,
+        new: usize,
+    ) -> Result<HookAction, HookError> {
+        self.change(time, name, old, new);
+        Ok(HookAction::Continue)
+    }
+
+    fn on_signal_change(
+        &mut self,
+        time: u64,
+        name: &str,
+        old: usize,
+        new: usize,
+    ) -> Result<HookAction, HookError> {
+        self.change(time, name, old, new);
+        Ok(HookAction::Continue)
+    }
+
+    fn post_clock(
+        &mut self,
+        time: u64,
+        clock_name: &str,
+        _model: &Model,
+    ) -> Result<HookAction, HookError> {
+        if self.clock.as_deref() == Some(clock_name) {
+            self.cycles += 1;
+            self.update(time);
+        }
+        Ok(HookAction::Continue)
+    }
+
+    fn on_reset(&mut self, time: u64, _model: &Model) -> Result<(), HookError> {
+        self.begin(time);
+        Ok(())
+    }
+
+    fn on_finish(&mut self, time: u64, _model: &Model) -> Result<(), HookError> {
+        self.update(time);
+        Ok(())
+    }
+}
@@ -0,0 +1,374 @@
+use super::{Hook, HookAction, HookError};
+use crate::Model;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// Access requested to an APB master
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApbRequest {
+    Read(usize),
+    Write(usize, usize),
+}
+
+/// Completed APB transfer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApbTransfer {
+    /// Time of the rising edge where PREADY was sampled
+    pub time: u64,
+    pub write: bool,
+    pub addr: usize,
+    /// PWDATA for writes, PRDATA for reads
+    pub data: usize,
+    /// PSLVERR
+    pub error: bool,
+}
+
+impl fmt::Display for ApbTransfer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.write {
+            write!(
+                f,
+                "{}ns: write 0x{:x} = 0x{:x}",
+                self.time, self.addr, self.data
+            )?;
+        } else {
+            write!(
+                f,
+                "{}ns: read 0x{:x} -> 0x{:x}",
+                self.time, self.addr, self.data
+            )?;
+        }
+        if self.error {
+            write!(f, " (error)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Transfers observed by an APB master or slave
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ApbReport {
+    pub transfers: Vec<ApbTransfer>,
+}
+
+impl ApbReport {
+    /// Data of the completed reads in the order of completion
+    pub fn reads(&self) -> Vec<usize> {
+        self.transfers
+            .iter()
+            .filter(|x| !x.write)
+            .map(|x| x.data)
+            .collect()
+    }
+}
+
+impl fmt::Display for ApbReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} transfers", self.transfers.len())?;
+        for transfer in &self.transfers {
+            write!(f, "\n  {transfer}")?;
+        }
+        Ok(())
+    }
+}
+
+// APB の信号名（接頭辞付き）
+struct ApbPorts {
+    prefix: String,
+}
+
+impl ApbPorts {
+    fn name(&self, signal: &str) -> String {
+        format!("{}{signal}", self.prefix)
+    }
+
+    fn get(&self, model: &Model, signal: &str) -> Result<usize, HookError> {
+        let name = self.name(signal);
+        model
+            .peek(&name)
+            .ok_or_else(|| HookError::Other(format!("signal \"{name}\" is not found")))
+    }
+
+    fn set(&self, model: &mut Model, signal: &str, value: usize) -> Result<(), HookError> {
+        model
+            .try_input(&self.name(signal), value)
+            .map_err(|x| HookError::Other(x.to_string()))
+    }
+}
+
+enum MasterPhase {
+    Idle,
+    Setup(ApbRequest),
+    Access(ApbRequest),
+}
+
+// This hook drives the master side of an APB interface, and issues the queued requests in order
+// PSEL/PENABLE/PWRITE/PADDR/PWDATA are driven after rising edges, PRDATA/PREADY/PSLVERR are sampled before them
+pub struct ApbMaster {
+    clock: String,
+    ports: ApbPorts,
+    requests: VecDeque<ApbRequest>,
+    phase: MasterPhase,
+    report: Arc<Mutex<ApbReport>>,
+}
+
+impl ApbMaster {
+    pub fn new(clock: &str) -> Self {
+        ApbMaster {
+            clock: clock.to_string(),
+            ports: ApbPorts {
+                prefix: String::new(),
+            },
+            requests: VecDeque::new(),
+            phase: MasterPhase::Idle,
+            report: Arc::new(Mutex::new(ApbReport::default())),
+        }
+    }
+
+    /// Prefix of the signal names, e.g. "s_" for `s_psel`
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.ports.prefix = prefix.to_string();
+        self
+    }
+
+    pub fn write(mut self, addr: usize, data: usize) -> Self {
+        self.push(ApbRequest::Write(addr, data));
+        self
+    }
+
+    pub fn read(mut self, addr: usize) -> Self {
+        self.push(ApbRequest::Read(addr));
+        self
+    }
+
+    /// Queue a request while the hook is owned by the simulator, see `Simulator::with_hook_mut`
+    pub fn push(&mut self, request: ApbRequest) {
+        self.requests.push_back(request);
+    }
+
+    /// All requests are completed
+    pub fn is_idle(&self) -> bool {
+        self.requests.is_empty() && matches!(self.phase, MasterPhase::Idle)
+    }
+
+    /// Shared report updated at every completed transfer
+    pub fn report(&self) -> Arc<Mutex<ApbReport>> {
+        self.report.clone()
+    }
+}
+
+impl Hook for ApbMaster {
+    fn pre_clock(
+        &mut self,
+        time: u64,
+        clock_name: &str,
+        model: &Model,
+    ) -> Result<HookAction, HookError> {
+        if clock_name != self.clock {
+            return Ok(HookAction::Continue);
+        }
+        let MasterPhase::Access(request) = self.phase else {
+            return Ok(HookAction::Continue);
+        };
+        if self.ports.get(model, "pready")? == 0 {
+            return Ok(HookAction::Continue);
+        }
+
+        // PSLVERR は省略可能
+        let error = model.peek(&self.ports.name("pslverr")).unwrap_or(0) != 0;
+        let transfer = match request {
+            ApbRequest::Read(addr) => ApbTransfer {
+                time,
+                write: false,
+                addr,
+                data: self.ports.get(model, "prdata")?,
+                error,
+            },
+            ApbRequest::Write(addr, data) => ApbTransfer {
+                time,
+                write: true,
+                addr,
+                data,
+                error,
+            },
+        };
+        self.report.lock().unwrap().transfers.push(transfer);
+        self.phase = MasterPhase::Idle;
+        Ok(HookAction::Continue)
+    }
+
+    fn drive(
+        &mut self,
+        _time: u64,
+        clock_name: &str,
+        model: &mut Model,
+    ) -> Result<HookAction, HookError> {
+        if clock_name != self.clock {
+            return Ok(HookAction::Continue);
+        }
+        match self.phase {
+            MasterPhase::Idle => {
+                if let Some(request) = self.requests.pop_front() {
+                    let (write, addr) = match request {
+                        ApbRequest::Read(addr) => (0, addr),
+                        ApbRequest::Write(addr, data) => {
+                            self.ports.set(model, "pwdata", data)?;
+                            (1, addr)
+                        }
+                    };
+                    self.ports.set(model, "paddr", addr)?;
+                    self.ports.set(model, "pwrite", write)?;
+                    self.ports.set(model, "psel", 1)?;
+                    self.ports.set(model, "penable", 0)?;
+                    self.phase = MasterPhase::Setup(request);
+                } else {
+                    self.ports.set(model, "psel", 0)?;
+                    self.ports.set(model, "penable", 0)?;
+                }
+            }
+            MasterPhase::Setup(request) => {
+                self.ports.set(model, "penable", 1)?;
+                self.phase = MasterPhase::Access(request);
+            }
+            // PREADY を待つ
+            MasterPhase::Access(_) => {}
+        }
+        Ok(HookAction::Continue)
+    }
+
+    fn on_reset(&mut self, _time: u64, _model: &Model) -> Result<(), HookError> {
+        self.phase = MasterPhase::Idle;
+        Ok(())
+    }
+}
+
+// This hook responds to an APB master in the design as a memory
+// PRDATA/PREADY are driven after rising edges, and writes are committed at the edge of the completion
+pub struct ApbSlave {
+    clock: String,
+    ports: ApbPorts,
+    wait_states: usize,
+    remaining: usize,
+    memory: Arc<Mutex<BTreeMap<usize, usize>>>,
+    report: Arc<Mutex<ApbReport>>,
+}
+
+impl ApbSlave {
+    pub fn new(clock: &str) -> Self {
+        ApbSlave {
+            clock: clock.to_string(),
+            ports: ApbPorts {
+                prefix: String::new(),
+            },
+            wait_states: 0,
+            remaining: 0,
+            memory: Arc::new(Mutex::new(BTreeMap::new())),
+            report: Arc::new(Mutex::new(ApbReport::default())),
+        }
+    }
+
+    /// Prefix of the signal names, e.g. "s_" for `s_psel`
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.ports.prefix = prefix.to_string();
+        self
+    }
+
+    /// Cycles with PREADY low in each access phase
+    pub fn wait_states(mut self, cycles: usize) -> Self {
+        self.wait_states = cycles;
+        self
+    }
+
+    /// Initial value of the memory
+    pub fn preload(self, addr: usize, data: usize) -> Self {
+        self.memory.lock().unwrap().insert(addr, data);
+        self
+    }
+
+    /// Shared memory, unwritten addresses read as 0
+    pub fn memory(&self) -> Arc<Mutex<BTreeMap<usize, usize>>> {
+        self.memory.clone()
+    }
+
+    /// Shared report updated at every completed transfer
+    pub fn report(&self) -> Arc<Mutex<ApbReport>> {
+        self.report.clone()
+    }
+}
+
+impl Hook for ApbSlave {
+    fn pre_clock(
+        &mut self,
+        time: u64,
+        clock_name: &str,
+        model: &Model,
+    ) -> Result<HookAction, HookError> {
+        if clock_name != self.clock {
+            return Ok(HookAction::Continue);
+        }
+        let psel = self.ports.get(model, "psel")? != 0;
+        let penable = self.ports.get(model, "penable")? != 0;
+        let pready = self.ports.get(model, "pready")? != 0;
+        if !(psel && penable && pready) {
+            return Ok(HookAction::Continue);
+        }
+
+        let write = self.ports.get(model, "pwrite")? != 0;
+        let addr = self.ports.get(model, "paddr")?;
+        let data = if write {
+            let data = self.ports.get(model, "pwdata")?;
+            self.memory.lock().unwrap().insert(addr, data);
+            data
+        } else {
+            self.ports.get(model, "prdata")?
+        };
+        self.report.lock().unwrap().transfers.push(ApbTransfer {
+            time,
+            write,
+            addr,
+            data,
+            error: false,
+        });
+        Ok(HookAction::Continue)
+    }
+
+    fn drive(
+        &mut self,
+        _time: u64,
+        clock_name: &str,
+        model: &mut Model,
+    ) -> Result<HookAction, HookError> {
+        if clock_name != self.clock {
+            return Ok(HookAction::Continue);
+        }
+        let psel = self.ports.get(model, "psel")? != 0;
+        let penable = self.ports.get(model, "penable")? != 0;
+
+        let pready = match (psel, penable) {
+            // セットアップフェーズでウェイト数を設定する
+            (true, false) => {
+                self.remaining = self.wait_states;
+                self.wait_states == 0
+            }
+            (true, true) => {
+                let ready = self.remaining == 0;
+                self.remaining = self.remaining.saturating_sub(1);
+                ready
+            }
+            _ => false,
+        };
+        if psel && self.ports.get(model, "pwrite")? == 0 {
+            let addr = self.ports.get(model, "paddr")?;
+            let data = self.memory.lock().unwrap().get(&addr).copied();
+            self.ports.set(model, "prdata", data.unwrap_or(0))?;
+        }
+        self.ports.set(model, "pready", pready as usize)?;
+        Ok(HookAction::Continue)
+    }
+
+    fn on_reset(&mut self, _time: u64, _model: &Model) -> Result<(), HookError> {
+        self.remaining = 0;
+        Ok(())
+    }
+}
@@ -0,0 +1,171 @@
+use super::{Hook, HookAction, HookError};
+use crate::Model;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+type Check = Box<dyn FnMut(&Model) -> bool + Send>;
+
+struct Assertion {
+    name: String,
+    after: usize, // checked from this number of cycles after reset
+    check: Check,
+}
+
+/// Failed assertion
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssertFailure {
+    pub time: u64,
+    pub name: String,
+}
+
+impl fmt::Display for AssertFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "assertion \"{}\" failed at {}ns", self.name, self.time)
+    }
+}
+
+/// Result of the assertions
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AssertReport {
+    /// Number of evaluated assertions
+    pub checks: usize,
+    pub failures: Vec<AssertFailure>,
+}
+
+impl AssertReport {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+impl fmt::Display for AssertReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} checks, {} failures",
+            self.checks,
+            self.failures.len()
+        )?;
+        for failure in &self.failures {
+            write!(f, "\n  {failure}")?;
+        }
+        Ok(())
+    }
+}
+
+// This hook checks immediate assertions over the model after every rising edge
+// failures are collected into the report, and the action is requested to the simulator
+pub struct AssertHook {
+    assertions: Vec<Assertion>,
+    clock: Option<String>, // checked clock, all clocks if None
+    cycles: usize,         // rising edges since reset
+    action: HookAction,
+    report: Arc<Mutex<AssertReport>>,
+}
+
+impl Default for AssertHook {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AssertHook {
+    pub fn new() -> Self {
+        AssertHook {
+            assertions: Vec::new(),
+            clock: None,
+            cycles: 0,
+            action: HookAction::Continue,
+            report: Arc::new(Mutex::new(AssertReport::default())),
+        }
+    }
+
+    /// Assert that the predicate is true at every edge
+    pub fn assert<F>(self, name: &str, check: F) -> Self
+    where
+        F: FnMut(&Model) -> bool + Send + 'static,
+    {
+        self.assert_after(name, 0, check)
+    }
+
+    /// Assert that the signal equals the value at every edge
+    pub fn assert_eq(self, name: &str, signal: &str, value: usize) -> Self {
+        self.assert_eq_after(name, 0, signal, value)
+    }
+
+    /// Assert that the predicate is true at every edge after `cycles` cycles from reset
+    pub fn assert_after<F>(mut self, name: &str, cycles: usize, check: F) -> Self
+    where
+        F: FnMut(&Model) -> bool + Send + 'static,
+    {
+        self.assertions.push(Assertion {
+            name: name.to_string(),
+            after: cycles,
+            check: Box::new(check),
+        });
+        self
+    }
+
+    /// Assert that the signal equals the value at every edge after `cycles` cycles from reset
+    pub fn assert_eq_after(self, name: &str, cycles: usize, signal: &str, value: usize) -> Self {
+        let signal = signal.to_string();
+        self.assert_after(name, cycles, move |model| {
+            model.peek(&signal) == Some(value)
+        })
+    }
+
+    /// Check only at the rising edges of the clock
+    pub fn clock(mut self, clock: &str) -> Self {
+        self.clock = Some(clock.to_string());
+        self
+    }
+
+    /// Action requested to the simulator on failures, `HookAction::Continue` by default
+    /// use `HookAction::Abort` to stop the run at the first failure
+    pub fn action(mut self, action: HookAction) -> Self {
+        self.action = action;
+        self
+    }
+
+    /// Shared report of the assertions
+    /// it can be read while the hook is owned by the simulator
+    pub fn report(&self) -> Arc<Mutex<AssertReport>> {
+        self.report.clone()
+    }
+}
+
+impl Hook for AssertHook {
+    fn post_clock(
+        &mut self,
+        time: u64,
+        clock_name: &str,
+        model: &Model,
+    ) -> Result<HookAction, HookError> {
+        if self.clock.as_deref().is_some_and(|x| x != clock_name) {
+            return Ok(HookAction::Continue);
+        }
+        self.cycles += 1;
+
+        let mut action = HookAction::Continue;
+        let mut report = self.report.lock().unwrap();
+        for assertion in &mut self.assertions {
+            if self.cycles < assertion.after {
+                continue;
+            }
+            report.checks += 1;
+            if !(assertion.check)(model) {
+                report.failures.push(AssertFailure {
+                    time,
+                    name: assertion.name.clone(),
+                });
+                action = self.action;
+            }
+        }
+        Ok(action)
+    }
+
+    fn on_reset(&mut self, _time: u64, _model: &Model) -> Result<(), HookError> {
+        self.cycles = 0;
+        Ok(())
+    }
+}
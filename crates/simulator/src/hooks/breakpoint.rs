@@ -1,18 +1,268 @@
-use super::Hook;
+use super::{Hook, HookAction, HookError};
+use crate::path;
+use crate::{Model, ModelError};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+type Predicate = Box<dyn FnMut(&[usize]) -> bool + Send>;
+
+// Condition of a breakpoint
+enum BreakCondition {
+    Equals(String, usize),
+    ChangesTo(String, usize),
+    Changes(String),
+    Expression(Vec<String>, Predicate),
+    Time(u64),
+}
+
+impl BreakCondition {
+    // 条件に書かれたパス
+    fn paths(&self) -> Vec<&String> {
+        match self {
+            BreakCondition::Equals(x, _)
+            | BreakCondition::ChangesTo(x, _)
+            | BreakCondition::Changes(x) => vec![x],
+            BreakCondition::Expression(x, _) => x.iter().collect(),
+            BreakCondition::Time(_) => Vec::new(),
+        }
+    }
+
+    // 参照する信号（グロブはモデルの信号に展開する）
+    fn signals(&self, model: &Model) -> Vec<String> {
+        self.paths()
+            .into_iter()
+            .flat_map(|x| expand(model, x))
+            .collect()
+    }
+}
+
+fn expand(model: &Model, path: &str) -> Vec<String> {
+    if path::is_glob(path) {
+        model.select(path).unwrap_or_default()
+    } else {
+        vec![path.to_string()]
+    }
+}
+
+impl fmt::Display for BreakCondition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BreakCondition::Equals(x, value) => write!(f, "{x} == {value}"),
+            BreakCondition::ChangesTo(x, value) => write!(f, "{x} changes to {value}"),
+            BreakCondition::Changes(x) => write!(f, "{x} changes"),
+            BreakCondition::Expression(x, _) => write!(f, "expression over {}", x.join(", ")),
+            BreakCondition::Time(x) => write!(f, "time >= {x}"),
+        }
+    }
+}
+
+struct Entry {
+    condition: BreakCondition,
+    active: bool, // result of the last evaluation, a breakpoint triggers when it becomes true
+}
+
+/// Report of a triggered breakpoint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BreakHit {
+    pub time: u64,
+    /// Description of the triggered condition
+    pub condition: String,
+    /// Values of the signals referenced by the condition
+    pub values: Vec<(String, usize)>,
+}
+
+impl fmt::Display for BreakHit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "break at {}ns: {}", self.time, self.condition)?;
+        if !self.values.is_empty() {
+            let values: Vec<_> = self
+                .values
+                .iter()
+                .map(|(x, y)| format!("{x}={y}"))
+                .collect();
+            write!(f, " ({})", values.join(", "))?;
+        }
+        Ok(())
+    }
+}
 
 // This hook traps the simulation when a specific condition is met
 // useful for debugging
+// conditions are evaluated after every clock edge, and trigger when they become true
+// a glob pattern like "regs[*]" in a condition on a signal triggers on any matched signal
 pub struct BreakPoint {
-    // TODO: Implement conditional breakpoints
+    entries: Vec<Entry>,
+    action: HookAction,
+    previous: HashMap<String, usize>, // values at the last evaluation
+    hits: Arc<Mutex<Vec<BreakHit>>>,
+}
+
+impl Default for BreakPoint {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl BreakPoint {
-    #[allow(dead_code)]
     pub fn new() -> Self {
-        BreakPoint {}
+        BreakPoint {
+            entries: Vec::new(),
+            action: HookAction::Pause,
+            previous: HashMap::new(),
+            hits: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Break when the signal equals the value
+    pub fn when_equals(self, signal: &str, value: usize) -> Self {
+        self.add(BreakCondition::Equals(signal.to_string(), value))
+    }
+
+    /// Break when the signal changes from another value to the value
+    pub fn when_changes_to(self, signal: &str, value: usize) -> Self {
+        self.add(BreakCondition::ChangesTo(signal.to_string(), value))
+    }
+
+    /// Break when the signal changes
+    pub fn when_changes(self, signal: &str) -> Self {
+        self.add(BreakCondition::Changes(signal.to_string()))
+    }
+
+    /// Break when the predicate over the values of the signals is true
+    /// the values are passed in the order of `signals`
+    pub fn when<F>(self, signals: &[&str], predicate: F) -> Self
+    where
+        F: FnMut(&[usize]) -> bool + Send + 'static,
+    {
+        let signals = signals.iter().map(|x| x.to_string()).collect();
+        self.add(BreakCondition::Expression(signals, Box::new(predicate)))
+    }
+
+    /// Break at the first clock edge at or after the time
+    pub fn at_time(self, time: u64) -> Self {
+        self.add(BreakCondition::Time(time))
+    }
+
+    /// Action requested to the simulator on trigger, `HookAction::Pause` by default
+    pub fn action(mut self, action: HookAction) -> Self {
+        self.action = action;
+        self
+    }
+
+    /// Shared list of the triggered breakpoints
+    /// it can be read while the hook is owned by the simulator
+    pub fn hits(&self) -> Arc<Mutex<Vec<BreakHit>>> {
+        self.hits.clone()
+    }
+
+    fn add(mut self, condition: BreakCondition) -> Self {
+        self.entries.push(Entry {
+            condition,
+            active: false,
+        });
+        self
+    }
+
+    fn evaluate(&mut self, time: u64, model: &Model) -> HookAction {
+        let mut action = HookAction::Continue;
+        for entry in &mut self.entries {
+            let value = |x: &String| model.peek(x);
+            let result = match &mut entry.condition {
+                BreakCondition::Equals(x, v) => {
+                    expand(model, x).iter().any(|x| value(x) == Some(*v))
+                }
+                BreakCondition::ChangesTo(x, v) => expand(model, x)
+                    .iter()
+                    .any(|x| value(x) == Some(*v) && self.previous.get(x).is_some_and(|y| y != v)),
+                BreakCondition::Changes(x) => expand(model, x)
+                    .iter()
+                    .any(|x| value(x).is_some() && self.previous.get(x).copied() != value(x)),
+                BreakCondition::Expression(x, f) => {
+                    let values: Option<Vec<_>> = x.iter().map(value).collect();
+                    values.is_some_and(|x| f(&x))
+                }
+                BreakCondition::Time(x) => time >= *x,
+            };
+
+            // 変化を見る条件は毎回成立し直せる
+            let edge = match entry.condition {
+                BreakCondition::ChangesTo(..) | BreakCondition::Changes(_) => result,
+                _ => result && !entry.active,
+            };
+            entry.active = result;
+
+            if edge {
+                let values = entry
+                    .condition
+                    .signals(model)
+                    .into_iter()
+                    .filter_map(|x| value(&x).map(|v| (x, v)))
+                    .collect();
+                self.hits.lock().unwrap().push(BreakHit {
+                    time,
+                    condition: entry.condition.to_string(),
+                    values,
+                });
+                action = self.action;
+            }
+        }
+
+        self.update_previous(model);
+        action
+    }
+
+    fn update_previous(&mut self, model: &Model) {
+        for entry in &self.entries {
+            for name in entry.condition.signals(model) {
+                if let Some(value) = model.peek(&name) {
+                    self.previous.insert(name, value);
+                }
+            }
+        }
     }
 }
 
 impl Hook for BreakPoint {
-    // TODO: Implement hook methods for conditional breakpoints
+    fn on_start(&mut self, model: &Model) -> Result<(), HookError> {
+        // 存在しない信号の条件は成立しないので、書き間違いとして報告する
+        for entry in &self.entries {
+            for path in entry.condition.paths() {
+                if let Err(ModelError::PathNotFound { path, hint }) = model.select(path) {
+                    return Err(HookError::Other(format!(
+                        "breakpoint on \"{path}\" matches no signal, {hint}"
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn post_clock(
+        &mut self,
+        time: u64,
+        _clock_name: &str,
+        model: &Model,
+    ) -> Result<HookAction, HookError> {
+        Ok(self.evaluate(time, model))
+    }
+
+    fn post_clock_fall(
+        &mut self,
+        time: u64,
+        _clock_name: &str,
+        model: &Model,
+    ) -> Result<HookAction, HookError> {
+        Ok(self.evaluate(time, model))
+    }
+
+    fn on_reset(&mut self, _time: u64, model: &Model) -> Result<(), HookError> {
+        // リセット後の値を基準にする
+        for entry in &mut self.entries {
+            entry.active = false;
+        }
+        self.previous.clear();
+        self.update_previous(model);
+        Ok(())
+    }
 }
@@ -1,16 +1,219 @@
-use super::Hook;
+use super::csv_logger::quote;
+use super::vcd_logger::{format_date, generate_id};
+use super::{Hook, HookAction, HookError, SignalFilter};
 use crate::Model;
-use std::collections::HashMap;
+use crate::time::SystemTime;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
 
 // Log all changes to buffer
 // this logger consumes more memory, but useful for waveform analysis
 pub struct BufLogger {
-    events: Vec<(u64, HashMap<String, usize>)>, // (time, signals)
+    events: VecDeque<(u64, HashMap<String, usize>)>, // (time, signals)
+    max_events: Option<usize>,                       // number of kept events
+    max_duration: Option<u64>,                       // kept period [ns]
+    dropped: usize,                                  // number of discarded events
+    column_width: usize,                             // characters per time in `render`
+    filter: SignalFilter,
+    module: String,                 // name of the top module
+    widths: HashMap<String, usize>, // widths of the recorded signals
+}
+
+impl Default for BufLogger {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl BufLogger {
     pub fn new() -> Self {
-        BufLogger { events: Vec::new() }
+        BufLogger {
+            events: VecDeque::new(),
+            max_events: None,
+            max_duration: None,
+            dropped: 0,
+            column_width: 4,
+            filter: SignalFilter::default(),
+            module: "top".to_string(),
+            widths: HashMap::new(),
+        }
+    }
+
+    /// Record only the signals selected by the glob patterns, e.g. `["top.u_core.*", "!*_debug"]`
+    /// see `SignalFilter` for the syntax
+    pub fn filter(mut self, patterns: &[&str]) -> Self {
+        self.filter = SignalFilter::new(patterns);
+        self
+    }
+
+    /// Keep only the last `n` events like a ring buffer
+    pub fn max_events(mut self, n: usize) -> Self {
+        self.max_events = Some(n);
+        self
+    }
+
+    /// Keep only the events in the last `duration_ns` nanoseconds
+    pub fn max_duration(mut self, duration_ns: u64) -> Self {
+        self.max_duration = Some(duration_ns);
+        self
+    }
+
+    /// Characters per recorded time in `render`, 4 by default
+    pub fn column_width(mut self, width: usize) -> Self {
+        self.column_width = width.max(1);
+        self
+    }
+
+    /// Recorded signal values at each time
+    /// the oldest events are discarded if `max_events` or `max_duration` is specified
+    pub fn events(&self) -> &VecDeque<(u64, HashMap<String, usize>)> {
+        &self.events
+    }
+
+    /// Number of the events discarded by `max_events` or `max_duration`
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// Value of the signal at the time, i.e. the value at the last event at or before the time
+    /// returns None if the events before the time were discarded
+    pub fn value_at(&self, signal: &str, time: u64) -> Option<usize> {
+        let index = self.events.partition_point(|(x, _)| *x <= time);
+        self.events
+            .range(..index)
+            .rev()
+            .find_map(|(_, x)| x.get(signal).copied())
+    }
+
+    /// Times and values where the signal changed, including the first recorded value
+    pub fn changes_of(&self, signal: &str) -> Vec<(u64, usize)> {
+        let mut changes: Vec<(u64, usize)> = Vec::new();
+        for (time, signals) in &self.events {
+            if let Some(value) = signals.get(signal).copied()
+                && changes.last().is_none_or(|(_, x)| *x != value)
+            {
+                changes.push((*time, value));
+            }
+        }
+        changes
+    }
+
+    /// Last recorded value of the signal
+    pub fn last_value(&self, signal: &str) -> Option<usize> {
+        self.events
+            .iter()
+            .rev()
+            .find_map(|(_, x)| x.get(signal).copied())
+    }
+
+    /// Write the recorded events to VCD file
+    pub fn write_vcd(&self, path: &str) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        let names = self.signal_names();
+        let ids: HashMap<_, _> = names
+            .iter()
+            .enumerate()
+            .map(|(i, x)| (x.as_str(), generate_id(i)))
+            .collect();
+        let width = |x: &str| self.widths.get(x).copied().unwrap_or(1).max(1);
+
+        writeln!(writer, "$date")?;
+        writeln!(writer, "    {}", format_date(SystemTime::now()))?;
+        writeln!(writer, "$end")?;
+        writeln!(writer, "$version")?;
+        writeln!(writer, "    Veryl Simulator {}", env!("CARGO_PKG_VERSION"))?;
+        writeln!(writer, "$end")?;
+        writeln!(writer, "$timescale")?;
+        writeln!(writer, "    1ns")?;
+        writeln!(writer, "$end")?;
+        writeln!(writer, "$scope module {} $end", self.module)?;
+        for name in &names {
+            let id = &ids[name.as_str()];
+            match width(name) {
+                1 => writeln!(writer, "$var wire 1 {id} {name} $end")?,
+                x => writeln!(writer, "$var wire {x} {id} {name} [{}:0] $end", x - 1)?,
+            }
+        }
+        writeln!(writer, "$upscope $end")?;
+        writeln!(writer, "$enddefinitions $end")?;
+
+        // 最初のイベントは初期値として、以降は変化した値のみ出力する
+        let mut last: HashMap<&str, usize> = HashMap::new();
+        for (i, (time, signals)) in self.events.iter().enumerate() {
+            let mut changes = Vec::new();
+            for name in &names {
+                let Some(value) = signals.get(name).copied() else {
+                    continue;
+                };
+                if last.insert(name.as_str(), value) != Some(value) {
+                    let id = &ids[name.as_str()];
+                    match width(name) {
+                        1 => changes.push(format!("{value}{id}")),
+                        _ => changes.push(format!("b{value:b} {id}")),
+                    }
+                }
+            }
+            if i == 0 {
+                writeln!(writer, "#{time}")?;
+                writeln!(writer, "$dumpvars")?;
+                for change in changes {
+                    writeln!(writer, "{change}")?;
+                }
+                writeln!(writer, "$end")?;
+            } else if !changes.is_empty() {
+                writeln!(writer, "#{time}")?;
+                for change in changes {
+                    writeln!(writer, "{change}")?;
+                }
+            }
+        }
+        writer.flush()
+    }
+
+    /// Write the recorded events to CSV file
+    /// a row is written for each recorded time with a column for each signal
+    pub fn write_csv(&self, path: &str) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        let names = self.signal_names();
+        let header: Vec<_> = names.iter().map(|x| quote(x)).collect();
+        writeln!(writer, "time,{}", header.join(","))?;
+
+        for (i, (time, signals)) in self.events.iter().enumerate() {
+            // 同時刻のイベントは最後の値のみ出力する
+            if self.events.get(i + 1).is_some_and(|(x, _)| x == time) {
+                continue;
+            }
+            let values: Vec<_> = names
+                .iter()
+                .map(|x| signals.get(x).map(|x| x.to_string()).unwrap_or_default())
+                .collect();
+            writeln!(writer, "{time},{}", values.join(","))?;
+        }
+        writer.flush()
+    }
+
+    /// Histories of the recorded signals in a stable text format for snapshot testing
+    /// each signal is a line of its name followed by lines of the changes in `time: value`
+    /// see `assert_wave_snapshot!`
+    pub fn snapshot(&self) -> String {
+        let mut text = String::new();
+        for name in self.signal_names() {
+            text.push_str(&format!("{name}\n"));
+            for (time, value) in self.changes_of(&name) {
+                text.push_str(&format!("    {time}ns: {value:#x}\n"));
+            }
+        }
+        text
+    }
+
+    fn signal_names(&self) -> Vec<String> {
+        let names: BTreeSet<_> = self
+            .events
+            .iter()
+            .flat_map(|(_, x)| x.keys().cloned())
+            .collect();
+        names.into_iter().collect()
     }
 
     /// Print waveform to stdout
@@ -33,97 +236,137 @@ impl BufLogger {
         }
         println!("=== End of Waveform ===\n");
 
-        // 波形ビジュアライゼーション
-        self.visualize_waveform();
+        println!("\n=== Waveform Visualization ===");
+        print!("{}", self.render());
+        println!("=== End of Visualization ===\n");
     }
 
-    fn visualize_waveform(&self) {
-        if self.events.is_empty() {
-            return;
+    /// Render the waveform as text
+    /// each recorded time is a column of `column_width` characters with the time ruler on top,
+    /// 1-bit signals are drawn with ▁▔ and multi-bit signals with hex values in brackets
+    pub fn render(&self) -> String {
+        // 同時刻のイベントは最後の値を使う
+        let mut columns: Vec<(u64, &HashMap<String, usize>)> = Vec::new();
+        for (time, signals) in &self.events {
+            match columns.last_mut() {
+                Some((x, y)) if x == time => *y = signals,
+                _ => columns.push((*time, signals)),
+            }
         }
+        let names = self.signal_names();
+        let label_width = names
+            .iter()
+            .map(|x| x.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max(4);
+        let width = self.column_width;
 
-        println!("\n=== Waveform Visualization ===");
-
-        // すべての信号名を収集
-        let mut signal_names = std::collections::HashSet::new();
-        for (_, signals) in &self.events {
-            for name in signals.keys() {
-                signal_names.insert(name.clone());
+        let mut ruler = String::new();
+        for (i, (time, _)) in columns.iter().enumerate() {
+            // 前の時刻と重なる場合は省略する
+            let length = ruler.chars().count();
+            if length <= i * width {
+                ruler.push_str(&" ".repeat(i * width - length));
+                ruler.push_str(&time.to_string());
             }
         }
+        let mut text = format!("{:label_width$}  {}\n", "time", ruler.trim_end());
 
-        // 各信号の波形を表示
-        for signal_name in signal_names {
-            print!("{:8} : ", signal_name);
-
-            let mut last_value = None;
-            let mut last_time = 0u64;
-
-            for (time, signals) in &self.events {
-                if let Some(value) = signals.get(&signal_name) {
-                    // 時間の幅を考慮した表示
-                    let time_diff = (*time - last_time) / 100; // スケーリング
-
-                    if last_value.is_none() {
-                        // 初回
-                        for _ in 0..time_diff {
-                            print!("_");
-                        }
-                        print!("|{}", value);
-                    } else if Some(value) != last_value.as_ref() {
-                        // 値が変化した
-                        for _ in 0..time_diff.saturating_sub(1) {
-                            print!("_");
-                        }
-                        print!("|{}", value);
-                    } else {
-                        // 値が同じ
-                        for _ in 0..time_diff {
-                            print!("_");
-                        }
-                    }
-
-                    last_value = Some(*value);
-                    last_time = *time;
-                }
-            }
-            println!();
+        for name in &names {
+            let values: Vec<_> = columns.iter().map(|(_, x)| x.get(name).copied()).collect();
+            let lane: String = if self.widths.get(name).copied().unwrap_or(1) <= 1 {
+                values
+                    .iter()
+                    .map(|x| match x {
+                        Some(0) => "▁".repeat(width),
+                        Some(_) => "▔".repeat(width),
+                        None => " ".repeat(width),
+                    })
+                    .collect()
+            } else {
+                render_bus(&values, width)
+            };
+            text.push_str(&format!("{name:label_width$}  {}\n", lane.trim_end()));
         }
-        println!("=== End of Visualization ===\n");
+        text
     }
 
-    fn collect_signals(&self, model: &Model) -> HashMap<String, usize> {
+    fn collect_signals(&mut self, model: &Model) -> HashMap<String, usize> {
+        self.module = model.module_name().to_string();
         let mut signals = HashMap::new();
+        for signal in model.signals() {
+            if self.filter.matches(model.module_name(), &signal.name) {
+                self.widths.insert(signal.name.clone(), signal.width);
+                signals.insert(signal.name, signal.value);
+            }
+        }
+        signals
+    }
 
-        // Modelのget_all_variablesメソッドがprivateなので、
-        // 出力ポートのみを記録する（テスト用途では十分）
-        // 将来的にはModelにpub get_all_variables()を追加すべき
+    fn push(&mut self, time: u64, signals: HashMap<String, usize>) {
+        self.events.push_back((time, signals));
 
-        // とりあえず主要な出力を記録
-        if let Some(val) = model.get("a") {
-            signals.insert("a".to_string(), val);
-        }
-        if let Some(val) = model.get("b") {
-            signals.insert("b".to_string(), val);
+        // 容量を超えた古いイベントを捨てる
+        let mut excess = self
+            .max_events
+            .map(|x| self.events.len().saturating_sub(x))
+            .unwrap_or(0);
+        if let Some(duration) = self.max_duration {
+            let expired = self
+                .events
+                .iter()
+                .take_while(|(x, _)| x + duration < time)
+                .count();
+            excess = excess.max(expired);
         }
+        self.events.drain(..excess);
+        self.dropped += excess;
+    }
+}
 
-        signals
+// 同じ値が続く区間ごとに "[値]" を書き、残りを '─' で埋める
+pub(super) fn render_bus(values: &[Option<usize>], width: usize) -> String {
+    let mut lane = String::new();
+    let mut i = 0;
+    while i < values.len() {
+        let count = values[i..].iter().take_while(|x| **x == values[i]).count();
+        let length = count * width;
+        let segment: String = match values[i] {
+            Some(x) => format!("[{x:x}]")
+                .chars()
+                .chain(std::iter::repeat('─'))
+                .take(length)
+                .collect(),
+            None => " ".repeat(length),
+        };
+        lane.push_str(&segment);
+        i += count;
     }
+    lane
 }
 
 impl Hook for BufLogger {
-    fn on_reset(&mut self, time: u64, model: &Model) {
+    fn on_reset(&mut self, time: u64, model: &Model) -> Result<(), HookError> {
         let signals = self.collect_signals(model);
-        self.events.push((time, signals));
+        self.push(time, signals);
+        Ok(())
     }
 
-    fn post_clock(&mut self, time: u64, _clock_name: &str, model: &Model) {
+    fn post_clock(
+        &mut self,
+        time: u64,
+        _clock_name: &str,
+        model: &Model,
+    ) -> Result<HookAction, HookError> {
         let signals = self.collect_signals(model);
-        self.events.push((time, signals));
+        self.push(time, signals);
+        Ok(HookAction::Continue)
     }
 
-    fn on_finish(&mut self, _time: u64, _model: &Model) {
+    fn on_finish(&mut self, _time: u64, _model: &Model) -> Result<(), HookError> {
         // Automatically print the results when simulation finishes
         self.print();
+        Ok(())
     }
 }
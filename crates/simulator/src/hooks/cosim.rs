@@ -0,0 +1,175 @@
+use super::{Hook, HookAction, HookError};
+use crate::Model;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, Command, Stdio};
+
+// 外部プロセスとの入出力
+struct Pipe {
+    reader: Box<dyn BufRead + Send>,
+    writer: Box<dyn Write + Send>,
+}
+
+// This hook runs the model in lockstep with an external process, e.g. a Verilator or Icarus
+// simulation of legacy Verilog, and exchanges the boundary signals over a line based protocol
+// after every rising edge of the clock, the hook writes "step <time> <values>" with the sent
+// signals, and reads a line of the values driven into the received input ports
+// values are hexadecimal and in the order of the signals added by `send` and `receive`
+// the hook also writes "reset <time>" at reset and "finish <time>" at the end of each run,
+// which have no reply, and the standard input of the process is closed when the hook is dropped
+// the exchanged values are sampled at the next edge on both sides, so the boundary signals
+// should be registered to keep the result independent of the order of the simulators
+pub struct CosimHook {
+    clock: String,
+    command: Option<Command>,
+    child: Option<Child>,
+    pipe: Option<Pipe>,
+    sent: Vec<String>,
+    received: Vec<String>,
+}
+
+impl CosimHook {
+    /// The external process is spawned at the start of the simulation,
+    /// and the protocol is spoken over its standard input and output
+    pub fn new(clock: &str, command: Command) -> Self {
+        CosimHook {
+            clock: clock.to_string(),
+            command: Some(command),
+            child: None,
+            pipe: None,
+            sent: Vec::new(),
+            received: Vec::new(),
+        }
+    }
+
+    /// Speak the protocol over the given reader and writer instead of a process
+    pub fn from_io(
+        clock: &str,
+        reader: impl BufRead + Send + 'static,
+        writer: impl Write + Send + 'static,
+    ) -> Self {
+        CosimHook {
+            clock: clock.to_string(),
+            command: None,
+            child: None,
+            pipe: Some(Pipe {
+                reader: Box::new(reader),
+                writer: Box::new(writer),
+            }),
+            sent: Vec::new(),
+            received: Vec::new(),
+        }
+    }
+
+    /// Signal of the model sent to the external process
+    pub fn send(mut self, signal: &str) -> Self {
+        self.sent.push(signal.to_string());
+        self
+    }
+
+    /// Input port of the model driven by the external process
+    pub fn receive(mut self, port: &str) -> Self {
+        self.received.push(port.to_string());
+        self
+    }
+
+    fn pipe(&mut self) -> Result<&mut Pipe, HookError> {
+        if self.pipe.is_none()
+            && let Some(mut command) = self.command.take()
+        {
+            let program = command.get_program().to_string_lossy().to_string();
+            let mut child = command
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .spawn()
+                .map_err(|x| HookError::Io(format!("failed to spawn \"{program}\": {x}")))?;
+            if let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) {
+                self.pipe = Some(Pipe {
+                    reader: Box::new(BufReader::new(stdout)),
+                    writer: Box::new(stdin),
+                });
+            }
+            self.child = Some(child);
+        }
+        self.pipe
+            .as_mut()
+            .ok_or_else(|| HookError::Other("external process is not running".to_string()))
+    }
+
+    // 応答のない命令を書き込む
+    fn notify(&mut self, command: &str) -> Result<(), HookError> {
+        let pipe = self.pipe()?;
+        writeln!(pipe.writer, "{command}")?;
+        pipe.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl Hook for CosimHook {
+    fn on_start(&mut self, _model: &Model) -> Result<(), HookError> {
+        self.pipe().map(|_| ())
+    }
+
+    fn drive(
+        &mut self,
+        time: u64,
+        clock_name: &str,
+        model: &mut Model,
+    ) -> Result<HookAction, HookError> {
+        if clock_name != self.clock {
+            return Ok(HookAction::Continue);
+        }
+
+        let mut line = format!("step {time}");
+        for signal in &self.sent {
+            let value = model
+                .peek(signal)
+                .ok_or_else(|| HookError::Other(format!("signal \"{signal}\" is not found")))?;
+            line.push_str(&format!(" {value:x}"));
+        }
+        let pipe = self.pipe()?;
+        writeln!(pipe.writer, "{line}")?;
+        pipe.writer.flush()?;
+
+        let mut reply = String::new();
+        if pipe.reader.read_line(&mut reply)? == 0 {
+            return Err(HookError::Other(format!(
+                "external process closed the pipe at {time}ns"
+            )));
+        }
+        let values: Vec<_> = reply.split_whitespace().collect();
+        if values.len() != self.received.len() {
+            return Err(HookError::Other(format!(
+                "expected {} values from the external process at {time}ns, got \"{}\"",
+                self.received.len(),
+                reply.trim()
+            )));
+        }
+        for (port, value) in self.received.iter().zip(values) {
+            let value = usize::from_str_radix(value, 16).map_err(|_| {
+                HookError::Other(format!("invalid value \"{value}\" for \"{port}\""))
+            })?;
+            model
+                .try_input(port, value)
+                .map_err(|x| HookError::Other(x.to_string()))?;
+        }
+        Ok(HookAction::Continue)
+    }
+
+    fn on_reset(&mut self, time: u64, _model: &Model) -> Result<(), HookError> {
+        self.notify(&format!("reset {time}"))
+    }
+
+    fn on_finish(&mut self, time: u64, _model: &Model) -> Result<(), HookError> {
+        self.notify(&format!("finish {time}"))
+    }
+}
+
+impl Drop for CosimHook {
+    fn drop(&mut self) {
+        // 標準入力を閉じてプロセスの終了を待つ
+        self.pipe = None;
+        if let Some(mut child) = self.child.take() {
+            let _ = child.wait();
+        }
+    }
+}
@@ -0,0 +1,267 @@
+use super::{Hook, HookAction, HookError};
+use crate::Model;
+use crate::model::CoveragePoint;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// Numbers of 0 -> 1 and 1 -> 0 transitions of each bit of a signal
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToggleReport {
+    pub signal: String,
+    /// Indexed by the bit position
+    pub rises: Vec<u64>,
+    pub falls: Vec<u64>,
+}
+
+impl ToggleReport {
+    /// Number of bits which rose and fell, and all bits
+    pub fn toggled(&self) -> (usize, usize) {
+        let toggled = self
+            .rises
+            .iter()
+            .zip(&self.falls)
+            .filter(|(r, f)| **r > 0 && **f > 0)
+            .count();
+        (toggled, self.rises.len())
+    }
+
+    fn record(&mut self, old: usize, new: usize) {
+        for (i, (rises, falls)) in self.rises.iter_mut().zip(&mut self.falls).enumerate() {
+            let (old, new) = ((old >> i) & 1, (new >> i) & 1);
+            if old == 0 && new == 1 {
+                *rises += 1;
+            } else if old == 1 && new == 0 {
+                *falls += 1;
+            }
+        }
+    }
+
+    fn merge(&mut self, other: &ToggleReport) {
+        let width = self.rises.len().max(other.rises.len());
+        self.rises.resize(width, 0);
+        self.falls.resize(width, 0);
+        for (i, (r, f)) in other.rises.iter().zip(&other.falls).enumerate() {
+            self.rises[i] += r;
+            self.falls[i] += f;
+        }
+    }
+}
+
+/// Statement, branch and toggle coverage of a simulation
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoverageReport {
+    /// Coverage points and their execution counts in the order of the source
+    pub points: Vec<(CoveragePoint, u64)>,
+    /// Toggles of the signals in the order of the names
+    pub toggles: Vec<ToggleReport>,
+}
+
+impl CoverageReport {
+    /// Number of executed statements and all statements
+    pub fn statements(&self) -> (usize, usize) {
+        self.count(false)
+    }
+
+    /// Number of taken branches and all branches
+    pub fn branches(&self) -> (usize, usize) {
+        self.count(true)
+    }
+
+    /// Statements which were never executed and branches which were never taken
+    pub fn uncovered(&self) -> impl Iterator<Item = &CoveragePoint> {
+        self.points
+            .iter()
+            .filter(|(_, hits)| *hits == 0)
+            .map(|(x, _)| x)
+    }
+
+    /// Number of bits which rose and fell, and all bits of the signals
+    pub fn toggles(&self) -> (usize, usize) {
+        self.toggles
+            .iter()
+            .map(|x| x.toggled())
+            .fold((0, 0), |(a, b), (x, y)| (a + x, b + y))
+    }
+
+    /// Add the counts of another run of the same design
+    /// points and signals which don't exist in self are appended
+    pub fn merge(&mut self, other: &CoverageReport) {
+        for (point, hits) in &other.points {
+            match self.points.iter_mut().find(|(x, _)| x == point) {
+                Some((_, x)) => *x += hits,
+                None => self.points.push((point.clone(), *hits)),
+            }
+        }
+        self.points.sort_by_key(|(x, _)| x.location);
+        for toggle in &other.toggles {
+            match self.toggles.iter_mut().find(|x| x.signal == toggle.signal) {
+                Some(x) => x.merge(toggle),
+                None => self.toggles.push(toggle.clone()),
+            }
+        }
+        self.toggles.sort_by(|x, y| x.signal.cmp(&y.signal));
+    }
+
+    fn count(&self, branch: bool) -> (usize, usize) {
+        let points: Vec<_> = self
+            .points
+            .iter()
+            .filter(|(x, _)| x.kind.is_branch() == branch)
+            .collect();
+        let covered = points.iter().filter(|(_, hits)| *hits > 0).count();
+        (covered, points.len())
+    }
+}
+
+impl fmt::Display for CoverageReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let ratio = |(covered, total): (usize, usize)| {
+            let percent = if total == 0 {
+                100.0
+            } else {
+                covered as f64 * 100.0 / total as f64
+            };
+            format!("{covered}/{total} ({percent:.1}%)")
+        };
+        write!(f, "statements: {}", ratio(self.statements()))?;
+        write!(f, "\nbranches: {}", ratio(self.branches()))?;
+        write!(f, "\ntoggles: {}", ratio(self.toggles()))?;
+        for x in self.uncovered() {
+            if x.kind.is_branch() {
+                write!(f, "\n  {x} not taken")?;
+            } else {
+                write!(f, "\n  {x} not executed")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// This hook collects the execution counts of statements and branches in the design,
+// and the toggles of the signals except clocks
+// the execution counts are taken from the model, so they include evaluations before the hook is added
+pub struct CoverageHook {
+    report: Arc<Mutex<CoverageReport>>,
+}
+
+impl Default for CoverageHook {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CoverageHook {
+    pub fn new() -> Self {
+        CoverageHook {
+            report: Arc::new(Mutex::new(CoverageReport::default())),
+        }
+    }
+
+    /// Shared report updated during the simulation
+    /// it can be read while the hook is owned by the simulator
+    pub fn report(&self) -> Arc<Mutex<CoverageReport>> {
+        self.report.clone()
+    }
+
+    fn toggle(&mut self, name: &str, old: usize, new: usize) {
+        let mut report = self.report.lock().unwrap();
+        if let Some(x) = report.toggles.iter_mut().find(|x| x.signal == name) {
+            x.record(old, new);
+        }
+    }
+
+    fn update(&mut self, model: &Model) {
+        let mut report = self.report.lock().unwrap();
+        // 再スタート時はトグルの集計を保持する
+        if report.toggles.is_empty() {
+            let clocks: Vec<_> = model.clocks().iter().map(|(x, _)| x).collect();
+            report.toggles = model
+                .signals()
+                .filter(|x| !clocks.contains(&&x.name))
+                .map(|x| {
+                    let width = x.width.min(usize::BITS as usize);
+                    ToggleReport {
+                        signal: x.name,
+                        rises: vec![0; width],
+                        falls: vec![0; width],
+                    }
+                })
+                .collect();
+            report.toggles.sort_by(|x, y| x.signal.cmp(&y.signal));
+        }
+        report.points = model
+            .coverage_points()
+            .iter()
+            .cloned()
+            .zip(model.coverage_hits().iter().copied())
+            .collect();
+        // ソース上の位置順に並べる
+        report.points.sort_by_key(|(x, _)| x.location);
+    }
+}
+
+impl Hook for CoverageHook {
+    fn on_start(&mut self, model: &Model) -> Result<(), HookError> {
+        self.update(model);
+        Ok(())
+    }
+
+    fn on_input_change(
+        &mut self,
+        _time: u64,
+        name: &str,
+        old: usize,
+        new: usize,
+    ) -> Result<HookAction, HookError> {
+        self.toggle(name, old, new);
+        Ok(HookAction::Continue)
+    }
+
+    fn on_signal_change(
+        &mut self,
+        _time: u64,
+        name: &str,
+        old: usize,
+        new: usize,
+    ) -> Result<HookAction, HookError> {
+        self.toggle(name, old, new);
+        Ok(HookAction::Continue)
+    }
+
+    fn on_step(&mut self, _time: u64, model: &Model) -> Result<HookAction, HookError> {
+        // ステップ間の入力による組み合わせ回路の評価を反映する
+        self.update(model);
+        Ok(HookAction::Continue)
+    }
+
+    fn post_clock(
+        &mut self,
+        _time: u64,
+        _clock_name: &str,
+        model: &Model,
+    ) -> Result<HookAction, HookError> {
+        self.update(model);
+        Ok(HookAction::Continue)
+    }
+
+    fn post_clock_fall(
+        &mut self,
+        _time: u64,
+        _clock_name: &str,
+        model: &Model,
+    ) -> Result<HookAction, HookError> {
+        self.update(model);
+        Ok(HookAction::Continue)
+    }
+
+    fn on_reset(&mut self, _time: u64, model: &Model) -> Result<(), HookError> {
+        self.update(model);
+        Ok(())
+    }
+
+    fn on_finish(&mut self, _time: u64, model: &Model) -> Result<(), HookError> {
+        self.update(model);
+        Ok(())
+    }
+}
@@ -0,0 +1,380 @@
+use super::{Hook, HookAction, HookError};
+use crate::Model;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::sync::{Arc, Mutex};
+
+// 自動生成する bin の最大数（SystemVerilog の auto_bin_max と同じ）
+const AUTO_BIN_MAX: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Bin {
+    name: String,
+    range: RangeInclusive<usize>,
+}
+
+/// Signal sampled by a covergroup and its bins
+/// without explicit bins, the values are divided into up to 64 automatic bins
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Coverpoint {
+    name: String,
+    signal: String,
+    bins: Vec<Bin>,
+}
+
+impl Coverpoint {
+    /// The coverpoint is named after the signal
+    pub fn new(signal: &str) -> Self {
+        Coverpoint {
+            name: signal.to_string(),
+            signal: signal.to_string(),
+            bins: Vec::new(),
+        }
+    }
+
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    /// Bin hit by a single value
+    pub fn bin(self, name: &str, value: usize) -> Self {
+        self.range(name, value..=value)
+    }
+
+    /// Bin hit by any value in the range
+    pub fn range(mut self, name: &str, range: RangeInclusive<usize>) -> Self {
+        self.bins.push(Bin {
+            name: name.to_string(),
+            range,
+        });
+        self
+    }
+
+    // 幅から自動の bin を作る
+    fn auto_bins(&mut self, width: usize) {
+        let count = if width >= usize::BITS as usize {
+            usize::MAX
+        } else {
+            (1usize << width) - 1
+        };
+        let size = count / AUTO_BIN_MAX.min(count.saturating_add(1)) + 1;
+        let mut lo = 0usize;
+        loop {
+            let hi = lo.saturating_add(size - 1).min(count);
+            let name = if lo == hi {
+                format!("auto[{lo}]")
+            } else {
+                format!("auto[{lo}:{hi}]")
+            };
+            self.bins.push(Bin {
+                name,
+                range: lo..=hi,
+            });
+            if hi == count {
+                break;
+            }
+            lo = hi + 1;
+        }
+    }
+
+    // 値に一致する bin の番号
+    fn matches(&self, value: usize) -> Vec<usize> {
+        self.bins
+            .iter()
+            .enumerate()
+            .filter(|(_, x)| x.range.contains(&value))
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+/// Hit count of a bin
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BinReport {
+    pub name: String,
+    pub hits: u64,
+}
+
+/// Bins of a coverpoint or a cross
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoverpointReport {
+    pub name: String,
+    pub bins: Vec<BinReport>,
+}
+
+impl CoverpointReport {
+    /// Ratio of the hit bins in percent
+    pub fn coverage(&self) -> f64 {
+        if self.bins.is_empty() {
+            return 100.0;
+        }
+        let hit = self.bins.iter().filter(|x| x.hits > 0).count();
+        hit as f64 * 100.0 / self.bins.len() as f64
+    }
+
+    /// Add the hits of the bins with the same names, other bins are appended
+    pub fn merge(&mut self, other: &CoverpointReport) {
+        for bin in &other.bins {
+            match self.bins.iter_mut().find(|x| x.name == bin.name) {
+                Some(x) => x.hits += bin.hits,
+                None => self.bins.push(bin.clone()),
+            }
+        }
+    }
+}
+
+// 名前が同じものを合算し、無いものは追加する
+fn merge_by_name(to: &mut Vec<CoverpointReport>, from: &[CoverpointReport]) {
+    for x in from {
+        match to.iter_mut().find(|y| y.name == x.name) {
+            Some(y) => y.merge(x),
+            None => to.push(x.clone()),
+        }
+    }
+}
+
+/// Result of a covergroup
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoverGroupReport {
+    pub name: String,
+    /// Number of sampled clock edges
+    pub samples: u64,
+    pub coverpoints: Vec<CoverpointReport>,
+    pub crosses: Vec<CoverpointReport>,
+}
+
+impl CoverGroupReport {
+    /// Average of the coverage of the coverpoints and the crosses in percent
+    pub fn coverage(&self) -> f64 {
+        let all: Vec<_> = self.coverpoints.iter().chain(&self.crosses).collect();
+        if all.is_empty() {
+            return 100.0;
+        }
+        all.iter().map(|x| x.coverage()).sum::<f64>() / all.len() as f64
+    }
+
+    /// Add the samples and the hits of another run of the same covergroup
+    pub fn merge(&mut self, other: &CoverGroupReport) {
+        self.samples += other.samples;
+        merge_by_name(&mut self.coverpoints, &other.coverpoints);
+        merge_by_name(&mut self.crosses, &other.crosses);
+    }
+
+    /// Report as JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+}
+
+impl fmt::Display for CoverGroupReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "covergroup {}: {:.1}% ({} samples)",
+            self.name,
+            self.coverage(),
+            self.samples
+        )?;
+        let items = self
+            .coverpoints
+            .iter()
+            .map(|x| ("coverpoint", x))
+            .chain(self.crosses.iter().map(|x| ("cross", x)));
+        for (kind, x) in items {
+            write!(f, "\n  {kind} {}: {:.1}%", x.name, x.coverage())?;
+            for bin in &x.bins {
+                write!(f, "\n    {}: {}", bin.name, bin.hits)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Cross {
+    name: String,
+    coverpoints: Vec<usize>, // 対象の coverpoint の番号
+}
+
+// This hook samples coverpoints at every rising edge of the clock
+// signals are sampled just before the edge like covergroups in SystemVerilog
+pub struct CoverGroup {
+    clock: Option<String>,
+    coverpoints: Vec<Coverpoint>,
+    crosses: Vec<(String, Vec<String>)>,
+    resolved: Vec<Cross>,
+    report: Arc<Mutex<CoverGroupReport>>,
+}
+
+impl CoverGroup {
+    pub fn new(name: &str) -> Self {
+        CoverGroup {
+            clock: None,
+            coverpoints: Vec::new(),
+            crosses: Vec::new(),
+            resolved: Vec::new(),
+            report: Arc::new(Mutex::new(CoverGroupReport {
+                name: name.to_string(),
+                ..Default::default()
+            })),
+        }
+    }
+
+    /// Sample at rising edges of the clock only, all clocks by default
+    pub fn clock(mut self, clock: &str) -> Self {
+        self.clock = Some(clock.to_string());
+        self
+    }
+
+    pub fn coverpoint(mut self, coverpoint: Coverpoint) -> Self {
+        self.coverpoints.push(coverpoint);
+        self
+    }
+
+    /// Cross of the coverpoints specified by their names
+    /// each combination of their bins becomes a bin of the cross
+    pub fn cross(mut self, name: &str, coverpoints: &[&str]) -> Self {
+        self.crosses.push((
+            name.to_string(),
+            coverpoints.iter().map(|x| x.to_string()).collect(),
+        ));
+        self
+    }
+
+    /// Shared report updated at every sample
+    /// it can be read while the hook is owned by the simulator
+    pub fn report(&self) -> Arc<Mutex<CoverGroupReport>> {
+        self.report.clone()
+    }
+
+    fn sample(&mut self, model: &Model) {
+        let mut report = self.report.lock().unwrap();
+        report.samples += 1;
+
+        let mut matched = Vec::new();
+        for (point, result) in self.coverpoints.iter().zip(&mut report.coverpoints) {
+            let bins = model
+                .peek(&point.signal)
+                .map(|x| point.matches(x))
+                .unwrap_or_default();
+            for i in &bins {
+                result.bins[*i].hits += 1;
+            }
+            matched.push(bins);
+        }
+
+        // 各 coverpoint で一致した bin の組み合わせを数える
+        for (cross, result) in self.resolved.iter().zip(&mut report.crosses) {
+            let mut indices = vec![0];
+            let mut stride = 1;
+            for point in cross.coverpoints.iter().rev() {
+                indices = matched[*point]
+                    .iter()
+                    .flat_map(|bin| indices.iter().map(move |x| x + bin * stride))
+                    .collect();
+                stride *= self.coverpoints[*point].bins.len();
+            }
+            for i in indices {
+                result.bins[i].hits += 1;
+            }
+        }
+    }
+}
+
+impl Hook for CoverGroup {
+    fn on_start(&mut self, model: &Model) -> Result<(), HookError> {
+        // 再スタート時は集計を保持する
+        if !self.report.lock().unwrap().coverpoints.is_empty() {
+            return Ok(());
+        }
+
+        for point in &mut self.coverpoints {
+            let Some(info) = model.signals().find(|x| x.name == point.signal) else {
+                return Err(HookError::Other(format!(
+                    "signal \"{}\" of coverpoint \"{}\" is not found",
+                    point.signal, point.name
+                )));
+            };
+            if point.bins.is_empty() {
+                point.auto_bins(info.width);
+            }
+        }
+
+        self.resolved.clear();
+        for (name, targets) in &self.crosses {
+            let mut coverpoints = Vec::new();
+            for target in targets {
+                let Some(i) = self.coverpoints.iter().position(|x| &x.name == target) else {
+                    return Err(HookError::Other(format!(
+                        "coverpoint \"{target}\" of cross \"{name}\" is not found"
+                    )));
+                };
+                coverpoints.push(i);
+            }
+            self.resolved.push(Cross {
+                name: name.clone(),
+                coverpoints,
+            });
+        }
+
+        let mut report = self.report.lock().unwrap();
+        report.coverpoints = self
+            .coverpoints
+            .iter()
+            .map(|x| CoverpointReport {
+                name: x.name.clone(),
+                bins: x
+                    .bins
+                    .iter()
+                    .map(|x| BinReport {
+                        name: x.name.clone(),
+                        hits: 0,
+                    })
+                    .collect(),
+            })
+            .collect();
+        report.crosses = self
+            .resolved
+            .iter()
+            .map(|cross| {
+                let mut names = vec![Vec::new()];
+                for point in &cross.coverpoints {
+                    names = names
+                        .into_iter()
+                        .flat_map(|x: Vec<&str>| {
+                            self.coverpoints[*point].bins.iter().map(move |bin| {
+                                let mut x = x.clone();
+                                x.push(&bin.name);
+                                x
+                            })
+                        })
+                        .collect();
+                }
+                CoverpointReport {
+                    name: cross.name.clone(),
+                    bins: names
+                        .into_iter()
+                        .map(|x| BinReport {
+                            name: format!("<{}>", x.join(",")),
+                            hits: 0,
+                        })
+                        .collect(),
+                }
+            })
+            .collect();
+        Ok(())
+    }
+
+    fn pre_clock(
+        &mut self,
+        _time: u64,
+        clock_name: &str,
+        model: &Model,
+    ) -> Result<HookAction, HookError> {
+        if self.clock.as_ref().is_none_or(|x| x == clock_name) {
+            self.sample(model);
+        }
+        Ok(HookAction::Continue)
+    }
+}
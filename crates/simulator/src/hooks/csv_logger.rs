@@ -0,0 +1,130 @@
+use super::{Hook, HookAction, HookError, LogWriter, SignalFilter, create_log};
+use crate::Model;
+use std::io::{self, BufWriter, Write};
+
+// Log sampled values to CSV file
+// one row is written for each sampled time with a column for each signal
+// signals are sampled after every rising edge, and rows at the same time are merged
+pub struct CsvLoggerHook {
+    writer: Option<LogWriter>,
+    error: Option<String>, // error while creating the file
+    filter: SignalFilter,
+    columns: Vec<String>,               // recorded signals
+    pending: Option<(u64, Vec<usize>)>, // row which is not written yet
+    initialized: bool,
+}
+
+impl CsvLoggerHook {
+    pub fn new(path: &str) -> Self {
+        let (writer, error) = create_log(path);
+        Self::with_writer(writer, error)
+    }
+
+    /// Write the CSV to the writer instead of a file
+    pub fn from_writer(writer: impl Write + Send + 'static) -> Self {
+        Self::with_writer(Some(BufWriter::new(Box::new(writer))), None)
+    }
+
+    fn with_writer(writer: Option<LogWriter>, error: Option<String>) -> Self {
+        CsvLoggerHook {
+            writer,
+            error,
+            filter: SignalFilter::default(),
+            columns: Vec::new(),
+            pending: None,
+            initialized: false,
+        }
+    }
+
+    /// Record only the signals selected by the glob patterns, e.g. `["top.u_core.*", "!*_debug"]`
+    /// see `SignalFilter` for the syntax
+    pub fn filter(mut self, patterns: &[&str]) -> Self {
+        self.filter = SignalFilter::new(patterns);
+        self
+    }
+
+    fn write_header(&mut self, model: &Model) -> io::Result<()> {
+        self.columns = model
+            .signals()
+            .filter(|x| self.filter.matches(model.module_name(), &x.name))
+            .map(|x| x.name)
+            .collect();
+
+        let Some(ref mut writer) = self.writer else {
+            return Ok(());
+        };
+        let header: Vec<_> = self.columns.iter().map(|x| quote(x)).collect();
+        writeln!(writer, "time,{}", header.join(","))
+    }
+
+    fn write_pending(&mut self) -> io::Result<()> {
+        let (Some((time, values)), Some(writer)) = (self.pending.take(), &mut self.writer) else {
+            return Ok(());
+        };
+        let values: Vec<_> = values.iter().map(|x| x.to_string()).collect();
+        writeln!(writer, "{time},{}", values.join(","))
+    }
+
+    fn record(&mut self, time: u64, model: &Model) -> Result<(), HookError> {
+        if let Some(error) = self.error.take() {
+            return Err(HookError::Io(error));
+        }
+        if !self.initialized {
+            self.write_header(model)?;
+            self.initialized = true;
+        }
+        // 同時刻の行は最後の値で上書きする
+        if self.pending.as_ref().is_some_and(|(x, _)| *x != time) {
+            self.write_pending()?;
+        }
+        let values = self
+            .columns
+            .iter()
+            .map(|x| model.peek(x).unwrap_or(0))
+            .collect();
+        self.pending = Some((time, values));
+        Ok(())
+    }
+}
+
+// 区切り文字や引用符を含む名前を引用する
+pub(super) fn quote(name: &str) -> String {
+    if name.contains([',', '"', '\n']) {
+        format!("\"{}\"", name.replace('"', "\"\""))
+    } else {
+        name.to_string()
+    }
+}
+
+impl Hook for CsvLoggerHook {
+    fn on_reset(&mut self, time: u64, model: &Model) -> Result<(), HookError> {
+        self.record(time, model)
+    }
+
+    fn post_clock(
+        &mut self,
+        time: u64,
+        _clock_name: &str,
+        model: &Model,
+    ) -> Result<HookAction, HookError> {
+        self.record(time, model)?;
+        Ok(HookAction::Continue)
+    }
+
+    fn on_finish(&mut self, _time: u64, _model: &Model) -> Result<(), HookError> {
+        self.write_pending()?;
+        if let Some(ref mut writer) = self.writer {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for CsvLoggerHook {
+    fn drop(&mut self) {
+        self.write_pending().ok();
+        if let Some(ref mut writer) = self.writer {
+            writer.flush().ok();
+        }
+    }
+}
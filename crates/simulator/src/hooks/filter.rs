@@ -0,0 +1,35 @@
+use crate::path;
+
+/// Signal selection by glob patterns
+///
+/// `*` matches any sequence of characters and `?` matches any character.
+/// Patterns starting with `!` exclude the matched signals.
+/// A signal is selected if it matches an include pattern (or there is no include pattern),
+/// and doesn't match any exclude pattern.
+/// Patterns are matched against both the signal name (`u_core.a`) and the path
+/// prefixed by the top module (`top.u_core.a`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SignalFilter {
+    include: Vec<String>,
+    exclude: Vec<String>,
+}
+
+impl SignalFilter {
+    pub fn new(patterns: &[&str]) -> Self {
+        let mut filter = SignalFilter::default();
+        for pattern in patterns {
+            match pattern.strip_prefix('!') {
+                Some(x) => filter.exclude.push(x.to_string()),
+                None => filter.include.push(pattern.to_string()),
+            }
+        }
+        filter
+    }
+
+    /// Whether the signal of the top module is selected
+    pub fn matches(&self, module: &str, name: &str) -> bool {
+        let matched = |x: &String| path::matches(module, x, name);
+        (self.include.is_empty() || self.include.iter().any(matched))
+            && !self.exclude.iter().any(matched)
+    }
+}
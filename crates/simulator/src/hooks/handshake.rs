@@ -0,0 +1,187 @@
+use super::{Hook, HookAction, HookError};
+use crate::Model;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// Transfer accepted by valid && ready
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HandshakeTransfer {
+    pub time: u64,
+    /// Values of the data signals in the configured order
+    pub data: Vec<usize>,
+}
+
+/// Rule of the valid/ready protocol
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HandshakeRule {
+    /// valid was deasserted before ready was asserted
+    ValidDropped,
+    /// Data signal changed while valid && !ready
+    DataChanged {
+        signal: String,
+        old: usize,
+        new: usize,
+    },
+}
+
+/// Violation of the valid/ready protocol
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HandshakeViolation {
+    pub time: u64,
+    pub rule: HandshakeRule,
+}
+
+impl fmt::Display for HandshakeViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.rule {
+            HandshakeRule::ValidDropped => {
+                write!(f, "{}ns: valid dropped without ready", self.time)
+            }
+            HandshakeRule::DataChanged { signal, old, new } => write!(
+                f,
+                "{}ns: {signal} changed from {old} to {new} while waiting for ready",
+                self.time
+            ),
+        }
+    }
+}
+
+/// Transactions and violations found by a handshake checker
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HandshakeReport {
+    pub transactions: Vec<HandshakeTransfer>,
+    pub violations: Vec<HandshakeViolation>,
+}
+
+impl HandshakeReport {
+    pub fn passed(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+impl fmt::Display for HandshakeReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} transactions, {} violations",
+            self.transactions.len(),
+            self.violations.len()
+        )?;
+        for violation in &self.violations {
+            write!(f, "\n  {violation}")?;
+        }
+        Ok(())
+    }
+}
+
+// This hook monitors a valid/ready interface at every rising edge of the clock
+// signals are sampled just before the edge, and a transfer happens when valid && ready
+pub struct HandshakeChecker {
+    clock: String,
+    valid: String,
+    ready: String,
+    data: Vec<String>,
+    stalled: Option<Vec<usize>>, // valid && !ready だった前のサイクルのデータ
+    action: HookAction,
+    report: Arc<Mutex<HandshakeReport>>,
+}
+
+impl HandshakeChecker {
+    pub fn new(clock: &str, valid: &str, ready: &str) -> Self {
+        HandshakeChecker {
+            clock: clock.to_string(),
+            valid: valid.to_string(),
+            ready: ready.to_string(),
+            data: Vec::new(),
+            stalled: None,
+            action: HookAction::Continue,
+            report: Arc::new(Mutex::new(HandshakeReport::default())),
+        }
+    }
+
+    /// Signals which must be stable while waiting for ready, and are recorded in transactions
+    pub fn data(mut self, signals: &[&str]) -> Self {
+        self.data = signals.iter().map(|x| x.to_string()).collect();
+        self
+    }
+
+    /// Action requested to the simulator on violations, `HookAction::Continue` by default
+    pub fn action(mut self, action: HookAction) -> Self {
+        self.action = action;
+        self
+    }
+
+    /// Shared report updated at every rising edge
+    /// it can be read while the hook is owned by the simulator
+    pub fn report(&self) -> Arc<Mutex<HandshakeReport>> {
+        self.report.clone()
+    }
+
+    fn check(&mut self, time: u64, model: &Model) -> Result<HookAction, HookError> {
+        let get = |x: &String| {
+            model
+                .peek(x)
+                .ok_or_else(|| HookError::Other(format!("signal \"{x}\" is not found")))
+        };
+        let valid = get(&self.valid)? != 0;
+        let ready = get(&self.ready)? != 0;
+        let data = self.data.iter().map(get).collect::<Result<Vec<_>, _>>()?;
+
+        let mut report = self.report.lock().unwrap();
+        let violations = report.violations.len();
+        if let Some(stalled) = &self.stalled {
+            if !valid {
+                report.violations.push(HandshakeViolation {
+                    time,
+                    rule: HandshakeRule::ValidDropped,
+                });
+            } else {
+                for ((signal, old), new) in self.data.iter().zip(stalled).zip(&data) {
+                    if old != new {
+                        report.violations.push(HandshakeViolation {
+                            time,
+                            rule: HandshakeRule::DataChanged {
+                                signal: signal.clone(),
+                                old: *old,
+                                new: *new,
+                            },
+                        });
+                    }
+                }
+            }
+        }
+
+        if valid && ready {
+            report.transactions.push(HandshakeTransfer {
+                time,
+                data: data.clone(),
+            });
+        }
+        self.stalled = (valid && !ready).then_some(data);
+
+        if report.violations.len() > violations {
+            Ok(self.action)
+        } else {
+            Ok(HookAction::Continue)
+        }
+    }
+}
+
+impl Hook for HandshakeChecker {
+    fn pre_clock(
+        &mut self,
+        time: u64,
+        clock_name: &str,
+        model: &Model,
+    ) -> Result<HookAction, HookError> {
+        if clock_name != self.clock {
+            return Ok(HookAction::Continue);
+        }
+        self.check(time, model)
+    }
+
+    fn on_reset(&mut self, _time: u64, _model: &Model) -> Result<(), HookError> {
+        self.stalled = None;
+        Ok(())
+    }
+}
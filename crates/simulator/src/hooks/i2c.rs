@@ -0,0 +1,634 @@
+use super::{Hook, HookAction, HookError};
+use crate::Model;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// Open-drain I2C bus shared by models
+/// the bus is low when any model or the `scl_oe` / `sda_oe` outputs of the design pull it low
+#[derive(Debug, Clone, Default)]
+pub struct I2cBus {
+    pulls: Arc<Mutex<Vec<(bool, bool)>>>, // 各モデルの (SCL, SDA) を Low に引いているか
+}
+
+impl I2cBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn attach(&self) -> usize {
+        let mut pulls = self.pulls.lock().unwrap();
+        pulls.push((false, false));
+        pulls.len() - 1
+    }
+}
+
+/// I2C transaction from a START to the STOP
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct I2cTransfer {
+    /// Time of the STOP condition
+    pub time: u64,
+    /// 7-bit address
+    pub addr: u8,
+    /// Bytes written after the address and acknowledged
+    pub written: Vec<u8>,
+    pub read: Vec<u8>,
+    /// The address was acknowledged
+    pub acked: bool,
+}
+
+impl fmt::Display for I2cTransfer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}ns: 0x{:02x}", self.time, self.addr)?;
+        if !self.acked {
+            return write!(f, " nack");
+        }
+        if !self.written.is_empty() {
+            write!(f, " write {:02x?}", self.written)?;
+        }
+        if !self.read.is_empty() {
+            write!(f, " read {:02x?}", self.read)?;
+        }
+        Ok(())
+    }
+}
+
+/// Transactions observed by an I2C master or slave
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct I2cReport {
+    pub transfers: Vec<I2cTransfer>,
+}
+
+impl fmt::Display for I2cReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} transfers", self.transfers.len())?;
+        for transfer in &self.transfers {
+            write!(f, "\n  {transfer}")?;
+        }
+        Ok(())
+    }
+}
+
+// バスへの接続
+// 設計の scl_i / sda_i にバスのレベルを入力し、scl_oe / sda_oe の出力を Low への駆動とみなす
+struct I2cPort {
+    prefix: String,
+    bus: I2cBus,
+    id: usize,
+}
+
+impl I2cPort {
+    fn new() -> Self {
+        let bus = I2cBus::new();
+        let id = bus.attach();
+        I2cPort {
+            prefix: String::new(),
+            bus,
+            id,
+        }
+    }
+
+    fn connect(&mut self, bus: &I2cBus) {
+        self.bus = bus.clone();
+        self.id = bus.attach();
+    }
+
+    fn pulled_by_design(&self, model: &Model, signal: &str) -> bool {
+        let name = format!("{}{signal}_oe", self.prefix);
+        model.peek(&name).unwrap_or(0) != 0
+    }
+
+    // バスの (SCL, SDA) のレベル
+    fn levels(&self, model: &Model) -> (bool, bool) {
+        let pulls = self.bus.pulls.lock().unwrap();
+        let scl = pulls.iter().any(|x| x.0) || self.pulled_by_design(model, "scl");
+        let sda = pulls.iter().any(|x| x.1) || self.pulled_by_design(model, "sda");
+        (!scl, !sda)
+    }
+
+    // このモデルが解放しているかを設定し、バスのレベルを設計に入力する
+    fn drive(&self, model: &mut Model, scl: bool, sda: bool) -> Result<(bool, bool), HookError> {
+        self.bus.pulls.lock().unwrap()[self.id] = (!scl, !sda);
+        let levels = self.levels(model);
+        for (signal, level) in [("scl_i", levels.0), ("sda_i", levels.1)] {
+            model
+                .try_input(&format!("{}{signal}", self.prefix), level as usize)
+                .map_err(|x| HookError::Other(x.to_string()))?;
+        }
+        Ok(levels)
+    }
+}
+
+// マスタの 1 ステップの操作
+#[derive(Debug, Clone, Copy)]
+enum MasterOp {
+    /// SCL を設定する、解放した場合は High になるまで待つ
+    Scl(bool),
+    Sda(bool),
+    Sample,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Segment {
+    Start,
+    Write { byte: u8, address: bool },
+    Read { ack: bool },
+    Stop,
+}
+
+impl Segment {
+    fn ops(&self) -> Vec<MasterOp> {
+        use MasterOp::*;
+        match *self {
+            Segment::Start => vec![Sda(true), Scl(true), Sda(false), Scl(false)],
+            Segment::Write { byte, .. } => {
+                let mut ops = Vec::new();
+                for i in (0..8).rev() {
+                    ops.extend([Sda((byte >> i) & 1 != 0), Scl(true), Scl(false)]);
+                }
+                ops.extend([Sda(true), Scl(true), Sample, Scl(false)]);
+                ops
+            }
+            Segment::Read { ack } => {
+                let mut ops = vec![Sda(true)];
+                for _ in 0..8 {
+                    ops.extend([Scl(true), Sample, Scl(false)]);
+                }
+                ops.extend([Sda(!ack), Scl(true), Scl(false)]);
+                ops
+            }
+            Segment::Stop => vec![Sda(false), Scl(true), Sda(true)],
+        }
+    }
+}
+
+// This hook is an I2C master issuing the queued transactions
+// each SCL/SDA operation takes `divider` rising edges of the clock,
+// and a released SCL is waited to become high to support clock stretching
+pub struct I2cMaster {
+    clock: String,
+    port: I2cPort,
+    divider: usize,
+    count: usize,
+    requests: VecDeque<Vec<Segment>>,
+    segments: VecDeque<Segment>,
+    segment: Option<Segment>,
+    ops: VecDeque<MasterOp>,
+    scl: bool,
+    sda: bool,
+    byte: u8,
+    current: Option<I2cTransfer>,
+    report: Arc<Mutex<I2cReport>>,
+}
+
+impl I2cMaster {
+    pub fn new(clock: &str) -> Self {
+        I2cMaster {
+            clock: clock.to_string(),
+            port: I2cPort::new(),
+            divider: 4,
+            count: 0,
+            requests: VecDeque::new(),
+            segments: VecDeque::new(),
+            segment: None,
+            ops: VecDeque::new(),
+            scl: true,
+            sda: true,
+            byte: 0,
+            current: None,
+            report: Arc::new(Mutex::new(I2cReport::default())),
+        }
+    }
+
+    /// Prefix of the signal names, e.g. "s_" for `s_scl_i`
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.port.prefix = prefix.to_string();
+        self
+    }
+
+    /// Share the bus with other models
+    pub fn bus(mut self, bus: &I2cBus) -> Self {
+        self.port.connect(bus);
+        self
+    }
+
+    /// Rising edges of the clock per SCL/SDA operation, 4 by default
+    pub fn divider(mut self, divider: usize) -> Self {
+        self.divider = divider.max(1);
+        self
+    }
+
+    pub fn write(self, addr: u8, data: &[u8]) -> Self {
+        self.write_read(addr, data, 0)
+    }
+
+    pub fn read(self, addr: u8, len: usize) -> Self {
+        self.write_read(addr, &[], len)
+    }
+
+    /// Write the data, then read with a repeated START, e.g. a register read
+    pub fn write_read(mut self, addr: u8, data: &[u8], len: usize) -> Self {
+        self.push(addr, data, len);
+        self
+    }
+
+    /// Queue a transaction while the hook is owned by the simulator, see `Simulator::with_hook_mut`
+    pub fn push(&mut self, addr: u8, data: &[u8], len: usize) {
+        let mut segments = vec![Segment::Start];
+        if !data.is_empty() || len == 0 {
+            segments.push(Segment::Write {
+                byte: addr << 1,
+                address: true,
+            });
+            for &byte in data {
+                segments.push(Segment::Write {
+                    byte,
+                    address: false,
+                });
+            }
+            if len > 0 {
+                segments.push(Segment::Start);
+            }
+        }
+        if len > 0 {
+            segments.push(Segment::Write {
+                byte: (addr << 1) | 1,
+                address: true,
+            });
+            for i in 0..len {
+                segments.push(Segment::Read { ack: i + 1 < len });
+            }
+        }
+        segments.push(Segment::Stop);
+        self.requests.push_back(segments);
+    }
+
+    /// All queued transactions are completed
+    pub fn is_idle(&self) -> bool {
+        self.requests.is_empty() && self.segments.is_empty() && self.ops.is_empty()
+    }
+
+    /// Shared report updated at every STOP
+    pub fn report(&self) -> Arc<Mutex<I2cReport>> {
+        self.report.clone()
+    }
+
+    fn next_segment(&mut self, time: u64) {
+        if let Some(Segment::Stop) = self.segment
+            && let Some(mut transfer) = self.current.take()
+        {
+            transfer.time = time;
+            self.report.lock().unwrap().transfers.push(transfer);
+        }
+        if self.segments.is_empty()
+            && let Some(segments) = self.requests.pop_front()
+        {
+            let addr = segments.iter().find_map(|x| match x {
+                Segment::Write {
+                    byte,
+                    address: true,
+                } => Some(byte >> 1),
+                _ => None,
+            });
+            self.current = Some(I2cTransfer {
+                addr: addr.unwrap_or(0),
+                acked: true,
+                ..Default::default()
+            });
+            self.segments.extend(segments);
+        }
+        self.segment = self.segments.pop_front();
+        if let Some(segment) = &self.segment {
+            self.ops.extend(segment.ops());
+            self.byte = 0;
+        }
+    }
+
+    fn sample(&mut self, sda: bool) {
+        let Some(current) = &mut self.current else {
+            return;
+        };
+        match self.segment {
+            Some(Segment::Write { byte, address }) => {
+                if sda {
+                    // NACK の場合は STOP まで飛ばす
+                    if address {
+                        current.acked = false;
+                    }
+                    self.segments.retain(|x| matches!(x, Segment::Stop));
+                } else if !address {
+                    current.written.push(byte);
+                }
+            }
+            Some(Segment::Read { .. }) => {
+                self.byte = (self.byte << 1) | sda as u8;
+                if self.ops.len() == 5 {
+                    current.read.push(self.byte);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn step(&mut self, time: u64, model: &mut Model) -> Result<(), HookError> {
+        if self.ops.is_empty() {
+            self.next_segment(time);
+        }
+        let Some(op) = self.ops.front().copied() else {
+            self.port.drive(model, true, true)?;
+            return Ok(());
+        };
+        match op {
+            MasterOp::Scl(level) => {
+                self.scl = level;
+                let (scl, _) = self.port.drive(model, self.scl, self.sda)?;
+                if level && !scl {
+                    return Ok(());
+                }
+            }
+            MasterOp::Sda(level) => {
+                self.sda = level;
+                self.port.drive(model, self.scl, self.sda)?;
+            }
+            MasterOp::Sample => {
+                let (_, sda) = self.port.levels(model);
+                self.sample(sda);
+            }
+        }
+        self.ops.pop_front();
+        Ok(())
+    }
+}
+
+impl Hook for I2cMaster {
+    fn drive(
+        &mut self,
+        time: u64,
+        clock_name: &str,
+        model: &mut Model,
+    ) -> Result<HookAction, HookError> {
+        if clock_name != self.clock {
+            return Ok(HookAction::Continue);
+        }
+        self.count += 1;
+        if self.count >= self.divider {
+            self.count = 0;
+            self.step(time, model)?;
+        }
+        Ok(HookAction::Continue)
+    }
+
+    fn on_reset(&mut self, _time: u64, _model: &Model) -> Result<(), HookError> {
+        self.count = 0;
+        self.segments.clear();
+        self.segment = None;
+        self.ops.clear();
+        self.scl = true;
+        self.sda = true;
+        self.current = None;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SlaveState {
+    Idle,
+    Address,
+    Write,
+    Read,
+}
+
+// This hook is an I2C slave backed by 8-bit registers
+// the first byte written after the address sets the register pointer,
+// and following writes and reads access the pointed register with auto-increment
+pub struct I2cSlave {
+    clock: String,
+    port: I2cPort,
+    address: u8,
+    stretch: usize,
+    registers: Arc<Mutex<BTreeMap<u8, u8>>>,
+    state: SlaveState,
+    mode: SlaveState, // アドレスの R/W ビットで決まる状態
+    last: Option<(bool, bool)>,
+    bit: usize, // フレーム内の SCL の立ち上がりの数
+    shift: u8,
+    pointer: Option<u8>,
+    master_ack: bool,
+    sda: bool,
+    hold: usize,
+    current: Option<I2cTransfer>,
+    report: Arc<Mutex<I2cReport>>,
+}
+
+impl I2cSlave {
+    /// `address` is the 7-bit address
+    pub fn new(clock: &str, address: u8) -> Self {
+        I2cSlave {
+            clock: clock.to_string(),
+            port: I2cPort::new(),
+            address,
+            stretch: 0,
+            registers: Arc::new(Mutex::new(BTreeMap::new())),
+            state: SlaveState::Idle,
+            mode: SlaveState::Idle,
+            last: None,
+            bit: 0,
+            shift: 0,
+            pointer: None,
+            master_ack: false,
+            sda: true,
+            hold: 0,
+            current: None,
+            report: Arc::new(Mutex::new(I2cReport::default())),
+        }
+    }
+
+    /// Prefix of the signal names, e.g. "s_" for `s_scl_i`
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.port.prefix = prefix.to_string();
+        self
+    }
+
+    /// Share the bus with other models
+    pub fn bus(mut self, bus: &I2cBus) -> Self {
+        self.port.connect(bus);
+        self
+    }
+
+    /// Rising edges of the clock to hold SCL low after each acknowledged byte
+    pub fn stretch(mut self, cycles: usize) -> Self {
+        self.stretch = cycles;
+        self
+    }
+
+    /// Initial value of the register
+    pub fn preload(self, register: u8, data: u8) -> Self {
+        self.registers.lock().unwrap().insert(register, data);
+        self
+    }
+
+    /// Shared registers, unwritten registers read as 0
+    pub fn registers(&self) -> Arc<Mutex<BTreeMap<u8, u8>>> {
+        self.registers.clone()
+    }
+
+    /// Shared report updated at every STOP addressed to this slave
+    pub fn report(&self) -> Arc<Mutex<I2cReport>> {
+        self.report.clone()
+    }
+
+    // 読み出すバイトを取り出して最上位ビットを出力する
+    fn load(&mut self) {
+        let pointer = self.pointer.unwrap_or(0);
+        let byte = self
+            .registers
+            .lock()
+            .unwrap()
+            .get(&pointer)
+            .copied()
+            .unwrap_or(0);
+        self.pointer = Some(pointer.wrapping_add(1));
+        self.shift = byte;
+        self.sda = byte & 0x80 != 0;
+        if let Some(current) = &mut self.current {
+            current.read.push(byte);
+        }
+    }
+
+    // 受信したバイトを処理し、ACK するかを返す
+    fn receive(&mut self) -> bool {
+        let byte = self.shift;
+        match self.state {
+            SlaveState::Address => {
+                if byte >> 1 != self.address {
+                    self.state = SlaveState::Idle;
+                    return false;
+                }
+                self.mode = if byte & 1 != 0 {
+                    SlaveState::Read
+                } else {
+                    SlaveState::Write
+                };
+                self.current.get_or_insert_with(|| I2cTransfer {
+                    addr: self.address,
+                    acked: true,
+                    ..Default::default()
+                });
+                true
+            }
+            SlaveState::Write => {
+                match self.pointer {
+                    Some(pointer) => {
+                        self.registers.lock().unwrap().insert(pointer, byte);
+                        self.pointer = Some(pointer.wrapping_add(1));
+                    }
+                    None => self.pointer = Some(byte),
+                }
+                if let Some(current) = &mut self.current {
+                    current.written.push(byte);
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn on_rise(&mut self, sda: bool) {
+        self.bit += 1;
+        match self.state {
+            SlaveState::Address | SlaveState::Write if self.bit <= 8 => {
+                self.shift = (self.shift << 1) | sda as u8;
+            }
+            SlaveState::Read if self.bit == 9 => self.master_ack = !sda,
+            _ => (),
+        }
+    }
+
+    fn on_fall(&mut self) {
+        match (self.state, self.bit) {
+            (SlaveState::Idle, _) => self.sda = true,
+            (SlaveState::Address | SlaveState::Write, 8) => {
+                let ack = self.receive();
+                self.sda = !ack;
+                if ack {
+                    self.hold = self.stretch;
+                }
+            }
+            // マスタの ACK のために解放する
+            (SlaveState::Read, 8) => self.sda = true,
+            (_, 9) => {
+                self.bit = 0;
+                self.shift = 0;
+                self.sda = true;
+                match self.state {
+                    SlaveState::Address => {
+                        self.state = self.mode;
+                        if self.mode == SlaveState::Read {
+                            self.load();
+                        }
+                    }
+                    SlaveState::Read if self.master_ack => self.load(),
+                    SlaveState::Read => self.state = SlaveState::Idle,
+                    _ => (),
+                }
+            }
+            (SlaveState::Read, x) if (1..8).contains(&x) => {
+                self.sda = (self.shift >> (7 - x)) & 1 != 0;
+            }
+            _ => (),
+        }
+    }
+}
+
+impl Hook for I2cSlave {
+    fn drive(
+        &mut self,
+        time: u64,
+        clock_name: &str,
+        model: &mut Model,
+    ) -> Result<HookAction, HookError> {
+        if clock_name != self.clock {
+            return Ok(HookAction::Continue);
+        }
+        let (scl, sda) = self.port.levels(model);
+        if let Some((last_scl, last_sda)) = self.last {
+            if last_scl && scl && last_sda && !sda {
+                // START (repeated START を含む)
+                self.state = SlaveState::Address;
+                self.bit = 0;
+                self.shift = 0;
+            } else if last_scl && scl && !last_sda && sda {
+                // STOP
+                if let Some(mut transfer) = self.current.take() {
+                    transfer.time = time;
+                    self.report.lock().unwrap().transfers.push(transfer);
+                }
+                self.state = SlaveState::Idle;
+                self.mode = SlaveState::Idle;
+                self.pointer = None;
+            } else if !last_scl && scl {
+                self.on_rise(sda);
+            } else if last_scl && !scl {
+                self.on_fall();
+            }
+        }
+
+        let hold = self.hold > 0;
+        self.hold = self.hold.saturating_sub(1);
+        self.port.drive(model, !hold, self.sda)?;
+        // 自身が解放した SCL の立ち上がりも次に検出するよう、駆動前のレベルを保持する
+        self.last = Some((scl, sda));
+        Ok(HookAction::Continue)
+    }
+
+    fn on_reset(&mut self, _time: u64, _model: &Model) -> Result<(), HookError> {
+        self.state = SlaveState::Idle;
+        self.mode = SlaveState::Idle;
+        self.last = None;
+        self.pointer = None;
+        self.sda = true;
+        self.hold = 0;
+        self.current = None;
+        Ok(())
+    }
+}
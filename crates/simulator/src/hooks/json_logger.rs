@@ -0,0 +1,197 @@
+use super::{Hook, HookAction, HookError, LogWriter, SignalFilter, create_log};
+use crate::Model;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::{self, BufWriter, Write};
+
+// Change of a signal written as a line of JSON
+#[derive(Serialize)]
+struct ChangeRecord<'a> {
+    time: u64,
+    signal: &'a str,
+    old: usize,
+    new: usize,
+    clock: Option<&'a str>, // clock edge which caused the change
+}
+
+// Log signal changes to JSON lines file
+// each line is an object like {"time":5,"signal":"a","old":0,"new":1,"clock":"clk"}
+// "clock" is null for changes which are not caused by clock edges, e.g. inputs driven by the testbench
+pub struct JsonLoggerHook {
+    writer: Option<LogWriter>,
+    error: Option<String>, // error while creating the file
+    filter: SignalFilter,
+    last_values: HashMap<String, usize>, // last recorded values
+}
+
+impl JsonLoggerHook {
+    pub fn new(path: &str) -> Self {
+        let (writer, error) = create_log(path);
+        Self::with_writer(writer, error)
+    }
+
+    /// Write the JSON lines to the writer instead of a file
+    pub fn from_writer(writer: impl Write + Send + 'static) -> Self {
+        Self::with_writer(Some(BufWriter::new(Box::new(writer))), None)
+    }
+
+    fn with_writer(writer: Option<LogWriter>, error: Option<String>) -> Self {
+        JsonLoggerHook {
+            writer,
+            error,
+            filter: SignalFilter::default(),
+            last_values: HashMap::new(),
+        }
+    }
+
+    /// Record only the signals selected by the glob patterns, e.g. `["top.u_core.*", "!*_debug"]`
+    /// see `SignalFilter` for the syntax
+    pub fn filter(mut self, patterns: &[&str]) -> Self {
+        self.filter = SignalFilter::new(patterns);
+        self
+    }
+
+    // 基準となる値を記録する
+    fn load(&mut self, model: &Model) {
+        self.last_values = model
+            .signals()
+            .filter(|x| self.filter.matches(model.module_name(), &x.name))
+            .map(|x| (x.name, x.value))
+            .collect();
+    }
+
+    fn write_changes(&mut self, time: u64, clock: Option<&str>, model: &Model) -> io::Result<()> {
+        for signal in model.signals() {
+            self.write_change(time, &signal.name, signal.value, clock)?;
+        }
+        Ok(())
+    }
+
+    // 前回記録した値から変化した場合のみ出力する
+    fn write_change(
+        &mut self,
+        time: u64,
+        name: &str,
+        value: usize,
+        clock: Option<&str>,
+    ) -> io::Result<()> {
+        let Some(last) = self.last_values.get_mut(name) else {
+            return Ok(());
+        };
+        if *last == value {
+            return Ok(());
+        }
+        let record = ChangeRecord {
+            time,
+            signal: name,
+            old: *last,
+            new: value,
+            clock,
+        };
+        *last = value;
+
+        let Some(ref mut writer) = self.writer else {
+            return Ok(());
+        };
+        serde_json::to_writer(&mut *writer, &record)?;
+        writeln!(writer)
+    }
+
+    fn check_error(&mut self) -> Result<(), HookError> {
+        match self.error.take() {
+            Some(error) => Err(HookError::Io(error)),
+            None => Ok(()),
+        }
+    }
+
+    fn record_change(&mut self, time: u64, name: &str, value: usize) -> Result<(), HookError> {
+        self.check_error()?;
+        self.write_change(time, name, value, None)?;
+        Ok(())
+    }
+
+    fn record_edge(&mut self, time: u64, clock: &str, model: &Model) -> Result<(), HookError> {
+        self.check_error()?;
+        self.write_changes(time, Some(clock), model)?;
+        Ok(())
+    }
+}
+
+impl Hook for JsonLoggerHook {
+    fn on_start(&mut self, model: &Model) -> Result<(), HookError> {
+        self.check_error()?;
+        if self.last_values.is_empty() {
+            self.load(model);
+        }
+        Ok(())
+    }
+
+    fn on_reset(&mut self, time: u64, model: &Model) -> Result<(), HookError> {
+        self.check_error()?;
+        if self.last_values.is_empty() {
+            self.load(model);
+        } else {
+            self.write_changes(time, None, model)?;
+        }
+        Ok(())
+    }
+
+    fn on_input_change(
+        &mut self,
+        time: u64,
+        name: &str,
+        _old: usize,
+        new: usize,
+    ) -> Result<HookAction, HookError> {
+        self.record_change(time, name, new)?;
+        Ok(HookAction::Continue)
+    }
+
+    fn on_signal_change(
+        &mut self,
+        time: u64,
+        name: &str,
+        _old: usize,
+        new: usize,
+    ) -> Result<HookAction, HookError> {
+        self.record_change(time, name, new)?;
+        Ok(HookAction::Continue)
+    }
+
+    // エッジによる変化はクロック名を付けて記録する
+    // 変化の通知より先に呼ばれるため、通知では同じ変化が重複しない
+    fn post_clock(
+        &mut self,
+        time: u64,
+        clock_name: &str,
+        model: &Model,
+    ) -> Result<HookAction, HookError> {
+        self.record_edge(time, clock_name, model)?;
+        Ok(HookAction::Continue)
+    }
+
+    fn post_clock_fall(
+        &mut self,
+        time: u64,
+        clock_name: &str,
+        model: &Model,
+    ) -> Result<HookAction, HookError> {
+        self.record_edge(time, clock_name, model)?;
+        Ok(HookAction::Continue)
+    }
+
+    fn on_finish(&mut self, _time: u64, _model: &Model) -> Result<(), HookError> {
+        if let Some(ref mut writer) = self.writer {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for JsonLoggerHook {
+    fn drop(&mut self) {
+        if let Some(ref mut writer) = self.writer {
+            writer.flush().ok();
+        }
+    }
+}
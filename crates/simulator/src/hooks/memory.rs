@@ -0,0 +1,177 @@
+use super::{Hook, HookAction, HookError};
+use crate::{Model, ModelError};
+use std::collections::{BTreeMap, VecDeque};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+// This hook is a synchronous memory attached to the port signals of the design
+// addr/we/wdata/re are sampled just before rising edges, and rdata/rvalid are driven after them
+// `re` and `rvalid` are optional, the memory is read at every edge without `re`
+pub struct MemoryModel {
+    clock: String,
+    prefix: String,
+    latency: usize,
+    read_only: bool,
+    memory: Arc<Mutex<BTreeMap<usize, usize>>>,
+    pending: VecDeque<(usize, usize)>, // 読み出しデータと出力までの残りエッジ数
+}
+
+impl MemoryModel {
+    pub fn new(clock: &str) -> Self {
+        MemoryModel {
+            clock: clock.to_string(),
+            prefix: String::new(),
+            latency: 1,
+            read_only: false,
+            memory: Arc::new(Mutex::new(BTreeMap::new())),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Prefix of the signal names, e.g. "mem_" for `mem_addr`
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    /// Rising edges from a read request to the edge driving its data, 1 by default
+    /// reads are pipelined, so a request can be issued at every edge
+    pub fn latency(mut self, cycles: usize) -> Self {
+        self.latency = cycles.max(1);
+        self
+    }
+
+    /// Writes from the design are reported as an error
+    pub fn read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    /// Initial value of the word
+    pub fn preload(self, addr: usize, data: usize) -> Self {
+        self.memory.lock().unwrap().insert(addr, data);
+        self
+    }
+
+    /// Load words from a hex file in the `$readmemh` format
+    /// words are separated by whitespace, and `@addr` sets the address of the next word
+    pub fn load_hex<T: AsRef<Path>>(self, path: T) -> Result<Self, ModelError> {
+        let path = path.as_ref().to_string_lossy().to_string();
+        let text = std::fs::read_to_string(&path).map_err(|x| ModelError::ReadFailed {
+            path: path.clone(),
+            cause: x.to_string(),
+        })?;
+        let words = parse_hex(&text, &path)?;
+        self.memory.lock().unwrap().extend(words);
+        Ok(self)
+    }
+
+    /// Shared memory for the backdoor access, unwritten words read as 0
+    pub fn memory(&self) -> Arc<Mutex<BTreeMap<usize, usize>>> {
+        self.memory.clone()
+    }
+
+    fn name(&self, signal: &str) -> String {
+        format!("{}{signal}", self.prefix)
+    }
+
+    fn get(&self, model: &Model, signal: &str) -> Result<usize, HookError> {
+        let name = self.name(signal);
+        model
+            .peek(&name)
+            .ok_or_else(|| HookError::Other(format!("signal \"{name}\" is not found")))
+    }
+}
+
+fn parse_hex(text: &str, path: &str) -> Result<Vec<(usize, usize)>, ModelError> {
+    let error = |line: usize, cause: &str| ModelError::ParseFailed {
+        path: path.to_string(),
+        cause: format!("line {line}: {cause}"),
+    };
+    let parse = |x: &str| usize::from_str_radix(&x.replace('_', ""), 16).ok();
+
+    let mut words = Vec::new();
+    let mut addr = 0;
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split("//").next().unwrap_or_default();
+        for token in line.split_whitespace() {
+            if let Some(x) = token.strip_prefix('@') {
+                addr = parse(x).ok_or_else(|| error(i + 1, &format!("invalid address \"{x}\"")))?;
+            } else {
+                let word = parse(token)
+                    .ok_or_else(|| error(i + 1, &format!("invalid word \"{token}\"")))?;
+                words.push((addr, word));
+                addr += 1;
+            }
+        }
+    }
+    Ok(words)
+}
+
+impl Hook for MemoryModel {
+    fn pre_clock(
+        &mut self,
+        _time: u64,
+        clock_name: &str,
+        model: &Model,
+    ) -> Result<HookAction, HookError> {
+        if clock_name != self.clock {
+            return Ok(HookAction::Continue);
+        }
+        let addr = self.get(model, "addr")?;
+        let read = model.peek(&self.name("re")).is_none_or(|x| x != 0);
+        let write = model.peek(&self.name("we")).unwrap_or(0) != 0;
+
+        // 同じエッジの読み出しは書き込み前の値を返す
+        let mut memory = self.memory.lock().unwrap();
+        if read {
+            let data = memory.get(&addr).copied().unwrap_or(0);
+            self.pending.push_back((data, self.latency));
+        }
+        if write {
+            if self.read_only {
+                return Err(HookError::Other(format!(
+                    "write to read-only memory at 0x{addr:x}"
+                )));
+            }
+            memory.insert(addr, self.get(model, "wdata")?);
+        }
+        Ok(HookAction::Continue)
+    }
+
+    fn drive(
+        &mut self,
+        _time: u64,
+        clock_name: &str,
+        model: &mut Model,
+    ) -> Result<HookAction, HookError> {
+        if clock_name != self.clock {
+            return Ok(HookAction::Continue);
+        }
+        for (_, remaining) in self.pending.iter_mut() {
+            *remaining -= 1;
+        }
+        let ready = match self.pending.front() {
+            Some((data, 0)) => Some(*data),
+            _ => None,
+        };
+        if let Some(data) = ready {
+            self.pending.pop_front();
+            model
+                .try_input(&self.name("rdata"), data)
+                .map_err(|x| HookError::Other(x.to_string()))?;
+        }
+        let rvalid = self.name("rvalid");
+        if model.peek(&rvalid).is_some() {
+            model
+                .try_input(&rvalid, ready.is_some() as usize)
+                .map_err(|x| HookError::Other(x.to_string()))?;
+        }
+        Ok(HookAction::Continue)
+    }
+
+    fn on_reset(&mut self, _time: u64, _model: &Model) -> Result<(), HookError> {
+        self.pending.clear();
+        Ok(())
+    }
+}
@@ -1,27 +1,304 @@
 use crate::Model;
+use std::any::Any;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use thiserror::Error;
 
+pub mod activity;
+pub mod apb;
+pub mod assertion;
 pub mod breakpoint;
 pub mod buf_logger;
+pub mod cosim;
+pub mod coverage;
+pub mod covergroup;
+pub mod csv_logger;
+pub mod filter;
+pub mod handshake;
+pub mod i2c;
+pub mod json_logger;
+pub mod memory;
+pub mod random_driver;
+pub mod scoreboard;
+pub mod spi;
+pub mod stats;
+pub mod temporal;
+pub mod transaction;
+#[cfg(feature = "tui")]
+pub mod tui;
+pub mod uart;
+pub mod vcd_compare;
 pub mod vcd_logger;
+pub mod watchpoint;
 
-pub use breakpoint::BreakPoint;
+pub use activity::{ActivityHook, ActivityReport, BitActivity, SignalActivity};
+pub use apb::{ApbMaster, ApbReport, ApbRequest, ApbSlave, ApbTransfer};
+pub use assertion::{AssertFailure, AssertHook, AssertReport};
+pub use breakpoint::{BreakHit, BreakPoint};
 pub use buf_logger::BufLogger;
+pub use cosim::CosimHook;
+pub use coverage::{CoverageHook, CoverageReport, ToggleReport};
+pub use covergroup::{BinReport, CoverGroup, CoverGroupReport, Coverpoint, CoverpointReport};
+pub use csv_logger::CsvLoggerHook;
+pub use filter::SignalFilter;
+pub use handshake::{
+    HandshakeChecker, HandshakeReport, HandshakeRule, HandshakeTransfer, HandshakeViolation,
+};
+pub use i2c::{I2cBus, I2cMaster, I2cReport, I2cSlave, I2cTransfer};
+pub use json_logger::JsonLoggerHook;
+pub use memory::MemoryModel;
+pub use random_driver::{Distribution, RandomDriver};
+pub use scoreboard::{ReferenceModel, Scoreboard, ScoreboardMismatch, ScoreboardReport};
+pub use spi::{SpiMaster, SpiReport, SpiSlave, SpiTransfer};
+pub use stats::{SignalStats, SignalStatsHook, StatsReport};
+pub use temporal::{PropExpr, Property, PropertyResult, TemporalHook};
+pub use transaction::{Transaction, TransactionLog, TransactionRecorder};
+#[cfg(feature = "tui")]
+pub use tui::TuiHook;
+pub use uart::{UartModel, UartReport};
+pub use vcd_compare::{VcdCompareHook, VcdMismatch};
 pub use vcd_logger::VCDLoggerHook;
+pub use watchpoint::{WatchEvent, WatchPoint};
+
+/// Request from a hook to the simulator
+/// when hooks disagree, the strongest action wins (Abort > Pause > Continue)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum HookAction {
+    /// Keep running
+    #[default]
+    Continue,
+    /// Stop `run` after the current step, it can be resumed by `run` or `step`
+    Pause,
+    /// Stop the simulation, it can't be resumed until `reset` or `restore`
+    Abort,
+}
+
+/// Error reported by a hook, it aborts the simulation
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum HookError {
+    #[error("I/O error: {0}")]
+    Io(String),
+
+    #[error("assertion failed: {0}")]
+    Assertion(String),
+
+    #[error("{0}")]
+    Other(String),
+}
+
+impl From<std::io::Error> for HookError {
+    fn from(x: std::io::Error) -> Self {
+        HookError::Io(x.to_string())
+    }
+}
+
+// Output of the loggers, a file or a writer given by the user
+type LogWriter = BufWriter<Box<dyn Write + Send>>;
+
+// Create the output file of a logger
+// the error is reported by the first call of the hook
+fn create_log(path: &str) -> (Option<LogWriter>, Option<String>) {
+    match File::create(path) {
+        Ok(x) => (Some(BufWriter::new(Box::new(x))), None),
+        Err(x) => (None, Some(format!("failed to create \"{path}\": {x}"))),
+    }
+}
 
 // Hook trait for extending simulator behavior
-pub trait Hook: Send {
+pub trait Hook: Send + Any {
+    /// Called once before the first step, and before the first step after each reset
+    fn on_start(&mut self, _model: &Model) -> Result<(), HookError> {
+        Ok(())
+    }
+
+    /// Called when an input port is changed by the testbench, e.g. `Model::input` or stimuli
+    /// clocks driven by the simulator are not reported
+    fn on_input_change(
+        &mut self,
+        _time: u64,
+        _name: &str,
+        _old: usize,
+        _new: usize,
+    ) -> Result<HookAction, HookError> {
+        Ok(HookAction::Continue)
+    }
+
+    /// Called when an output or internal signal is changed by an event
+    fn on_signal_change(
+        &mut self,
+        _time: u64,
+        _name: &str,
+        _old: usize,
+        _new: usize,
+    ) -> Result<HookAction, HookError> {
+        Ok(HookAction::Continue)
+    }
+
     /// Called at each simulation step
-    fn on_step(&mut self, _time: u64, _model: &Model) {}
+    fn on_step(&mut self, _time: u64, _model: &Model) -> Result<HookAction, HookError> {
+        Ok(HookAction::Continue)
+    }
+
+    /// Called before rising clock edge
+    fn pre_clock(
+        &mut self,
+        _time: u64,
+        _clock_name: &str,
+        _model: &Model,
+    ) -> Result<HookAction, HookError> {
+        Ok(HookAction::Continue)
+    }
+
+    /// Called after rising clock edge
+    fn post_clock(
+        &mut self,
+        _time: u64,
+        _clock_name: &str,
+        _model: &Model,
+    ) -> Result<HookAction, HookError> {
+        Ok(HookAction::Continue)
+    }
+
+    /// Called after `post_clock` with the mutable model, to drive input ports like a testbench
+    /// the driven values are sampled by the design at the next edge
+    fn drive(
+        &mut self,
+        _time: u64,
+        _clock_name: &str,
+        _model: &mut Model,
+    ) -> Result<HookAction, HookError> {
+        Ok(HookAction::Continue)
+    }
 
-    /// Called before clock edge
-    fn pre_clock(&mut self, _time: u64, _clock_name: &str, _model: &Model) {}
+    /// Called before falling clock edge
+    fn pre_clock_fall(
+        &mut self,
+        _time: u64,
+        _clock_name: &str,
+        _model: &Model,
+    ) -> Result<HookAction, HookError> {
+        Ok(HookAction::Continue)
+    }
 
-    /// Called after clock edge
-    fn post_clock(&mut self, _time: u64, _clock_name: &str, _model: &Model) {}
+    /// Called after falling clock edge
+    fn post_clock_fall(
+        &mut self,
+        _time: u64,
+        _clock_name: &str,
+        _model: &Model,
+    ) -> Result<HookAction, HookError> {
+        Ok(HookAction::Continue)
+    }
 
     /// Called at reset
-    fn on_reset(&mut self, _time: u64, _model: &Model) {}
+    fn on_reset(&mut self, _time: u64, _model: &Model) -> Result<(), HookError> {
+        Ok(())
+    }
 
     /// Called at simulation end
-    fn on_finish(&mut self, _time: u64, _model: &Model) {}
+    fn on_finish(&mut self, _time: u64, _model: &Model) -> Result<(), HookError> {
+        Ok(())
+    }
+}
+
+/// Identifier of a hook registered to a simulator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HookId(pub(crate) usize);
+
+struct HookEntry {
+    id: HookId,
+    priority: i32,
+    enabled: bool,
+    hook: Box<dyn Hook>,
+}
+
+// Hooks registered to a simulator, in the order of calls
+// entries are sorted by priority, and keep the order of registration in the same priority
+#[derive(Default)]
+pub(crate) struct HookSet {
+    entries: Vec<HookEntry>,
+    next_id: usize,
+}
+
+impl HookSet {
+    pub(crate) fn add(&mut self, hook: Box<dyn Hook>, priority: i32) -> HookId {
+        let id = HookId(self.next_id);
+        self.next_id += 1;
+        self.insert(HookEntry {
+            id,
+            priority,
+            enabled: true,
+            hook,
+        });
+        id
+    }
+
+    fn insert(&mut self, entry: HookEntry) {
+        let index = self
+            .entries
+            .partition_point(|x| x.priority <= entry.priority);
+        self.entries.insert(index, entry);
+    }
+
+    pub(crate) fn remove(&mut self, id: HookId) -> Option<Box<dyn Hook>> {
+        let index = self.entries.iter().position(|x| x.id == id)?;
+        Some(self.entries.remove(index).hook)
+    }
+
+    pub(crate) fn ids(&self) -> Vec<HookId> {
+        self.entries.iter().map(|x| x.id).collect()
+    }
+
+    pub(crate) fn set_enabled(&mut self, id: HookId, enabled: bool) -> bool {
+        match self.entries.iter_mut().find(|x| x.id == id) {
+            Some(entry) => {
+                entry.enabled = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // 優先度を変えたフックは同じ優先度の中で最後になる
+    pub(crate) fn set_priority(&mut self, id: HookId, priority: i32) -> bool {
+        let Some(index) = self.entries.iter().position(|x| x.id == id) else {
+            return false;
+        };
+        let mut entry = self.entries.remove(index);
+        entry.priority = priority;
+        self.insert(entry);
+        true
+    }
+
+    pub(crate) fn priority(&self, id: HookId) -> Option<i32> {
+        self.entries.iter().find(|x| x.id == id).map(|x| x.priority)
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.entries.iter().all(|x| !x.enabled)
+    }
+
+    pub(crate) fn is_enabled(&self, id: HookId) -> bool {
+        self.entries.iter().any(|x| x.id == id && x.enabled)
+    }
+
+    pub(crate) fn get_mut(&mut self, id: HookId) -> Option<&mut Box<dyn Hook>> {
+        self.entries
+            .iter_mut()
+            .find(|x| x.id == id)
+            .map(|x| &mut x.hook)
+    }
+
+    // Call the enabled hooks, and return the strongest action or the first error
+    // hooks after the error are not called
+    pub(crate) fn call<F>(&mut self, mut f: F) -> Result<HookAction, HookError>
+    where
+        F: FnMut(&mut dyn Hook) -> Result<HookAction, HookError>,
+    {
+        let mut action = HookAction::Continue;
+        for entry in self.entries.iter_mut().filter(|x| x.enabled) {
+            action = action.max(f(entry.hook.as_mut())?);
+        }
+        Ok(action)
+    }
 }
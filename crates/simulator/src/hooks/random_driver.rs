@@ -0,0 +1,176 @@
+use super::{Hook, HookAction, HookError};
+use crate::Model;
+use crate::random::Random;
+use crate::time::{SystemTime, UNIX_EPOCH};
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
+/// Environment variable to override the default seed of `RandomDriver`
+pub const SEED_ENV: &str = "VERYL_SIM_SEED";
+
+/// Distribution of the values driven by `RandomDriver`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Distribution {
+    /// Uniform over all values of the signal width
+    Full,
+    /// Uniform in the range
+    Range(RangeInclusive<usize>),
+    /// Values chosen with the relative weights
+    Values(Vec<(usize, u32)>),
+    /// Ranges chosen with the relative weights, uniform in the chosen range
+    Ranges(Vec<(RangeInclusive<usize>, u32)>),
+}
+
+impl Distribution {
+    fn sample(&self, random: &mut Random, width: usize) -> Option<usize> {
+        match self {
+            Distribution::Full => {
+                let value = random.next_u64() as usize;
+                if width >= usize::BITS as usize {
+                    Some(value)
+                } else {
+                    Some(value & ((1 << width) - 1))
+                }
+            }
+            Distribution::Range(range) => Some(uniform(random, range)),
+            Distribution::Values(values) => {
+                let index = weighted(random, values.iter().map(|x| x.1))?;
+                Some(values[index].0)
+            }
+            Distribution::Ranges(ranges) => {
+                let index = weighted(random, ranges.iter().map(|x| x.1))?;
+                Some(uniform(random, &ranges[index].0))
+            }
+        }
+    }
+}
+
+fn uniform(random: &mut Random, range: &RangeInclusive<usize>) -> usize {
+    let span = range.end().saturating_sub(*range.start()) as u64;
+    match span.checked_add(1) {
+        Some(x) => range.start() + (random.next_u64() % x) as usize,
+        None => random.next_u64() as usize,
+    }
+}
+
+// 重みに従って選んだ要素の位置、重みの合計が 0 なら None
+fn weighted(random: &mut Random, weights: impl Iterator<Item = u32> + Clone) -> Option<usize> {
+    let total: u64 = weights.clone().map(u64::from).sum();
+    if total == 0 {
+        return None;
+    }
+    let mut pick = random.next_u64() % total;
+    for (i, weight) in weights.enumerate() {
+        let weight = u64::from(weight);
+        if pick < weight {
+            return Some(i);
+        }
+        pick -= weight;
+    }
+    None
+}
+
+// This hook drives the selected inputs with random values after every rising edge of the clock
+// the seed is printed when the thread panics, e.g. an assertion of the test fails,
+// and the run can be reproduced by `seed` or the `VERYL_SIM_SEED` environment variable
+pub struct RandomDriver {
+    clock: String,
+    seed: u64,
+    random: Random,
+    signals: Vec<(String, Distribution)>,
+    widths: HashMap<String, usize>,
+}
+
+impl RandomDriver {
+    /// The seed is taken from `VERYL_SIM_SEED`, or the current time if it's not set
+    pub fn new(clock: &str) -> Self {
+        let seed = std::env::var(SEED_ENV)
+            .ok()
+            .and_then(|x| x.parse().ok())
+            .unwrap_or_else(|| {
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|x| x.as_nanos() as u64)
+                    .unwrap_or_default()
+            });
+        RandomDriver {
+            clock: clock.to_string(),
+            seed,
+            random: Random::new(seed),
+            signals: Vec::new(),
+            widths: HashMap::new(),
+        }
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self.random = Random::new(seed);
+        self
+    }
+
+    /// Drive the input with the distribution
+    pub fn drive(mut self, signal: &str, distribution: Distribution) -> Self {
+        self.signals.push((signal.to_string(), distribution));
+        self
+    }
+
+    /// Seed of the current run
+    pub fn current_seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl Hook for RandomDriver {
+    fn on_start(&mut self, model: &Model) -> Result<(), HookError> {
+        self.widths = model.signals().map(|x| (x.name, x.width)).collect();
+        for (signal, _) in &self.signals {
+            if !self.widths.contains_key(signal) {
+                return Err(HookError::Other(format!(
+                    "signal \"{signal}\" is not found"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn drive(
+        &mut self,
+        _time: u64,
+        clock_name: &str,
+        model: &mut Model,
+    ) -> Result<HookAction, HookError> {
+        if clock_name != self.clock {
+            return Ok(HookAction::Continue);
+        }
+        for (signal, distribution) in &self.signals {
+            let width = self
+                .widths
+                .get(signal)
+                .copied()
+                .unwrap_or(usize::BITS as usize);
+            if let Some(value) = distribution.sample(&mut self.random, width) {
+                model
+                    .try_input(signal, value)
+                    .map_err(|x| HookError::Other(x.to_string()))?;
+            }
+        }
+        Ok(HookAction::Continue)
+    }
+
+    // 同じ乱数列を再現できるよう、リセットで乱数の状態を戻す
+    fn on_reset(&mut self, _time: u64, _model: &Model) -> Result<(), HookError> {
+        self.random = Random::new(self.seed);
+        Ok(())
+    }
+}
+
+impl Drop for RandomDriver {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            eprintln!(
+                "RandomDriver seed: {} (set {SEED_ENV}={} to reproduce)",
+                self.seed, self.seed
+            );
+        }
+    }
+}
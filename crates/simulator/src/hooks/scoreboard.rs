@@ -0,0 +1,166 @@
+use super::{Hook, HookAction, HookError};
+use crate::{Direction, Model};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// Expected behavior of the design written in Rust
+pub trait ReferenceModel: Send {
+    /// Called at every rising edge of the clock with the input values sampled before the edge,
+    /// and returns the expected output values after the edge
+    /// outputs which are not returned are not checked
+    fn clock(&mut self, inputs: &HashMap<String, usize>) -> HashMap<String, usize>;
+
+    /// Called at reset of the simulator
+    fn reset(&mut self) {}
+}
+
+impl<F> ReferenceModel for F
+where
+    F: FnMut(&HashMap<String, usize>) -> HashMap<String, usize> + Send,
+{
+    fn clock(&mut self, inputs: &HashMap<String, usize>) -> HashMap<String, usize> {
+        self(inputs)
+    }
+}
+
+/// Output which differs from the reference model
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScoreboardMismatch {
+    pub time: u64,
+    pub signal: String,
+    pub expected: usize,
+    pub actual: usize,
+}
+
+impl fmt::Display for ScoreboardMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}ns: {} expected {} but got {}",
+            self.time, self.signal, self.expected, self.actual
+        )
+    }
+}
+
+/// Result of the comparisons by a scoreboard
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScoreboardReport {
+    /// Number of compared outputs
+    pub checks: usize,
+    pub mismatches: Vec<ScoreboardMismatch>,
+}
+
+impl ScoreboardReport {
+    pub fn passed(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+impl fmt::Display for ScoreboardReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} checks, {} mismatches",
+            self.checks,
+            self.mismatches.len()
+        )?;
+        for mismatch in &self.mismatches {
+            write!(f, "\n  {mismatch}")?;
+        }
+        Ok(())
+    }
+}
+
+// This hook compares the outputs of the design with a reference model
+// the reference model is fed the same inputs at every rising edge of the clock
+pub struct Scoreboard {
+    clock: String,
+    reference: Box<dyn ReferenceModel>,
+    inputs: HashMap<String, usize>, // inputs sampled before the edge
+    action: HookAction,
+    report: Arc<Mutex<ScoreboardReport>>,
+}
+
+impl Scoreboard {
+    pub fn new<T: ReferenceModel + 'static>(clock: &str, reference: T) -> Self {
+        Scoreboard {
+            clock: clock.to_string(),
+            reference: Box::new(reference),
+            inputs: HashMap::new(),
+            action: HookAction::Continue,
+            report: Arc::new(Mutex::new(ScoreboardReport::default())),
+        }
+    }
+
+    /// Action requested to the simulator on mismatches, `HookAction::Continue` by default
+    pub fn action(mut self, action: HookAction) -> Self {
+        self.action = action;
+        self
+    }
+
+    /// Shared report of the comparisons
+    /// it can be read while the hook is owned by the simulator
+    pub fn report(&self) -> Arc<Mutex<ScoreboardReport>> {
+        self.report.clone()
+    }
+}
+
+impl Hook for Scoreboard {
+    fn pre_clock(
+        &mut self,
+        _time: u64,
+        clock_name: &str,
+        model: &Model,
+    ) -> Result<HookAction, HookError> {
+        if clock_name == self.clock {
+            // クロックを除いた入力を参照モデルに渡す
+            let clocks = model.clocks();
+            self.inputs = model
+                .signals()
+                .filter(|x| x.direction == Direction::Input)
+                .filter(|x| !clocks.iter().any(|(y, _)| *y == x.name))
+                .map(|x| (x.name, x.value))
+                .collect();
+        }
+        Ok(HookAction::Continue)
+    }
+
+    fn post_clock(
+        &mut self,
+        time: u64,
+        clock_name: &str,
+        model: &Model,
+    ) -> Result<HookAction, HookError> {
+        if clock_name != self.clock {
+            return Ok(HookAction::Continue);
+        }
+
+        let expected = self.reference.clock(&self.inputs);
+        let mut names: Vec<_> = expected.keys().collect();
+        names.sort();
+
+        let mut action = HookAction::Continue;
+        let mut report = self.report.lock().unwrap();
+        for name in names {
+            let expected = expected[name];
+            report.checks += 1;
+            let actual = model.peek(name).unwrap_or(0);
+            if actual != expected {
+                report.mismatches.push(ScoreboardMismatch {
+                    time,
+                    signal: name.clone(),
+                    expected,
+                    actual,
+                });
+                action = self.action;
+            }
+        }
+        Ok(action)
+    }
+
+    fn on_reset(&mut self, _time: u64, _model: &Model) -> Result<(), HookError> {
+        self.reference.reset();
+        Ok(())
+    }
+}
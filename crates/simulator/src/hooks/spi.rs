@@ -0,0 +1,398 @@
+use super::{Hook, HookAction, HookError};
+use crate::Model;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// Word exchanged on an SPI bus
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpiTransfer {
+    /// Time when the word was completed
+    pub time: u64,
+    pub mosi: usize,
+    pub miso: usize,
+}
+
+impl fmt::Display for SpiTransfer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}ns: mosi 0x{:x}, miso 0x{:x}",
+            self.time, self.mosi, self.miso
+        )
+    }
+}
+
+/// Words exchanged by an SPI master or slave
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SpiReport {
+    pub transfers: Vec<SpiTransfer>,
+}
+
+impl fmt::Display for SpiReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} transfers", self.transfers.len())?;
+        for transfer in &self.transfers {
+            write!(f, "\n  {transfer}")?;
+        }
+        Ok(())
+    }
+}
+
+// SPI のモードとワード形式
+#[derive(Clone)]
+struct SpiFormat {
+    prefix: String,
+    cpol: bool,
+    cpha: bool,
+    bits: usize,
+    lsb_first: bool,
+}
+
+impl SpiFormat {
+    fn new() -> Self {
+        SpiFormat {
+            prefix: String::new(),
+            cpol: false,
+            cpha: false,
+            bits: 8,
+            lsb_first: false,
+        }
+    }
+
+    fn name(&self, signal: &str) -> String {
+        format!("{}{signal}", self.prefix)
+    }
+
+    fn get(&self, model: &Model, signal: &str) -> Result<usize, HookError> {
+        let name = self.name(signal);
+        model
+            .peek(&name)
+            .ok_or_else(|| HookError::Other(format!("signal \"{name}\" is not found")))
+    }
+
+    fn set(&self, model: &mut Model, signal: &str, value: usize) -> Result<(), HookError> {
+        model
+            .try_input(&self.name(signal), value)
+            .map_err(|x| HookError::Other(x.to_string()))
+    }
+
+    // i 番目に転送するビット
+    fn bit(&self, word: usize, i: usize) -> usize {
+        let index = if self.lsb_first { i } else { self.bits - 1 - i };
+        (word >> index) & 1
+    }
+
+    fn shift_in(&self, word: usize, i: usize, bit: usize) -> usize {
+        let index = if self.lsb_first { i } else { self.bits - 1 - i };
+        word | ((bit & 1) << index)
+    }
+}
+
+// 転送中のワード
+struct SpiShift {
+    send: usize,
+    received: usize,
+    index: usize,
+}
+
+// This hook is an SPI master driving sclk, mosi and cs_n, and sampling miso
+// each half period of sclk takes `divider` rising edges of the clock
+pub struct SpiMaster {
+    clock: String,
+    format: SpiFormat,
+    divider: usize,
+    count: usize,
+    words: VecDeque<usize>,
+    shift: Option<SpiShift>,
+    edges: usize, // 現在のワードで出力した sclk のエッジ数
+    report: Arc<Mutex<SpiReport>>,
+}
+
+impl SpiMaster {
+    pub fn new(clock: &str) -> Self {
+        SpiMaster {
+            clock: clock.to_string(),
+            format: SpiFormat::new(),
+            divider: 1,
+            count: 0,
+            words: VecDeque::new(),
+            shift: None,
+            edges: 0,
+            report: Arc::new(Mutex::new(SpiReport::default())),
+        }
+    }
+
+    /// Prefix of the signal names, e.g. "s_" for `s_sclk`
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.format.prefix = prefix.to_string();
+        self
+    }
+
+    /// SPI mode 0-3, the upper bit is CPOL and the lower bit is CPHA
+    pub fn mode(mut self, mode: u8) -> Self {
+        self.format.cpol = mode & 2 != 0;
+        self.format.cpha = mode & 1 != 0;
+        self
+    }
+
+    /// Bits per word, 8 by default
+    pub fn word_bits(mut self, bits: usize) -> Self {
+        self.format.bits = bits.clamp(1, usize::BITS as usize);
+        self
+    }
+
+    /// Transfer the LSB first instead of the MSB
+    pub fn lsb_first(mut self) -> Self {
+        self.format.lsb_first = true;
+        self
+    }
+
+    /// Rising edges of the clock per half period of sclk
+    pub fn divider(mut self, divider: usize) -> Self {
+        self.divider = divider.max(1);
+        self
+    }
+
+    pub fn transfer(mut self, word: usize) -> Self {
+        self.push(word);
+        self
+    }
+
+    /// Queue a word while the hook is owned by the simulator, see `Simulator::with_hook_mut`
+    pub fn push(&mut self, word: usize) {
+        self.words.push_back(word);
+    }
+
+    /// All queued words are transferred
+    pub fn is_idle(&self) -> bool {
+        self.words.is_empty() && self.shift.is_none()
+    }
+
+    /// Shared report updated at every transferred word
+    pub fn report(&self) -> Arc<Mutex<SpiReport>> {
+        self.report.clone()
+    }
+
+    fn step(&mut self, time: u64, model: &mut Model) -> Result<(), HookError> {
+        let format = &self.format;
+        let Some(shift) = &mut self.shift else {
+            // ワードの開始で cs_n をアサートし、CPHA=0 では最初のビットを出力する
+            // sclk をアイドルのレベルにしてから cs_n をアサートする
+            let idle = format.get(model, "cs_n")? != 0
+                && format.get(model, "sclk")? == format.cpol as usize;
+            if !idle {
+                format.set(model, "sclk", format.cpol as usize)?;
+                return format.set(model, "cs_n", 1);
+            }
+            if let Some(word) = self.words.pop_front() {
+                format.set(model, "cs_n", 0)?;
+                if !format.cpha {
+                    format.set(model, "mosi", format.bit(word, 0))?;
+                }
+                self.shift = Some(SpiShift {
+                    send: word,
+                    received: 0,
+                    index: 0,
+                });
+                self.edges = 0;
+            } else {
+                format.set(model, "cs_n", 1)?;
+            }
+            return Ok(());
+        };
+
+        // 全ビットを転送したらデアサートする
+        if self.edges == format.bits * 2 {
+            let transfer = SpiTransfer {
+                time,
+                mosi: shift.send,
+                miso: shift.received,
+            };
+            self.report.lock().unwrap().transfers.push(transfer);
+            self.shift = None;
+            return format.set(model, "cs_n", 1);
+        }
+
+        let leading = self.edges.is_multiple_of(2);
+        if leading != format.cpha {
+            let bit = format.get(model, "miso")?;
+            shift.received = format.shift_in(shift.received, shift.index, bit);
+            shift.index += 1;
+        } else if format.cpha || shift.index < format.bits {
+            format.set(model, "mosi", format.bit(shift.send, shift.index))?;
+        }
+        let level = format.cpol != leading;
+        format.set(model, "sclk", level as usize)?;
+        self.edges += 1;
+        Ok(())
+    }
+}
+
+impl Hook for SpiMaster {
+    fn drive(
+        &mut self,
+        time: u64,
+        clock_name: &str,
+        model: &mut Model,
+    ) -> Result<HookAction, HookError> {
+        if clock_name != self.clock {
+            return Ok(HookAction::Continue);
+        }
+        self.count += 1;
+        if self.count >= self.divider {
+            self.count = 0;
+            self.step(time, model)?;
+        }
+        Ok(HookAction::Continue)
+    }
+
+    fn on_reset(&mut self, _time: u64, _model: &Model) -> Result<(), HookError> {
+        self.count = 0;
+        self.shift = None;
+        Ok(())
+    }
+}
+
+// This hook is an SPI slave responding to sclk, mosi and cs_n driven by the design
+// the signals are checked after every rising edge of the clock, so the design must change them synchronously
+pub struct SpiSlave {
+    clock: String,
+    format: SpiFormat,
+    responses: VecDeque<usize>,
+    shift: Option<SpiShift>,
+    last: Option<(bool, usize, usize)>, // 前回の cs と sclk と mosi
+    report: Arc<Mutex<SpiReport>>,
+}
+
+impl SpiSlave {
+    pub fn new(clock: &str) -> Self {
+        SpiSlave {
+            clock: clock.to_string(),
+            format: SpiFormat::new(),
+            responses: VecDeque::new(),
+            shift: None,
+            last: None,
+            report: Arc::new(Mutex::new(SpiReport::default())),
+        }
+    }
+
+    /// Prefix of the signal names, e.g. "s_" for `s_sclk`
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.format.prefix = prefix.to_string();
+        self
+    }
+
+    /// SPI mode 0-3, the upper bit is CPOL and the lower bit is CPHA
+    pub fn mode(mut self, mode: u8) -> Self {
+        self.format.cpol = mode & 2 != 0;
+        self.format.cpha = mode & 1 != 0;
+        self
+    }
+
+    /// Bits per word, 8 by default
+    pub fn word_bits(mut self, bits: usize) -> Self {
+        self.format.bits = bits.clamp(1, usize::BITS as usize);
+        self
+    }
+
+    /// Transfer the LSB first instead of the MSB
+    pub fn lsb_first(mut self) -> Self {
+        self.format.lsb_first = true;
+        self
+    }
+
+    /// Words sent on miso in order, 0 is sent after they run out
+    pub fn respond(mut self, words: &[usize]) -> Self {
+        self.responses.extend(words);
+        self
+    }
+
+    /// Shared report updated at every transferred word
+    pub fn report(&self) -> Arc<Mutex<SpiReport>> {
+        self.report.clone()
+    }
+
+    fn start(&mut self, model: &mut Model) -> Result<(), HookError> {
+        let send = self.responses.pop_front().unwrap_or(0);
+        if !self.format.cpha {
+            self.format.set(model, "miso", self.format.bit(send, 0))?;
+        }
+        self.shift = Some(SpiShift {
+            send,
+            received: 0,
+            index: 0,
+        });
+        Ok(())
+    }
+}
+
+impl Hook for SpiSlave {
+    fn drive(
+        &mut self,
+        time: u64,
+        clock_name: &str,
+        model: &mut Model,
+    ) -> Result<HookAction, HookError> {
+        if clock_name != self.clock {
+            return Ok(HookAction::Continue);
+        }
+        let cs = self.format.get(model, "cs_n")? == 0;
+        let sclk = self.format.get(model, "sclk")?;
+        let mosi = self.format.get(model, "mosi")?;
+        let last = self.last.replace((cs, sclk, mosi));
+
+        if !cs {
+            // 途中で終わったワードは破棄し、始まっていないワードの応答は次に回す
+            if let Some(shift) = self.shift.take()
+                && shift.index == 0
+            {
+                self.responses.push_front(shift.send);
+            }
+            return Ok(HookAction::Continue);
+        }
+        let Some((last_cs, last_sclk, last_mosi)) = last else {
+            return Ok(HookAction::Continue);
+        };
+        // cs_n の立ち下がりでワードを始める
+        if self.shift.is_none() {
+            if !last_cs {
+                self.start(model)?;
+            }
+            return Ok(HookAction::Continue);
+        }
+        if last_sclk == sclk {
+            return Ok(HookAction::Continue);
+        }
+
+        let format = &self.format;
+        let Some(shift) = &mut self.shift else {
+            return Ok(HookAction::Continue);
+        };
+        let leading = (sclk != 0) != format.cpol;
+        if leading != format.cpha {
+            // エッジ直前の mosi を標本化する
+            shift.received = format.shift_in(shift.received, shift.index, last_mosi);
+            shift.index += 1;
+            if shift.index == format.bits {
+                let transfer = SpiTransfer {
+                    time,
+                    mosi: shift.received,
+                    miso: shift.send,
+                };
+                self.report.lock().unwrap().transfers.push(transfer);
+                // cs_n をアサートしたまま次のワードを続ける
+                self.start(model)?;
+            }
+        } else if format.cpha || shift.index < format.bits {
+            format.set(model, "miso", format.bit(shift.send, shift.index))?;
+        }
+        Ok(HookAction::Continue)
+    }
+
+    fn on_reset(&mut self, _time: u64, _model: &Model) -> Result<(), HookError> {
+        self.shift = None;
+        self.last = None;
+        Ok(())
+    }
+}
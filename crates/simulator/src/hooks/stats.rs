@@ -0,0 +1,218 @@
+use super::{Hook, HookAction, HookError, SignalFilter};
+use crate::Model;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::sync::{Arc, Mutex};
+
+// 既定のヒストグラムの区間数
+const DEFAULT_BINS: usize = 8;
+
+// ヒストグラムの棒の最大の長さ
+const BAR_WIDTH: usize = 20;
+
+/// Statistics of the sampled values of a signal
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SignalStats {
+    pub name: String,
+    pub samples: u64,
+    pub min: usize,
+    pub max: usize,
+    pub mean: f64,
+    /// Equal-width ranges between min and max, and the number of samples in them
+    pub histogram: Vec<(RangeInclusive<usize>, u64)>,
+}
+
+impl fmt::Display for SignalStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: min {}, max {}, mean {:.2} ({} samples)",
+            self.name, self.min, self.max, self.mean, self.samples
+        )?;
+        let peak = self.histogram.iter().map(|(_, x)| *x).max().unwrap_or(0);
+        for (range, count) in &self.histogram {
+            let bar = if peak == 0 {
+                0
+            } else {
+                (*count as usize * BAR_WIDTH).div_ceil(peak as usize)
+            };
+            let label = if range.start() == range.end() {
+                range.start().to_string()
+            } else {
+                format!("{}..={}", range.start(), range.end())
+            };
+            write!(f, "\n  {label:>12} {:<BAR_WIDTH$} {count}", "#".repeat(bar))?;
+        }
+        Ok(())
+    }
+}
+
+/// Statistics of the signals in the order of the names
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StatsReport {
+    pub signals: Vec<SignalStats>,
+}
+
+impl StatsReport {
+    pub fn signal(&self, name: &str) -> Option<&SignalStats> {
+        self.signals.iter().find(|x| x.name == name)
+    }
+}
+
+impl fmt::Display for StatsReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, x) in self.signals.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{x}")?;
+        }
+        Ok(())
+    }
+}
+
+// This hook samples the signals at every rising edge of the clock and computes their statistics
+// the summary is printed at the end of the simulation unless disabled by `print(false)`
+pub struct SignalStatsHook {
+    filter: SignalFilter,
+    clock: Option<String>,
+    bins: usize,
+    print: bool,
+    values: BTreeMap<String, BTreeMap<usize, u64>>, // 信号ごとの値と出現回数
+    report: Arc<Mutex<StatsReport>>,
+}
+
+impl Default for SignalStatsHook {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SignalStatsHook {
+    pub fn new() -> Self {
+        SignalStatsHook {
+            filter: SignalFilter::default(),
+            clock: None,
+            bins: DEFAULT_BINS,
+            print: true,
+            values: BTreeMap::new(),
+            report: Arc::new(Mutex::new(StatsReport::default())),
+        }
+    }
+
+    /// Sample only the signals selected by the glob patterns
+    /// see `SignalFilter` for the syntax
+    pub fn filter(mut self, patterns: &[&str]) -> Self {
+        self.filter = SignalFilter::new(patterns);
+        self
+    }
+
+    /// Clock whose rising edges are sampled, the first clock of the model by default
+    pub fn clock(mut self, clock: &str) -> Self {
+        self.clock = Some(clock.to_string());
+        self
+    }
+
+    /// Number of the ranges of the histograms, 8 by default
+    pub fn bins(mut self, bins: usize) -> Self {
+        self.bins = bins.max(1);
+        self
+    }
+
+    /// Print the summary at the end of the simulation, true by default
+    pub fn print(mut self, print: bool) -> Self {
+        self.print = print;
+        self
+    }
+
+    /// Shared report updated at the end of the simulation
+    /// it can be read while the hook is owned by the simulator
+    pub fn report(&self) -> Arc<Mutex<StatsReport>> {
+        self.report.clone()
+    }
+
+    fn stats(&self, name: &str, values: &BTreeMap<usize, u64>) -> SignalStats {
+        let samples: u64 = values.values().sum();
+        let (Some(min), Some(max)) = (values.keys().next(), values.keys().next_back()) else {
+            return SignalStats {
+                name: name.to_string(),
+                ..Default::default()
+            };
+        };
+        let sum: f64 = values.iter().map(|(v, n)| *v as f64 * *n as f64).sum();
+
+        // min から max までを等幅の区間に分ける
+        let span = max - min;
+        let size = span / self.bins + 1;
+        let mut histogram = Vec::new();
+        let mut lo = *min;
+        loop {
+            let hi = lo.saturating_add(size - 1).min(*max);
+            let count = values.range(lo..=hi).map(|(_, n)| n).sum();
+            histogram.push((lo..=hi, count));
+            if hi == *max {
+                break;
+            }
+            lo = hi + 1;
+        }
+
+        SignalStats {
+            name: name.to_string(),
+            samples,
+            min: *min,
+            max: *max,
+            mean: sum / samples as f64,
+            histogram,
+        }
+    }
+}
+
+impl Hook for SignalStatsHook {
+    fn on_start(&mut self, model: &Model) -> Result<(), HookError> {
+        // 再スタート時は集計を保持する
+        if !self.values.is_empty() {
+            return Ok(());
+        }
+        if self.clock.is_none() {
+            self.clock = model.clocks().first().map(|(x, _)| x.clone());
+        }
+        let clocks: Vec<_> = model.clocks().iter().map(|(x, _)| x.clone()).collect();
+        for x in model.signals() {
+            if !clocks.contains(&x.name) && self.filter.matches(model.module_name(), &x.name) {
+                self.values.insert(x.name, BTreeMap::new());
+            }
+        }
+        Ok(())
+    }
+
+    fn post_clock(
+        &mut self,
+        _time: u64,
+        clock_name: &str,
+        model: &Model,
+    ) -> Result<HookAction, HookError> {
+        if self.clock.as_deref() == Some(clock_name) {
+            for (name, values) in &mut self.values {
+                if let Some(x) = model.peek(name) {
+                    *values.entry(x).or_default() += 1;
+                }
+            }
+        }
+        Ok(HookAction::Continue)
+    }
+
+    fn on_finish(&mut self, _time: u64, _model: &Model) -> Result<(), HookError> {
+        let signals = self
+            .values
+            .iter()
+            .map(|(name, values)| self.stats(name, values))
+            .collect();
+        let mut report = self.report.lock().unwrap();
+        report.signals = signals;
+        if self.print {
+            println!("{report}");
+        }
+        Ok(())
+    }
+}
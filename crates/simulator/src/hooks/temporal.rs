@@ -0,0 +1,316 @@
+use super::{Hook, HookAction, HookError};
+use crate::Model;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// Boolean expression over the sampled signals
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PropExpr {
+    Equals(String, usize),
+    /// The signal was 0 at the previous cycle and is not 0 now
+    Rose(String),
+    /// The signal was not 0 at the previous cycle and is 0 now
+    Fell(String),
+    Not(Box<PropExpr>),
+    And(Box<PropExpr>, Box<PropExpr>),
+    Or(Box<PropExpr>, Box<PropExpr>),
+}
+
+impl PropExpr {
+    /// The signal is not 0
+    pub fn high(signal: &str) -> Self {
+        PropExpr::Not(Box::new(Self::low(signal)))
+    }
+
+    /// The signal is 0
+    pub fn low(signal: &str) -> Self {
+        PropExpr::Equals(signal.to_string(), 0)
+    }
+
+    pub fn equals(signal: &str, value: usize) -> Self {
+        PropExpr::Equals(signal.to_string(), value)
+    }
+
+    pub fn rose(signal: &str) -> Self {
+        PropExpr::Rose(signal.to_string())
+    }
+
+    pub fn fell(signal: &str) -> Self {
+        PropExpr::Fell(signal.to_string())
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(self) -> Self {
+        PropExpr::Not(Box::new(self))
+    }
+
+    pub fn and(self, other: PropExpr) -> Self {
+        PropExpr::And(Box::new(self), Box::new(other))
+    }
+
+    pub fn or(self, other: PropExpr) -> Self {
+        PropExpr::Or(Box::new(self), Box::new(other))
+    }
+
+    fn signals(&self, signals: &mut HashSet<String>) {
+        match self {
+            PropExpr::Equals(x, _) | PropExpr::Rose(x) | PropExpr::Fell(x) => {
+                signals.insert(x.clone());
+            }
+            PropExpr::Not(x) => x.signals(signals),
+            PropExpr::And(x, y) | PropExpr::Or(x, y) => {
+                x.signals(signals);
+                y.signals(signals);
+            }
+        }
+    }
+
+    fn eval(&self, current: &HashMap<String, usize>, previous: &HashMap<String, usize>) -> bool {
+        let value = |x: &String, values: &HashMap<String, usize>| values.get(x).copied();
+        match self {
+            PropExpr::Equals(x, v) => value(x, current) == Some(*v),
+            PropExpr::Rose(x) => value(x, previous) == Some(0) && value(x, current) != Some(0),
+            PropExpr::Fell(x) => {
+                value(x, previous).is_some_and(|x| x != 0) && value(x, current) == Some(0)
+            }
+            PropExpr::Not(x) => !x.eval(current, previous),
+            PropExpr::And(x, y) => x.eval(current, previous) && y.eval(current, previous),
+            PropExpr::Or(x, y) => x.eval(current, previous) || y.eval(current, previous),
+        }
+    }
+}
+
+impl fmt::Display for PropExpr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PropExpr::Equals(x, 0) => write!(f, "!{x}"),
+            PropExpr::Equals(x, v) => write!(f, "{x} == {v}"),
+            PropExpr::Rose(x) => write!(f, "$rose({x})"),
+            PropExpr::Fell(x) => write!(f, "$fell({x})"),
+            PropExpr::Not(x) => match x.as_ref() {
+                PropExpr::Equals(x, 0) => write!(f, "{x}"),
+                x => write!(f, "!({x})"),
+            },
+            PropExpr::And(x, y) => write!(f, "({x} && {y})"),
+            PropExpr::Or(x, y) => write!(f, "({x} || {y})"),
+        }
+    }
+}
+
+/// Temporal property checked at every clock cycle
+///
+/// - `Property::always(expr)` is `always (expr)`
+/// - `Property::implies(antecedent, min, max, consequent)` is `always (antecedent |-> ##[min:max] consequent)`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Property {
+    name: String,
+    antecedent: Option<PropExpr>,
+    delay: (usize, usize), // ##[min:max]
+    consequent: PropExpr,
+}
+
+impl Property {
+    pub fn always(name: &str, expr: PropExpr) -> Self {
+        Property {
+            name: name.to_string(),
+            antecedent: None,
+            delay: (0, 0),
+            consequent: expr,
+        }
+    }
+
+    /// When the antecedent holds, the consequent must hold within `min` to `max` cycles
+    /// the cycle of the antecedent is 0, so `min = 0` is the overlapping implication
+    pub fn implies(
+        name: &str,
+        antecedent: PropExpr,
+        min: usize,
+        max: usize,
+        consequent: PropExpr,
+    ) -> Self {
+        Property {
+            name: name.to_string(),
+            antecedent: Some(antecedent),
+            delay: (min, max.max(min)),
+            consequent,
+        }
+    }
+}
+
+impl fmt::Display for Property {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.antecedent {
+            Some(x) => {
+                let (min, max) = self.delay;
+                write!(f, "always ({x} |-> ##[{min}:{max}] {})", self.consequent)
+            }
+            None => write!(f, "always ({})", self.consequent),
+        }
+    }
+}
+
+/// Result of a property
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PropertyResult {
+    pub name: String,
+    /// Number of started evaluations
+    pub attempts: usize,
+    pub passes: usize,
+    /// Times of the failures
+    pub failures: Vec<u64>,
+    /// Number of evaluations which are not completed yet
+    pub pending: usize,
+}
+
+impl fmt::Display for PropertyResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} attempts, {} passes, {} failures",
+            self.name,
+            self.attempts,
+            self.passes,
+            self.failures.len()
+        )?;
+        if let Some(x) = self.failures.first() {
+            write!(f, " (first at {x}ns)")?;
+        }
+        Ok(())
+    }
+}
+
+struct Entry {
+    property: Property,
+    attempts: Vec<usize>, // cycles where the antecedent held
+}
+
+// This hook evaluates temporal properties at every rising edge of the clock
+// signals are sampled just before the edge like SVA
+pub struct TemporalHook {
+    clock: String,
+    entries: Vec<Entry>,
+    signals: HashSet<String>,
+    previous: HashMap<String, usize>,
+    cycle: usize,
+    action: HookAction,
+    results: Arc<Mutex<Vec<PropertyResult>>>,
+}
+
+impl TemporalHook {
+    pub fn new(clock: &str) -> Self {
+        TemporalHook {
+            clock: clock.to_string(),
+            entries: Vec::new(),
+            signals: HashSet::new(),
+            previous: HashMap::new(),
+            cycle: 0,
+            action: HookAction::Continue,
+            results: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn property(mut self, property: Property) -> Self {
+        if let Some(x) = &property.antecedent {
+            x.signals(&mut self.signals);
+        }
+        property.consequent.signals(&mut self.signals);
+        self.results.lock().unwrap().push(PropertyResult {
+            name: property.name.clone(),
+            ..Default::default()
+        });
+        self.entries.push(Entry {
+            property,
+            attempts: Vec::new(),
+        });
+        self
+    }
+
+    /// Action requested to the simulator on failures, `HookAction::Continue` by default
+    pub fn action(mut self, action: HookAction) -> Self {
+        self.action = action;
+        self
+    }
+
+    /// Shared results in the order of the properties
+    /// it can be read while the hook is owned by the simulator
+    pub fn results(&self) -> Arc<Mutex<Vec<PropertyResult>>> {
+        self.results.clone()
+    }
+
+    fn evaluate(&mut self, time: u64, model: &Model) -> HookAction {
+        let current: HashMap<_, _> = self
+            .signals
+            .iter()
+            .filter_map(|x| model.peek(x).map(|v| (x.clone(), v)))
+            .collect();
+
+        let mut action = HookAction::Continue;
+        let mut results = self.results.lock().unwrap();
+        for (entry, result) in self.entries.iter_mut().zip(results.iter_mut()) {
+            let property = &entry.property;
+            let started = match &property.antecedent {
+                Some(x) => x.eval(&current, &self.previous),
+                None => true,
+            };
+            if started {
+                entry.attempts.push(self.cycle);
+                result.attempts += 1;
+            }
+
+            // 期間内に後件が成立すれば成功、期間を過ぎれば失敗
+            let (min, max) = property.delay;
+            let matched = property.consequent.eval(&current, &self.previous);
+            let cycle = self.cycle;
+            let mut failed = false;
+            entry.attempts.retain(|start| {
+                let offset = cycle - start;
+                if offset >= min && matched {
+                    result.passes += 1;
+                    false
+                } else if offset >= max {
+                    result.failures.push(time);
+                    failed = true;
+                    false
+                } else {
+                    true
+                }
+            });
+            result.pending = entry.attempts.len();
+            if failed {
+                action = self.action;
+            }
+        }
+
+        self.previous = current;
+        self.cycle += 1;
+        action
+    }
+}
+
+impl Hook for TemporalHook {
+    fn pre_clock(
+        &mut self,
+        time: u64,
+        clock_name: &str,
+        model: &Model,
+    ) -> Result<HookAction, HookError> {
+        if clock_name != self.clock {
+            return Ok(HookAction::Continue);
+        }
+        Ok(self.evaluate(time, model))
+    }
+
+    fn on_reset(&mut self, _time: u64, _model: &Model) -> Result<(), HookError> {
+        // 評価中の試行は破棄する
+        for entry in &mut self.entries {
+            entry.attempts.clear();
+        }
+        for result in self.results.lock().unwrap().iter_mut() {
+            result.pending = 0;
+        }
+        self.previous.clear();
+        self.cycle = 0;
+        Ok(())
+    }
+}
@@ -0,0 +1,191 @@
+use super::vcd_logger::{format_date, generate_id};
+use super::{Hook, HookAction, HookError};
+use crate::Model;
+use crate::time::SystemTime;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::sync::{Arc, Mutex};
+
+/// Transaction recorded on a stream, it spans from `start` to `end`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Transaction {
+    pub start: u64,
+    pub end: u64,
+    pub fields: BTreeMap<String, usize>,
+}
+
+impl Transaction {
+    pub fn new(start: u64, end: u64) -> Self {
+        Transaction {
+            start,
+            end,
+            fields: BTreeMap::new(),
+        }
+    }
+
+    pub fn field(mut self, name: &str, value: usize) -> Self {
+        self.fields.insert(name.to_string(), value);
+        self
+    }
+}
+
+impl fmt::Display for Transaction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let fields: Vec<_> = self
+            .fields
+            .iter()
+            .map(|(name, value)| format!("{name}=0x{value:x}"))
+            .collect();
+        write!(f, "{}", fields.join(","))
+    }
+}
+
+/// Transactions grouped by the stream name
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionLog {
+    pub streams: BTreeMap<String, Vec<Transaction>>,
+}
+
+impl TransactionLog {
+    pub fn push(&mut self, stream: &str, transaction: Transaction) {
+        self.streams
+            .entry(stream.to_string())
+            .or_default()
+            .push(transaction);
+    }
+
+    /// Transactions of the stream in the recorded order
+    pub fn stream(&self, name: &str) -> &[Transaction] {
+        self.streams.get(name).map(|x| x.as_slice()).unwrap_or(&[])
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    pub fn write_json(&self, path: &str) -> io::Result<()> {
+        std::fs::write(path, self.to_json())
+    }
+
+    /// Render the streams as VCD string signals
+    /// a signal shows the fields of the active transaction, and "-" while idle
+    pub fn to_vcd(&self) -> String {
+        let names: Vec<_> = self.streams.keys().collect();
+        let mut out = String::new();
+        out.push_str("$date\n");
+        out.push_str(&format!("    {}\n", format_date(SystemTime::now())));
+        out.push_str("$end\n");
+        out.push_str("$version\n");
+        out.push_str(&format!(
+            "    Veryl Simulator {}\n",
+            env!("CARGO_PKG_VERSION")
+        ));
+        out.push_str("$end\n");
+        out.push_str("$timescale\n    1ns\n$end\n");
+        out.push_str("$scope module transactions $end\n");
+        for (i, name) in names.iter().enumerate() {
+            out.push_str(&format!("$var string 1 {} {name} $end\n", generate_id(i)));
+        }
+        out.push_str("$upscope $end\n");
+        out.push_str("$enddefinitions $end\n");
+        out.push_str("#0\n$dumpvars\n");
+        for i in 0..names.len() {
+            out.push_str(&format!("s- {}\n", generate_id(i)));
+        }
+        out.push_str("$end\n");
+
+        // 同じ時刻では終了を先に出力し、続くトランザクションの開始で上書きする
+        let mut changes = Vec::new();
+        for (i, name) in names.iter().enumerate() {
+            for transaction in &self.streams[*name] {
+                changes.push((transaction.end, 0, i, "-".to_string()));
+                changes.push((transaction.start, 1, i, transaction.to_string()));
+            }
+        }
+        changes.sort();
+
+        let mut time = None;
+        for (at, _, i, text) in changes {
+            if time != Some(at) {
+                out.push_str(&format!("#{at}\n"));
+                time = Some(at);
+            }
+            out.push_str(&format!("s{text} {}\n", generate_id(i)));
+        }
+        out
+    }
+
+    pub fn write_vcd(&self, path: &str) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(self.to_vcd().as_bytes())?;
+        writer.flush()
+    }
+}
+
+type Monitor = Box<dyn FnMut(u64, &Model) -> Option<Transaction> + Send>;
+
+// This hook calls monitors just before every rising edge of their clocks
+// a monitor keeps its own state, and returns a transaction when it is completed
+pub struct TransactionRecorder {
+    monitors: Vec<(String, String, Monitor)>,
+    log: Arc<Mutex<TransactionLog>>,
+}
+
+impl Default for TransactionRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TransactionRecorder {
+    pub fn new() -> Self {
+        TransactionRecorder {
+            monitors: Vec::new(),
+            log: Arc::new(Mutex::new(TransactionLog::default())),
+        }
+    }
+
+    /// Add a monitor which pushes transactions onto the stream
+    pub fn monitor<F>(mut self, stream: &str, clock: &str, f: F) -> Self
+    where
+        F: FnMut(u64, &Model) -> Option<Transaction> + Send + 'static,
+    {
+        self.log
+            .lock()
+            .unwrap()
+            .streams
+            .entry(stream.to_string())
+            .or_default();
+        self.monitors
+            .push((stream.to_string(), clock.to_string(), Box::new(f)));
+        self
+    }
+
+    /// Shared log of the streams
+    /// other hooks or the testbench can push transactions to it directly
+    pub fn log(&self) -> Arc<Mutex<TransactionLog>> {
+        self.log.clone()
+    }
+}
+
+impl Hook for TransactionRecorder {
+    fn pre_clock(
+        &mut self,
+        time: u64,
+        clock_name: &str,
+        model: &Model,
+    ) -> Result<HookAction, HookError> {
+        for (stream, clock, monitor) in &mut self.monitors {
+            if clock != clock_name {
+                continue;
+            }
+            if let Some(transaction) = monitor(time, model) {
+                self.log.lock().unwrap().push(stream, transaction);
+            }
+        }
+        Ok(HookAction::Continue)
+    }
+}
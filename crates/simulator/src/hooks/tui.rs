@@ -0,0 +1,312 @@
+use super::buf_logger::render_bus;
+use super::{Hook, HookAction, HookError};
+use crate::{Model, TraceBucket, TraceStorage};
+use ratatui::Terminal;
+use ratatui::backend::{Backend, CrosstermBackend};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph};
+use std::io::Stdout;
+use std::time::{Duration, Instant};
+
+// Number of samples kept at full resolution, it's enough for usual terminal widths
+// older samples are summarized by TraceStorage
+const HISTORY: usize = 512;
+
+// Characters per sample
+const COLUMN_WIDTH: usize = 2;
+
+// Request by a key
+enum Command {
+    Pause,
+    Resume,
+    Step,
+    Overview,
+    Break,
+    Quit,
+}
+
+// This hook shows the selected signals scrolling in the terminal during `run`
+// keys: [space] pause/resume, [s] step while paused, [o] toggle the overview of the whole run,
+// [b] break `run`, [q] abort the simulation
+// signals are sampled after every rising edge into a TraceStorage, so the overview covers
+// the whole run with bounded memory
+//
+// [space] and [s] hold the simulation inside the hook while the viewer is paused, and the
+// simulator isn't paused. only [b] goes through the pause mechanism of the simulator: it returns
+// `HookAction::Pause`, so `run` returns `StopReason::Paused` as on a BreakPoint hit.
+// when `run` returns for any reason, including a BreakPoint hit, the last frame is drawn and
+// the terminal is restored until the next `run`
+pub struct TuiHook<B: Backend = CrosstermBackend<Stdout>> {
+    terminal: Option<Terminal<B>>,
+    interactive: bool, // the terminal is owned by the hook and keys are read
+    init: Option<fn() -> std::io::Result<Terminal<B>>>, // initializer of the owned terminal
+    signals: Vec<String>,
+    trace: TraceStorage,
+    widths: Vec<usize>,
+    refresh: Duration,
+    last_draw: Option<Instant>,
+    paused: bool,
+    overview: bool, // show the whole run instead of the latest samples
+}
+
+impl TuiHook<CrosstermBackend<Stdout>> {
+    /// Show the signals in the terminal
+    /// the terminal enters the alternate screen at the start and is restored at the finish
+    pub fn new(signals: &[&str]) -> Self {
+        let mut hook = Self::build(None, signals);
+        hook.interactive = true;
+        hook.init = Some(ratatui::try_init);
+        hook
+    }
+}
+
+impl<B: Backend> TuiHook<B> {
+    /// Draw to the terminal with any backend, e.g. `TestBackend`
+    /// keys are not read from the terminal
+    pub fn with_terminal(terminal: Terminal<B>, signals: &[&str]) -> Self {
+        Self::build(Some(terminal), signals)
+    }
+
+    fn build(terminal: Option<Terminal<B>>, signals: &[&str]) -> Self {
+        TuiHook {
+            terminal,
+            interactive: false,
+            init: None,
+            signals: signals.iter().map(|x| x.to_string()).collect(),
+            trace: TraceStorage::new(HISTORY, 1),
+            widths: Vec::new(),
+            refresh: Duration::from_millis(33),
+            last_draw: None,
+            paused: false,
+            overview: false,
+        }
+    }
+
+    /// Show the overview of the whole run instead of the latest samples at the start
+    /// the view is toggled by [o] in the terminal
+    pub fn overview(mut self, overview: bool) -> Self {
+        self.overview = overview;
+        self
+    }
+
+    /// Minimum interval of redraws while running, 33ms by default
+    pub fn refresh_interval(mut self, interval: Duration) -> Self {
+        self.refresh = interval;
+        self
+    }
+
+    /// Terminal drawn by the hook
+    pub fn terminal(&self) -> Option<&Terminal<B>> {
+        self.terminal.as_ref()
+    }
+
+    /// Samples of the signals recorded so far
+    pub fn trace(&self) -> &TraceStorage {
+        &self.trace
+    }
+
+    fn draw(&mut self, time: u64) -> std::io::Result<()> {
+        let state = if self.paused {
+            "paused  [space] resume [s] step [o] view [b] break [q] quit"
+        } else {
+            "running  [space] pause [o] view [b] break [q] quit"
+        };
+        let view = if self.overview { "overview, " } else { "" };
+        let title = format!(" {time}ns: {view}{state} ");
+        let label_width = self.signals.iter().map(|x| x.len()).max().unwrap_or(0);
+
+        let Some(terminal) = &mut self.terminal else {
+            return Ok(());
+        };
+        let trace = &self.trace;
+        let signals = &self.signals;
+        let widths = &self.widths;
+        let overview = self.overview;
+        // 最新のサンプルの時刻（どの信号も同じ時刻に記録している）
+        let times: Vec<_> = signals
+            .iter()
+            .map(|x| trace.recent(x))
+            .find(|x| !x.is_empty())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(x, _)| x)
+            .collect();
+        terminal.draw(|frame| {
+            let area = frame.area();
+            // 枠とラベルを除いた幅に、実行全体か、収まる数の最新のサンプルを表示する
+            let lane_width = (area.width as usize).saturating_sub(label_width + 4);
+            let columns = lane_width / COLUMN_WIDTH;
+            let (start, columns) = if overview {
+                (0, columns)
+            } else {
+                let count = columns.min(times.len());
+                (times.get(times.len() - count).copied().unwrap_or(0), count)
+            };
+
+            let mut lines = Vec::new();
+            for (i, name) in signals.iter().enumerate() {
+                let buckets = trace.overview(name, start, time + 1, columns);
+                let lane: String = if widths.get(i).copied().unwrap_or(1) <= 1 {
+                    buckets.iter().map(|x| render_bit(x.as_ref())).collect()
+                } else {
+                    let values: Vec<_> = buckets.iter().map(|x| x.map(|x| x.last)).collect();
+                    render_bus(&values, COLUMN_WIDTH)
+                };
+                lines.push(Line::from(format!("{name:label_width$}  {lane}")));
+            }
+            let widget = Paragraph::new(lines).block(Block::bordered().title(title.as_str()));
+            frame.render_widget(widget, area);
+        })?;
+        self.last_draw = Some(Instant::now());
+        Ok(())
+    }
+
+    // 押されたキーを読む、一時停止中は入力を待つ
+    fn read_command(&self) -> std::io::Result<Option<Command>> {
+        if !self.interactive {
+            return Ok(None);
+        }
+        let wait = if self.paused {
+            Duration::from_secs(3600)
+        } else {
+            Duration::ZERO
+        };
+        while event::poll(wait)? {
+            if let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+            {
+                let command = match key.code {
+                    KeyCode::Char(' ') if self.paused => Command::Resume,
+                    KeyCode::Char(' ') => Command::Pause,
+                    KeyCode::Char('s') if self.paused => Command::Step,
+                    KeyCode::Char('o') => Command::Overview,
+                    KeyCode::Char('b') => Command::Break,
+                    KeyCode::Char('q') => Command::Quit,
+                    _ => continue,
+                };
+                return Ok(Some(command));
+            }
+            if !self.paused {
+                break;
+            }
+        }
+        Ok(None)
+    }
+
+    fn sample(&mut self, time: u64, model: &Model) -> Result<HookAction, HookError> {
+        for name in &self.signals {
+            if let Some(value) = model.peek(name) {
+                self.trace.record(time, name, value);
+            }
+        }
+
+        // 前回の run の終わりに戻した端末を開き直す
+        if let Some(init) = self.init
+            && self.terminal.is_none()
+        {
+            self.terminal = Some(init()?);
+        }
+
+        if self.paused || self.last_draw.is_none_or(|x| x.elapsed() >= self.refresh) {
+            self.draw(time)?;
+        }
+
+        loop {
+            match self.read_command()? {
+                Some(Command::Pause) => {
+                    self.paused = true;
+                    self.draw(time)?;
+                }
+                Some(Command::Resume) => {
+                    self.paused = false;
+                    return Ok(HookAction::Continue);
+                }
+                Some(Command::Overview) => {
+                    self.overview = !self.overview;
+                    self.draw(time)?;
+                }
+                // 次のエッジで再び停止する
+                Some(Command::Step) => return Ok(HookAction::Continue),
+                Some(Command::Break) => {
+                    self.paused = false;
+                    self.draw(time)?;
+                    return Ok(HookAction::Pause);
+                }
+                Some(Command::Quit) => return Ok(HookAction::Abort),
+                None if self.paused => continue,
+                None => return Ok(HookAction::Continue),
+            }
+        }
+    }
+
+    fn close(&mut self) {
+        if self.interactive && self.terminal.take().is_some() {
+            ratatui::restore();
+        }
+    }
+}
+
+// 1 ビットの信号の列、列の中で変化した場合は両方の値を示す
+fn render_bit(bucket: Option<&TraceBucket>) -> String {
+    let symbol = match bucket {
+        Some(x) if x.min != x.max => "▒",
+        Some(x) if x.last == 0 => "▁",
+        Some(_) => "▔",
+        None => " ",
+    };
+    symbol.repeat(COLUMN_WIDTH)
+}
+
+impl<B: Backend + Send + 'static> Hook for TuiHook<B> {
+    fn on_start(&mut self, model: &Model) -> Result<(), HookError> {
+        self.widths = self
+            .signals
+            .iter()
+            .map(|x| {
+                model
+                    .signals()
+                    .find(|y| y.name == *x)
+                    .map(|y| y.width)
+                    .unwrap_or(1)
+            })
+            .collect();
+        if let Some(init) = self.init
+            && self.terminal.is_none()
+        {
+            self.terminal = Some(init()?);
+        }
+        Ok(())
+    }
+
+    fn post_clock(
+        &mut self,
+        time: u64,
+        _clock_name: &str,
+        model: &Model,
+    ) -> Result<HookAction, HookError> {
+        let action = self.sample(time, model);
+        if matches!(action, Ok(HookAction::Abort) | Err(_)) {
+            self.close();
+        }
+        action
+    }
+
+    fn on_reset(&mut self, _time: u64, _model: &Model) -> Result<(), HookError> {
+        // リセットで時刻が 0 に戻るので、記録をやり直す
+        self.trace = TraceStorage::new(HISTORY, 1);
+        Ok(())
+    }
+
+    fn on_finish(&mut self, time: u64, _model: &Model) -> Result<(), HookError> {
+        self.draw(time)?;
+        self.close();
+        Ok(())
+    }
+}
+
+impl<B: Backend> Drop for TuiHook<B> {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
@@ -0,0 +1,216 @@
+use super::{Hook, HookAction, HookError};
+use crate::Model;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+// start bit, 8 data bits (LSB first) and stop bit
+const FRAME_BITS: u64 = 10;
+
+/// Bytes received from the DUT by a UART model
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UartReport {
+    pub received: Vec<u8>,
+    /// Times of the frames whose stop bit was low
+    pub framing_errors: Vec<u64>,
+}
+
+impl UartReport {
+    /// Received bytes as text, invalid UTF-8 is replaced
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.received).into_owned()
+    }
+}
+
+impl fmt::Display for UartReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} bytes received, {} framing errors",
+            self.received.len(),
+            self.framing_errors.len()
+        )
+    }
+}
+
+// 送信中のフレーム
+struct TxFrame {
+    start: u64,
+    data: u8,
+}
+
+// 受信中のフレーム
+struct RxFrame {
+    start: u64,
+    bit: u64,
+    data: u8,
+}
+
+// This hook is the partner of a UART peripheral, with 8N1 frames at the configured baud rate
+// bytes are serialized onto the DUT rx pin after rising edges of the clock,
+// and the DUT tx pin is sampled at the middle of each bit just before rising edges
+pub struct UartModel {
+    clock: String,
+    rx: String,
+    tx: String,
+    bit_ns: u64,
+    queue: VecDeque<u8>,
+    sending: Option<TxFrame>,
+    line_high: bool, // スタートビットの前に一度はアイドル状態を出力する
+    receiving: Option<RxFrame>,
+    report: Arc<Mutex<UartReport>>,
+}
+
+impl UartModel {
+    /// `rx` and `tx` are the pins of the DUT, the default baud rate is 115200
+    pub fn new(clock: &str, rx: &str, tx: &str) -> Self {
+        UartModel {
+            clock: clock.to_string(),
+            rx: rx.to_string(),
+            tx: tx.to_string(),
+            bit_ns: 1_000_000_000 / 115_200,
+            queue: VecDeque::new(),
+            sending: None,
+            line_high: false,
+            receiving: None,
+            report: Arc::new(Mutex::new(UartReport::default())),
+        }
+    }
+
+    /// Baud rate in bits per second of the simulation time
+    pub fn baud(mut self, baud: u64) -> Self {
+        self.bit_ns = (1_000_000_000 / baud.max(1)).max(1);
+        self
+    }
+
+    /// Bytes sent to the DUT from the start of the simulation
+    pub fn send(mut self, bytes: &[u8]) -> Self {
+        self.queue.extend(bytes);
+        self
+    }
+
+    /// Queue a byte while the hook is owned by the simulator, see `Simulator::with_hook_mut`
+    pub fn push(&mut self, byte: u8) {
+        self.queue.push_back(byte);
+    }
+
+    /// All queued bytes are sent
+    pub fn is_idle(&self) -> bool {
+        self.queue.is_empty() && self.sending.is_none()
+    }
+
+    /// Shared report updated at every received frame
+    pub fn report(&self) -> Arc<Mutex<UartReport>> {
+        self.report.clone()
+    }
+
+    // 送信中のビットの値、フレームが終わったら次のバイトを送り始める
+    fn tx_level(&mut self, time: u64) -> usize {
+        if let Some(frame) = &self.sending {
+            let end = frame.start + FRAME_BITS * self.bit_ns;
+            if time >= end {
+                let start = end;
+                self.sending = self.queue.pop_front().map(|data| TxFrame { start, data });
+            }
+        } else if self.line_high {
+            self.sending = self
+                .queue
+                .pop_front()
+                .map(|data| TxFrame { start: time, data });
+        }
+
+        let Some(frame) = &self.sending else {
+            self.line_high = true;
+            return 1;
+        };
+        match time.saturating_sub(frame.start) / self.bit_ns {
+            0 => 0,
+            x if x <= 8 => ((frame.data >> (x - 1)) & 1) as usize,
+            _ => 1,
+        }
+    }
+}
+
+impl Hook for UartModel {
+    fn on_signal_change(
+        &mut self,
+        time: u64,
+        name: &str,
+        old: usize,
+        new: usize,
+    ) -> Result<HookAction, HookError> {
+        // スタートビットの立ち下がりでフレームの受信を始める
+        if name == self.tx && self.receiving.is_none() && old != 0 && new == 0 {
+            self.receiving = Some(RxFrame {
+                start: time,
+                bit: 0,
+                data: 0,
+            });
+        }
+        Ok(HookAction::Continue)
+    }
+
+    fn pre_clock(
+        &mut self,
+        time: u64,
+        clock_name: &str,
+        model: &Model,
+    ) -> Result<HookAction, HookError> {
+        if clock_name != self.clock {
+            return Ok(HookAction::Continue);
+        }
+        let Some(frame) = &mut self.receiving else {
+            return Ok(HookAction::Continue);
+        };
+        // ビットの中央を過ぎた最初のエッジで標本化する
+        if time < frame.start + frame.bit * self.bit_ns + self.bit_ns / 2 {
+            return Ok(HookAction::Continue);
+        }
+        let level = model
+            .peek(&self.tx)
+            .ok_or_else(|| HookError::Other(format!("signal \"{}\" is not found", self.tx)))?;
+
+        match frame.bit {
+            // スタートビットが短すぎる場合はノイズとして無視する
+            0 if level != 0 => self.receiving = None,
+            0 => frame.bit += 1,
+            x if x < FRAME_BITS - 1 => {
+                frame.data |= ((level & 1) as u8) << (x - 1);
+                frame.bit += 1;
+            }
+            _ => {
+                let mut report = self.report.lock().unwrap();
+                if level != 0 {
+                    report.received.push(frame.data);
+                } else {
+                    report.framing_errors.push(frame.start);
+                }
+                self.receiving = None;
+            }
+        }
+        Ok(HookAction::Continue)
+    }
+
+    fn drive(
+        &mut self,
+        time: u64,
+        clock_name: &str,
+        model: &mut Model,
+    ) -> Result<HookAction, HookError> {
+        if clock_name != self.clock {
+            return Ok(HookAction::Continue);
+        }
+        let level = self.tx_level(time);
+        model
+            .try_input(&self.rx, level)
+            .map_err(|x| HookError::Other(x.to_string()))?;
+        Ok(HookAction::Continue)
+    }
+
+    fn on_reset(&mut self, _time: u64, _model: &Model) -> Result<(), HookError> {
+        self.sending = None;
+        self.line_high = false;
+        self.receiving = None;
+        Ok(())
+    }
+}
@@ -0,0 +1,203 @@
+use super::{Hook, HookAction, HookError, SignalFilter};
+use crate::{Model, ModelError};
+use std::collections::HashMap;
+use std::fmt;
+
+// Value changes of a signal, None is an unknown value
+type Wave = Vec<(u64, Option<usize>)>;
+
+/// First difference between the simulation and the golden waveform
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VcdMismatch {
+    pub time: u64,
+    pub signal: String,
+    pub expected: usize,
+    pub actual: usize,
+}
+
+impl fmt::Display for VcdMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "mismatch at {}ns: {} expected {} but got {}",
+            self.time, self.signal, self.expected, self.actual
+        )
+    }
+}
+
+// This hook compares the simulation with a golden VCD file
+// signals are compared after every clock edge with the golden value at the same time,
+// and the first mismatch fails the simulation
+// unknown values (x, z) in the golden waveform and signals missing in the model are not compared
+pub struct VcdCompareHook {
+    waves: Vec<(String, Wave)>, // (signal, value changes)
+    filter: SignalFilter,
+    mismatch: Option<VcdMismatch>,
+}
+
+impl VcdCompareHook {
+    pub fn new(golden_path: &str) -> Result<Self, ModelError> {
+        let text = std::fs::read_to_string(golden_path).map_err(|x| ModelError::ReadFailed {
+            path: golden_path.to_string(),
+            cause: x.to_string(),
+        })?;
+        Self::parse(&text).map_err(|x| ModelError::ParseFailed {
+            path: golden_path.to_string(),
+            cause: x,
+        })
+    }
+
+    /// Compare only the signals selected by the glob patterns
+    /// see `SignalFilter` for the syntax
+    pub fn filter(mut self, patterns: &[&str]) -> Self {
+        self.filter = SignalFilter::new(patterns);
+        self
+    }
+
+    /// First mismatch found by the hook
+    pub fn mismatch(&self) -> Option<&VcdMismatch> {
+        self.mismatch.as_ref()
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut tokens = text.split_whitespace();
+        let mut scopes: Vec<String> = Vec::new();
+        let mut ids: HashMap<String, Vec<usize>> = HashMap::new(); // VCD identifier -> signals
+        let mut waves: Vec<(String, Wave)> = Vec::new();
+        let mut time = 0;
+
+        // 宣言部: 最上位の scope を除いた階層名で信号を登録する
+        let mut declarations = Vec::new();
+        while let Some(token) = tokens.next() {
+            match token {
+                "$scope" => {
+                    let _kind = tokens.next();
+                    let name = tokens.next().ok_or("missing scope name")?;
+                    scopes.push(name.to_string());
+                    skip_to_end(&mut tokens);
+                }
+                "$upscope" => {
+                    scopes.pop();
+                    skip_to_end(&mut tokens);
+                }
+                "$var" => {
+                    let fields: Vec<_> = tokens.by_ref().take_while(|x| *x != "$end").collect();
+                    let [_kind, _width, id, reference, ..] = fields[..] else {
+                        return Err("invalid $var".to_string());
+                    };
+                    let mut path: Vec<_> = scopes.iter().skip(1).map(|x| x.as_str()).collect();
+                    path.push(reference);
+                    declarations.push((id.to_string(), path.join(".")));
+                }
+                "$enddefinitions" => {
+                    skip_to_end(&mut tokens);
+                    break;
+                }
+                _ if token.starts_with('$') => skip_to_end(&mut tokens),
+                _ => return Err(format!("unexpected \"{token}\" in the header")),
+            }
+        }
+        for (id, name) in declarations {
+            ids.entry(id).or_default().push(waves.len());
+            waves.push((name, Vec::new()));
+        }
+
+        let mut change = |id: &str, value: Option<usize>, time: u64| -> Result<(), String> {
+            let signals = ids
+                .get(id)
+                .ok_or_else(|| format!("unknown identifier \"{id}\""))?;
+            for i in signals {
+                waves[*i].1.push((time, value));
+            }
+            Ok(())
+        };
+
+        // 値の変化
+        while let Some(token) = tokens.next() {
+            if let Some(x) = token.strip_prefix('#') {
+                time = x.parse().map_err(|_| format!("invalid time \"{token}\""))?;
+            } else if let Some(x) = token.strip_prefix(['b', 'B']) {
+                let id = tokens.next().ok_or("missing identifier")?;
+                change(id, usize::from_str_radix(x, 2).ok(), time)?;
+            } else if token.starts_with(['r', 'R']) {
+                // 実数は比較しない
+                tokens.next();
+            } else if let Some(x) = token.strip_prefix(['0', '1', 'x', 'X', 'z', 'Z']) {
+                let value = match &token[..1] {
+                    "0" => Some(0),
+                    "1" => Some(1),
+                    _ => None,
+                };
+                change(x, value, time)?;
+            } else if token == "$comment" {
+                skip_to_end(&mut tokens);
+            } else if !token.starts_with('$') {
+                return Err(format!("unexpected \"{token}\""));
+            }
+        }
+
+        Ok(VcdCompareHook {
+            waves,
+            filter: SignalFilter::default(),
+            mismatch: None,
+        })
+    }
+
+    fn compare(&mut self, time: u64, model: &Model) -> Result<HookAction, HookError> {
+        if self.mismatch.is_some() {
+            return Ok(HookAction::Continue);
+        }
+        for (name, wave) in &self.waves {
+            if !self.filter.matches(model.module_name(), name) {
+                continue;
+            }
+            let index = wave.partition_point(|(x, _)| *x <= time);
+            let (Some(expected), Some(actual)) = (
+                index.checked_sub(1).and_then(|x| wave[x].1),
+                model.peek(name),
+            ) else {
+                continue;
+            };
+            if expected != actual {
+                let mismatch = VcdMismatch {
+                    time,
+                    signal: name.clone(),
+                    expected,
+                    actual,
+                };
+                let message = mismatch.to_string();
+                self.mismatch = Some(mismatch);
+                return Err(HookError::Assertion(message));
+            }
+        }
+        Ok(HookAction::Continue)
+    }
+}
+
+fn skip_to_end<'a>(tokens: &mut impl Iterator<Item = &'a str>) {
+    for token in tokens.by_ref() {
+        if token == "$end" {
+            break;
+        }
+    }
+}
+
+impl Hook for VcdCompareHook {
+    fn post_clock(
+        &mut self,
+        time: u64,
+        _clock_name: &str,
+        model: &Model,
+    ) -> Result<HookAction, HookError> {
+        self.compare(time, model)
+    }
+
+    fn post_clock_fall(
+        &mut self,
+        time: u64,
+        _clock_name: &str,
+        model: &Model,
+    ) -> Result<HookAction, HookError> {
+        self.compare(time, model)
+    }
+}
@@ -1,180 +1,522 @@
-use super::Hook;
-use crate::Model;
+use super::{Hook, HookAction, HookError, LogWriter, SignalFilter, create_log};
+use crate::time::{SystemTime, UNIX_EPOCH};
+use crate::{Direction, Model};
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::{BufWriter, Write};
+use std::io::{self, BufWriter, Write};
+
+// Condition to start or stop dumping
+enum DumpTrigger {
+    Time(u64),
+    Equals(String, usize),
+}
+
+impl DumpTrigger {
+    fn is_met(&self, time: u64, model: &Model) -> bool {
+        match self {
+            DumpTrigger::Time(x) => time >= *x,
+            DumpTrigger::Equals(x, value) => model.peek(x) == Some(*value),
+        }
+    }
+}
 
 // Log changes to VCD file, and not to buffer
 // this logger consumes less memory, but cannot pre-process waveform data
+// all signals of the model are recorded, and only changed values are written at each time
 pub struct VCDLoggerHook {
-    writer: Option<BufWriter<File>>,
+    writer: Option<LogWriter>,
+    path: String,
+    gtkw_path: Option<String>,           // GTKWave save file
+    error: Option<String>,               // error while creating the file
     signal_ids: HashMap<String, String>, // signal name -> VCD identifier
+    widths: HashMap<String, usize>,      // signal name -> width
+    filter: SignalFilter,                // recorded signals
     last_values: HashMap<String, usize>, // last recorded values
-    next_id_char: u8,                    // for generating unique identifiers
+    last_time: Option<u64>,              // time of the last written timestamp
     initialized: bool,
+    dumping: bool,              // whether changes are written
+    requested: Option<bool>,    // dump on/off requested by the testbench
+    start: Option<DumpTrigger>, // condition to start dumping
+    stop: Option<DumpTrigger>,  // condition to stop dumping
 }
 
 impl VCDLoggerHook {
     pub fn new(path: &str) -> Self {
-        let file = File::create(path).ok();
-        let writer = file.map(|f| BufWriter::new(f));
+        let (writer, error) = create_log(path);
+        Self::with_writer(writer, path, error)
+    }
+
+    /// Write the VCD to the writer instead of a file, e.g. a buffer streamed to a browser
+    /// the dump file of `gtkw` is left empty
+    pub fn from_writer(writer: impl Write + Send + 'static) -> Self {
+        Self::with_writer(Some(BufWriter::new(Box::new(writer))), "", None)
+    }
 
+    fn with_writer(writer: Option<LogWriter>, path: &str, error: Option<String>) -> Self {
         VCDLoggerHook {
             writer,
+            path: path.to_string(),
+            gtkw_path: None,
+            error,
             signal_ids: HashMap::new(),
+            widths: HashMap::new(),
+            filter: SignalFilter::default(),
             last_values: HashMap::new(),
-            next_id_char: b'!', // Start with ASCII '!'
+            last_time: None,
             initialized: false,
+            dumping: true,
+            requested: None,
+            start: None,
+            stop: None,
         }
     }
 
-    fn generate_id(&mut self) -> String {
-        let id = String::from(char::from(self.next_id_char));
-        self.next_id_char += 1;
-        // Skip problematic characters
-        if self.next_id_char == b'#' || self.next_id_char == b'$' {
-            self.next_id_char += 1;
-        }
-        // Wrap around to multi-character IDs if needed
-        if self.next_id_char > b'~' {
-            self.next_id_char = b'!';
-        }
-        id
+    /// Write a GTKWave save file which pre-loads the clocks, resets and ports of the top module
+    /// it's written with the VCD header, and opened by `gtkwave <path>`
+    pub fn gtkw(mut self, path: &str) -> Self {
+        self.gtkw_path = Some(path.to_string());
+        self
     }
 
-    fn write_header(&mut self, model: &Model) {
-        // Collect signals before borrowing writer
-        let signals = self.collect_signals(model);
+    /// Start dumping at the first event at or after the time
+    /// dumping is off until then
+    pub fn start_at(mut self, time: u64) -> Self {
+        self.start = Some(DumpTrigger::Time(time));
+        self.dumping = false;
+        self
+    }
 
-        // Generate all IDs first
-        let mut signal_id_pairs = Vec::new();
-        for signal_name in signals.keys() {
-            let id = self.generate_id();
-            self.signal_ids.insert(signal_name.clone(), id.clone());
-            signal_id_pairs.push((signal_name.clone(), id));
-        }
+    /// Stop dumping at the first event at or after the time
+    pub fn stop_at(mut self, time: u64) -> Self {
+        self.stop = Some(DumpTrigger::Time(time));
+        self
+    }
 
-        if let Some(ref mut writer) = self.writer {
-            // Write VCD header
-            writeln!(writer, "$date").ok();
-            writeln!(writer, "    Generated by Veryl Simulator").ok();
-            writeln!(writer, "$end").ok();
+    /// Start dumping when the signal equals the value
+    /// dumping is off until then
+    pub fn start_when_equals(mut self, signal: &str, value: usize) -> Self {
+        self.start = Some(DumpTrigger::Equals(signal.to_string(), value));
+        self.dumping = false;
+        self
+    }
 
-            writeln!(writer, "$version").ok();
-            writeln!(writer, "    Veryl Simulator 0.1.0").ok();
-            writeln!(writer, "$end").ok();
+    /// Stop dumping when the signal equals the value
+    pub fn stop_when_equals(mut self, signal: &str, value: usize) -> Self {
+        self.stop = Some(DumpTrigger::Equals(signal.to_string(), value));
+        self
+    }
 
-            writeln!(writer, "$timescale").ok();
-            writeln!(writer, "    1ns").ok();
-            writeln!(writer, "$end").ok();
+    /// Resume dumping like `$dumpon`, all current values are written at the next event
+    pub fn dump_on(&mut self) {
+        self.requested = Some(true);
+    }
 
-            // Define module scope
-            writeln!(writer, "$scope module top $end").ok();
+    /// Suspend dumping like `$dumpoff`, all signals become unknown from the next event
+    pub fn dump_off(&mut self) {
+        self.requested = Some(false);
+    }
 
-            // Register all signals
-            for (signal_name, id) in signal_id_pairs {
-                writeln!(writer, "$var wire 32 {} {} $end", id, signal_name).ok();
-            }
+    /// Whether changes are written to the file
+    pub fn is_dumping(&self) -> bool {
+        self.requested.unwrap_or(self.dumping)
+    }
 
-            writeln!(writer, "$upscope $end").ok();
-            writeln!(writer, "$enddefinitions $end").ok();
+    /// Record only the signals selected by the glob patterns, e.g. `["top.u_core.*", "!*_debug"]`
+    /// see `SignalFilter` for the syntax
+    pub fn filter(mut self, patterns: &[&str]) -> Self {
+        self.filter = SignalFilter::new(patterns);
+        self
+    }
 
-            writer.flush().ok();
+    /// Write the buffered changes to the file or the writer
+    pub fn flush(&mut self) -> io::Result<()> {
+        match self.writer {
+            Some(ref mut writer) => writer.flush(),
+            None => Ok(()),
+        }
+    }
+
+    fn format_value(&self, name: &str, value: usize) -> String {
+        let id = &self.signal_ids[name];
+        if self.widths[name] == 1 {
+            format!("{value}{id}")
+        } else {
+            format!("b{value:b} {id}")
         }
     }
 
-    fn write_initial_values(&mut self, model: &Model) {
-        // Collect signals before borrowing writer
-        let signals = self.collect_signals(model);
+    fn write_header(&mut self, model: &Model) -> io::Result<()> {
+        // all ports, internals and registers selected by the filter are recorded
+        let signals: Vec<_> = model
+            .signals()
+            .filter(|x| self.filter.matches(model.module_name(), &x.name))
+            .collect();
+        for (i, signal) in signals.iter().cloned().enumerate() {
+            self.signal_ids.insert(signal.name.clone(), generate_id(i));
+            self.widths.insert(signal.name, signal.width.max(1));
+        }
+
+        let Some(ref mut writer) = self.writer else {
+            return Ok(());
+        };
 
-        if let Some(ref mut writer) = self.writer {
-            writeln!(writer, "$dumpvars").ok();
-            for (signal_name, value) in &signals {
-                if let Some(id) = self.signal_ids.get(signal_name) {
-                    writeln!(writer, "b{:b} {}", value, id).ok();
-                    self.last_values.insert(signal_name.clone(), *value);
-                }
+        // Write VCD header
+        writeln!(writer, "$date")?;
+        writeln!(writer, "    {}", format_date(SystemTime::now()))?;
+        writeln!(writer, "$end")?;
+
+        writeln!(writer, "$version")?;
+        writeln!(writer, "    Veryl Simulator {}", env!("CARGO_PKG_VERSION"))?;
+        writeln!(writer, "$end")?;
+
+        // シミュレータの時間単位はナノ秒
+        writeln!(writer, "$timescale")?;
+        writeln!(writer, "    1ns")?;
+        writeln!(writer, "$end")?;
+
+        // 階層名 "a.b.c" を scope に展開する
+        // signals are sorted by name, so signals in the same scope are adjacent
+        writeln!(writer, "$scope module {} $end", model.module_name())?;
+        let mut scopes: Vec<&str> = Vec::new();
+        for signal in &signals {
+            let mut path: Vec<_> = signal.name.split('.').collect();
+            let leaf = path.pop().unwrap_or_default();
+
+            let common = scopes.iter().zip(&path).take_while(|(x, y)| x == y).count();
+            for _ in common..scopes.len() {
+                writeln!(writer, "$upscope $end")?;
+            }
+            scopes.truncate(common);
+            for scope in &path[common..] {
+                writeln!(writer, "$scope module {scope} $end")?;
+                scopes.push(scope);
             }
-            writeln!(writer, "$end").ok();
 
-            writer.flush().ok();
+            let id = &self.signal_ids[&signal.name];
+            let width = self.widths[&signal.name];
+            if width == 1 {
+                writeln!(writer, "$var wire 1 {id} {leaf} $end")?;
+            } else {
+                writeln!(
+                    writer,
+                    "$var wire {width} {id} {leaf} [{}:0] $end",
+                    width - 1
+                )?;
+            }
+        }
+        for _ in 0..scopes.len() {
+            writeln!(writer, "$upscope $end")?;
         }
+
+        writeln!(writer, "$upscope $end")?;
+        writeln!(writer, "$enddefinitions $end")?;
+
+        writer.flush()
     }
 
-    fn write_changes(&mut self, time: u64, model: &Model) {
-        // Collect signals before borrowing writer
-        let signals = self.collect_signals(model);
-        let mut has_changes = false;
-        let mut changes = Vec::new();
+    fn write_gtkw(&self, path: &str, model: &Model) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        // 相対パスでは GTKWave の作業ディレクトリに依存するため絶対パスにする
+        let dumpfile = std::fs::canonicalize(&self.path)
+            .map(|x| x.to_string_lossy().to_string())
+            .unwrap_or_else(|_| self.path.clone());
+        writeln!(
+            writer,
+            "[*] Generated by Veryl Simulator {}",
+            env!("CARGO_PKG_VERSION")
+        )?;
+        writeln!(writer, "[dumpfile] \"{dumpfile}\"")?;
+        writeln!(writer, "[timestart] 0")?;
 
-        // Check for changes
-        for (signal_name, value) in &signals {
-            let last_value = self.last_values.get(signal_name).copied().unwrap_or(0);
-            if *value != last_value {
-                has_changes = true;
-                if let Some(id) = self.signal_ids.get(signal_name) {
-                    changes.push((id.clone(), *value));
-                    self.last_values.insert(signal_name.clone(), *value);
-                }
+        // クロック、リセット、入力ポート、出力ポートの順に並べる
+        let is_clock = |x: &str| model.clocks().iter().any(|(y, _)| y == x);
+        let is_reset = |x: &str| model.resets().iter().any(|(y, _)| y == x);
+        let mut signals: Vec<_> = model
+            .signals()
+            .filter(|x| x.direction != Direction::Internal && self.signal_ids.contains_key(&x.name))
+            .collect();
+        signals.sort_by_key(|x| {
+            let order = if is_clock(&x.name) {
+                0
+            } else if is_reset(&x.name) {
+                1
+            } else if x.direction == Direction::Input {
+                2
+            } else {
+                3
+            };
+            (order, x.name.clone())
+        });
+
+        // モジュールごとのグループにまとめる
+        let module = model.module_name();
+        writeln!(writer, "@800200")?;
+        writeln!(writer, "-{module}")?;
+        for signal in signals {
+            let width = self.widths[&signal.name];
+            if width == 1 {
+                // binary
+                writeln!(writer, "@28")?;
+                writeln!(writer, "{module}.{}", signal.name)?;
+            } else {
+                // hexadecimal
+                writeln!(writer, "@22")?;
+                writeln!(writer, "{module}.{}[{}:0]", signal.name, width - 1)?;
             }
         }
+        writeln!(writer, "@1000200")?;
+        writeln!(writer, "-{module}")?;
+        writer.flush()
+    }
 
-        // Write changes to file
-        if has_changes {
-            if let Some(ref mut writer) = self.writer {
-                writeln!(writer, "#{}", time).ok();
-                for (id, value) in changes {
-                    writeln!(writer, "b{:b} {}", value, id).ok();
-                }
-                writer.flush().ok();
+    fn write_initial_values(&mut self, time: u64, model: &Model) -> io::Result<()> {
+        let mut lines = Vec::new();
+        for signal in model.signals() {
+            if !self.signal_ids.contains_key(&signal.name) {
+                continue;
             }
+            lines.push(self.format_value(&signal.name, signal.value));
+            self.last_values.insert(signal.name, signal.value);
         }
+
+        let Some(ref mut writer) = self.writer else {
+            return Ok(());
+        };
+        writeln!(writer, "#{time}")?;
+        writeln!(writer, "$dumpvars")?;
+        for line in lines {
+            writeln!(writer, "{line}")?;
+        }
+        writeln!(writer, "$end")?;
+
+        writer.flush()
     }
 
-    fn collect_signals(&self, model: &Model) -> HashMap<String, usize> {
-        let mut signals = HashMap::new();
+    fn write_changes(&mut self, time: u64, model: &Model) -> io::Result<()> {
+        for signal in model.signals() {
+            self.write_change(time, &signal.name, signal.value)?;
+        }
+        Ok(())
+    }
 
-        // Collect all output signals
-        // Note: This is limited to outputs accessible via Model::get
-        // In a real implementation, we'd want access to all signals
+    // 前回記録した値から変化した場合のみ出力する
+    fn write_change(&mut self, time: u64, name: &str, value: usize) -> io::Result<()> {
+        if !self.dumping
+            || !self.signal_ids.contains_key(name)
+            || self.last_values.get(name) == Some(&value)
+        {
+            return Ok(());
+        }
+        self.last_values.insert(name.to_string(), value);
+        let change = self.format_value(name, value);
+        self.write_lines(time, &[change])
+    }
 
-        // Try to collect common signal names
-        for signal_name in &["a", "b", "c", "clk", "reset"] {
-            if let Some(val) = model.get(signal_name) {
-                signals.insert(signal_name.to_string(), val);
-            }
+    // 同時刻の変化は1つのタイムスタンプにまとめる
+    fn write_lines(&mut self, time: u64, lines: &[String]) -> io::Result<()> {
+        let Some(ref mut writer) = self.writer else {
+            return Ok(());
+        };
+        if self.last_time != Some(time) {
+            writeln!(writer, "#{time}")?;
+            self.last_time = Some(time);
+        }
+        for line in lines {
+            writeln!(writer, "{line}")?;
         }
+        Ok(())
+    }
+
+    // 要求とトリガーに従ってダンプを切り替える
+    fn update_dumping(&mut self, time: u64, model: &Model) -> io::Result<()> {
+        let mut dumping = self.requested.take().unwrap_or(self.dumping);
+        if self.start.as_ref().is_some_and(|x| x.is_met(time, model)) {
+            self.start = None;
+            dumping = true;
+        }
+        if self.stop.as_ref().is_some_and(|x| x.is_met(time, model)) {
+            self.stop = None;
+            dumping = false;
+        }
+        if dumping == self.dumping {
+            return Ok(());
+        }
+        self.dumping = dumping;
 
-        signals
+        let mut lines = Vec::new();
+        if dumping {
+            lines.push("$dumpon".to_string());
+            for signal in model.signals() {
+                if self.signal_ids.contains_key(&signal.name) {
+                    lines.push(self.format_value(&signal.name, signal.value));
+                    self.last_values.insert(signal.name, signal.value);
+                }
+            }
+        } else {
+            // 停止中の値は不定として扱う
+            lines.push("$dumpoff".to_string());
+            let mut names: Vec<_> = self.signal_ids.keys().collect();
+            names.sort();
+            for name in names {
+                let id = &self.signal_ids[name];
+                if self.widths[name] == 1 {
+                    lines.push(format!("x{id}"));
+                } else {
+                    lines.push(format!("bx {id}"));
+                }
+            }
+            self.last_values.clear();
+        }
+        lines.push("$end".to_string());
+        self.write_lines(time, &lines)
     }
 }
 
-impl Hook for VCDLoggerHook {
-    fn on_reset(&mut self, time: u64, model: &Model) {
+impl VCDLoggerHook {
+    fn record(&mut self, time: u64, model: &Model) -> Result<(), HookError> {
+        if let Some(error) = self.error.take() {
+            return Err(HookError::Io(error));
+        }
         if !self.initialized {
-            self.write_header(model);
-            self.write_initial_values(model);
+            self.write_header(model)?;
+            if let Some(path) = &self.gtkw_path {
+                self.write_gtkw(path, model)?;
+            }
+            self.write_initial_values(time, model)?;
+            self.last_time = Some(time);
             self.initialized = true;
+            // 開始条件があれば最初の値を書いた後に停止する
+            if !self.dumping {
+                self.dumping = true;
+                self.requested.get_or_insert(false);
+            }
         }
-        self.write_changes(time, model);
+        self.update_dumping(time, model)?;
+        self.write_changes(time, model)?;
+        Ok(())
     }
 
-    fn post_clock(&mut self, time: u64, _clock_name: &str, model: &Model) {
-        if !self.initialized {
-            self.write_header(model);
-            self.write_initial_values(model);
-            self.initialized = true;
+    fn record_change(&mut self, time: u64, name: &str, value: usize) -> Result<(), HookError> {
+        if let Some(error) = self.error.take() {
+            return Err(HookError::Io(error));
         }
-        self.write_changes(time, model);
+        self.write_change(time, name, value)?;
+        Ok(())
     }
+}
 
-    fn on_finish(&mut self, _time: u64, _model: &Model) {
+impl Hook for VCDLoggerHook {
+    fn on_start(&mut self, model: &Model) -> Result<(), HookError> {
+        let time = self.last_time.unwrap_or(0);
+        self.record(time, model)
+    }
+
+    fn on_input_change(
+        &mut self,
+        time: u64,
+        name: &str,
+        _old: usize,
+        new: usize,
+    ) -> Result<HookAction, HookError> {
+        self.record_change(time, name, new)?;
+        Ok(HookAction::Continue)
+    }
+
+    fn on_signal_change(
+        &mut self,
+        time: u64,
+        name: &str,
+        _old: usize,
+        new: usize,
+    ) -> Result<HookAction, HookError> {
+        self.record_change(time, name, new)?;
+        Ok(HookAction::Continue)
+    }
+
+    // クロック入力は変化の通知に含まれないため、エッジごとに記録する
+    fn post_clock(
+        &mut self,
+        time: u64,
+        _clock_name: &str,
+        model: &Model,
+    ) -> Result<HookAction, HookError> {
+        self.record(time, model)?;
+        Ok(HookAction::Continue)
+    }
+
+    fn post_clock_fall(
+        &mut self,
+        time: u64,
+        _clock_name: &str,
+        model: &Model,
+    ) -> Result<HookAction, HookError> {
+        self.record(time, model)?;
+        Ok(HookAction::Continue)
+    }
+
+    fn on_reset(&mut self, time: u64, model: &Model) -> Result<(), HookError> {
+        self.record(time, model)
+    }
+
+    fn on_finish(&mut self, _time: u64, _model: &Model) -> Result<(), HookError> {
         if let Some(ref mut writer) = self.writer {
-            writer.flush().ok();
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+// 印字可能文字 '!'..='~' の94進数で識別子を作る
+pub(super) fn generate_id(mut index: usize) -> String {
+    let mut id = String::new();
+    loop {
+        id.push(char::from(b'!' + (index % 94) as u8));
+        index /= 94;
+        if index == 0 {
+            return id;
         }
+        index -= 1;
     }
 }
 
+// Format the time like "Fri Oct 16 09:30:00 2026" in UTC
+pub(super) fn format_date(time: SystemTime) -> String {
+    const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or(0);
+    let days = secs / 86400;
+    let secs = secs % 86400;
+
+    // 1970-01-01 からの日数を年月日に変換する
+    // 3月始まりの400年周期で計算する
+    let z = days + 719468;
+    let era = z / 146097;
+    let doe = z % 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{} {} {:2} {:02}:{:02}:{:02} {}",
+        WEEKDAYS[(days % 7) as usize],
+        MONTHS[(month - 1) as usize],
+        day,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60,
+        year
+    )
+}
+
 impl Drop for VCDLoggerHook {
     fn drop(&mut self) {
         if let Some(ref mut writer) = self.writer {
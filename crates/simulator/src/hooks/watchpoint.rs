@@ -0,0 +1,136 @@
+use super::{Hook, HookAction, HookError};
+use crate::{Model, ModelError};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// Change of a watched signal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchEvent {
+    pub time: u64,
+    pub signal: String,
+    pub old: usize,
+    pub new: usize,
+}
+
+impl fmt::Display for WatchEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}ns: {}: {} -> {}",
+            self.time, self.signal, self.old, self.new
+        )
+    }
+}
+
+// This hook records every change of the watched signals
+// unlike BreakPoint, it doesn't test a condition
+// changes are detected after every clock edge
+// signals are dotted paths or glob patterns like "regs[*]", resolved by Model::select
+pub struct WatchPoint {
+    paths: Vec<String>,            // watched paths as given
+    last: BTreeMap<String, usize>, // last values of the matched signals
+    action: HookAction,
+    events: Arc<Mutex<Vec<WatchEvent>>>,
+}
+
+impl WatchPoint {
+    pub fn new(signals: &[&str]) -> Self {
+        WatchPoint {
+            paths: signals.iter().map(|x| x.to_string()).collect(),
+            last: BTreeMap::new(),
+            action: HookAction::Continue,
+            events: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Watch an additional signal
+    pub fn watch(mut self, signal: &str) -> Self {
+        self.paths.push(signal.to_string());
+        self
+    }
+
+    /// Action requested to the simulator on every change, `HookAction::Continue` by default
+    /// use `HookAction::Pause` to break on changes
+    pub fn action(mut self, action: HookAction) -> Self {
+        self.action = action;
+        self
+    }
+
+    /// Shared list of the recorded changes
+    /// it can be read while the hook is owned by the simulator
+    pub fn events(&self) -> Arc<Mutex<Vec<WatchEvent>>> {
+        self.events.clone()
+    }
+
+    // 監視するパスをモデルの信号名に展開する（一致しないパスは無視する）
+    fn signals(&self, model: &Model) -> Vec<String> {
+        self.paths
+            .iter()
+            .flat_map(|x| model.select(x).unwrap_or_default())
+            .collect()
+    }
+
+    fn evaluate(&mut self, time: u64, model: &Model) -> HookAction {
+        let mut action = HookAction::Continue;
+        for name in self.signals(model) {
+            let Some(new) = model.peek(&name) else {
+                continue;
+            };
+            if let Some(old) = self.last.insert(name.clone(), new)
+                && old != new
+            {
+                self.events.lock().unwrap().push(WatchEvent {
+                    time,
+                    signal: name,
+                    old,
+                    new,
+                });
+                action = self.action;
+            }
+        }
+        action
+    }
+}
+
+impl Hook for WatchPoint {
+    fn on_start(&mut self, model: &Model) -> Result<(), HookError> {
+        // 存在しない信号は変化しないので、書き間違いとして報告する
+        for path in &self.paths {
+            if let Err(ModelError::PathNotFound { path, hint }) = model.select(path) {
+                return Err(HookError::Other(format!(
+                    "watchpoint on \"{path}\" matches no signal, {hint}"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn post_clock(
+        &mut self,
+        time: u64,
+        _clock_name: &str,
+        model: &Model,
+    ) -> Result<HookAction, HookError> {
+        Ok(self.evaluate(time, model))
+    }
+
+    fn post_clock_fall(
+        &mut self,
+        time: u64,
+        _clock_name: &str,
+        model: &Model,
+    ) -> Result<HookAction, HookError> {
+        Ok(self.evaluate(time, model))
+    }
+
+    fn on_reset(&mut self, _time: u64, model: &Model) -> Result<(), HookError> {
+        // リセット後の値を基準にする
+        self.last = self
+            .signals(model)
+            .into_iter()
+            .filter_map(|x| model.peek(&x).map(|v| (x, v)))
+            .collect();
+        Ok(())
+    }
+}
@@ -0,0 +1,274 @@
+use crate::bytecode::{Member, Op, Program};
+use cranelift_codegen::ir::condcodes::IntCC;
+use cranelift_codegen::ir::{AbiParam, InstBuilder, MemFlags, Type, Value, types};
+use cranelift_codegen::settings::{self, Configurable};
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext};
+use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::{FuncId, Linkage, Module, default_libcall_names};
+
+// 状態ベクタの先頭を受け取り、式の値を返すネイティブ関数
+pub(crate) type NativeFn = unsafe extern "C" fn(*const usize) -> usize;
+
+// Program を Cranelift でネイティブコードに変換する
+// 関数は define で定義し、finalize の後に function で取り出す
+pub(crate) struct Jit {
+    module: Option<JITModule>,
+    context: FunctionBuilderContext,
+    functions: usize,
+}
+
+// SAFETY: JITModule は生成したコードのメモリを所有するだけで、スレッドに依存する状態を持たない
+// Model と同様に同時に複数のスレッドから使われることはない
+unsafe impl Send for Jit {}
+
+impl Jit {
+    pub(crate) fn new() -> Result<Self, String> {
+        let mut flags = settings::builder();
+        flags.set("opt_level", "speed").map_err(|x| x.to_string())?;
+        let isa = cranelift_native::builder()?
+            .finish(settings::Flags::new(flags))
+            .map_err(|x| x.to_string())?;
+        let module = JITModule::new(JITBuilder::with_isa(isa, default_libcall_names()));
+        Ok(Jit {
+            module: Some(module),
+            context: FunctionBuilderContext::new(),
+            functions: 0,
+        })
+    }
+
+    // 式をネイティブ関数として定義する
+    // 未知の信号を通知する可能性がある式と、変換しても速くならない式は None を返す
+    pub(crate) fn define(&mut self, program: &Program) -> Result<Option<FuncId>, String> {
+        let ops = program.ops();
+        if ops.len() <= 1
+            || ops
+                .iter()
+                .any(|x| matches!(x, Op::Unknown(_) | Op::LoadIndex(_) | Op::Call(_)))
+        {
+            return Ok(None);
+        }
+        let Some(module) = &mut self.module else {
+            return Ok(None);
+        };
+
+        let word = module.target_config().pointer_type();
+        let mut context = module.make_context();
+        context.func.signature.params.push(AbiParam::new(word));
+        context.func.signature.returns.push(AbiParam::new(word));
+
+        let mut builder = FunctionBuilder::new(&mut context.func, &mut self.context);
+        let block = builder.create_block();
+        builder.append_block_params_for_function_params(block);
+        builder.switch_to_block(block);
+        builder.seal_block(block);
+        let values = builder.block_params(block)[0];
+
+        let mut stack = Vec::new();
+        for op in ops {
+            let value = match *op {
+                Op::Const(x) => builder.ins().iconst(word, x as i64),
+                Op::Load(x) => {
+                    let offset = (x * size_of::<usize>()) as i32;
+                    builder
+                        .ins()
+                        .load(word, MemFlags::trusted(), values, offset)
+                }
+                Op::Unknown(_) | Op::LoadIndex(_) | Op::Call(_) => unreachable!(),
+                Op::Select => {
+                    let lsb = pop(&mut stack)?;
+                    let msb = pop(&mut stack)?;
+                    let value = pop(&mut stack)?;
+                    select_bits(&mut builder, word, value, msb, lsb)
+                }
+                Op::Add
+                | Op::Sub
+                | Op::SatSub
+                | Op::Mul
+                | Op::Div
+                | Op::Rem
+                | Op::And
+                | Op::Or
+                | Op::Xor
+                | Op::Shl
+                | Op::Shr => {
+                    let right = pop(&mut stack)?;
+                    let left = pop(&mut stack)?;
+                    binary(&mut builder, word, *op, left, right)
+                }
+                Op::Not => {
+                    let value = pop(&mut stack)?;
+                    let zero = builder.ins().icmp_imm(IntCC::Equal, value, 0);
+                    builder.ins().uextend(word, zero)
+                }
+                Op::Eq(mask) => {
+                    let right = pop(&mut stack)?;
+                    let left = pop(&mut stack)?;
+                    let diff = builder.ins().bxor(left, right);
+                    let masked = builder.ins().band_imm(diff, mask as i64);
+                    let equal = builder.ins().icmp_imm(IntCC::Equal, masked, 0);
+                    builder.ins().uextend(word, equal)
+                }
+                Op::Inside(x) => {
+                    let members = program.members(x);
+                    let count = members.iter().map(Member::operands).sum::<usize>();
+                    let start = stack.len().checked_sub(count).ok_or("invalid bytecode")?;
+                    let mut operands = stack.split_off(start).into_iter();
+                    let value = pop(&mut stack)?;
+                    let mut matched = builder.ins().iconst(types::I8, 0);
+                    for member in members {
+                        let x = match *member {
+                            Member::Value => {
+                                let x = operands.next().ok_or("invalid bytecode")?;
+                                builder.ins().icmp(IntCC::Equal, value, x)
+                            }
+                            Member::Wildcard(x, mask) => {
+                                let diff = builder.ins().bxor_imm(value, x as i64);
+                                let masked = builder.ins().band_imm(diff, mask as i64);
+                                builder.ins().icmp_imm(IntCC::Equal, masked, 0)
+                            }
+                            Member::Range(inclusive) => {
+                                let lo = operands.next().ok_or("invalid bytecode")?;
+                                let hi = operands.next().ok_or("invalid bytecode")?;
+                                let cc = if inclusive {
+                                    IntCC::UnsignedLessThanOrEqual
+                                } else {
+                                    IntCC::UnsignedLessThan
+                                };
+                                let above = builder.ins().icmp(
+                                    IntCC::UnsignedGreaterThanOrEqual,
+                                    value,
+                                    lo,
+                                );
+                                let below = builder.ins().icmp(cc, value, hi);
+                                builder.ins().band(above, below)
+                            }
+                        };
+                        matched = builder.ins().bor(matched, x);
+                    }
+                    builder.ins().uextend(word, matched)
+                }
+            };
+            stack.push(value);
+        }
+        let result = pop(&mut stack)?;
+        builder.ins().return_(&[result]);
+        builder.finalize();
+
+        let name = format!("expr{}", self.functions);
+        self.functions += 1;
+        let id = module
+            .declare_function(&name, Linkage::Local, &context.func.signature)
+            .map_err(|x| x.to_string())?;
+        module
+            .define_function(id, &mut context)
+            .map_err(|x| x.to_string())?;
+        module.clear_context(&mut context);
+        Ok(Some(id))
+    }
+
+    pub(crate) fn finalize(&mut self) -> Result<(), String> {
+        match &mut self.module {
+            Some(module) => module.finalize_definitions().map_err(|x| x.to_string()),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn function(&self, id: FuncId) -> Option<NativeFn> {
+        let module = self.module.as_ref()?;
+        let ptr = module.get_finalized_function(id);
+        // SAFETY: 関数は define で NativeFn と同じシグネチャで定義している
+        Some(unsafe { std::mem::transmute::<*const u8, NativeFn>(ptr) })
+    }
+}
+
+impl Drop for Jit {
+    fn drop(&mut self) {
+        if let Some(module) = self.module.take() {
+            // SAFETY: Model は Jit を破棄する前に Program からネイティブ関数を取り除く
+            unsafe { module.free_memory() };
+        }
+    }
+}
+
+fn pop(stack: &mut Vec<Value>) -> Result<Value, String> {
+    stack.pop().ok_or_else(|| "invalid bytecode".to_string())
+}
+
+// 下位 width ビットのマスク（width が語長以上なら全ビット）
+fn bit_mask(builder: &mut FunctionBuilder, word: Type, width: Value) -> Value {
+    let one = builder.ins().iconst(word, 1);
+    let shifted = builder.ins().ishl(one, width);
+    let mask = builder.ins().iadd_imm(shifted, -1);
+    let full = builder.ins().iconst(word, -1);
+    let wide = builder
+        .ins()
+        .icmp_imm(IntCC::UnsignedGreaterThanOrEqual, width, word.bits() as i64);
+    builder.ins().select(wide, full, mask)
+}
+
+// model::select_bits と同じく、範囲外の選択は 0 とする
+fn select_bits(
+    builder: &mut FunctionBuilder,
+    word: Type,
+    value: Value,
+    msb: Value,
+    lsb: Value,
+) -> Value {
+    let reversed = builder.ins().icmp(IntCC::UnsignedLessThan, msb, lsb);
+    let outside =
+        builder
+            .ins()
+            .icmp_imm(IntCC::UnsignedGreaterThanOrEqual, lsb, word.bits() as i64);
+    let invalid = builder.ins().bor(reversed, outside);
+
+    let diff = builder.ins().isub(msb, lsb);
+    let width = builder.ins().iadd_imm(diff, 1);
+    let mask = bit_mask(builder, word, width);
+    let shifted = builder.ins().ushr(value, lsb);
+    let selected = builder.ins().band(shifted, mask);
+    let zero = builder.ins().iconst(word, 0);
+    builder.ins().select(invalid, zero, selected)
+}
+
+// 算術演算はバイトコードと同じく、SatSub は 0 で飽和し、ゼロでの除算・剰余と語長以上のシフトは 0 とする
+fn binary(builder: &mut FunctionBuilder, word: Type, op: Op, left: Value, right: Value) -> Value {
+    let zero = builder.ins().iconst(word, 0);
+    match op {
+        Op::Add => builder.ins().iadd(left, right),
+        Op::Sub => builder.ins().isub(left, right),
+        Op::SatSub => {
+            let borrow = builder.ins().icmp(IntCC::UnsignedLessThan, left, right);
+            let diff = builder.ins().isub(left, right);
+            builder.ins().select(borrow, zero, diff)
+        }
+        Op::Mul => builder.ins().imul(left, right),
+        Op::And => builder.ins().band(left, right),
+        Op::Or => builder.ins().bor(left, right),
+        Op::Xor => builder.ins().bxor(left, right),
+        Op::Shl | Op::Shr => {
+            // Cranelift のシフト量は語長で剰余を取るので、語長以上の場合を別に扱う
+            let shifted = if matches!(op, Op::Shl) {
+                builder.ins().ishl(left, right)
+            } else {
+                builder.ins().ushr(left, right)
+            };
+            let wide = builder.ins().icmp_imm(
+                IntCC::UnsignedGreaterThanOrEqual,
+                right,
+                word.bits() as i64,
+            );
+            builder.ins().select(wide, zero, shifted)
+        }
+        _ => {
+            let by_zero = builder.ins().icmp_imm(IntCC::Equal, right, 0);
+            let one = builder.ins().iconst(word, 1);
+            let divisor = builder.ins().select(by_zero, one, right);
+            let quotient = if matches!(op, Op::Rem) {
+                builder.ins().urem(left, divisor)
+            } else {
+                builder.ins().udiv(left, divisor)
+            };
+            builder.ins().select(by_zero, zero, quotient)
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use crate::random::Random;
+use serde::{Deserialize, Serialize};
+
+/// Random deviation of each clock edge from its ideal time
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Jitter {
+    #[default]
+    None,
+    /// Uniformly distributed in [-max_ns, max_ns]
+    Uniform { max_ns: u64 },
+    /// Normally distributed with the standard deviation, rounded to nanoseconds
+    Gaussian { sigma_ns: f64 },
+}
+
+/// Jitter and drift of a clock
+/// the deviation by jitter doesn't accumulate, while drift shifts every edge by the ppm of the period
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct ClockJitter {
+    pub jitter: Jitter,
+    pub drift_ppm: i64,
+    pub seed: u64,
+}
+
+/// State of the jitter model of a clock
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JitterState {
+    config: ClockJitter,
+    random: Random,
+    deviation: i64, // deviation of the last edge from its ideal time [ns]
+    residual: i64,  // drift below 1 ns [ns * 1e-6]
+}
+
+impl JitterState {
+    pub(crate) fn new(config: ClockJitter) -> Self {
+        JitterState {
+            config,
+            random: Random::new(config.seed),
+            deviation: 0,
+            residual: 0,
+        }
+    }
+
+    pub(crate) fn config(&self) -> &ClockJitter {
+        &self.config
+    }
+
+    // Time to the next edge from the last edge, the result is at least 1 ns
+    pub(crate) fn next_interval(&mut self, half_period: u64) -> u64 {
+        self.residual += half_period as i64 * self.config.drift_ppm;
+        let drift = self.residual / 1_000_000;
+        self.residual -= drift * 1_000_000;
+
+        let deviation = match self.config.jitter {
+            Jitter::None => 0,
+            Jitter::Uniform { max_ns } => self.random.symmetric(max_ns),
+            Jitter::Gaussian { sigma_ns } => (self.random.gaussian() * sigma_ns).round() as i64,
+        };
+        let ideal = half_period as i64 + drift;
+        let interval = (ideal - self.deviation + deviation).max(1);
+        self.deviation += interval - ideal;
+        interval as u64
+    }
+}
@@ -0,0 +1,234 @@
+use crate::model::{CaseLabel, Expr, Statement, Target};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::ops::Range;
+
+// 依存順に並べた組み合わせ回路の文
+pub(crate) struct Levelized {
+    pub(crate) statements: Vec<Statement>,
+    pub(crate) groups: Vec<Group>, // 並べた順の組
+    pub(crate) acyclic: bool,      // ループがなく、1 回の評価で値が確定するか
+}
+
+// 組み合わせ回路の文の組（assign 宣言または always_comb ブロック）
+pub(crate) struct Group {
+    pub(crate) range: Range<usize>,      // statements の範囲
+    pub(crate) reads: BTreeSet<String>,  // 組の中で代入される前に読み出す信号
+    pub(crate) writes: BTreeSet<String>, // 代入する信号
+}
+
+// 文の組を依存順に並べる
+// 組の中の文の順序は保ち、依存関係のレベルの順、同じレベルでは宣言の順に並べる
+// ループがある場合は宣言の順のまま並べ、値が変化しなくなるまで繰り返し評価する
+pub(crate) fn levelize(groups: Vec<Vec<Statement>>) -> Levelized {
+    let accesses: Vec<_> = groups.iter().map(|x| Access::new(x)).collect();
+
+    // 信号を代入する組
+    let mut writers: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, access) in accesses.iter().enumerate() {
+        for name in &access.writes {
+            writers.entry(name).or_default().push(i);
+        }
+    }
+
+    // 各組が値を読み出す信号を代入する組
+    let predecessors: Vec<BTreeSet<usize>> = accesses
+        .iter()
+        .map(|access| {
+            access
+                .reads
+                .iter()
+                .filter_map(|x| writers.get(x.as_str()))
+                .flatten()
+                .copied()
+                .collect()
+        })
+        .collect();
+
+    let mut order: Vec<usize> = (0..groups.len()).collect();
+    let levels = levels(&predecessors);
+    if let Some(levels) = &levels {
+        order.sort_by_key(|x| (levels[*x], *x));
+    }
+
+    let mut groups: Vec<_> = groups.into_iter().map(Some).collect();
+    let mut accesses: Vec<_> = accesses.into_iter().map(Some).collect();
+    let mut levelized = Levelized {
+        statements: Vec::new(),
+        groups: Vec::new(),
+        acyclic: levels.is_some(),
+    };
+    for i in order {
+        let (Some(statements), Some(access)) = (groups[i].take(), accesses[i].take()) else {
+            continue;
+        };
+        let start = levelized.statements.len();
+        levelized.statements.extend(statements);
+        levelized.groups.push(Group {
+            range: start..levelized.statements.len(),
+            reads: access.reads,
+            writes: access.writes,
+        });
+    }
+    levelized
+}
+
+// 各組のレベル（依存する組のレベルの最大値 + 1）、ループがあれば None
+fn levels(predecessors: &[BTreeSet<usize>]) -> Option<Vec<usize>> {
+    let mut levels = vec![None; predecessors.len()];
+    let mut visiting = vec![false; predecessors.len()];
+    for i in 0..predecessors.len() {
+        level(i, predecessors, &mut levels, &mut visiting)?;
+    }
+    levels.into_iter().collect()
+}
+
+fn level(
+    i: usize,
+    predecessors: &[BTreeSet<usize>],
+    levels: &mut [Option<usize>],
+    visiting: &mut [bool],
+) -> Option<usize> {
+    if let Some(x) = levels[i] {
+        return Some(x);
+    }
+    // 探索中の組に戻ればループ
+    if visiting[i] {
+        return None;
+    }
+    visiting[i] = true;
+    let mut ret = 0;
+    for x in &predecessors[i] {
+        ret = ret.max(level(*x, predecessors, levels, visiting)? + 1);
+    }
+    visiting[i] = false;
+    levels[i] = Some(ret);
+    Some(ret)
+}
+
+// 組が読み出す信号と代入する信号（配列は要素ではなく配列名で扱う）
+#[derive(Default)]
+struct Access {
+    reads: BTreeSet<String>, // 組の中で代入される前に読み出す信号
+    writes: BTreeSet<String>,
+}
+
+impl Access {
+    fn new(statements: &[Statement]) -> Self {
+        let mut access = Access::default();
+        access.statements(statements, &mut HashSet::new());
+        access
+    }
+
+    // defined は組の中で既に全ビットが代入された信号
+    // 分岐の中の代入は実行されるとは限らないので、分岐の後には引き継がない
+    fn statements(&mut self, statements: &[Statement], defined: &mut HashSet<String>) {
+        for statement in statements {
+            match statement {
+                Statement::Assign(x) => {
+                    self.expr(&x.expression, defined);
+                    self.targets(&x.targets, defined);
+                }
+                Statement::Instance(x) => {
+                    for input in &x.inputs {
+                        self.expr(input, defined);
+                    }
+                    self.targets(&x.outputs, defined);
+                }
+                Statement::If(branches, otherwise) => {
+                    for (condition, statements) in branches {
+                        self.expr(condition, defined);
+                        self.statements(statements, &mut defined.clone());
+                    }
+                    self.statements(otherwise, &mut defined.clone());
+                }
+                Statement::Case(x) => {
+                    self.expr(&x.expression, defined);
+                    for (labels, statements) in &x.arms {
+                        for label in labels {
+                            self.label(label, defined);
+                        }
+                        self.statements(statements, &mut defined.clone());
+                    }
+                    self.statements(&x.otherwise, &mut defined.clone());
+                }
+                Statement::Assert(_) | Statement::Cover(_) => {}
+            }
+        }
+    }
+
+    fn targets(&mut self, targets: &[Target], defined: &mut HashSet<String>) {
+        for target in targets {
+            if let Some(index) = &target.index {
+                self.expr(index, defined);
+            }
+            if let Some((msb, lsb)) = &target.select {
+                self.expr(msb, defined);
+                self.expr(lsb, defined);
+            }
+        }
+        for target in targets {
+            self.writes.insert(target.name.clone());
+            if target.index.is_none() && target.select.is_none() {
+                defined.insert(target.name.clone());
+            }
+        }
+    }
+
+    fn label(&mut self, label: &CaseLabel, defined: &HashSet<String>) {
+        match label {
+            CaseLabel::Value(x) => self.expr(x, defined),
+            CaseLabel::Wildcard(_, _) => {}
+            CaseLabel::Range(lo, hi, _) => {
+                self.expr(lo, defined);
+                self.expr(hi, defined);
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr, defined: &HashSet<String>) {
+        match expr {
+            Expr::Const(_) => {}
+            Expr::Var(name) => {
+                if !defined.contains(name) {
+                    self.reads.insert(name.clone());
+                }
+            }
+            Expr::Index(name, index) => {
+                self.reads.insert(name.clone());
+                self.expr(index, defined);
+            }
+            Expr::Select(expr, msb, lsb) => {
+                self.expr(expr, defined);
+                self.expr(msb, defined);
+                self.expr(lsb, defined);
+            }
+            Expr::Add(left, right)
+            | Expr::Sub(left, right)
+            | Expr::SatSub(left, right)
+            | Expr::Mul(left, right)
+            | Expr::Div(left, right)
+            | Expr::Rem(left, right)
+            | Expr::And(left, right)
+            | Expr::Or(left, right)
+            | Expr::Xor(left, right)
+            | Expr::Shl(left, right)
+            | Expr::Shr(left, right)
+            | Expr::Eq(left, right, _) => {
+                self.expr(left, defined);
+                self.expr(right, defined);
+            }
+            Expr::Not(expr) => self.expr(expr, defined),
+            Expr::Inside(expr, labels) => {
+                self.expr(expr, defined);
+                for label in labels {
+                    self.label(label, defined);
+                }
+            }
+            Expr::Call(_, args) => {
+                for arg in args {
+                    self.expr(arg, defined);
+                }
+            }
+        }
+    }
+}
@@ -1,7 +1,87 @@
+mod async_testbench;
+mod behavioral;
+mod bit_vec;
+mod bytecode;
+pub mod capability;
+mod codegen;
+mod coverage_db;
+#[cfg(feature = "differential")]
+mod differential;
+mod elaborate;
+mod event_queue;
 pub mod hooks;
+#[cfg(feature = "jit")]
+mod jit;
+mod jitter;
+mod levelize;
 mod model;
+mod model_error;
+mod model_state;
+mod path;
+mod random;
+mod regression;
+pub mod repl;
+mod replay;
+#[cfg(feature = "server")]
+mod server;
 mod simulator;
+mod simulator_builder;
+mod snapshot;
+mod stimulus;
+mod testbench;
+mod time;
+mod time_travel;
+mod trace;
+mod vectors;
+#[cfg(feature = "wasm")]
+mod wasm;
 
-pub use hooks::{BreakPoint, BufLogger, Hook, VCDLoggerHook};
-pub use model::Model;
-pub use simulator::Simulator;
+pub use async_testbench::{AsyncTestBench, TbHandle, Wait};
+pub use behavioral::{BehavioralModule, Ports};
+pub use bit_vec::BitVec;
+pub use coverage_db::CoverageDb;
+#[cfg(feature = "differential")]
+pub use differential::{DiffMismatch, DiffReport, DiffSources, Differential, SvSimulator};
+#[cfg(feature = "tui")]
+pub use hooks::TuiHook;
+pub use hooks::{
+    ActivityHook, ActivityReport, ApbMaster, ApbReport, ApbRequest, ApbSlave, ApbTransfer,
+    AssertFailure, AssertHook, AssertReport, BinReport, BitActivity, BreakHit, BreakPoint,
+    BufLogger, CosimHook, CoverGroup, CoverGroupReport, CoverageHook, CoverageReport, Coverpoint,
+    CoverpointReport, CsvLoggerHook, Distribution, HandshakeChecker, HandshakeReport,
+    HandshakeRule, HandshakeTransfer, HandshakeViolation, Hook, HookAction, HookError, HookId,
+    I2cBus, I2cMaster, I2cReport, I2cSlave, I2cTransfer, JsonLoggerHook, MemoryModel, PropExpr,
+    Property, PropertyResult, RandomDriver, ReferenceModel, Scoreboard, ScoreboardMismatch,
+    ScoreboardReport, SignalActivity, SignalFilter, SignalStats, SignalStatsHook, SpiMaster,
+    SpiReport, SpiSlave, SpiTransfer, StatsReport, TemporalHook, ToggleReport, Transaction,
+    TransactionLog, TransactionRecorder, UartModel, UartReport, VCDLoggerHook, VcdCompareHook,
+    VcdMismatch, WatchEvent, WatchPoint,
+};
+pub use jitter::{ClockJitter, Jitter, JitterState};
+pub use model::{
+    AssertSeverity, AssertionFailure, CaseCheck, CaseOverlap, CaseViolation, CoverageKind,
+    CoveragePoint, Direction, EvalMode, Model, ModelWarning, SignalInfo, Span, UnknownPolicy,
+    UnknownSignal,
+};
+pub use model_error::ModelError;
+pub use model_state::{ModelState, SignalDelta};
+pub use regression::{Regression, RegressionReport, Scenario, ScenarioResult};
+pub use repl::Repl;
+pub use replay::{Replay, ReplayRow};
+#[cfg(feature = "server")]
+pub use server::Server;
+pub use simulator::{
+    ClockEdge, FinishReason, RunResult, Simulator, SimulatorState, SimulatorStats, StepEvent,
+    StopReason,
+};
+pub use simulator_builder::SimulatorBuilder;
+#[doc(hidden)]
+pub use snapshot::{check_snapshot, snapshot_name};
+pub use stimulus::{Stimulus, StimulusRow};
+pub use testbench::{ExpectFailure, TestBench};
+pub use time::{TimeUnit, parse_time};
+pub use trace::{TraceBucket, TraceStorage};
+pub use vectors::{TestVectors, VectorMismatch};
+pub use veryl_metadata::{ClockType, ResetType};
+#[cfg(feature = "wasm")]
+pub use wasm::WasmSimulator;
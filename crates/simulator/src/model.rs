@@ -1,121 +1,1033 @@
-use std::collections::HashMap;
-use veryl_analyzer::symbol::SymbolKind;
+use crate::behavioral::{BehavioralModule, Instance};
+use crate::bit_vec::BitVec;
+use crate::bytecode::{self, Code, Layout};
+use crate::capability::{self, Lint, Support};
+use crate::codegen::RustModel;
+use crate::elaborate;
+#[cfg(feature = "jit")]
+use crate::jit::Jit;
+use crate::levelize::{Levelized, levelize};
+use crate::model_error::ModelError;
+use crate::model_state::ModelState;
+use crate::path;
+use serde::{Deserialize, Serialize};
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use veryl_analyzer::attribute::{Attribute, CondTypeItem};
+use veryl_analyzer::attribute_table;
+use veryl_analyzer::evaluator::Evaluator;
+use veryl_analyzer::symbol::{SymbolKind, Type, TypeKind};
 use veryl_analyzer::{definition_table, symbol_table};
+use veryl_metadata::{Build, ClockType, ResetType};
 use veryl_parser::ParolError;
+use veryl_parser::token_range::TokenRange;
 use veryl_parser::veryl_grammar_trait::{self as syntax_tree, VerylGrammarTrait};
+use veryl_parser::veryl_token::{Token, VerylToken};
 use veryl_parser::veryl_walker::{Handler, HandlerPoint, VerylWalker};
 
+// 組み合わせ回路の評価を収束するまで繰り返す最大回数
+pub(crate) const MAX_SETTLE_ITERATIONS: usize = 64;
+
+// case 文の分岐の重なりを全ての値を列挙して検査する最大ビット幅
+const MAX_CASE_CHECK_WIDTH: usize = 16;
+
+// from_source で与えたソースのファイル名（エラーメッセージに使う）
+const SOURCE_NAME: &str = "<source>";
+
 // 代入式を表す構造体
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Assignment {
-    target: String,   // 代入先の信号名
-    expression: Expr, // 代入する式
+    pub(crate) targets: Vec<Target>, // 代入先（連接の場合は MSB 側から順に並ぶ）
+    pub(crate) expression: Expr,     // 代入する式
+}
+
+// 文を表す列挙型
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Statement {
+    Assign(Assignment),
+    If(Vec<(Expr, Vec<Statement>)>, Vec<Statement>), // if / else if の (条件, 文) と else の文
+    Case(CaseStatement),
+    Assert(AssertStatement),
+    Cover(usize), // カバレッジの計測点（実行されると回数を数える）
+    Instance(InstanceStatement),
+}
+
+// 振る舞いモデルで置き換えるインスタンスの評価
+#[derive(Debug, Clone, Serialize)]
+pub struct InstanceStatement {
+    pub(crate) index: usize,         // Model::instances の位置
+    pub(crate) inputs: Vec<Expr>,    // 入力ポートに接続した式
+    pub(crate) outputs: Vec<Target>, // 出力ポートに接続した信号
+}
+
+// $error / $fatal などのアサーション（実行されると失敗として記録する）
+#[derive(Debug, Clone, Serialize)]
+pub struct AssertStatement {
+    severity: AssertSeverity, // 重要度
+    message: Option<String>,  // 文字列リテラルの引数
+    span: Span,               // システム関数の位置
+}
+
+/// Severity of an assertion system task in the design
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AssertSeverity {
+    /// `$warning`
+    Warning,
+    /// `$error`
+    Error,
+    /// `$fatal`, the simulator finishes the simulation
+    Fatal,
+}
+
+impl AssertSeverity {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "$warning" => Some(AssertSeverity::Warning),
+            "$error" => Some(AssertSeverity::Error),
+            "$fatal" => Some(AssertSeverity::Fatal),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for AssertSeverity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssertSeverity::Warning => write!(f, "$warning"),
+            AssertSeverity::Error => write!(f, "$error"),
+            AssertSeverity::Fatal => write!(f, "$fatal"),
+        }
+    }
+}
+
+/// Kind of a coverage point
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CoverageKind {
+    Statement,
+    If,
+    ElseIf,
+    Else,
+    IfReset,
+    CaseArm,
+    Default,
+}
+
+impl CoverageKind {
+    pub fn is_branch(&self) -> bool {
+        *self != CoverageKind::Statement
+    }
+}
+
+impl fmt::Display for CoverageKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CoverageKind::Statement => write!(f, "statement"),
+            CoverageKind::If => write!(f, "if"),
+            CoverageKind::ElseIf => write!(f, "else if"),
+            CoverageKind::Else => write!(f, "else"),
+            CoverageKind::IfReset => write!(f, "if_reset"),
+            CoverageKind::CaseArm => write!(f, "case arm"),
+            CoverageKind::Default => write!(f, "default"),
+        }
+    }
+}
+
+/// Statement or branch in the design whose executions are counted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoveragePoint {
+    pub kind: CoverageKind,
+    /// Location of the statement, or the keyword / label of the branch
+    pub location: Span,
+    /// The branch doesn't exist in the source, e.g. else of an if without else
+    pub implicit: bool,
+}
+
+impl fmt::Display for CoveragePoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.implicit {
+            write!(f, "implicit {} at {}", self.kind, self.location)
+        } else {
+            write!(f, "{} at {}", self.kind, self.location)
+        }
+    }
+}
+
+/// Assertion system task in the design executed during simulation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssertionFailure {
+    pub severity: AssertSeverity,
+    /// Location of the system task
+    pub location: Span,
+    /// String literal given as the argument
+    pub message: Option<String>,
+}
+
+impl fmt::Display for AssertionFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at {}", self.severity, self.location)?;
+        if let Some(x) = &self.message {
+            write!(f, ": {x}")?;
+        }
+        Ok(())
+    }
+}
+
+// case 文
+#[derive(Debug, Clone, Serialize)]
+pub struct CaseStatement {
+    pub(crate) expression: Expr,                            // 対象の式
+    pub(crate) arms: Vec<(Vec<CaseLabel>, Vec<Statement>)>, // 各分岐の (ラベル, 文)
+    pub(crate) spans: Vec<Span>,                            // 各分岐の位置
+    pub(crate) otherwise: Vec<Statement>,                   // default の文
+    pub(crate) has_default: bool,                           // default があるかどうか
+    pub(crate) check: Option<CaseCheck>,                    // #[cond_type] による実行時検査
+    pub(crate) span: Span,                                  // case キーワードの位置
+}
+
+/// Runtime check of a case statement specified by `#[cond_type]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaseCheck {
+    /// Exactly one arm matches, unless default exists
+    Unique,
+    /// At most one arm matches
+    Unique0,
+    /// At least one arm matches, unless default exists
+    Priority,
+}
+
+impl fmt::Display for CaseCheck {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CaseCheck::Unique => write!(f, "unique"),
+            CaseCheck::Unique0 => write!(f, "unique0"),
+            CaseCheck::Priority => write!(f, "priority"),
+        }
+    }
+}
+
+/// Violation of a unique / unique0 / priority case found during simulation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaseViolation {
+    pub check: CaseCheck,
+    /// Location of the case statement
+    pub location: Span,
+    /// Value of the case expression
+    pub value: usize,
+    /// Locations of the matched arms
+    pub matched: Vec<Span>,
+}
+
+impl fmt::Display for CaseViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.matched.is_empty() {
+            write!(
+                f,
+                "{} case at {} has no matching arm for value {}",
+                self.check, self.location, self.value
+            )
+        } else {
+            let matched: Vec<_> = self.matched.iter().map(|x| x.to_string()).collect();
+            write!(
+                f,
+                "{} case at {} has multiple matching arms ({}) for value {}",
+                self.check,
+                self.location,
+                matched.join(", "),
+                self.value
+            )
+        }
+    }
+}
+
+// case 文の分岐のラベル
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaseLabel {
+    Value(Expr),             // 値との一致
+    Wildcard(usize, usize),  // x / z を含む数値との一致 (値, 比較するビットのマスク)
+    Range(Expr, Expr, bool), // 範囲 (下限, 上限, 上限を含むか)
+}
+
+impl CaseLabel {
+    fn is_const(&self) -> bool {
+        match self {
+            CaseLabel::Value(x) => x.is_const(),
+            CaseLabel::Wildcard(_, _) => true,
+            CaseLabel::Range(lo, hi, _) => lo.is_const() && hi.is_const(),
+        }
+    }
+
+    fn matches(
+        &self,
+        value: usize,
+        env: &HashMap<String, usize>,
+        functions: &Functions,
+        unknown: &mut dyn FnMut(&str),
+    ) -> bool {
+        match self {
+            CaseLabel::Value(x) => x.eval_with(env, functions, unknown) == value,
+            CaseLabel::Wildcard(x, mask) => (x ^ value) & mask == 0,
+            CaseLabel::Range(lo, hi, inclusive) => {
+                let lo = lo.eval_with(env, functions, unknown);
+                let hi = hi.eval_with(env, functions, unknown);
+                if *inclusive {
+                    lo <= value && value <= hi
+                } else {
+                    lo <= value && value < hi
+                }
+            }
+        }
+    }
+
+    // 定数ラベル同士が共通の値を持つかどうか
+    fn overlaps(&self, other: &CaseLabel) -> bool {
+        let env = HashMap::new();
+        let functions = Functions::default();
+        match (self, other) {
+            (CaseLabel::Value(x), _) => other.matches(x.eval(&env), &env, &functions, &mut |_| {}),
+            (_, CaseLabel::Value(x)) => self.matches(x.eval(&env), &env, &functions, &mut |_| {}),
+            (CaseLabel::Wildcard(x0, mask0), CaseLabel::Wildcard(x1, mask1)) => {
+                (x0 ^ x1) & mask0 & mask1 == 0
+            }
+            (CaseLabel::Range(lo, hi, inclusive), label)
+            | (label, CaseLabel::Range(lo, hi, inclusive)) => {
+                let lo = lo.eval(&env);
+                let hi = hi.eval(&env);
+                let hi = if *inclusive {
+                    Some(hi)
+                } else {
+                    hi.checked_sub(1)
+                };
+                let Some(hi) = hi.filter(|hi| lo <= *hi) else {
+                    return false;
+                };
+                match label {
+                    CaseLabel::Range(lo1, hi1, inclusive1) => {
+                        let lo1 = lo1.eval(&env);
+                        let hi1 = hi1.eval(&env);
+                        if *inclusive1 {
+                            lo1 <= hi && lo <= hi1
+                        } else {
+                            lo1 <= hi && lo < hi1 && lo1 < hi1
+                        }
+                    }
+                    // 範囲が広すぎる場合は検査しない
+                    _ => {
+                        hi - lo < 1 << MAX_CASE_CHECK_WIDTH
+                            && (lo..=hi).any(|x| label.matches(x, &env, &functions, &mut |_| {}))
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Unreachable or overlapping arm of a case statement found at elaboration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaseOverlap {
+    /// Location of the arm
+    pub location: Span,
+    /// Location of the earlier arm which shares values with this arm
+    pub previous: Option<Span>,
+    /// Whether all values of this arm are taken by earlier arms
+    pub unreachable: bool,
+}
+
+impl fmt::Display for CaseOverlap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.unreachable, &self.previous) {
+            (true, Some(previous)) => write!(
+                f,
+                "case arm at {} is unreachable because it is covered by the arm at {}",
+                self.location, previous
+            ),
+            (true, None) => write!(f, "case arm at {} matches no value", self.location),
+            (false, Some(previous)) => write!(
+                f,
+                "case arm at {} overlaps with the arm at {}",
+                self.location, previous
+            ),
+            (false, None) => write!(f, "case arm at {} overlaps", self.location),
+        }
+    }
+}
+
+// 代入先を表す構造体
+#[derive(Debug, Clone, Serialize)]
+pub struct Target {
+    pub(crate) name: String,                 // 代入先の信号名
+    pub(crate) width: usize,                 // 代入先の信号のビット幅
+    pub(crate) index: Option<Expr>,          // 配列要素の添字
+    pub(crate) select: Option<(Expr, Expr)>, // ビット選択 (MSB, LSB)
+}
+
+impl Target {
+    fn new(name: String, width: usize) -> Self {
+        Target {
+            name,
+            width,
+            index: None,
+            select: None,
+        }
+    }
+
+    // 代入先の要素名を求める（配列の場合は "mem[2]" の形式）
+    fn resolve(&self, env: &HashMap<String, usize>) -> String {
+        match &self.index {
+            Some(index) => element_name(&self.name, index.eval(env)),
+            None => self.name.clone(),
+        }
+    }
+
+    // 代入先の現在値を読み出す式
+    fn value(&self) -> Expr {
+        let value = match &self.index {
+            Some(index) => Expr::Index(self.name.clone(), Box::new(index.clone())),
+            None => Expr::Var(self.name.clone()),
+        };
+        match &self.select {
+            Some((msb, lsb)) => Expr::Select(
+                Box::new(value),
+                Box::new(msb.clone()),
+                Box::new(lsb.clone()),
+            ),
+            None => value,
+        }
+    }
+
+    // 代入先のビット幅を求める
+    fn width(&self, env: &HashMap<String, usize>) -> usize {
+        match &self.select {
+            Some((msb, lsb)) => (msb.eval(env) + 1).saturating_sub(lsb.eval(env)),
+            None => self.width,
+        }
+    }
+
+    // 現在値 current に value を書き込んだ結果を返す（信号全体への代入は信号の幅に切り詰める）
+    fn merge(&self, current: usize, value: usize, env: &HashMap<String, usize>) -> usize {
+        let Some((msb, lsb)) = &self.select else {
+            return value & bit_mask(self.width);
+        };
+        merge_bits(current, value, msb.eval(env), lsb.eval(env))
+    }
+}
+
+// 現在値 current の [msb:lsb] に value を書き込んだ結果を返す
+pub(crate) fn merge_bits(current: usize, value: usize, msb: usize, lsb: usize) -> usize {
+    // 範囲外の選択は書き込まない
+    if msb < lsb || lsb >= usize::BITS as usize {
+        return current;
+    }
+    let mask = bit_mask(msb - lsb + 1);
+    (current & !(mask << lsb)) | ((value & mask) << lsb)
+}
+
+// value の [msb:lsb] を返す
+pub(crate) fn select_bits(value: usize, msb: usize, lsb: usize) -> usize {
+    // 範囲外の選択は 0 とする
+    if msb < lsb || lsb >= usize::BITS as usize {
+        return 0;
+    }
+    (value >> lsb) & bit_mask(msb - lsb + 1)
+}
+
+// 語長以上のシフトは 0 とする
+pub(crate) fn shift_left(value: usize, amount: usize) -> usize {
+    u32::try_from(amount)
+        .ok()
+        .and_then(|x| value.checked_shl(x))
+        .unwrap_or(0)
+}
+
+pub(crate) fn shift_right(value: usize, amount: usize) -> usize {
+    u32::try_from(amount)
+        .ok()
+        .and_then(|x| value.checked_shr(x))
+        .unwrap_or(0)
+}
+
+// 下位 width ビットのマスク
+pub(crate) fn bit_mask(width: usize) -> usize {
+    if width >= usize::BITS as usize {
+        usize::MAX
+    } else {
+        (1 << width) - 1
+    }
+}
+
+// 配列要素の信号名
+pub(crate) fn element_name(name: &str, index: usize) -> String {
+    format!("{name}[{index}]")
 }
 
 // 式を表す列挙型
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Expr {
-    Const(usize),              // 定数値
-    Var(String),               // 変数参照
-    Add(Box<Expr>, Box<Expr>), // 加算
-    Sub(Box<Expr>, Box<Expr>), // 減算
-    Mul(Box<Expr>, Box<Expr>), // 乗算
-    Div(Box<Expr>, Box<Expr>), // 除算
-    Not(Box<Expr>),            // ビット反転
+    Const(usize),                            // 定数値
+    Var(String),                             // 変数参照
+    Index(String, Box<Expr>),                // 配列要素の参照
+    Select(Box<Expr>, Box<Expr>, Box<Expr>), // ビット選択 (対象, MSB, LSB)
+    Add(Box<Expr>, Box<Expr>),               // 加算
+    Sub(Box<Expr>, Box<Expr>),               // 減算
+    SatSub(Box<Expr>, Box<Expr>),            // 0 で飽和する減算（ビット選択の範囲の計算用）
+    Mul(Box<Expr>, Box<Expr>),               // 乗算
+    Div(Box<Expr>, Box<Expr>),               // 除算
+    Rem(Box<Expr>, Box<Expr>),               // 剰余
+    And(Box<Expr>, Box<Expr>),               // ビット積
+    Or(Box<Expr>, Box<Expr>),                // ビット和
+    Xor(Box<Expr>, Box<Expr>),               // 排他的論理和
+    Shl(Box<Expr>, Box<Expr>),               // 左シフト
+    Shr(Box<Expr>, Box<Expr>),               // 論理右シフト
+    Not(Box<Expr>),                          // ビット反転
+    Eq(Box<Expr>, Box<Expr>, usize),         // マスクのビットが一致するか (左辺, 右辺, マスク)
+    Inside(Box<Expr>, Vec<CaseLabel>),       // いずれかのラベルに一致するか (値, ラベル)
+    Call(String, Vec<Expr>),                 // 外部関数の呼び出し (関数名, 引数)
 }
 
+// 二項演算の式を作る関数（Expr::Add など）
+type BinaryOp = fn(Box<Expr>, Box<Expr>) -> Expr;
+
 impl Expr {
+    // 変数を含まない式かどうか
+    pub fn is_const(&self) -> bool {
+        match self {
+            Expr::Const(_) => true,
+            Expr::Var(_) | Expr::Index(_, _) => false,
+            Expr::Select(expr, msb, lsb) => expr.is_const() && msb.is_const() && lsb.is_const(),
+            Expr::Add(left, right)
+            | Expr::Sub(left, right)
+            | Expr::SatSub(left, right)
+            | Expr::Mul(left, right)
+            | Expr::Div(left, right)
+            | Expr::Rem(left, right)
+            | Expr::And(left, right)
+            | Expr::Or(left, right)
+            | Expr::Xor(left, right)
+            | Expr::Shl(left, right)
+            | Expr::Shr(left, right)
+            | Expr::Eq(left, right, _) => left.is_const() && right.is_const(),
+            Expr::Not(expr) => expr.is_const(),
+            Expr::Inside(expr, labels) => expr.is_const() && labels.iter().all(CaseLabel::is_const),
+            // 外部関数は状態を持つことがあるので畳み込まない
+            Expr::Call(_, _) => false,
+        }
+    }
+
     pub fn eval(&self, env: &HashMap<String, usize>) -> usize {
+        self.eval_with(env, &Functions::default(), &mut |_| {})
+    }
+
+    // 未知の信号や登録されていない関数を参照した場合は unknown にその名前を通知する
+    pub fn eval_with(
+        &self,
+        env: &HashMap<String, usize>,
+        functions: &Functions,
+        unknown: &mut dyn FnMut(&str),
+    ) -> usize {
         match self {
             Expr::Const(val) => *val,
             Expr::Var(name) => {
                 // 変数の値を取得（見つからない場合は0）
-                env.get(name).copied().unwrap_or(0)
+                env.get(name).copied().unwrap_or_else(|| {
+                    unknown(name);
+                    0
+                })
+            }
+            Expr::Index(name, index) => {
+                let name = element_name(name, index.eval_with(env, functions, unknown));
+                env.get(&name).copied().unwrap_or_else(|| {
+                    unknown(&name);
+                    0
+                })
             }
-            Expr::Add(left, right) => left.eval(env) + right.eval(env),
-            Expr::Sub(left, right) => left.eval(env).saturating_sub(right.eval(env)),
-            Expr::Mul(left, right) => left.eval(env) * right.eval(env),
+            Expr::Select(expr, msb, lsb) => {
+                let val = expr.eval_with(env, functions, unknown);
+                let msb = msb.eval_with(env, functions, unknown);
+                let lsb = lsb.eval_with(env, functions, unknown);
+                select_bits(val, msb, lsb)
+            }
+            Expr::Add(left, right) => left
+                .eval_with(env, functions, unknown)
+                .wrapping_add(right.eval_with(env, functions, unknown)),
+            Expr::Sub(left, right) => left
+                .eval_with(env, functions, unknown)
+                .wrapping_sub(right.eval_with(env, functions, unknown)),
+            Expr::SatSub(left, right) => left
+                .eval_with(env, functions, unknown)
+                .saturating_sub(right.eval_with(env, functions, unknown)),
+            Expr::Mul(left, right) => left
+                .eval_with(env, functions, unknown)
+                .wrapping_mul(right.eval_with(env, functions, unknown)),
             Expr::Div(left, right) => {
-                let right_val = right.eval(env);
-                if right_val != 0 {
-                    left.eval(env) / right_val
-                } else {
-                    0 // ゼロ除算を回避
-                }
+                let left_val = left.eval_with(env, functions, unknown);
+                let right_val = right.eval_with(env, functions, unknown);
+                // ゼロ除算は 0 とする
+                left_val.checked_div(right_val).unwrap_or(0)
+            }
+            Expr::Rem(left, right) => {
+                let left_val = left.eval_with(env, functions, unknown);
+                let right_val = right.eval_with(env, functions, unknown);
+                // ゼロでの剰余は 0 とする
+                left_val.checked_rem(right_val).unwrap_or(0)
+            }
+            Expr::And(left, right) => {
+                left.eval_with(env, functions, unknown) & right.eval_with(env, functions, unknown)
+            }
+            Expr::Or(left, right) => {
+                left.eval_with(env, functions, unknown) | right.eval_with(env, functions, unknown)
             }
+            Expr::Xor(left, right) => {
+                left.eval_with(env, functions, unknown) ^ right.eval_with(env, functions, unknown)
+            }
+            Expr::Shl(left, right) => shift_left(
+                left.eval_with(env, functions, unknown),
+                right.eval_with(env, functions, unknown),
+            ),
+            Expr::Shr(left, right) => shift_right(
+                left.eval_with(env, functions, unknown),
+                right.eval_with(env, functions, unknown),
+            ),
             Expr::Not(expr) => {
-                let val = expr.eval(env);
+                let val = expr.eval_with(env, functions, unknown);
                 // ビット反転（値が0なら1、それ以外なら0にする）
                 // これによりトグルフリップフロップのような動作になる
                 if val == 0 { 1 } else { 0 }
             }
+            Expr::Eq(left, right, mask) => {
+                let left_val = left.eval_with(env, functions, unknown);
+                let right_val = right.eval_with(env, functions, unknown);
+                ((left_val ^ right_val) & mask == 0) as usize
+            }
+            Expr::Inside(expr, labels) => {
+                let val = expr.eval_with(env, functions, unknown);
+                labels
+                    .iter()
+                    .any(|x| x.matches(val, env, functions, unknown)) as usize
+            }
+            Expr::Call(name, args) => {
+                let args: Vec<_> = args
+                    .iter()
+                    .map(|x| x.eval_with(env, functions, unknown))
+                    .collect();
+                functions.call(name, &args).unwrap_or_else(|| {
+                    unknown(name);
+                    0
+                })
+            }
+        }
+    }
+}
+
+// 設計から呼び出す Rust の関数（Model::register_function で登録する）
+type ForeignFn = Box<dyn FnMut(&[usize]) -> usize + Send>;
+
+#[derive(Default)]
+pub(crate) struct Functions(HashMap<String, Mutex<ForeignFn>>);
+
+impl Functions {
+    // 登録されていない関数は None
+    pub(crate) fn call(&self, name: &str, args: &[usize]) -> Option<usize> {
+        let function = self.0.get(name)?;
+        let mut function = function.lock().unwrap_or_else(|x| x.into_inner());
+        Some(function(args))
+    }
+}
+
+/// Source location of a construct in the Veryl source
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Span {
+    pub line: u32,
+    pub column: u32,
+    pub length: u32,
+}
+
+impl From<&Token> for Span {
+    fn from(token: &Token) -> Self {
+        Span {
+            line: token.line,
+            column: token.column,
+            length: token.length,
+        }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Direction of a signal in the model
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Input,
+    Output,
+    Internal,
+}
+
+/// Signal in the model with its current value
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SignalInfo {
+    /// Signal name, array elements are named like "mem[2]"
+    pub name: String,
+    pub direction: Direction,
+    pub width: usize,
+    pub value: usize,
+}
+
+/// Construct which was ignored or approximated while building the model
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelWarning {
+    pub construct: String,
+    pub span: Span,
+}
+
+impl fmt::Display for ModelWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} at {} is not supported and isn't modeled exactly",
+            self.construct, self.span
+        )
+    }
+}
+
+/// How references to signals which don't exist in the model are handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnknownPolicy {
+    /// Fail at the first reference, reported by `Model::try_input` and the simulator
+    Error,
+    /// Log a warning at the first reference of each signal
+    WarnOnce,
+    /// Evaluate as 0 silently
+    #[default]
+    Zero,
+}
+
+/// How the model evaluates its statements
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EvalMode {
+    /// Run the bytecode compiled when the model is built
+    #[default]
+    Compiled,
+    /// Walk the expression trees, kept as the reference for differential testing
+    Interpreted,
+    /// Run native code compiled from the bytecode by Cranelift
+    /// expressions which may reference unknown signals are left to the bytecode
+    #[cfg(feature = "jit")]
+    Jit,
+}
+
+/// Unknown signal referenced during simulation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownSignal {
+    pub name: String,
+    pub count: usize,
+    pub locations: Vec<Span>,
+}
+
+// 未知の信号への参照を記録する
+#[derive(Default)]
+struct UnknownTracker {
+    policy: UnknownPolicy,
+    counts: HashMap<String, usize>,         // 信号名と参照回数
+    references: HashMap<String, Vec<Span>>, // 信号名とソース上の参照位置
+    captured: Option<Vec<String>>,          // 組み合わせ回路の組の評価中に参照した信号名
+    failure: Option<ModelError>,            // Error ポリシーで最初に記録した参照
+}
+
+impl UnknownTracker {
+    fn record(&mut self, name: &str) {
+        let count = self.counts.entry(name.to_string()).or_insert(0);
+        *count += 1;
+        if let Some(captured) = &mut self.captured {
+            captured.push(name.to_string());
+        }
+
+        match self.policy {
+            UnknownPolicy::Error if self.failure.is_none() => {
+                self.failure = Some(ModelError::UnknownSignal {
+                    name: name.to_string(),
+                    locations: self.locations_str(name),
+                });
+            }
+            UnknownPolicy::WarnOnce if *count == 1 => {
+                log::warn!(
+                    "unknown signal `{}` is referenced at {}",
+                    name,
+                    self.locations_str(name)
+                );
+            }
+            _ => {}
+        }
+    }
+
+    fn locations(&self, name: &str) -> Vec<Span> {
+        // 配列要素 "mem[2]" の参照位置は "mem" として記録されている
+        let base = name.split('[').next().unwrap_or(name);
+        self.references.get(base).cloned().unwrap_or_default()
+    }
+
+    fn locations_str(&self, name: &str) -> String {
+        let locations: Vec<_> = self.locations(name).iter().map(|x| x.to_string()).collect();
+        if locations.is_empty() {
+            "unknown location".to_string()
+        } else {
+            locations.join(", ")
         }
     }
+
+    fn report(&self) -> Vec<UnknownSignal> {
+        let mut ret: Vec<_> = self
+            .counts
+            .iter()
+            .map(|(name, count)| UnknownSignal {
+                name: name.clone(),
+                count: *count,
+                locations: self.locations(name),
+            })
+            .collect();
+        ret.sort_by(|a, b| a.name.cmp(&b.name));
+        ret
+    }
 }
 
 // 順序回路のブロック（always_ff）
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct SequentialBlock {
-    reset_assignments: Vec<Assignment>, // リセット時の代入
-    clock_assignments: Vec<Assignment>, // クロック時の代入
+    pub(crate) reset: Vec<Statement>,        // リセット時の文
+    pub(crate) clock: Vec<Statement>,        // クロック時の文
+    pub(crate) clock_signal: Option<String>, // ブロックを駆動するクロック信号
+    pub(crate) reset_signal: Option<String>, // if_reset が参照するリセット信号
 }
 
 // ASTから代入式を収集するハンドラ
 struct AssignCollector {
-    assignments: Vec<Assignment>,
+    widths: HashMap<String, usize>, // 信号名とビット幅（msb や $bits の解決に使う）
+    arrays: HashSet<String>,        // 配列として宣言された信号名
+    default_clock: Option<String>,  // always_ff でクロックが省略された場合のクロック信号
+    default_reset: Option<String>,  // always_ff でリセットが省略された場合のリセット信号
+    select_msb: Cell<Option<usize>>, // 変換中のビット選択における msb の値
+    references: RefCell<HashMap<String, Vec<Span>>>, // 式中の信号参照の位置
+    case_overlaps: RefCell<Vec<CaseOverlap>>, // case 文の到達不能・重なりのある分岐
+    unsupported: RefCell<Vec<(String, Span)>>, // モデル化できない構文とその位置
+    warnings: RefCell<Vec<ModelWarning>>, // 無視・近似した構文
+    approximations: RefCell<Vec<ModelWarning>>, // 対応しているが結果が近似となる構文
+    coverage: RefCell<Vec<CoveragePoint>>, // カバレッジの計測点
+    combinational: Vec<Vec<Statement>>, // assign 宣言と always_comb ブロックごとの文
     sequential_blocks: Vec<SequentialBlock>,
-    in_always_ff: bool,
-    current_sequential: Option<SequentialBlock>,
+    instances: Vec<InstanceDecl>, // 振る舞いモデルで置き換えるインスタンス
     handler_point: HandlerPoint,
 }
 
+// インスタンスのポート接続 (ポート名, 接続した式, 出力ポートか)
+// $sv:: のモジュールはポートの方向が分からないので None とし、全ての文を収集した後に決める
+struct InstanceDecl {
+    name: String,
+    module: String,
+    ports: Vec<(String, Expr, Option<bool>)>,
+}
+
 impl AssignCollector {
-    fn new() -> Self {
+    fn new(
+        widths: HashMap<String, usize>,
+        arrays: HashSet<String>,
+        default_clock: Option<String>,
+        default_reset: Option<String>,
+    ) -> Self {
         Self {
-            assignments: Vec::new(),
+            widths,
+            arrays,
+            default_clock,
+            default_reset,
+            select_msb: Cell::new(None),
+            references: RefCell::new(HashMap::new()),
+            case_overlaps: RefCell::new(Vec::new()),
+            unsupported: RefCell::new(Vec::new()),
+            warnings: RefCell::new(Vec::new()),
+            approximations: RefCell::new(Vec::new()),
+            coverage: RefCell::new(Vec::new()),
+            combinational: Vec::new(),
             sequential_blocks: Vec::new(),
-            in_always_ff: false,
-            current_sequential: None,
+            instances: Vec::new(),
             handler_point: HandlerPoint::Before,
         }
     }
 
+    // カバレッジの計測点を追加する
+    fn cover(&self, kind: CoverageKind, token: &Token, implicit: bool) -> Statement {
+        let mut coverage = self.coverage.borrow_mut();
+        coverage.push(CoveragePoint {
+            kind,
+            location: Span::from(token),
+            implicit,
+        });
+        Statement::Cover(coverage.len() - 1)
+    }
+
+    // 分岐の先頭に計測点を置く
+    fn cover_branch(
+        &self,
+        kind: CoverageKind,
+        token: &Token,
+        mut statements: Vec<Statement>,
+    ) -> Vec<Statement> {
+        statements.insert(0, self.cover(kind, token, false));
+        statements
+    }
+
+    // 無視・近似した構文を警告として記録する
+    fn warn(&self, construct: &str, token: &Token) {
+        self.warnings.borrow_mut().push(ModelWarning {
+            construct: construct.to_string(),
+            span: Span::from(token),
+        });
+    }
+
+    fn warn_operator(&self, token: &VerylToken) {
+        self.warn(&format!("operator `{token}`"), &token.token);
+    }
+
+    fn warn_unary_operator(&self, token: &VerylToken) {
+        self.warn(&format!("unary operator `{token}`"), &token.token);
+    }
+
+    // 対応している演算子のうち、結果が近似となるものを記録する
+    fn use_operator(&self, token: &VerylToken) {
+        let construct = format!("operator `{token}`");
+        if capability::support(&construct) == Support::Approximated {
+            self.approximations.borrow_mut().push(ModelWarning {
+                construct,
+                span: Span::from(&token.token),
+            });
+        }
+    }
+
     // Expressionを評価してExprに変換
     fn convert_expression(&self, expr: &syntax_tree::Expression) -> Expr {
+        // if 式は今のところ else 側の式として扱う
+        for x in &expr.if_expression.if_expression_list {
+            self.warn("if expression", &x.r#if.if_token.token);
+        }
         self.convert_expression01(&expr.if_expression.expression01)
     }
 
+    // 以下、未対応の二項演算子は今のところ左辺の項だけを処理
     fn convert_expression01(&self, expr: &syntax_tree::Expression01) -> Expr {
-        // 今のところ、最初の項だけを処理
+        for x in &expr.expression01_list {
+            self.warn_operator(&x.operator02.operator02_token);
+        }
         self.convert_expression02(&expr.expression02)
     }
 
     fn convert_expression02(&self, expr: &syntax_tree::Expression02) -> Expr {
+        for x in &expr.expression02_list {
+            self.warn_operator(&x.operator03.operator03_token);
+        }
         self.convert_expression03(&expr.expression03)
     }
 
     fn convert_expression03(&self, expr: &syntax_tree::Expression03) -> Expr {
-        self.convert_expression04(&expr.expression04)
+        // ビット和の処理
+        let mut result = self.convert_expression04(&expr.expression04);
+        for item in &expr.expression03_list {
+            let right = self.convert_expression04(&item.expression04);
+            self.use_operator(&item.operator04.operator04_token);
+            result = Expr::Or(Box::new(result), Box::new(right));
+        }
+        result
     }
 
     fn convert_expression04(&self, expr: &syntax_tree::Expression04) -> Expr {
-        self.convert_expression05(&expr.expression05)
+        // 排他的論理和の処理
+        let mut result = self.convert_expression05(&expr.expression05);
+        for item in &expr.expression04_list {
+            let right = self.convert_expression05(&item.expression05);
+            let token = &item.operator05.operator05_token;
+            match token.to_string().as_str() {
+                "^" => {
+                    self.use_operator(token);
+                    result = Expr::Xor(Box::new(result), Box::new(right));
+                }
+                _ => self.warn_operator(token), // ~^ は今のところ無視
+            }
+        }
+        result
     }
 
     fn convert_expression05(&self, expr: &syntax_tree::Expression05) -> Expr {
-        self.convert_expression06(&expr.expression06)
+        // ビット積の処理
+        let mut result = self.convert_expression06(&expr.expression06);
+        for item in &expr.expression05_list {
+            let right = self.convert_expression06(&item.expression06);
+            self.use_operator(&item.operator06.operator06_token);
+            result = Expr::And(Box::new(result), Box::new(right));
+        }
+        result
     }
 
     fn convert_expression06(&self, expr: &syntax_tree::Expression06) -> Expr {
-        self.convert_expression07(&expr.expression07)
+        // 等価演算子の処理
+        let mut result = self.convert_expression07(&expr.expression07);
+        for item in &expr.expression06_list {
+            let token = &item.operator07.operator07_token;
+            self.use_operator(token);
+            let op_str = token.to_string();
+            // ==? / !=? は右辺の x / z の桁を比較しない
+            let wildcard = if op_str.ends_with('?') {
+                wildcard_number(&item.expression07)
+            } else {
+                None
+            };
+            let (right, mask) = match wildcard {
+                Some((value, mask)) => (Expr::Const(value), mask),
+                None => (self.convert_expression07(&item.expression07), usize::MAX),
+            };
+            result = Expr::Eq(Box::new(result), Box::new(right), mask);
+            if op_str.starts_with('!') {
+                result = Expr::Not(Box::new(result));
+            }
+        }
+        result
     }
 
     fn convert_expression07(&self, expr: &syntax_tree::Expression07) -> Expr {
+        for x in &expr.expression07_list {
+            self.warn_operator(&x.operator08.operator08_token);
+        }
         self.convert_expression08(&expr.expression08)
     }
 
     fn convert_expression08(&self, expr: &syntax_tree::Expression08) -> Expr {
-        self.convert_expression09(&expr.expression09)
+        // シフトの処理
+        let mut result = self.convert_expression09(&expr.expression09);
+        for item in &expr.expression08_list {
+            let right = self.convert_expression09(&item.expression09);
+            let token = &item.operator09.operator09_token;
+            match token.to_string().as_str() {
+                // 符号なしの値では <<< は << と同じ
+                "<<" | "<<<" => {
+                    self.use_operator(token);
+                    result = Expr::Shl(Box::new(result), Box::new(right));
+                }
+                ">>" => {
+                    self.use_operator(token);
+                    result = Expr::Shr(Box::new(result), Box::new(right));
+                }
+                _ => self.warn_operator(token), // >>> は今のところ無視
+            }
+        }
+        result
     }
 
     fn convert_expression09(&self, expr: &syntax_tree::Expression09) -> Expr {
@@ -125,6 +1037,7 @@ impl AssignCollector {
             let right = self.convert_expression10(&item.expression10);
             // Operator10は+と-を表す
             let op_str = item.operator10.operator10_token.to_string();
+            self.use_operator(&item.operator10.operator10_token);
             match op_str.as_str() {
                 "+" => {
                     result = Expr::Add(Box::new(result), Box::new(right));
@@ -149,15 +1062,22 @@ impl AssignCollector {
                     let op_str = op.operator11.operator11_token.to_string();
                     match op_str.as_str() {
                         "*" => {
+                            self.use_operator(&op.operator11.operator11_token);
                             result = Expr::Mul(Box::new(result), Box::new(right));
                         }
                         "/" => {
+                            self.use_operator(&op.operator11.operator11_token);
                             result = Expr::Div(Box::new(result), Box::new(right));
                         }
-                        _ => {} // その他の演算子は今のところ無視
+                        "%" => {
+                            self.use_operator(&op.operator11.operator11_token);
+                            result = Expr::Rem(Box::new(result), Box::new(right));
+                        }
+                        _ => self.warn_operator(&op.operator11.operator11_token), // その他の演算子は今のところ無視
                     }
                 }
-                syntax_tree::Expression10ListGroup::Star(_) => {
+                syntax_tree::Expression10ListGroup::Star(x) => {
+                    self.use_operator(&x.star.star_token);
                     result = Expr::Mul(Box::new(result), Box::new(right));
                 }
             }
@@ -166,11 +1086,17 @@ impl AssignCollector {
     }
 
     fn convert_expression11(&self, expr: &syntax_tree::Expression11) -> Expr {
+        for x in &expr.expression11_list {
+            self.warn_operator(&x.operator12.operator12_token);
+        }
         self.convert_expression12(&expr.expression12)
     }
 
     fn convert_expression12(&self, expr: &syntax_tree::Expression12) -> Expr {
-        // Expression12は型キャスト用なので、そのままexpression13に委譲
+        // Expression12は型キャスト用なので、値を変えずにexpression13に委譲
+        if let Some(x) = &expr.expression12_opt {
+            self.warn_operator(&x.r#as.as_token);
+        }
         self.convert_expression13(&expr.expression13)
     }
 
@@ -184,95 +1110,736 @@ impl AssignCollector {
                     let op_str = unary_op.unary_operator.unary_operator_token.to_string();
                     match op_str.as_str() {
                         "~" => {
+                            self.use_operator(&unary_op.unary_operator.unary_operator_token);
                             result = Expr::Not(Box::new(result));
                         }
-                        _ => {} // その他の単項演算子は今のところ無視
+                        // その他の単項演算子は今のところ無視
+                        _ => {
+                            self.warn_unary_operator(&unary_op.unary_operator.unary_operator_token)
+                        }
                     }
                 }
-                _ => {} // その他の演算子グループは今のところ無視
+                // その他の演算子グループは今のところ無視
+                syntax_tree::Expression13ListGroup::Operator10(x) => {
+                    self.warn_unary_operator(&x.operator10.operator10_token)
+                }
+                syntax_tree::Expression13ListGroup::Operator06(x) => {
+                    self.warn_unary_operator(&x.operator06.operator06_token)
+                }
+                syntax_tree::Expression13ListGroup::Operator04(x) => {
+                    self.warn_unary_operator(&x.operator04.operator04_token)
+                }
+                syntax_tree::Expression13ListGroup::Operator05(x) => {
+                    self.warn_unary_operator(&x.operator05.operator05_token)
+                }
             }
         }
         result
     }
 
-    fn extract_assignment_from_statement_block(
+    // StatementBlock を文の列に変換
+    fn convert_statement_block(&self, block: &syntax_tree::StatementBlock) -> Vec<Statement> {
+        let mut statements = Vec::new();
+        for x in &block.statement_block_list {
+            self.convert_statement_block_group(&x.statement_block_group, &mut statements);
+        }
+        statements
+    }
+
+    fn convert_statement_block_group(
         &self,
-        statement_list: &syntax_tree::StatementBlockList,
-    ) -> Option<Assignment> {
-        // StatementBlockListからAssignmentを抽出
-        // StatementBlockGroupを処理
-        match &*statement_list
-            .statement_block_group
-            .statement_block_group_group
-        {
+        group: &syntax_tree::StatementBlockGroup,
+        statements: &mut Vec<Statement>,
+    ) {
+        match &*group.statement_block_group_group {
+            syntax_tree::StatementBlockGroupGroup::LBraceStatementBlockGroupGroupListRBrace(x) => {
+                for x in &x.statement_block_group_group_list {
+                    self.convert_statement_block_group(&x.statement_block_group, statements);
+                }
+            }
             syntax_tree::StatementBlockGroupGroup::StatementBlockItem(item) => {
+                // var / let は今のところ無視する（const は const_declaration で記録する）
                 match &*item.statement_block_item {
-                    syntax_tree::StatementBlockItem::Statement(stmt) => {
-                        if let syntax_tree::Statement::IdentifierStatement(id_stmt) =
-                            &*stmt.statement
-                        {
-                            let stmt = &id_stmt.identifier_statement;
-
-                            // 識別子から代入先を取得
-                            let target = match &*stmt.expression_identifier.scoped_identifier.scoped_identifier_group {
-                                syntax_tree::ScopedIdentifierGroup::IdentifierScopedIdentifierOpt(id_group) => {
-                                    id_group.identifier.identifier_token.to_string()
-                                }
-                                _ => return None,
-                            };
-
-                            // IdentifierStatementGroupから代入の右辺を取得
-                            match &*stmt.identifier_statement_group {
-                                syntax_tree::IdentifierStatementGroup::Assignment(a) => {
-                                    let expression =
-                                        self.convert_expression(&a.assignment.expression);
-                                    Some(Assignment { target, expression })
-                                }
-                                _ => None,
-                            }
-                        } else {
-                            None
-                        }
+                    syntax_tree::StatementBlockItem::Statement(x) => {
+                        self.convert_covered_statement(&x.statement, statements);
                     }
-                    _ => None,
+                    syntax_tree::StatementBlockItem::VarDeclaration(x) => {
+                        self.warn("var", &x.var_declaration.var.var_token.token);
+                    }
+                    syntax_tree::StatementBlockItem::LetStatement(x) => {
+                        self.warn("let", &x.let_statement.r#let.let_token.token);
+                    }
+                    syntax_tree::StatementBlockItem::ConstDeclaration(_) => {}
                 }
             }
-            _ => None,
         }
     }
 
-    fn extract_assignment_from_statement(
+    // 文を変換し、実行回数を数える計測点を前に置く
+    fn convert_covered_statement(
         &self,
-        statement: &syntax_tree::IfResetStatementList,
-    ) -> Option<Assignment> {
-        // IfResetStatementListはstatement_blockを持つ
-        // StatementBlockを再帰的に処理
-        for statement_block_item in &statement.statement_block.statement_block_list {
-            if let Some(assignment) =
-                self.extract_assignment_from_statement_block(statement_block_item)
-            {
-                return Some(assignment);
-            }
+        statement: &syntax_tree::Statement,
+        statements: &mut Vec<Statement>,
+    ) {
+        if let Some(x) = self.convert_statement(statement) {
+            let token = TokenRange::from(statement).beg;
+            statements.push(self.cover(CoverageKind::Statement, &token, false));
+            statements.push(x);
         }
-        None
     }
 
-    fn convert_factor(&self, factor: &syntax_tree::Factor) -> Expr {
+    fn convert_statement(&self, statement: &syntax_tree::Statement) -> Option<Statement> {
+        match statement {
+            syntax_tree::Statement::IdentifierStatement(x) => {
+                self.convert_identifier_statement(&x.identifier_statement)
+            }
+            syntax_tree::Statement::IfStatement(x) => {
+                let x = &x.if_statement;
+                let token = &x.r#if.if_token.token;
+                let mut branches = vec![(
+                    self.convert_expression(&x.expression),
+                    self.cover_branch(
+                        CoverageKind::If,
+                        token,
+                        self.convert_statement_block(&x.statement_block),
+                    ),
+                )];
+                for x in &x.if_statement_list {
+                    branches.push((
+                        self.convert_expression(&x.expression),
+                        self.cover_branch(
+                            CoverageKind::ElseIf,
+                            &x.r#else.else_token.token,
+                            self.convert_statement_block(&x.statement_block),
+                        ),
+                    ));
+                }
+                // else が無い場合もどの分岐も実行されなかったことを数える
+                let otherwise = match &x.if_statement_opt {
+                    Some(x) => self.cover_branch(
+                        CoverageKind::Else,
+                        &x.r#else.else_token.token,
+                        self.convert_statement_block(&x.statement_block),
+                    ),
+                    None => vec![self.cover(CoverageKind::Else, token, true)],
+                };
+                Some(Statement::If(branches, otherwise))
+            }
+            syntax_tree::Statement::CaseStatement(x) => {
+                Some(self.convert_case_statement(&x.case_statement))
+            }
+            syntax_tree::Statement::IfResetStatement(x) => {
+                let token = &x.if_reset_statement.if_reset.if_reset_token.token;
+                self.record_unsupported("if_reset", token)
+            }
+            syntax_tree::Statement::ReturnStatement(x) => {
+                self.record_unsupported("return", &x.return_statement.r#return.return_token.token)
+            }
+            syntax_tree::Statement::BreakStatement(x) => {
+                self.record_unsupported("break", &x.break_statement.r#break.break_token.token)
+            }
+            syntax_tree::Statement::ForStatement(x) => {
+                self.record_unsupported("for", &x.for_statement.r#for.for_token.token)
+            }
+            syntax_tree::Statement::SwitchStatement(x) => {
+                self.record_unsupported("switch", &x.switch_statement.switch.switch_token.token)
+            }
+        }
+    }
+
+    // モデル化できない構文を記録する
+    fn record_unsupported<T>(&self, construct: &str, token: &Token) -> Option<T> {
+        self.unsupported
+            .borrow_mut()
+            .push((construct.to_string(), Span::from(token)));
+        None
+    }
+
+    fn convert_case_statement(&self, arg: &syntax_tree::CaseStatement) -> Statement {
+        let expression = self.convert_expression(&arg.expression);
+        let mut arms = Vec::new();
+        let mut spans = Vec::new();
+        let mut otherwise = Vec::new();
+        let mut has_default = false;
+        for x in &arg.case_statement_list {
+            let item = &x.case_item;
+            let statements = match &*item.case_item_group0 {
+                syntax_tree::CaseItemGroup0::Statement(x) => {
+                    let mut statements = Vec::new();
+                    self.convert_covered_statement(&x.statement, &mut statements);
+                    statements
+                }
+                syntax_tree::CaseItemGroup0::StatementBlock(x) => {
+                    self.convert_statement_block(&x.statement_block)
+                }
+            };
+            match &*item.case_item_group {
+                syntax_tree::CaseItemGroup::CaseCondition(x) => {
+                    let condition = &x.case_condition;
+                    let mut labels = vec![self.convert_case_label(&condition.range_item)];
+                    for x in &condition.case_condition_list {
+                        labels.push(self.convert_case_label(&x.range_item));
+                    }
+                    let token = TokenRange::from(&*condition.range_item.range.expression).beg;
+                    spans.push(Span::from(&token));
+                    arms.push((
+                        labels,
+                        self.cover_branch(CoverageKind::CaseArm, &token, statements),
+                    ));
+                }
+                // default は記述位置に関わらず、どの分岐にも一致しない場合に実行される
+                syntax_tree::CaseItemGroup::Defaul(x) => {
+                    let token = &x.defaul.default_token.token;
+                    otherwise = self.cover_branch(CoverageKind::Default, token, statements);
+                    has_default = true;
+                }
+            }
+        }
+        if !has_default {
+            otherwise.push(self.cover(CoverageKind::Default, &arg.case.case_token.token, true));
+        }
+        self.check_case_arms(self.expression_width(&expression), &arms, &spans);
+
+        // #[cond_type(...)] の指定を取得（後から指定したものを優先する）
+        let token = &arg.case.case_token.token;
+        let check = attribute_table::get(token)
+            .into_iter()
+            .rev()
+            .find_map(|x| match x {
+                Attribute::CondType(x) => Some(x),
+                _ => None,
+            })
+            .and_then(|x| match x {
+                CondTypeItem::Unique => Some(CaseCheck::Unique),
+                CondTypeItem::Unique0 => Some(CaseCheck::Unique0),
+                CondTypeItem::Priority => Some(CaseCheck::Priority),
+                CondTypeItem::None => None,
+            });
+
+        Statement::Case(CaseStatement {
+            expression,
+            arms,
+            spans,
+            otherwise,
+            has_default,
+            check,
+            span: Span::from(token),
+        })
+    }
+
+    // case 文のラベル a / a..b / a..=b を変換
+    fn convert_case_label(&self, item: &syntax_tree::RangeItem) -> CaseLabel {
+        let range = &item.range;
+        if let Some(x) = &range.range_opt {
+            let inclusive = matches!(*x.range_operator, syntax_tree::RangeOperator::DotDotEqu(_));
+            return CaseLabel::Range(
+                self.convert_expression(&range.expression),
+                self.convert_expression(&x.expression),
+                inclusive,
+            );
+        }
+        // x / z を含む数値リテラルはワイルドカードとして扱う
+        if let Some((value, mask)) = range.expression.unwrap_factor().and_then(wildcard_factor) {
+            return CaseLabel::Wildcard(value, mask);
+        }
+        CaseLabel::Value(self.convert_expression(&range.expression))
+    }
+
+    // inside / outside の範囲は case 文のラベルと同じく値、x / z を含む数値、範囲のいずれか
+    fn convert_inside(
+        &self,
+        expr: &syntax_tree::Expression,
+        list: &syntax_tree::RangeList,
+    ) -> Expr {
+        let mut labels = vec![self.convert_case_label(&list.range_item)];
+        for x in &list.range_list_list {
+            labels.push(self.convert_case_label(&x.range_item));
+        }
+        Expr::Inside(Box::new(self.convert_expression(expr)), labels)
+    }
+
+    // 式のビット幅（分かる場合のみ）
+    fn expression_width(&self, expr: &Expr) -> Option<usize> {
+        match expr {
+            Expr::Var(name) | Expr::Index(name, _) => self.widths.get(name).copied(),
+            Expr::Select(_, msb, lsb) if msb.is_const() && lsb.is_const() => {
+                let env = HashMap::new();
+                Some((msb.eval(&env) + 1).saturating_sub(lsb.eval(&env)))
+            }
+            _ => None,
+        }
+    }
+
+    // 定数ラベルの case 文について、到達不能な分岐と重なりのある分岐を検出する
+    fn check_case_arms(
+        &self,
+        width: Option<usize>,
+        arms: &[(Vec<CaseLabel>, Vec<Statement>)],
+        spans: &[Span],
+    ) {
+        // 変数を含むラベルがある場合は検査しない
+        if !arms
+            .iter()
+            .all(|(labels, _)| labels.iter().all(CaseLabel::is_const))
+        {
+            return;
+        }
+
+        let env = HashMap::new();
+        let functions = Functions::default();
+        let mut overlaps = self.case_overlaps.borrow_mut();
+        match width {
+            Some(width) if width <= MAX_CASE_CHECK_WIDTH => {
+                // 全ての値について、最初に一致する分岐とそれ以降に一致する分岐を調べる
+                let mut previous: Vec<Option<usize>> = vec![None; arms.len()];
+                let mut reachable = vec![false; arms.len()];
+                for value in 0..(1usize << width) {
+                    let mut first = None;
+                    for (i, (labels, _)) in arms.iter().enumerate() {
+                        if !labels
+                            .iter()
+                            .any(|x| x.matches(value, &env, &functions, &mut |_| {}))
+                        {
+                            continue;
+                        }
+                        match first {
+                            None => {
+                                first = Some(i);
+                                reachable[i] = true;
+                            }
+                            Some(j) => {
+                                previous[i].get_or_insert(j);
+                            }
+                        }
+                    }
+                }
+                for i in 0..arms.len() {
+                    if previous[i].is_some() || !reachable[i] {
+                        overlaps.push(CaseOverlap {
+                            location: spans[i],
+                            previous: previous[i].map(|j| spans[j]),
+                            unreachable: !reachable[i],
+                        });
+                    }
+                }
+            }
+            _ => {
+                // 値を列挙できない場合はラベル同士を比較する
+                for (i, (labels, _)) in arms.iter().enumerate() {
+                    let previous = arms[..i]
+                        .iter()
+                        .position(|(x, _)| labels.iter().any(|a| x.iter().any(|b| a.overlaps(b))));
+                    if let Some(j) = previous {
+                        overlaps.push(CaseOverlap {
+                            location: spans[i],
+                            previous: Some(spans[j]),
+                            unreachable: false,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    fn convert_identifier_statement(
+        &self,
+        stmt: &syntax_tree::IdentifierStatement,
+    ) -> Option<Statement> {
+        // f(a, b); の形の外部関数の呼び出しは代入先のない文とする
+        if let syntax_tree::IdentifierStatementGroup::FunctionCall(x) =
+            &*stmt.identifier_statement_group
+            && let Some(expression) =
+                self.convert_call(&stmt.expression_identifier, &x.function_call)
+        {
+            return Some(Statement::Assign(Assignment {
+                targets: Vec::new(),
+                expression,
+            }));
+        }
+
+        // 識別子から代入先を取得
+        let name = match &*stmt
+            .expression_identifier
+            .scoped_identifier
+            .scoped_identifier_group
+        {
+            syntax_tree::ScopedIdentifierGroup::IdentifierScopedIdentifierOpt(id_group) => {
+                id_group.identifier.identifier_token.to_string()
+            }
+            syntax_tree::ScopedIdentifierGroup::DollarIdentifier(x) => {
+                let token = &x.dollar_identifier.dollar_identifier_token;
+                if let Some(severity) = AssertSeverity::from_name(&token.to_string()) {
+                    return Some(Statement::Assert(AssertStatement {
+                        severity,
+                        message: assert_message(&stmt.identifier_statement_group),
+                        span: Span::from(&token.token),
+                    }));
+                }
+                self.warn(&format!("system function `{token}`"), &token.token);
+                return None;
+            }
+        };
+        let selects: Vec<_> = stmt
+            .expression_identifier
+            .expression_identifier_list
+            .iter()
+            .map(|x| x.select.as_ref())
+            .collect();
+        let target = self.convert_target(name, &selects);
+
+        // IdentifierStatementGroupから代入の右辺を取得
+        match &*stmt.identifier_statement_group {
+            syntax_tree::IdentifierStatementGroup::Assignment(a) => {
+                let mut expression = self.convert_expression(&a.assignment.expression);
+                // a op= b は a = a op b とする
+                if let syntax_tree::AssignmentGroup::AssignmentOperator(x) =
+                    &*a.assignment.assignment_group
+                {
+                    let token = &x.assignment_operator.assignment_operator_token;
+                    let op: Option<BinaryOp> = match token.to_string().as_str() {
+                        "+=" => Some(Expr::Add),
+                        "-=" => Some(Expr::Sub),
+                        "*=" => Some(Expr::Mul),
+                        "/=" => Some(Expr::Div),
+                        "%=" => Some(Expr::Rem),
+                        "&=" => Some(Expr::And),
+                        "|=" => Some(Expr::Or),
+                        "^=" => Some(Expr::Xor),
+                        "<<=" | "<<<=" => Some(Expr::Shl),
+                        ">>=" => Some(Expr::Shr),
+                        _ => None,
+                    };
+                    match op {
+                        Some(op) => {
+                            self.use_operator(token);
+                            expression = op(Box::new(target.value()), Box::new(expression));
+                        }
+                        // 符号を扱わないので >>>= はモデル化できない
+                        None => {
+                            return self
+                                .record_unsupported(&format!("operator `{token}`"), &token.token);
+                        }
+                    }
+                }
+                Some(Statement::Assign(Assignment {
+                    targets: vec![target],
+                    expression,
+                }))
+            }
+            syntax_tree::IdentifierStatementGroup::FunctionCall(_) => {
+                let token = TokenRange::from(&*stmt.expression_identifier).beg;
+                self.warn("function call", &token);
+                None
+            }
+        }
+    }
+
+    // ビット選択 a[x], a[x:y], a[x+:y], a[x-:y], a[x step y] を (MSB, LSB) に変換
+    fn convert_select(&self, select: &syntax_tree::Select, width: usize) -> (Expr, Expr) {
+        // 選択式の中の msb は選択対象の最上位ビットを指す
+        let saved = self.select_msb.replace(Some(width.saturating_sub(1)));
+        let first = self.convert_expression(&select.expression);
+        let ret = if let Some(ref opt) = select.select_opt {
+            let second = self.convert_expression(&opt.expression);
+            match &*opt.select_operator {
+                syntax_tree::SelectOperator::Colon(_) => (first, second),
+                syntax_tree::SelectOperator::PlusColon(_) => {
+                    let msb = Expr::SatSub(
+                        Box::new(Expr::Add(Box::new(first.clone()), Box::new(second))),
+                        Box::new(Expr::Const(1)),
+                    );
+                    (msb, first)
+                }
+                syntax_tree::SelectOperator::MinusColon(_) => {
+                    let lsb = Expr::Add(
+                        Box::new(Expr::SatSub(Box::new(first.clone()), Box::new(second))),
+                        Box::new(Expr::Const(1)),
+                    );
+                    (first, lsb)
+                }
+                syntax_tree::SelectOperator::Step(_) => {
+                    let lsb = Expr::Mul(Box::new(first), Box::new(second.clone()));
+                    let msb = Expr::SatSub(
+                        Box::new(Expr::Add(Box::new(lsb.clone()), Box::new(second))),
+                        Box::new(Expr::Const(1)),
+                    );
+                    (msb, lsb)
+                }
+            }
+        } else {
+            (first.clone(), first)
+        };
+        self.select_msb.set(saved);
+        ret
+    }
+
+    // 信号参照とそれに続く添字・ビット選択を変換
+    fn convert_selects(&self, name: String, selects: &[&syntax_tree::Select]) -> Expr {
+        let mut width = self
+            .widths
+            .get(&name)
+            .copied()
+            .unwrap_or(usize::BITS as usize);
+        let mut selects = selects.iter();
+
+        // 配列の場合は最初の選択が要素の添字になる
+        let mut result = if self.arrays.contains(&name) {
+            match selects.next() {
+                Some(select) => {
+                    let index = self.convert_expression(&select.expression);
+                    Expr::Index(name, Box::new(index))
+                }
+                None => Expr::Var(name),
+            }
+        } else {
+            Expr::Var(name)
+        };
+
+        for select in selects {
+            let (msb, lsb) = self.convert_select(select, width);
+
+            // 選択後の幅は定数で決まる場合のみ更新する
+            let env = HashMap::new();
+            if msb.is_const() && lsb.is_const() {
+                width = msb.eval(&env).saturating_sub(lsb.eval(&env)) + 1;
+            }
+
+            result = Expr::Select(Box::new(result), Box::new(msb), Box::new(lsb));
+        }
+        result
+    }
+
+    // 代入先の信号名と添字・ビット選択を変換
+    fn convert_target(&self, name: String, selects: &[&syntax_tree::Select]) -> Target {
+        let width = self
+            .widths
+            .get(&name)
+            .copied()
+            .unwrap_or(usize::BITS as usize);
+        let mut selects = selects.iter();
+        let mut target = Target::new(name, width);
+
+        if self.arrays.contains(&target.name)
+            && let Some(select) = selects.next()
+        {
+            target.index = Some(self.convert_expression(&select.expression));
+        }
+        if let Some(select) = selects.next() {
+            target.select = Some(self.convert_select(select, width));
+        }
+        target
+    }
+
+    fn convert_hierarchical_target(&self, h: &syntax_tree::HierarchicalIdentifier) -> Target {
+        let name = h.identifier.identifier_token.to_string();
+        let selects: Vec<_> = h
+            .hierarchical_identifier_list
+            .iter()
+            .map(|x| x.select.as_ref())
+            .collect();
+        self.convert_target(name, &selects)
+    }
+
+    // 外部関数の呼び出しを変換する
+    // 関数名は f(a) では "f"、pkg::f(a) と $sv::pkg::f(a) では "pkg::f" とする
+    // 名前付きの引数は書かれた順に渡す
+    fn convert_call(
+        &self,
+        identifier: &syntax_tree::ExpressionIdentifier,
+        call: &syntax_tree::FunctionCall,
+    ) -> Option<Expr> {
+        let name = scoped_name(&identifier.scoped_identifier)?;
+        let mut args = Vec::new();
+        if let Some(x) = &call.function_call_opt {
+            let list = &x.argument_list;
+            let items = std::iter::once(&list.argument_item)
+                .chain(list.argument_list_list.iter().map(|x| &x.argument_item));
+            for item in items {
+                let expression = match &item.argument_item_opt {
+                    Some(x) => &x.expression,
+                    None => &item.argument_expression.expression,
+                };
+                args.push(self.convert_expression(expression));
+            }
+        }
+        Some(Expr::Call(name, args))
+    }
+
+    // 振る舞いモデルで置き換えるインスタンスのポート接続を変換する
+    // $sv:: のモジュールと proto モジュールのインスタンスのみ対象とし、それ以外は None
+    fn convert_instance(&self, arg: &syntax_tree::ComponentInstantiation) -> Option<InstanceDecl> {
+        if arg.component_instantiation_opt0.is_some() {
+            return None;
+        }
+        let scoped = &arg.scoped_identifier;
+        let module = scoped_name(scoped)?;
+
+        // proto モジュールはポートの方向を参照する
+        let directions: Option<HashMap<String, bool>> = if matches!(
+            &*scoped.scoped_identifier_group,
+            syntax_tree::ScopedIdentifierGroup::DollarIdentifier(_)
+        ) {
+            None
+        } else {
+            let symbol = symbol_table::resolve(scoped.as_ref()).ok()?.found;
+            let proto = match &symbol.kind {
+                SymbolKind::ProtoModule(_) => symbol,
+                SymbolKind::GenericParameter(_) => symbol.proto()?,
+                _ => return None,
+            };
+            let SymbolKind::ProtoModule(x) = &proto.kind else {
+                return None;
+            };
+            let directions = x
+                .ports
+                .iter()
+                .filter_map(|port| match &symbol_table::get(port.symbol)?.kind {
+                    SymbolKind::Port(p) => Some((
+                        port.token.to_string(),
+                        matches!(p.direction, veryl_analyzer::symbol::Direction::Output),
+                    )),
+                    _ => None,
+                })
+                .collect();
+            Some(directions)
+        };
+
+        let mut items = Vec::new();
+        if let Some(x) = &arg.component_instantiation_opt2
+            && let Some(x) = &x.inst_port.inst_port_opt
+        {
+            collect_inst_ports(&x.inst_port_list, &mut items);
+        }
+        let ports = items
+            .into_iter()
+            .map(|item| {
+                let name = item.identifier.identifier_token.to_string();
+                // 式を省略した場合は同じ名前の信号を接続する
+                let expression = match &item.inst_port_item_opt {
+                    Some(x) => self.convert_expression(&x.expression),
+                    None => Expr::Var(name.clone()),
+                };
+                let output = directions
+                    .as_ref()
+                    .map(|x| x.get(&name).copied().unwrap_or(false));
+                (name, expression, output)
+            })
+            .collect();
+
+        Some(InstanceDecl {
+            name: arg.identifier.identifier_token.to_string(),
+            module,
+            ports,
+        })
+    }
+
+    // $bits(x) / $size(x) の引数となる信号のビット幅を求める
+    fn width_query(&self, factor: &syntax_tree::IdentifierFactor) -> Option<usize> {
+        let opt = factor.identifier_factor_opt.as_ref()?;
+        let syntax_tree::IdentifierFactorOptGroup::FunctionCall(call) =
+            &*opt.identifier_factor_opt_group
+        else {
+            return None;
+        };
+        let args = call.function_call.function_call_opt.as_ref()?;
+        let expr = &args
+            .argument_list
+            .argument_item
+            .argument_expression
+            .expression;
+        match self.convert_expression(expr) {
+            Expr::Var(name) | Expr::Index(name, _) => self.widths.get(&name).copied(),
+            Expr::Select(_, msb, lsb) if msb.is_const() && lsb.is_const() => {
+                let env = HashMap::new();
+                Some(msb.eval(&env).saturating_sub(lsb.eval(&env)) + 1)
+            }
+            _ => None,
+        }
+    }
+
+    fn convert_factor(&self, factor: &syntax_tree::Factor) -> Expr {
         match factor {
             syntax_tree::Factor::IdentifierFactor(f) => {
                 // 識別子の処理
                 // ScopedIdentifierはenumなので、パターンマッチング
-                match &*f
-                    .identifier_factor
-                    .expression_identifier
+                let expression_identifier = &f.identifier_factor.expression_identifier;
+                match &*expression_identifier
                     .scoped_identifier
                     .scoped_identifier_group
                 {
                     syntax_tree::ScopedIdentifierGroup::IdentifierScopedIdentifierOpt(id_group) => {
+                        // 関数呼び出しは外部関数の呼び出し、構造体は今のところ信号の参照として扱う
+                        if let Some(x) = &f.identifier_factor.identifier_factor_opt {
+                            match &*x.identifier_factor_opt_group {
+                                syntax_tree::IdentifierFactorOptGroup::FunctionCall(call) => {
+                                    if let Some(x) = self
+                                        .convert_call(expression_identifier, &call.function_call)
+                                    {
+                                        return x;
+                                    }
+                                }
+                                syntax_tree::IdentifierFactorOptGroup::StructConstructor(_) => {
+                                    self.warn(
+                                        "struct constructor",
+                                        &id_group.identifier.identifier_token.token,
+                                    );
+                                }
+                            }
+                        }
                         let id = id_group.identifier.identifier_token.to_string();
-                        Expr::Var(id)
+                        self.references
+                            .borrow_mut()
+                            .entry(id.clone())
+                            .or_default()
+                            .push((&id_group.identifier.identifier_token.token).into());
+                        let selects: Vec<_> = expression_identifier
+                            .expression_identifier_list
+                            .iter()
+                            .map(|x| x.select.as_ref())
+                            .collect();
+                        self.convert_selects(id, &selects)
+                    }
+                    syntax_tree::ScopedIdentifierGroup::DollarIdentifier(dollar) => {
+                        // $bits / $size によるビット幅の問い合わせ
+                        let name = dollar.dollar_identifier.dollar_identifier_token.to_string();
+                        match name.as_str() {
+                            "$bits" | "$size" => self
+                                .width_query(&f.identifier_factor)
+                                .map(Expr::Const)
+                                .unwrap_or(Expr::Const(0)),
+                            // $sv::pkg::f(a, b) は外部関数の呼び出し
+                            "$sv" if f.identifier_factor.identifier_factor_opt.is_some() => {
+                                let call =
+                                    f.identifier_factor.identifier_factor_opt.as_ref().and_then(
+                                        |x| match &*x.identifier_factor_opt_group {
+                                            syntax_tree::IdentifierFactorOptGroup::FunctionCall(
+                                                x,
+                                            ) => self.convert_call(
+                                                expression_identifier,
+                                                &x.function_call,
+                                            ),
+                                            _ => None,
+                                        },
+                                    );
+                                call.unwrap_or(Expr::Const(0))
+                            }
+                            // その他のシステム関数は今のところ0として扱う
+                            _ => {
+                                let token = &dollar.dollar_identifier.dollar_identifier_token;
+                                self.warn(&format!("system function `{token}`"), &token.token);
+                                Expr::Const(0)
+                            }
+                        }
+                    }
+                }
+            }
+            syntax_tree::Factor::FactorGroup(group) => {
+                // msb / lsb はビット選択の中でのみ意味を持つ
+                match &*group.factor_group {
+                    syntax_tree::FactorGroup::Msb(_) => {
+                        Expr::Const(self.select_msb.get().unwrap_or(0))
                     }
-                    _ => Expr::Const(0), // その他の形式は今のところ0として扱う
+                    syntax_tree::FactorGroup::Lsb(_) => Expr::Const(0),
                 }
             }
             syntax_tree::Factor::Number(n) => {
@@ -281,26 +1848,9 @@ impl AssignCollector {
                     syntax_tree::Number::IntegralNumber(integral) => {
                         match &*integral.integral_number {
                             syntax_tree::IntegralNumber::Based(based) => {
-                                // 基数指定の数値（例：32'h10）
+                                // 基数指定の数値（例：32'h10）、x / z の桁は 0 として扱う
                                 let s = based.based.based_token.to_string();
-                                // 基数指定のフォーマット（例：32'h10）をパース
-                                if let Some(pos) = s.rfind('\'') {
-                                    let num_part = &s[pos + 2..]; // 'h' や 'b' の後の部分
-                                    let base = match s.chars().nth(pos + 1) {
-                                        Some('h') | Some('H') => 16,
-                                        Some('d') | Some('D') => 10,
-                                        Some('b') | Some('B') => 2,
-                                        Some('o') | Some('O') => 8,
-                                        _ => 10,
-                                    };
-                                    if let Ok(val) = usize::from_str_radix(num_part, base) {
-                                        Expr::Const(val)
-                                    } else {
-                                        Expr::Const(0)
-                                    }
-                                } else {
-                                    Expr::Const(0)
-                                }
+                                Expr::Const(parse_based(&s).map(|x| x.0).unwrap_or(0))
                             }
                             syntax_tree::IntegralNumber::BaseLess(baseless) => {
                                 // 単純な10進数
@@ -311,13 +1861,34 @@ impl AssignCollector {
                                     Expr::Const(0)
                                 }
                             }
-                            _ => Expr::Const(0), // その他の形式は今のところ0として扱う
+                            // その他の形式は今のところ0として扱う
+                            _ => {
+                                self.warn("all-bit literal", &TokenRange::from(factor).beg);
+                                Expr::Const(0)
+                            }
                         }
                     }
-                    _ => Expr::Const(0), // RealNumberなどは今のところ0として扱う
+                    // RealNumberなどは今のところ0として扱う
+                    _ => {
+                        self.warn("real number", &TokenRange::from(factor).beg);
+                        Expr::Const(0)
+                    }
                 }
             }
-            _ => Expr::Const(0), // その他のFactorは今のところ0として扱う
+            // inside x {a, b..=c} はラベルのいずれかに一致するか、outside はその否定
+            syntax_tree::Factor::InsideExpression(x) => {
+                let x = &x.inside_expression;
+                self.convert_inside(&x.expression, &x.range_list)
+            }
+            syntax_tree::Factor::OutsideExpression(x) => {
+                let x = &x.outside_expression;
+                Expr::Not(Box::new(self.convert_inside(&x.expression, &x.range_list)))
+            }
+            // その他のFactorは今のところ0として扱う
+            _ => {
+                self.warn(factor_name(factor), &TokenRange::from(factor).beg);
+                Expr::Const(0)
+            }
         }
     }
 }
@@ -333,89 +1904,230 @@ impl VerylGrammarTrait for AssignCollector {
         &mut self,
         arg: &syntax_tree::AssignDeclaration,
     ) -> Result<(), ParolError> {
+        // ハンドラは前後 2 回呼ばれるので Before の場合のみ収集する
+        if !matches!(self.handler_point, HandlerPoint::Before) {
+            return Ok(());
+        }
+
         // 代入先の取得
-        let target = match &*arg.assign_destination {
-            syntax_tree::AssignDestination::HierarchicalIdentifier(h) => h
-                .hierarchical_identifier
-                .identifier
-                .identifier_token
-                .to_string(),
-            _ => return Ok(()), // 他の形式は今のところ無視
+        let targets = match &*arg.assign_destination {
+            syntax_tree::AssignDestination::HierarchicalIdentifier(h) => {
+                vec![self.convert_hierarchical_target(&h.hierarchical_identifier)]
+            }
+            syntax_tree::AssignDestination::LBraceAssignConcatenationListRBrace(x) => {
+                // 連接 {a, b} の場合は MSB 側から順に並べる
+                let list = &x.assign_concatenation_list;
+                let mut targets = vec![self.convert_hierarchical_target(
+                    &list.assign_concatenation_item.hierarchical_identifier,
+                )];
+                for item in &list.assign_concatenation_list_list {
+                    targets.push(self.convert_hierarchical_target(
+                        &item.assign_concatenation_item.hierarchical_identifier,
+                    ));
+                }
+                targets
+            }
         };
 
         // 式の変換
         let expression = self.convert_expression(&arg.expression);
 
         // 代入式を追加
-        self.assignments.push(Assignment { target, expression });
+        let cover = self.cover(
+            CoverageKind::Statement,
+            &arg.assign.assign_token.token,
+            false,
+        );
+        self.combinational.push(vec![
+            cover,
+            Statement::Assign(Assignment {
+                targets,
+                expression,
+            }),
+        ]);
 
         Ok(())
     }
 
-    fn always_ff_declaration(
+    fn always_comb_declaration(
         &mut self,
-        _arg: &syntax_tree::AlwaysFfDeclaration,
+        arg: &syntax_tree::AlwaysCombDeclaration,
     ) -> Result<(), ParolError> {
-        // HandlerPoint::Beforeの場合はalways_ffブロックの開始
+        // always_comb 内の文は組み合わせ回路として扱う
+        // Veryl には always_latch が無く、一部の分岐で代入されない信号（ラッチ）は
+        // アナライザがエラーとして報告するが、シミュレータでは代入されなかった信号は
+        // 値を保持するレベルセンシティブな記憶素子として評価する
         if matches!(self.handler_point, HandlerPoint::Before) {
-            self.in_always_ff = true;
-            self.current_sequential = Some(SequentialBlock {
-                reset_assignments: Vec::new(),
-                clock_assignments: Vec::new(),
-            });
-        }
-        // HandlerPoint::Afterの場合はalways_ffブロックの終了
-        else if matches!(self.handler_point, HandlerPoint::After) {
-            self.in_always_ff = false;
-            if let Some(block) = self.current_sequential.take() {
-                self.sequential_blocks.push(block);
-            }
+            let statements = self.convert_statement_block(&arg.statement_block);
+            self.combinational.push(statements);
         }
         Ok(())
     }
 
-    fn if_reset_statement(
+    fn always_ff_declaration(
         &mut self,
-        arg: &syntax_tree::IfResetStatement,
+        arg: &syntax_tree::AlwaysFfDeclaration,
     ) -> Result<(), ParolError> {
-        if !self.in_always_ff {
+        if !matches!(self.handler_point, HandlerPoint::Before) {
             return Ok(());
         }
 
-        // HandlerPoint::Beforeの場合は直接if_resetの内容を処理
-        if matches!(self.handler_point, HandlerPoint::Before) {
-            // if_resetブロックの処理（リセット時の代入）
-            for statement in &arg.if_reset_statement_list {
-                if let Some(assignment) = self.extract_assignment_from_statement(statement) {
-                    if let Some(ref mut block) = self.current_sequential {
-                        block.reset_assignments.push(assignment);
-                    }
+        // イベントリストでクロック・リセットが指定されていなければ既定のものを使う
+        let event_list = arg
+            .always_ff_declaration_opt
+            .as_ref()
+            .map(|x| &x.always_ff_event_list);
+        let clock_signal = event_list
+            .map(|x| {
+                x.always_ff_clock
+                    .hierarchical_identifier
+                    .identifier
+                    .identifier_token
+                    .to_string()
+            })
+            .or_else(|| self.default_clock.clone());
+        let reset_signal = event_list
+            .and_then(|x| x.always_ff_event_list_opt.as_ref())
+            .map(|x| {
+                x.always_ff_reset
+                    .hierarchical_identifier
+                    .identifier
+                    .identifier_token
+                    .to_string()
+            })
+            .or_else(|| self.default_reset.clone());
+
+        let mut block = SequentialBlock {
+            reset: Vec::new(),
+            clock: Vec::new(),
+            clock_signal,
+            reset_signal: None,
+        };
+        for x in &arg.statement_block.statement_block_list {
+            // if_reset の本体はリセット時、それ以外の分岐はクロック時の文として扱う
+            if let syntax_tree::StatementBlockGroupGroup::StatementBlockItem(item) =
+                &*x.statement_block_group.statement_block_group_group
+                && let syntax_tree::StatementBlockItem::Statement(stmt) =
+                    &*item.statement_block_item
+                && let syntax_tree::Statement::IfResetStatement(x) = &*stmt.statement
+            {
+                let x = &x.if_reset_statement;
+                let token = &x.if_reset.if_reset_token.token;
+                block.reset_signal.clone_from(&reset_signal);
+                block
+                    .reset
+                    .push(self.cover(CoverageKind::IfReset, token, false));
+                block
+                    .reset
+                    .extend(self.convert_statement_block(&x.statement_block));
+
+                let otherwise = match &x.if_reset_statement_opt {
+                    Some(x) => self.cover_branch(
+                        CoverageKind::Else,
+                        &x.r#else.else_token.token,
+                        self.convert_statement_block(&x.statement_block),
+                    ),
+                    None => vec![self.cover(CoverageKind::Else, token, true)],
+                };
+                if x.if_reset_statement_list.is_empty() {
+                    block.clock.extend(otherwise);
+                } else {
+                    let branches = x
+                        .if_reset_statement_list
+                        .iter()
+                        .map(|x| {
+                            (
+                                self.convert_expression(&x.expression),
+                                self.cover_branch(
+                                    CoverageKind::ElseIf,
+                                    &x.r#else.else_token.token,
+                                    self.convert_statement_block(&x.statement_block),
+                                ),
+                            )
+                        })
+                        .collect();
+                    block.clock.push(Statement::If(branches, otherwise));
                 }
+            } else {
+                self.convert_statement_block_group(&x.statement_block_group, &mut block.clock);
             }
+        }
+        self.sequential_blocks.push(block);
+        Ok(())
+    }
 
-            // else節の処理（クロック時の代入）
-            if let Some(ref else_clause) = arg.if_reset_statement_opt {
-                for statement_block_item in &else_clause.statement_block.statement_block_list {
-                    if let Some(assignment) =
-                        self.extract_assignment_from_statement_block(statement_block_item)
-                    {
-                        if let Some(ref mut block) = self.current_sequential {
-                            block.clock_assignments.push(assignment);
-                        }
-                    }
+    fn let_declaration(&mut self, arg: &syntax_tree::LetDeclaration) -> Result<(), ParolError> {
+        if matches!(self.handler_point, HandlerPoint::Before) {
+            self.warn("let", &arg.r#let.let_token.token);
+        }
+        Ok(())
+    }
+
+    fn const_declaration(&mut self, arg: &syntax_tree::ConstDeclaration) -> Result<(), ParolError> {
+        if matches!(self.handler_point, HandlerPoint::Before) {
+            self.warn("const", &arg.r#const.const_token.token);
+        }
+        Ok(())
+    }
+
+    fn function_declaration(
+        &mut self,
+        arg: &syntax_tree::FunctionDeclaration,
+    ) -> Result<(), ParolError> {
+        if matches!(self.handler_point, HandlerPoint::Before) {
+            self.warn("function", &arg.function.function_token.token);
+        }
+        Ok(())
+    }
+
+    fn initial_declaration(
+        &mut self,
+        arg: &syntax_tree::InitialDeclaration,
+    ) -> Result<(), ParolError> {
+        if matches!(self.handler_point, HandlerPoint::Before) {
+            self.warn("initial", &arg.initial.initial_token.token);
+        }
+        Ok(())
+    }
+
+    fn final_declaration(&mut self, arg: &syntax_tree::FinalDeclaration) -> Result<(), ParolError> {
+        if matches!(self.handler_point, HandlerPoint::Before) {
+            self.warn("final", &arg.r#final.final_token.token);
+        }
+        Ok(())
+    }
+
+    fn inst_declaration(&mut self, arg: &syntax_tree::InstDeclaration) -> Result<(), ParolError> {
+        // 階層構造は今のところモデル化できない
+        // 定義のないモジュールのインスタンスは振る舞いモデルで置き換える
+        if matches!(self.handler_point, HandlerPoint::Before) {
+            match self.convert_instance(&arg.component_instantiation) {
+                Some(x) => self.instances.push(x),
+                None => {
+                    self.record_unsupported::<()>("inst", &arg.inst.inst_token.token);
                 }
             }
         }
+        Ok(())
+    }
 
+    fn generate_if_declaration(
+        &mut self,
+        arg: &syntax_tree::GenerateIfDeclaration,
+    ) -> Result<(), ParolError> {
+        if matches!(self.handler_point, HandlerPoint::Before) {
+            self.record_unsupported::<()>("if (generate)", &arg.r#if.if_token.token);
+        }
         Ok(())
     }
 
-    fn identifier_statement(
+    fn generate_for_declaration(
         &mut self,
-        _arg: &syntax_tree::IdentifierStatement,
+        arg: &syntax_tree::GenerateForDeclaration,
     ) -> Result<(), ParolError> {
-        // if_reset_statementで直接処理するので、ここでは何もしない
-        // これにより重複を避ける
+        if matches!(self.handler_point, HandlerPoint::Before) {
+            self.record_unsupported::<()>("for (generate)", &arg.r#for.for_token.token);
+        }
         Ok(())
     }
 }
@@ -426,201 +2138,1547 @@ impl Handler for AssignCollector {
     }
 }
 
+// export_json で出力するエラボレーション結果
+#[derive(Serialize)]
+struct ModelExport<'a> {
+    module: &'a str,
+    clocks: &'a [(String, ClockType)],
+    resets: &'a [(String, ResetType)],
+    signals: Vec<SignalExport>,
+    combinational: Vec<Statement>,
+    sequential: Vec<SequentialBlock>,
+}
+
+#[derive(Serialize)]
+struct SignalExport {
+    name: String,
+    direction: Direction,
+    width: usize,
+}
+
 // Model は module のシミュレーションモデルを表します
+// エラボレーション結果は全て Model が保持し、構築後は symbol_table を参照しない
 pub struct Model {
     // モジュール名
-    _module_name: String,
+    module_name: String,
+
+    // クロックとその有効エッジ
+    clocks: Vec<(String, ClockType)>,
 
-    // クロック
-    _clocks: Vec<String>,
+    // リセットとその極性・同期/非同期
+    resets: Vec<(String, ResetType)>,
 
-    // リセット
-    _resets: Vec<String>,
+    // 全信号（入力、出力、内部信号）の状態ベクタ上の位置と方向
+    layout: Layout,
 
-    // 入力ポート
-    inputs: HashMap<String, usize>,
+    // 全信号の値（layout の位置に格納する）
+    values: Vec<usize>,
 
-    // 出力ポート
-    outputs: HashMap<String, usize>,
+    // 各信号が最後に変化した時刻と、変化の確認に使う前回の値（時刻は Simulator が与える）
+    last_changes: Vec<Option<u64>>,
+    changed_values: Vec<usize>,
 
-    // 内部信号
-    internals: HashMap<String, usize>,
+    // 信号名とビット幅（配列の場合は要素のビット幅）
+    widths: HashMap<String, usize>,
 
-    // 組み合わせ回路の式（assign文など）
-    combinational: Vec<Assignment>,
+    // 組み合わせ回路の文（assign文、always_combなど）
+    combinational: Vec<Statement>,
+
+    // 組み合わせ回路にループがなく、依存順に並べた 1 回の評価で値が確定するか
+    levelized: bool,
+
+    // 依存順に並べた文の組と、前回の組み合わせ回路の評価後の値
+    // 値が変化した信号を読み出す組だけを評価する
+    groups: Vec<CombGroup>,
+    settled: Vec<usize>,
+    evaluated_groups: u64,
 
     // 順序回路ブロック（always_ff）
     sequential: Vec<SequentialBlock>,
 
-    // リセット中かどうか
-    is_reset: bool,
+    // 組み合わせ回路と順序回路ブロックの (リセット時, クロック時) の文を変換したバイトコード
+    combinational_code: Vec<Code>,
+    sequential_code: Vec<(Vec<Code>, Vec<Code>)>,
+
+    // 文の評価方式と、バイトコードの評価に使うスタック
+    eval_mode: EvalMode,
+    stack: Vec<usize>,
+
+    // バイトコードから変換したネイティブコード（EvalMode::Jit の間だけ保持する）
+    #[cfg(feature = "jit")]
+    jit: Option<Jit>,
+
+    // 未知の信号への参照
+    unknown: UnknownTracker,
+
+    // case 文の到達不能・重なりのある分岐
+    case_overlaps: Vec<CaseOverlap>,
+
+    // unique / priority case の違反
+    case_violations: Vec<CaseViolation>,
+
+    // 実行された $warning / $error / $fatal
+    assertion_failures: Vec<AssertionFailure>,
+
+    // カバレッジの計測点とその実行回数
+    coverage_points: Vec<CoveragePoint>,
+    coverage_hits: Vec<u64>,
+
+    // 収束までの繰り返しを含む計測点の実行回数と、組み合わせ回路の評価の繰り返し回数
+    executions: Vec<u64>,
+    settle_iterations: u64,
+
+    // モデル化の際に無視・近似した構文
+    warnings: Vec<ModelWarning>,
+
+    // 対応しているが結果が近似となる構文
+    approximations: Vec<ModelWarning>,
+
+    // 評価中に検出した違反（評価が確定するまで保留する）
+    pending_violations: Vec<CaseViolation>,
+    pending_failures: Vec<AssertionFailure>,
+    pending_hits: Vec<usize>,
+
+    // force された信号
+    forces: HashMap<String, Force>,
+
+    // 設計から呼び出す外部関数
+    functions: Functions,
+
+    // 振る舞いモデルで置き換えるインスタンス
+    instances: Vec<Instance>,
+}
+
+// 組み合わせ回路の文の組（assign 宣言または always_comb ブロック）
+// 入力が変化しなければ前回と同じ結果になるので、評価を省いて前回の記録を確定する
+struct CombGroup {
+    range: Range<usize>, // combinational の範囲
+    inputs: Vec<usize>,  // 読み出しまたは代入する信号の位置
+    outputs: Vec<usize>, // 代入する信号の位置
+    hits: Vec<usize>,
+    failures: Vec<AssertionFailure>,
+    violations: Vec<CaseViolation>,
+    unknowns: Vec<String>,
+}
+
+// force された信号の値
+struct Force {
+    value: usize,
+    // 入力ポートの場合、force 中に input で設定された値（release 時に戻す）
+    driven: Option<usize>,
 }
 
 impl Model {
-    pub fn new(top: &str, init: HashMap<String, usize>) -> Self {
+    /// Create a model of the top module
+    /// `clock` and `reset` types are resolved as the default build settings
+    pub fn new(top: &str, init: HashMap<String, usize>) -> Result<Self, ModelError> {
+        Self::build(top, init, ClockType::default(), ResetType::default())
+    }
+
+    /// Create a model of the top module
+    /// `clock` and `reset` types are resolved as `clock_type` and `reset_type` of the build settings
+    pub fn with_build(
+        top: &str,
+        init: HashMap<String, usize>,
+        build: &Build,
+    ) -> Result<Self, ModelError> {
+        Self::build(top, init, build.clock_type, build.reset_type)
+    }
+
+    /// Create a model of the top module from Veryl source files
+    /// parsing and analysis are done in an isolated context,
+    /// so the symbol table of the calling thread is not touched
+    pub fn from_files<T: AsRef<Path>>(
+        paths: &[T],
+        top: &str,
+        init: HashMap<String, usize>,
+    ) -> Result<Self, ModelError> {
+        let paths: Vec<PathBuf> = paths.iter().map(|x| x.as_ref().to_path_buf()).collect();
+        elaborate::isolated(|| {
+            let metadata = elaborate::analyze_files(&paths)?;
+            Self::with_build(top, init, &metadata.build)
+        })
+    }
+
+    /// Create a model of the top module from Veryl source text
+    /// this is the same as `from_files`, and doesn't need a file system, e.g. on WebAssembly
+    pub fn from_source(
+        source: &str,
+        top: &str,
+        init: HashMap<String, usize>,
+    ) -> Result<Self, ModelError> {
+        let sources = [(PathBuf::from(SOURCE_NAME), source.to_string())];
+        elaborate::isolated(|| {
+            let (metadata, _) = elaborate::parse_sources(&sources)?;
+            Self::with_build(top, init, &metadata.build)
+        })
+    }
+
+    fn build(
+        top: &str,
+        init: HashMap<String, usize>,
+        clock_type: ClockType,
+        reset_type: ResetType,
+    ) -> Result<Self, ModelError> {
         // シミュレーションに必要な情報をsymbol_tableから収集する
         let mut inputs = HashMap::new();
         let mut outputs = HashMap::new();
-        let internals = HashMap::new();
-        let mut combinational = Vec::new();
-        let mut sequential = Vec::new();
+        let mut internals = HashMap::new();
+        let mut widths = HashMap::new();
+        let mut arrays = HashSet::new();
         let mut clocks = Vec::new();
         let mut resets = Vec::new();
 
         // symbol_tableからモジュールを検索
-        for symbol in symbol_table::get_all() {
-            if let SymbolKind::Module(m) = &symbol.kind {
-                if symbol.token.to_string() == top {
-                    // ポート情報を取得
-                    for port in &m.ports {
-                        if let Some(port_symbol) = symbol_table::get(port.symbol) {
-                            if let SymbolKind::Port(p) = &port_symbol.kind {
-                                let port_name = port_symbol.token.to_string();
-
-                                // 入力/出力ポートを分類
-                                match p.direction {
-                                    veryl_analyzer::symbol::Direction::Input => {
-                                        // 初期値がinitで指定されていればそれを使用
-                                        let initial_value =
-                                            init.get(&port_name).copied().unwrap_or(0);
-                                        inputs.insert(port_name.clone(), initial_value);
-
-                                        // クロック、リセット信号を識別
-                                        // TypeのDebug出力を使用して判定
-                                        let type_str = format!("{:?}", p.r#type);
-                                        if type_str.contains("Clock") {
-                                            clocks.push(port_name);
-                                        } else if type_str.contains("Reset") {
-                                            resets.push(port_name);
-                                        }
-                                    }
-                                    veryl_analyzer::symbol::Direction::Output => {
-                                        outputs.insert(port_name, 0);
-                                    }
-                                    _ => {}
-                                }
-                            }
+        let (symbol, m) = symbol_table::get_all()
+            .into_iter()
+            .find_map(|symbol| match &symbol.kind {
+                SymbolKind::Module(m) if symbol.token.to_string() == top => {
+                    let m = m.clone();
+                    Some((symbol, m))
+                }
+                _ => None,
+            })
+            .ok_or_else(|| ModelError::TopNotFound(top.to_string()))?;
+
+        // ポート情報を取得
+        for port in &m.ports {
+            if let Some(port_symbol) = symbol_table::get(port.symbol)
+                && let SymbolKind::Port(p) = &port_symbol.kind
+            {
+                let port_name = port_symbol.token.to_string();
+                widths.insert(port_name.clone(), type_width(&p.r#type));
+
+                // 入力/出力ポートを分類
+                match p.direction {
+                    veryl_analyzer::symbol::Direction::Input => {
+                        // クロック、リセット信号を識別
+                        let mut initial_value = 0;
+                        if let Some(x) = resolve_clock_type(&p.r#type.kind, clock_type) {
+                            clocks.push((port_name.clone(), x));
+                        } else if let Some(x) = resolve_reset_type(&p.r#type.kind, reset_type) {
+                            // リセットは非アクティブの状態から始める
+                            initial_value = reset_level(x, false);
+                            resets.push((port_name.clone(), x));
                         }
-                    }
-
-                    // definition_tableからモジュールの定義を取得してASTを解析
-                    if let Some(definition) = definition_table::get(m.definition) {
-                        if let veryl_analyzer::definition_table::Definition::Module(module_decl) =
-                            definition
-                        {
-                            // AssignCollectorを使ってassign文とalways_ffブロックを収集
-                            let mut collector = AssignCollector::new();
-
-                            // モジュール全体をトラバースする
-                            VerylWalker::module_declaration(&mut collector, &module_decl);
 
-                            // 収集した代入式を追加
-                            combinational = collector.assignments;
-                            sequential = collector.sequential_blocks;
-                        }
+                        // 初期値がinitで指定されていればそれを使用
+                        let initial_value = init.get(&port_name).copied().unwrap_or(initial_value);
+                        inputs.insert(port_name, initial_value);
+                    }
+                    veryl_analyzer::symbol::Direction::Output => {
+                        outputs.insert(port_name, 0);
                     }
+                    _ => {}
                 }
             }
         }
 
-        let mut model = Self {
-            _module_name: top.to_string(),
-            inputs,
-            outputs,
-            internals,
-            combinational,
-            sequential,
-            _clocks: clocks,
-            _resets: resets,
-            is_reset: false,
-        };
+        // モジュール直下の変数を内部信号として登録
+        let namespace = symbol.inner_namespace();
+        for var_symbol in symbol_table::get_all() {
+            if let SymbolKind::Variable(v) = &var_symbol.kind
+                && var_symbol.namespace.matched(&namespace)
+            {
+                let var_name = var_symbol.token.to_string();
+                widths.insert(var_name.clone(), type_width(&v.r#type));
+                if let Some(x) = resolve_clock_type(&v.r#type.kind, clock_type) {
+                    clocks.push((var_name.clone(), x));
+                } else if let Some(x) = resolve_reset_type(&v.r#type.kind, reset_type) {
+                    resets.push((var_name.clone(), x));
+                }
+                // 配列は要素ごとに内部信号として登録
+                if let Some(size) = array_size(&v.r#type) {
+                    for i in 0..size {
+                        internals.insert(element_name(&var_name, i), 0);
+                    }
+                    arrays.insert(var_name);
+                } else {
+                    internals.insert(var_name, 0);
+                }
+            }
+        }
+
+        // definition_tableからモジュールの定義を取得してASTを解析
+        let Some(veryl_analyzer::definition_table::Definition::Module(module_decl)) =
+            definition_table::get(m.definition)
+        else {
+            return Err(ModelError::DefinitionNotFound(top.to_string()));
+        };
+
+        // AssignCollectorを使ってassign文とalways_ffブロックを収集
+        let default_clock = clocks.first().map(|(x, _)| x.clone());
+        let default_reset = resets.first().map(|(x, _)| x.clone());
+        let mut collector =
+            AssignCollector::new(widths.clone(), arrays, default_clock, default_reset);
+
+        // モジュール全体をトラバースする
+        VerylWalker::module_declaration(&mut collector, &module_decl);
+
+        // モデル化できない構文があればエラーとする
+        if let Some((construct, span)) = collector.unsupported.into_inner().into_iter().next() {
+            return Err(ModelError::UnsupportedConstruct { construct, span });
+        }
+        if !collector.sequential_blocks.is_empty() && clocks.is_empty() {
+            return Err(ModelError::NoClockFound(top.to_string()));
+        }
+
+        // インスタンスのポートを入力と出力に分け、組み合わせ回路の組として追加する
+        // 方向の分からないポートは、他に代入されない信号を接続していれば出力とする
+        let mut assigned = HashSet::new();
+        for statements in &collector.combinational {
+            collect_assigned(statements, &mut assigned);
+        }
+        for block in &collector.sequential_blocks {
+            collect_assigned(&block.reset, &mut assigned);
+            collect_assigned(&block.clock, &mut assigned);
+        }
+        let mut instances = Vec::new();
+        for decl in std::mem::take(&mut collector.instances) {
+            let mut statement = InstanceStatement {
+                index: instances.len(),
+                inputs: Vec::new(),
+                outputs: Vec::new(),
+            };
+            let mut input_ports = Vec::new();
+            let mut output_ports = Vec::new();
+            let mut instance_clocks = Vec::new();
+            for (port, expression, output) in decl.ports {
+                let signal = match &expression {
+                    Expr::Var(name)
+                        if widths.contains_key(name)
+                            && !inputs.contains_key(name)
+                            && !collector.arrays.contains(name) =>
+                    {
+                        Some(name.clone())
+                    }
+                    _ => None,
+                };
+                let output = output
+                    .unwrap_or_else(|| signal.as_ref().is_some_and(|x| !assigned.contains(x)));
+                match (output, signal) {
+                    (true, Some(name)) => {
+                        assigned.insert(name.clone());
+                        statement
+                            .outputs
+                            .push(Target::new(name.clone(), widths[&name]));
+                        output_ports.push(port);
+                    }
+                    // 信号以外に接続した出力ポートは使わない
+                    (true, None) => {}
+                    (false, _) => {
+                        if let Expr::Var(name) = &expression
+                            && clocks.iter().any(|(x, _)| x == name)
+                        {
+                            instance_clocks.push(name.clone());
+                        }
+                        statement.inputs.push(expression);
+                        input_ports.push(port);
+                    }
+                }
+            }
+            collector
+                .combinational
+                .push(vec![Statement::Instance(statement)]);
+            instances.push(Instance::new(
+                decl.name,
+                decl.module,
+                input_ports,
+                output_ports,
+                instance_clocks,
+            ));
+        }
+
+        // 収集した代入式を追加
+        // 組み合わせ回路は依存順に並べる
+        let Levelized {
+            statements: combinational,
+            groups,
+            acyclic: levelized,
+        } = levelize(collector.combinational);
+        let sequential = collector.sequential_blocks;
+        let references = collector.references.into_inner();
+        let case_overlaps = collector.case_overlaps.into_inner();
+        let warnings = collector.warnings.into_inner();
+        let approximations = collector.approximations.into_inner();
+        let coverage_points = collector.coverage.into_inner();
+
+        // 全信号を状態ベクタに配置し、文をバイトコードに変換する
+        let signals = [
+            (inputs, Direction::Input),
+            (outputs, Direction::Output),
+            (internals, Direction::Internal),
+        ]
+        .into_iter()
+        .flat_map(|(signals, direction)| {
+            signals
+                .into_iter()
+                .map(move |(name, value)| (name, direction, value))
+        })
+        .collect();
+        let (layout, values) = Layout::new(signals);
+        let slots = |names: &BTreeSet<String>| -> Vec<usize> {
+            let mut slots: Vec<_> = names.iter().flat_map(|x| layout.slots(x)).collect();
+            slots.sort_unstable();
+            slots.dedup();
+            slots
+        };
+        let groups = groups
+            .into_iter()
+            .map(|x| {
+                let outputs = slots(&x.writes);
+                let inputs = slots(&x.reads.union(&x.writes).cloned().collect());
+                CombGroup {
+                    range: x.range,
+                    inputs,
+                    outputs,
+                    hits: Vec::new(),
+                    failures: Vec::new(),
+                    violations: Vec::new(),
+                    unknowns: Vec::new(),
+                }
+            })
+            .collect();
+        let combinational_code = bytecode::compile(&combinational, &layout);
+        let sequential_code = sequential
+            .iter()
+            .map(|x| {
+                (
+                    bytecode::compile(&x.reset, &layout),
+                    bytecode::compile(&x.clock, &layout),
+                )
+            })
+            .collect();
+
+        let mut model = Self {
+            module_name: top.to_string(),
+            layout,
+            values,
+            last_changes: Vec::new(),
+            changed_values: Vec::new(),
+            widths,
+            combinational,
+            levelized,
+            groups,
+            settled: Vec::new(),
+            evaluated_groups: 0,
+            sequential,
+            combinational_code,
+            sequential_code,
+            eval_mode: EvalMode::default(),
+            stack: Vec::new(),
+            #[cfg(feature = "jit")]
+            jit: None,
+            clocks,
+            resets,
+            unknown: UnknownTracker {
+                references,
+                ..Default::default()
+            },
+            case_overlaps,
+            case_violations: Vec::new(),
+            assertion_failures: Vec::new(),
+            coverage_hits: vec![0; coverage_points.len()],
+            executions: vec![0; coverage_points.len()],
+            settle_iterations: 0,
+            coverage_points,
+            warnings,
+            approximations,
+            pending_violations: Vec::new(),
+            pending_failures: Vec::new(),
+            pending_hits: Vec::new(),
+            forces: HashMap::new(),
+            functions: Functions::default(),
+            instances,
+        };
 
         // 初期評価（組み合わせ回路の評価）
         model.evaluate_combinational();
 
-        model
+        Ok(model)
     }
 
+    /// Set the input port, `port` can be prefixed by the module name,
+    /// or a glob pattern like "data_*" to set all the matched input ports
     pub fn input(&mut self, port: &str, value: usize) {
-        if self.inputs.contains_key(port) {
-            self.inputs.insert(port.to_string(), value);
+        if path::is_glob(port) {
+            for name in self.matched_inputs(port) {
+                self.input(&name, value);
+            }
+            return;
+        }
+        let port = path::strip_top(&self.module_name, port);
+        if let Some(force) = self.forces.get_mut(port) {
+            // force 中は値を保持しておき、release 時に反映する
+            force.driven = Some(value);
+            return;
+        }
+        if let Some(slot) = self.input_slot(port) {
+            self.values[slot] = value;
+            // 非同期リセットはクロックを待たずに反映する
+            if self.resets.iter().any(|(name, x)| {
+                name == port
+                    && matches!(x, ResetType::AsyncHigh | ResetType::AsyncLow)
+                    && reset_level(*x, true) == value
+            }) {
+                self.evaluate_sequential_async_reset(port);
+            }
             // 入力が変更されたら組み合わせ回路を再評価
             self.evaluate_combinational();
         }
     }
 
+    /// Same as `input`, but returns an error if the input port doesn't exist
+    pub fn try_input(&mut self, port: &str, value: usize) -> Result<(), ModelError> {
+        if path::is_glob(port) {
+            if self.matched_inputs(port).is_empty() {
+                return Err(self.path_not_found(port));
+            }
+        } else if self
+            .input_slot(path::strip_top(&self.module_name, port))
+            .is_none()
+        {
+            return Err(ModelError::UnknownPort(port.to_string()));
+        }
+        self.input(port, value);
+        // Error ポリシーでは未知の信号への参照を失敗として返す
+        self.unknown_failure().cloned().map_or(Ok(()), Err)
+    }
+
+    pub fn input_u64(&mut self, port: &str, value: u64) {
+        self.input(port, value as usize);
+    }
+
+    pub fn input_bool(&mut self, port: &str, value: bool) {
+        self.input(port, value as usize);
+    }
+
+    /// Set the input port with a bit vector, bits above 64 are ignored
+    pub fn input_bits(&mut self, port: &str, value: &BitVec) {
+        self.input(port, value.to_u64() as usize);
+    }
+
     pub fn get(&self, port: &str) -> Option<usize> {
-        self.outputs.get(port).copied()
+        self.layout
+            .slot(path::strip_top(&self.module_name, port))
+            .filter(|x| self.layout.direction(*x) == Direction::Output)
+            .map(|x| self.values[x])
     }
 
-    pub fn clock(&mut self) {
-        if !self.is_reset {
-            // リセット中でなければ、クロックエッジで順序回路を評価
-            self.evaluate_sequential_clock();
-            // 順序回路の出力が変わった可能性があるので組み合わせ回路も再評価
+    /// Read any signal including internal signals and registers
+    /// `path` is a signal name like "count" or "mem[2]", optionally prefixed by the module name
+    pub fn peek(&self, path: &str) -> Option<usize> {
+        let name = self.resolve_path(path)?;
+        self.layout.slot(name).map(|x| self.values[x])
+    }
+
+    /// Values of the signals matched by the path or glob pattern like "regs[*]"
+    pub fn peek_all(&self, pattern: &str) -> Vec<(String, usize)> {
+        self.select(pattern)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|x| self.peek(&x).map(|v| (x, v)))
+            .collect()
+    }
+
+    /// Names of the signals matched by the path or glob pattern in the order of the names
+    /// the error suggests a similar signal name if nothing matches
+    pub fn select(&self, pattern: &str) -> Result<Vec<String>, ModelError> {
+        let names: Vec<_> = if path::is_glob(pattern) {
+            self.layout
+                .iter()
+                .filter(|(_, name, _)| path::matches(&self.module_name, pattern, name))
+                .map(|(_, name, _)| name.to_string())
+                .collect()
+        } else {
+            self.resolve_path(pattern)
+                .filter(|x| self.layout.slot(x).is_some())
+                .map(|x| vec![x.to_string()])
+                .unwrap_or_default()
+        };
+        if names.is_empty() {
+            return Err(self.path_not_found(pattern));
+        }
+        Ok(names)
+    }
+
+    fn path_not_found(&self, pattern: &str) -> ModelError {
+        let names = self.layout.iter().map(|(_, name, _)| name);
+        ModelError::PathNotFound {
+            path: pattern.to_string(),
+            hint: path::hint(&self.module_name, pattern, names),
+        }
+    }
+
+    fn matched_inputs(&self, pattern: &str) -> Vec<String> {
+        self.layout
+            .iter()
+            .filter(|(_, name, direction)| {
+                *direction == Direction::Input && path::matches(&self.module_name, pattern, name)
+            })
+            .map(|(_, name, _)| name.to_string())
+            .collect()
+    }
+
+    /// Simulation time when the signal changed last
+    /// returns None if it hasn't changed since the reset or restore of the simulator
+    pub fn last_change(&self, path: &str) -> Option<u64> {
+        let name = self.resolve_path(path)?;
+        let slot = self.layout.slot(name)?;
+        self.last_changes.get(slot).copied().flatten()
+    }
+
+    // 前回から値が変化した信号の変化時刻を更新し、(位置, 前回の値, 現在の値) を changes に追加する
+    pub(crate) fn record_changes(&mut self, time: u64, changes: &mut Vec<(usize, usize, usize)>) {
+        if self.changed_values.len() != self.values.len() {
+            self.clear_changes();
+            return;
+        }
+        let values = self.values.iter().zip(&mut self.changed_values);
+        for (slot, ((value, last), change)) in values.zip(&mut self.last_changes).enumerate() {
+            if value != last {
+                changes.push((slot, *last, *value));
+                *last = *value;
+                *change = Some(time);
+            }
+        }
+    }
+
+    // 状態ベクタ上の位置にある信号の名前と方向
+    pub(crate) fn signal_at(&self, slot: usize) -> (&str, Direction) {
+        self.layout.signal(slot)
+    }
+
+    // 現在の値を基準に変化時刻の記録をやり直す
+    pub(crate) fn clear_changes(&mut self) {
+        self.changed_values = self.values.clone();
+        self.last_changes = vec![None; self.values.len()];
+    }
+
+    /// Write any signal including internal signals and registers, bypassing their drivers
+    /// combinational logic is re-evaluated, so signals driven by it are overwritten immediately
+    pub fn poke(&mut self, path: &str, value: usize) -> Result<(), ModelError> {
+        if path::is_glob(path) {
+            for name in self.select(path)? {
+                self.poke(&name, value)?;
+            }
+            return Ok(());
+        }
+        let name = self
+            .resolve_path(path)
+            .ok_or_else(|| ModelError::SignalNotFound(path.to_string()))?
+            .to_string();
+        let mask = bit_mask(self.width(&name));
+        let slot = self
+            .layout
+            .slot(&name)
+            .ok_or_else(|| ModelError::SignalNotFound(path.to_string()))?;
+        self.values[slot] = value & mask;
+        self.apply_forces();
+        self.evaluate_combinational();
+        Ok(())
+    }
+
+    /// Override the signal with `value` regardless of its drivers until `release` is called
+    /// the value is masked to the signal width
+    pub fn force(&mut self, path: &str, value: usize) -> Result<(), ModelError> {
+        if path::is_glob(path) {
+            for name in self.select(path)? {
+                self.force(&name, value)?;
+            }
+            return Ok(());
+        }
+        let name = self
+            .resolve_path(path)
+            .filter(|x| self.peek(x).is_some())
+            .ok_or_else(|| ModelError::SignalNotFound(path.to_string()))?
+            .to_string();
+        let value = value & bit_mask(self.width(&name));
+        let driven = match self.forces.remove(&name) {
+            Some(force) => force.driven,
+            None => self.input_slot(&name).map(|x| self.values[x]),
+        };
+        self.forces.insert(name, Force { value, driven });
+        self.apply_forces();
+        self.evaluate_combinational();
+        Ok(())
+    }
+
+    /// Stop overriding the signal
+    /// input ports return to the last value given by `input`, signals driven by combinational
+    /// logic are re-evaluated, and registers hold the forced value until the next assignment
+    pub fn release(&mut self, path: &str) -> Result<(), ModelError> {
+        if path::is_glob(path) {
+            for name in self.select(path)? {
+                self.release(&name)?;
+            }
+            return Ok(());
+        }
+        let name = self
+            .resolve_path(path)
+            .filter(|x| self.peek(x).is_some())
+            .ok_or_else(|| ModelError::SignalNotFound(path.to_string()))?;
+        if let Some(force) = self.forces.remove(name) {
+            if let Some(driven) = force.driven
+                && let Some(slot) = self.input_slot(name)
+            {
+                self.values[slot] = driven;
+            }
+            // 値が変化していなくてもドライバの値に戻すため、全ての組を評価する
+            self.settled.clear();
             self.evaluate_combinational();
         }
+        Ok(())
+    }
+
+    /// Whether the signal is forced
+    pub fn is_forced(&self, path: &str) -> bool {
+        self.resolve_path(path)
+            .is_some_and(|x| self.forces.contains_key(x))
+    }
+
+    /// Register a Rust function callable from the design as `name(args)` or `$sv::name(args)`
+    ///
+    /// A function declared in a package is called by `pkg::name`. The function is called
+    /// whenever the expression is evaluated, and calls to unregistered functions return 0 and
+    /// are reported by `unknown_signals`.
+    pub fn register_function(
+        &mut self,
+        name: &str,
+        function: impl FnMut(&[usize]) -> usize + Send + 'static,
+    ) {
+        self.functions
+            .0
+            .insert(name.to_string(), Mutex::new(Box::new(function)));
+        // 関数の結果を反映するため、全ての組を評価する
+        self.settled.clear();
+        self.evaluate_combinational();
+    }
+
+    /// Replace every instance of `module` with the behavioral model created by `new`
+    ///
+    /// `new` is called with the name of each instance. Only modules without a definition are
+    /// instantiable, and `$sv::pkg::M` is registered as `pkg::M`. The port directions of proto
+    /// modules follow the declaration, and ports of `$sv::` modules are outputs if the connected
+    /// signal isn't assigned in the design. Outputs of unregistered instances hold 0, and the
+    /// instances are reported by `unknown_signals`.
+    pub fn register_module<T: BehavioralModule + 'static>(
+        &mut self,
+        module: &str,
+        mut new: impl FnMut(&str) -> T,
+    ) -> Result<(), ModelError> {
+        let mut found = false;
+        for instance in self.instances.iter_mut().filter(|x| x.module == module) {
+            instance.model = Some(Box::new(new(&instance.name)));
+            found = true;
+        }
+        if !found {
+            return Err(ModelError::InstanceNotFound(module.to_string()));
+        }
+        // モデルの出力を反映するため、全ての組を評価する
+        self.settled.clear();
+        self.evaluate_combinational();
+        Ok(())
+    }
+
+    /// Snapshot of all signals including registers
+    pub fn snapshot(&self) -> ModelState {
+        let mut state = ModelState {
+            module: self.module_name.clone(),
+            inputs: BTreeMap::new(),
+            outputs: BTreeMap::new(),
+            internals: BTreeMap::new(),
+        };
+        for (slot, name, direction) in self.layout.iter() {
+            state
+                .by_direction_mut(direction)
+                .insert(name.to_string(), self.values[slot]);
+        }
+        state
+    }
+
+    /// Restore all signals from a snapshot
+    /// the state must be taken from a model of the same module, and forced signals keep the forced value
+    pub fn restore(&mut self, state: &ModelState) -> Result<(), ModelError> {
+        let matched = [Direction::Input, Direction::Output, Direction::Internal]
+            .into_iter()
+            .all(|direction| {
+                let signals = state.by_direction(direction);
+                let names: Vec<_> = self.layout.iter().filter(|x| x.2 == direction).collect();
+                names.len() == signals.len()
+                    && names.iter().all(|(_, name, _)| signals.contains_key(*name))
+            });
+        if state.module != self.module_name || !matched {
+            return Err(ModelError::StateMismatch {
+                model: self.module_name.clone(),
+                state: state.module.clone(),
+            });
+        }
+
+        for (slot, name, direction) in self.layout.iter() {
+            self.values[slot] = state.by_direction(direction)[name];
+        }
+        self.apply_forces();
+        self.evaluate_combinational();
+        Ok(())
+    }
+
+    // force された値で上書きする
+    fn apply_forces(&mut self) {
+        for (name, force) in &self.forces {
+            if let Some(slot) = self.layout.slot(name) {
+                self.values[slot] = force.value;
+            }
+        }
+    }
+
+    // 入力ポートの状態ベクタ上の位置
+    fn input_slot(&self, name: &str) -> Option<usize> {
+        self.layout
+            .slot(name)
+            .filter(|x| self.layout.direction(*x) == Direction::Input)
+    }
+
+    // 階層パスを信号名に変換する
+    // 階層は "u_core.a" のような信号名として展開されるので、先頭のモジュール名のみ取り除く
+    fn resolve_path<'a>(&self, path: &'a str) -> Option<&'a str> {
+        Some(path::strip_top(&self.module_name, path))
+    }
+
+    pub fn get_u64(&self, port: &str) -> Option<u64> {
+        self.get(port).map(|x| x as u64)
+    }
+
+    /// Whether the output port is non-zero
+    pub fn get_bool(&self, port: &str) -> Option<bool> {
+        self.get(port).map(|x| x != 0)
+    }
+
+    /// Value of the output port as a bit vector of the port width
+    pub fn get_bits(&self, port: &str) -> Option<BitVec> {
+        let width = self.width(path::strip_top(&self.module_name, port));
+        self.get(port).map(|x| BitVec::from_u64(width, x as u64))
+    }
+
+    /// Switch how statements are evaluated, the compiled bytecode is used by default
+    /// all modes give the same results, so the interpreter can be the reference in tests
+    pub fn set_eval_mode(&mut self, mode: EvalMode) -> Result<(), ModelError> {
+        #[cfg(feature = "jit")]
+        {
+            self.detach_native();
+            if mode == EvalMode::Jit {
+                self.attach_native()?;
+            }
+        }
+        self.eval_mode = mode;
+        Ok(())
+    }
+
+    pub fn eval_mode(&self) -> EvalMode {
+        self.eval_mode
+    }
+
+    /// Set the policy for references to unknown signals
+    pub fn set_unknown_policy(&mut self, policy: UnknownPolicy) {
+        self.unknown.policy = policy;
+    }
+
+    /// Unknown signals referenced so far, with reference counts and source locations
+    pub fn unknown_signals(&self) -> Vec<UnknownSignal> {
+        self.unknown.report()
+    }
+
+    /// First reference to an unknown signal under `UnknownPolicy::Error`, cleared by reset
+    pub fn unknown_failure(&self) -> Option<&ModelError> {
+        self.unknown.failure.as_ref()
+    }
+
+    /// All signals in the model sorted by name
+    pub fn signals(&self) -> impl Iterator<Item = SignalInfo> + '_ {
+        // 状態ベクタは信号名の順に並んでいる
+        self.layout
+            .iter()
+            .map(|(slot, name, direction)| SignalInfo {
+                name: name.to_string(),
+                direction,
+                width: self.width(name),
+                value: self.values[slot],
+            })
+    }
+
+    // 信号のビット幅（配列要素の場合は配列のビット幅）
+    fn width(&self, name: &str) -> usize {
+        let base = name.split('[').next().unwrap_or(name);
+        self.widths.get(base).copied().unwrap_or(0)
+    }
+
+    /// Constructs which were ignored or approximated while building the model
+    /// the simulation result may differ from the design if this is not empty
+    pub fn warnings(&self) -> &[ModelWarning] {
+        &self.warnings
+    }
+
+    /// Report of the constructs in the design which are outside the supported subset
+    pub fn lint(&self) -> Lint {
+        Lint::new(self.warnings.iter().chain(&self.approximations))
+    }
+
+    /// Unreachable or overlapping case arms found at elaboration
+    pub fn case_overlaps(&self) -> &[CaseOverlap] {
+        &self.case_overlaps
+    }
+
+    /// Violations of unique / unique0 / priority case found so far
+    pub fn case_violations(&self) -> &[CaseViolation] {
+        &self.case_violations
+    }
+
+    /// Assertion system tasks (`$warning` / `$error` / `$fatal`) executed so far
+    pub fn assertion_failures(&self) -> &[AssertionFailure] {
+        &self.assertion_failures
+    }
+
+    // 巻き戻した時点より後のアサーション失敗を捨てる
+    pub(crate) fn truncate_assertion_failures(&mut self, len: usize) {
+        self.assertion_failures.truncate(len);
+    }
+
+    /// Statements and branches in the design whose executions are counted
+    pub fn coverage_points(&self) -> &[CoveragePoint] {
+        &self.coverage_points
+    }
+
+    /// Execution counts of the coverage points, in the same order as `coverage_points`
+    /// combinational statements are counted once per evaluation after they settle
+    pub fn coverage_hits(&self) -> &[u64] {
+        &self.coverage_hits
+    }
+
+    /// Execution counts of the coverage points including the iterations until
+    /// combinational statements settle, in the same order as `coverage_points`
+    pub fn execution_counts(&self) -> &[u64] {
+        &self.executions
+    }
+
+    /// Number of iterations spent to settle combinational statements
+    pub fn settle_iterations(&self) -> u64 {
+        self.settle_iterations
+    }
+
+    /// Number of assign declarations and always_comb blocks evaluated so far
+    /// when levelized, blocks whose inputs didn't change since the last evaluation are skipped
+    pub fn evaluated_groups(&self) -> u64 {
+        self.evaluated_groups
+    }
+
+    /// Whether combinational statements have no loop and settle in a single pass
+    /// in the dependency order, otherwise they are iterated until no value changes
+    pub fn is_levelized(&self) -> bool {
+        self.levelized
+    }
+
+    /// Elaborated model as JSON
+    /// signals with their widths, combinational statements and sequential blocks as expression trees
+    pub fn export_json(&self) -> String {
+        let export = ModelExport {
+            module: &self.module_name,
+            clocks: &self.clocks,
+            resets: &self.resets,
+            signals: self
+                .signals()
+                .map(|x| SignalExport {
+                    name: x.name,
+                    direction: x.direction,
+                    width: x.width,
+                })
+                .collect(),
+            // カバレッジの計測点は設計の一部ではないので出力しない
+            combinational: strip_coverage(&self.combinational),
+            sequential: self
+                .sequential
+                .iter()
+                .map(|x| SequentialBlock {
+                    reset: strip_coverage(&x.reset),
+                    clock: strip_coverage(&x.clock),
+                    ..x.clone()
+                })
+                .collect(),
+        };
+        serde_json::to_string_pretty(&export).unwrap_or_default()
+    }
+
+    /// Standalone Rust source implementing the model, to be compiled separately for speed
+    /// the struct named after the module holds all signals, starting from the current state,
+    /// and has `set`, `get`, `clock_rise`, `clock_fall`, `reset` and `eval` like `Model`
+    /// coverage points, assertions, case checks, forces and behavioral models are not emitted
+    pub fn emit_rust(&self) -> String {
+        RustModel {
+            module: &self.module_name,
+            layout: &self.layout,
+            values: &self.values,
+            combinational: &self.combinational,
+            levelized: self.levelized,
+            sequential: &self.sequential,
+            clocks: &self.clocks,
+            resets: &self.resets,
+        }
+        .emit()
+    }
+
+    /// Name of the top module
+    pub fn module_name(&self) -> &str {
+        &self.module_name
+    }
+
+    /// Clock signals and their active edges
+    pub fn clocks(&self) -> &[(String, ClockType)] {
+        &self.clocks
+    }
+
+    /// Reset signals and their polarity and synchronicity
+    pub fn resets(&self) -> &[(String, ResetType)] {
+        &self.resets
+    }
+
+    /// Clock edge of all clocks
+    pub fn clock(&mut self) {
+        // クロックエッジで順序回路を評価
+        // リセットがアサートされているブロックはリセット時の文を実行する
+        self.evaluate_sequential_clock(None);
+        // 順序回路の出力が変わった可能性があるので組み合わせ回路も再評価
+        self.evaluate_combinational();
+    }
+
+    /// Rising edge of the clock
+    /// only the always_ff blocks driven by the clock at the rising edge are evaluated
+    pub fn clock_rise(&mut self, clock: &str) {
+        self.clock_transition(clock, true);
+    }
+
+    /// Falling edge of the clock
+    /// only the always_ff blocks driven by the clock at the falling edge are evaluated
+    pub fn clock_fall(&mut self, clock: &str) {
+        self.clock_transition(clock, false);
+    }
+
+    fn clock_transition(&mut self, clock: &str, rising: bool) {
+        // クロック入力の値を更新
+        if let Some(slot) = self.input_slot(clock) {
+            self.values[slot] = rising as usize;
+        }
+        // クロックの有効エッジであれば順序回路を評価
+        let clock_type = self
+            .clocks
+            .iter()
+            .find(|(name, _)| name == clock)
+            .map(|(_, x)| *x)
+            .unwrap_or_default();
+        if rising == matches!(clock_type, ClockType::PosEdge) {
+            self.evaluate_sequential_clock(Some(clock));
+        }
+        self.evaluate_combinational();
+    }
+
+    /// Assert or deassert all reset inputs according to their polarity
+    /// asynchronous resets take effect immediately, and synchronous resets at the next clock
+    pub fn set_reset(&mut self, active: bool) {
+        let resets: Vec<_> = self
+            .resets
+            .iter()
+            .filter(|(name, _)| self.input_slot(name).is_some())
+            .map(|(name, x)| (name.clone(), reset_level(*x, active)))
+            .collect();
+        for (name, value) in resets {
+            self.input(&name, value);
+        }
     }
 
     pub fn reset(&mut self) {
-        self.is_reset = true;
+        // 未知の信号の参照はリセット後の評価で改めて記録する
+        self.unknown.failure = None;
+        // リセット入力をアサート
+        self.drive_resets(true);
         // リセット時の順序回路を評価
         self.evaluate_sequential_reset();
+        self.reset_instances();
         // リセット解除
-        self.is_reset = false;
+        self.drive_resets(false);
         // リセット後の組み合わせ回路を評価
         self.evaluate_combinational();
     }
 
-    fn evaluate_combinational(&mut self) {
-        for assignment in &self.combinational {
-            let variables = self.get_all_variables();
-            let value = assignment.expression.eval(&variables);
+    // リセット入力をアサート・デアサートする
+    fn drive_resets(&mut self, active: bool) {
+        for (name, reset_type) in &self.resets {
+            if let Some(slot) = self.input_slot(name) {
+                self.values[slot] = reset_level(*reset_type, active);
+            }
+        }
+        self.apply_forces();
+    }
+
+    // ブロックのリセットがアサートされているか
+    fn is_reset_asserted(&self, block: &SequentialBlock) -> bool {
+        let Some(signal) = &block.reset_signal else {
+            return false;
+        };
+        let Some((_, reset_type)) = self.resets.iter().find(|(name, _)| name == signal) else {
+            return false;
+        };
+        self.peek(signal) == Some(reset_level(*reset_type, true))
+    }
 
-            // 出力ポートに値を設定
-            if self.outputs.contains_key(&assignment.target) {
-                self.outputs.insert(assignment.target.clone(), value);
+    fn evaluate_combinational(&mut self) {
+        let statements = std::mem::take(&mut self.combinational);
+        let code = std::mem::take(&mut self.combinational_code);
+
+        if self.levelized {
+            // 依存順に並んでいれば 1 回で確定する
+            self.settle_iterations += 1;
+            self.evaluate_changed(&statements, &code);
+        } else {
+            // ループがある場合は値が変化しなくなるまで繰り返し評価する
+            let mut previous = self.values.clone();
+            for _ in 0..MAX_SETTLE_ITERATIONS {
+                // 収束途中の値による違反は報告しない
+                self.pending_violations.clear();
+                self.pending_failures.clear();
+                self.pending_hits.clear();
+                self.settle_iterations += 1;
+                self.evaluated_groups += self.groups.len() as u64;
+                self.run(&statements, &code);
+                if self.values == previous {
+                    break;
+                }
+                previous.copy_from_slice(&self.values);
             }
-            // 内部信号に値を設定
-            else if self.internals.contains_key(&assignment.target) {
-                self.internals.insert(assignment.target.clone(), value);
+        }
+        self.combinational = statements;
+        self.combinational_code = code;
+        self.commit_violations();
+    }
+
+    // 前回の評価から値が変化した信号を読み出す、または代入する組だけを依存順に評価する
+    // 評価した組が代入した信号の変化は後に続く組へ伝搬する
+    fn evaluate_changed(&mut self, statements: &[Statement], code: &[Code]) {
+        let mut dirty: Vec<bool> = if self.settled.len() == self.values.len() {
+            self.values
+                .iter()
+                .zip(&self.settled)
+                .map(|(x, y)| x != y)
+                .collect()
+        } else {
+            vec![true; self.values.len()]
+        };
+
+        let mut groups = std::mem::take(&mut self.groups);
+        for group in &mut groups {
+            if group.inputs.iter().any(|x| dirty[*x]) {
+                let before: Vec<_> = group.outputs.iter().map(|x| self.values[*x]).collect();
+                let hits = self.pending_hits.len();
+                let failures = self.pending_failures.len();
+                let violations = self.pending_violations.len();
+                self.unknown.captured = Some(Vec::new());
+                self.evaluated_groups += 1;
+                self.run(&statements[group.range.clone()], &code[group.range.clone()]);
+
+                group.unknowns = self.unknown.captured.take().unwrap_or_default();
+                group.hits = self.pending_hits[hits..].to_vec();
+                group.failures = self.pending_failures[failures..].to_vec();
+                group.violations = self.pending_violations[violations..].to_vec();
+                for (slot, value) in group.outputs.iter().zip(before) {
+                    if self.values[*slot] != value {
+                        dirty[*slot] = true;
+                    }
+                }
+            } else {
+                for id in &group.hits {
+                    self.pending_hits.push(*id);
+                    self.executions[*id] += 1;
+                }
+                self.pending_failures.extend(group.failures.iter().cloned());
+                self.pending_violations
+                    .extend(group.violations.iter().cloned());
+                for name in &group.unknowns {
+                    self.unknown.record(name);
+                }
             }
         }
+        self.groups = groups;
+        self.settled.clone_from(&self.values);
     }
 
     fn evaluate_sequential_reset(&mut self) {
         // 全ての順序ブロックのリセット処理を実行
-        for block in &self.sequential {
-            for assignment in &block.reset_assignments {
-                let variables = self.get_all_variables();
-                let value = assignment.expression.eval(&variables);
+        let sequential = std::mem::take(&mut self.sequential);
+        let code = std::mem::take(&mut self.sequential_code);
+        for (block, (reset, _)) in sequential.iter().zip(&code) {
+            self.run(&block.reset, reset);
+        }
+        self.sequential = sequential;
+        self.sequential_code = code;
+        self.commit_violations();
+    }
+
+    fn evaluate_sequential_async_reset(&mut self, reset: &str) {
+        // 指定されたリセットを参照する順序ブロックのリセット処理を実行
+        let sequential = std::mem::take(&mut self.sequential);
+        let code = std::mem::take(&mut self.sequential_code);
+        for (block, (reset_code, _)) in sequential.iter().zip(&code) {
+            if block.reset_signal.as_deref() == Some(reset) {
+                self.run(&block.reset, reset_code);
+            }
+        }
+        self.sequential = sequential;
+        self.sequential_code = code;
+        self.commit_violations();
+    }
+
+    fn evaluate_sequential_clock(&mut self, clock: Option<&str>) {
+        self.clock_instances(clock);
+        // 順序ブロックのクロック処理を実行（clock が指定されればそのクロックのブロックのみ）
+        let sequential = std::mem::take(&mut self.sequential);
+        let code = std::mem::take(&mut self.sequential_code);
+        for (block, (reset, clock_code)) in sequential.iter().zip(&code) {
+            if clock.is_some() && block.clock_signal.as_deref() != clock {
+                continue;
+            }
+            if self.is_reset_asserted(block) {
+                self.run(&block.reset, reset);
+            } else {
+                self.run(&block.clock, clock_code);
+            }
+        }
+        self.sequential = sequential;
+        self.sequential_code = code;
+        self.commit_violations();
+    }
+
+    // インスタンスの振る舞いモデルにクロックエッジを与える
+    // ポートはエッジ前の値を保持しており、更新した出力は続く組み合わせ回路の評価で反映する
+    fn clock_instances(&mut self, clock: Option<&str>) {
+        let mut clocked = false;
+        for instance in &mut self.instances {
+            if instance.model.is_some() && instance.is_clocked_by(clock) {
+                instance.clock();
+                clocked = true;
+            }
+        }
+        // 入力が変化していなくても出力を反映するため、全ての組を評価する
+        if clocked {
+            self.settled.clear();
+        }
+    }
+
+    fn reset_instances(&mut self) {
+        let mut reset = false;
+        for instance in &mut self.instances {
+            if instance.model.is_some() {
+                instance.reset();
+                reset = true;
+            }
+        }
+        if reset {
+            self.settled.clear();
+        }
+    }
+
+    // 評価中に検出した違反を確定する
+    fn commit_violations(&mut self) {
+        self.case_violations.append(&mut self.pending_violations);
+        self.assertion_failures.append(&mut self.pending_failures);
+        for id in self.pending_hits.drain(..) {
+            self.coverage_hits[id] += 1;
+        }
+    }
+
+    // 評価方式に従って文を実行する
+    fn run(&mut self, statements: &[Statement], code: &[Code]) {
+        match self.eval_mode {
+            EvalMode::Compiled => self.execute_code(code),
+            EvalMode::Interpreted => self.execute(statements),
+            // ネイティブ関数は Program の評価から呼び出される
+            #[cfg(feature = "jit")]
+            EvalMode::Jit => self.execute_code(code),
+        }
+    }
+
+    // 全ての Program をネイティブコードに変換して設定する
+    #[cfg(feature = "jit")]
+    fn attach_native(&mut self) -> Result<(), ModelError> {
+        let mut jit = Jit::new().map_err(ModelError::JitFailed)?;
+        let mut code = std::mem::take(&mut self.combinational_code);
+        let mut sequential = std::mem::take(&mut self.sequential_code);
+
+        // 定義した順に関数を取り出す
+        let mut functions = Vec::new();
+        let mut result = Ok(());
+        let mut define = |program: &mut bytecode::Program| {
+            if result.is_ok() {
+                match jit.define(program) {
+                    Ok(x) => functions.push(x),
+                    Err(x) => result = Err(x),
+                }
+            }
+        };
+        bytecode::for_each_program(&mut code, &mut define);
+        for (reset, clock) in &mut sequential {
+            bytecode::for_each_program(reset, &mut define);
+            bytecode::for_each_program(clock, &mut define);
+        }
+        let result = result.and_then(|_| jit.finalize());
+
+        if result.is_ok() {
+            let mut functions = functions.into_iter();
+            let mut attach = |program: &mut bytecode::Program| {
+                let native = functions.next().flatten().and_then(|x| jit.function(x));
+                program.set_native(native);
+            };
+            bytecode::for_each_program(&mut code, &mut attach);
+            for (reset, clock) in &mut sequential {
+                bytecode::for_each_program(reset, &mut attach);
+                bytecode::for_each_program(clock, &mut attach);
+            }
+            self.jit = Some(jit);
+        }
+        self.combinational_code = code;
+        self.sequential_code = sequential;
+        result.map_err(ModelError::JitFailed)
+    }
+
+    // ネイティブ関数を取り除いてから、そのメモリを解放する
+    #[cfg(feature = "jit")]
+    fn detach_native(&mut self) {
+        let mut detach = |program: &mut bytecode::Program| program.set_native(None);
+        bytecode::for_each_program(&mut self.combinational_code, &mut detach);
+        for (reset, clock) in &mut self.sequential_code {
+            bytecode::for_each_program(reset, &mut detach);
+            bytecode::for_each_program(clock, &mut detach);
+        }
+        self.jit = None;
+    }
+
+    // バイトコードに変換した文を順に実行する（execute と同じ結果になる）
+    fn execute_code(&mut self, code: &[Code]) {
+        for code in code {
+            match code {
+                Code::Assign(targets, expression) => {
+                    let value = expression.eval(
+                        &self.values,
+                        &mut self.stack,
+                        &self.functions,
+                        &mut |name| self.unknown.record(name),
+                    );
+                    bytecode::store(targets, value, &mut self.values, &mut self.stack);
+                    // force された信号はドライバによらず値を保持する
+                    self.apply_forces();
+                }
+                Code::If(branches, otherwise) => {
+                    let mut taken = None;
+                    for (condition, code) in branches {
+                        if condition.eval(
+                            &self.values,
+                            &mut self.stack,
+                            &self.functions,
+                            &mut |name| self.unknown.record(name),
+                        ) != 0
+                        {
+                            taken = Some(code);
+                            break;
+                        }
+                    }
+                    self.execute_code(taken.unwrap_or(otherwise));
+                }
+                Code::Case(case) => {
+                    let mut unknown = |name: &str| self.unknown.record(name);
+                    let value = case.expression.eval(
+                        &self.values,
+                        &mut self.stack,
+                        &self.functions,
+                        &mut unknown,
+                    );
+                    let matched: Vec<usize> = case
+                        .arms
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, (labels, _))| {
+                            labels.iter().any(|x| {
+                                x.matches(
+                                    value,
+                                    &self.values,
+                                    &mut self.stack,
+                                    &self.functions,
+                                    &mut unknown,
+                                )
+                            })
+                        })
+                        .map(|(i, _)| i)
+                        .collect();
+
+                    if let Some(violation) = case_violation(
+                        case.check,
+                        case.has_default,
+                        case.span,
+                        &case.spans,
+                        value,
+                        &matched,
+                    ) {
+                        self.pending_violations.push(violation);
+                    }
 
-                // 出力ポートに値を設定
-                if self.outputs.contains_key(&assignment.target) {
-                    self.outputs.insert(assignment.target.clone(), value);
+                    let taken = matched.first().map(|i| &case.arms[*i].1);
+                    self.execute_code(taken.unwrap_or(&case.otherwise));
                 }
-                // 内部信号に値を設定
-                else if self.internals.contains_key(&assignment.target) {
-                    self.internals.insert(assignment.target.clone(), value);
+                Code::Cover(id) => {
+                    self.pending_hits.push(*id);
+                    self.executions[*id] += 1;
+                }
+                Code::Assert(x) => {
+                    self.pending_failures.push(AssertionFailure {
+                        severity: x.severity,
+                        location: x.span,
+                        message: x.message.clone(),
+                    });
+                }
+                Code::Instance(index, inputs, outputs) => {
+                    let inputs: Vec<_> = inputs
+                        .iter()
+                        .map(|x| {
+                            x.eval(
+                                &self.values,
+                                &mut self.stack,
+                                &self.functions,
+                                &mut |name| self.unknown.record(name),
+                            )
+                        })
+                        .collect();
+                    let Some(values) = self.eval_instance(*index, &inputs) else {
+                        continue;
+                    };
+                    for (target, value) in outputs.iter().zip(values) {
+                        bytecode::store(
+                            std::slice::from_ref(target),
+                            value,
+                            &mut self.values,
+                            &mut self.stack,
+                        );
+                    }
+                    self.apply_forces();
                 }
             }
         }
     }
 
-    fn evaluate_sequential_clock(&mut self) {
-        // 全ての順序ブロックのクロック処理を実行
-        for block in &self.sequential {
-            for assignment in &block.clock_assignments {
-                let variables = self.get_all_variables();
-                let value = assignment.expression.eval(&variables);
+    // インスタンスの振る舞いモデルを評価して出力の値を返す
+    // モデルが登録されていなければ出力は値を保持し、インスタンス名を未知の名前として記録する
+    fn eval_instance(&mut self, index: usize, inputs: &[usize]) -> Option<Vec<usize>> {
+        let instance = &mut self.instances[index];
+        let values = instance.eval(inputs);
+        if values.is_none() {
+            self.unknown.record(&instance.name);
+        }
+        values
+    }
 
-                // 出力ポートに値を設定
-                if self.outputs.contains_key(&assignment.target) {
-                    self.outputs.insert(assignment.target.clone(), value);
+    // 文を順に実行する
+    // 実行されなかった分岐の代入先は値を保持する
+    fn execute(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            match statement {
+                Statement::Instance(x) => {
+                    let variables = self.get_all_variables();
+                    let inputs: Vec<_> = x
+                        .inputs
+                        .iter()
+                        .map(|x| {
+                            x.eval_with(&variables, &self.functions, &mut |name| {
+                                self.unknown.record(name)
+                            })
+                        })
+                        .collect();
+                    let Some(values) = self.eval_instance(x.index, &inputs) else {
+                        continue;
+                    };
+                    for (target, value) in x.outputs.iter().zip(values) {
+                        store_target(&mut self.values, &self.layout, target, value, &variables);
+                    }
+                    self.apply_forces();
                 }
-                // 内部信号に値を設定
-                else if self.internals.contains_key(&assignment.target) {
-                    self.internals.insert(assignment.target.clone(), value);
+                Statement::Assign(assignment) => {
+                    let variables = self.get_all_variables();
+                    let value =
+                        assignment
+                            .expression
+                            .eval_with(&variables, &self.functions, &mut |name| {
+                                self.unknown.record(name)
+                            });
+                    store(
+                        &mut self.values,
+                        &self.layout,
+                        &assignment.targets,
+                        value,
+                        &variables,
+                    );
+                    // force された信号はドライバによらず値を保持する
+                    self.apply_forces();
+                }
+                Statement::If(branches, otherwise) => {
+                    let variables = self.get_all_variables();
+                    let mut taken = None;
+                    for (condition, statements) in branches {
+                        if condition.eval_with(&variables, &self.functions, &mut |name| {
+                            self.unknown.record(name)
+                        }) != 0
+                        {
+                            taken = Some(statements);
+                            break;
+                        }
+                    }
+                    self.execute(taken.unwrap_or(otherwise));
+                }
+                Statement::Case(case) => {
+                    // 最初に一致した分岐を実行し、どれにも一致しなければ default を実行する
+                    let variables = self.get_all_variables();
+                    let mut unknown = |name: &str| self.unknown.record(name);
+                    let value =
+                        case.expression
+                            .eval_with(&variables, &self.functions, &mut unknown);
+                    let matched: Vec<usize> = case
+                        .arms
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, (labels, _))| {
+                            labels.iter().any(|x| {
+                                x.matches(value, &variables, &self.functions, &mut unknown)
+                            })
+                        })
+                        .map(|(i, _)| i)
+                        .collect();
+
+                    if let Some(violation) = case_violation(
+                        case.check,
+                        case.has_default,
+                        case.span,
+                        &case.spans,
+                        value,
+                        &matched,
+                    ) {
+                        self.pending_violations.push(violation);
+                    }
+
+                    let taken = matched.first().map(|i| &case.arms[*i].1);
+                    self.execute(taken.unwrap_or(&case.otherwise));
+                }
+                Statement::Cover(id) => {
+                    self.pending_hits.push(*id);
+                    self.executions[*id] += 1;
+                }
+                Statement::Assert(x) => {
+                    self.pending_failures.push(AssertionFailure {
+                        severity: x.severity,
+                        location: x.span,
+                        message: x.message.clone(),
+                    });
                 }
             }
         }
@@ -628,23 +3686,326 @@ impl Model {
 
     /// すべての変数（入力、出力、内部信号）を一つのHashMapにまとめて返す
     fn get_all_variables(&self) -> HashMap<String, usize> {
-        let mut variables = HashMap::new();
+        self.layout
+            .iter()
+            .map(|(slot, name, _)| (name.to_string(), self.values[slot]))
+            .collect()
+    }
+}
+
+// unique / unique0 / priority case の違反（一致した分岐の番号から判定する）
+fn case_violation(
+    check: Option<CaseCheck>,
+    has_default: bool,
+    location: Span,
+    spans: &[Span],
+    value: usize,
+    matched: &[usize],
+) -> Option<CaseViolation> {
+    let check = check?;
+    let violated = match check {
+        CaseCheck::Unique => matched.len() > 1 || (matched.is_empty() && !has_default),
+        CaseCheck::Unique0 => matched.len() > 1,
+        CaseCheck::Priority => matched.is_empty() && !has_default,
+    };
+    violated.then(|| CaseViolation {
+        check,
+        location,
+        value,
+        matched: matched.iter().map(|i| spans[*i]).collect(),
+    })
+}
 
-        // 入力ポートの値を追加
-        for (name, value) in &self.inputs {
-            variables.insert(name.clone(), *value);
+// 文の列からカバレッジの計測点を取り除く
+fn strip_coverage(statements: &[Statement]) -> Vec<Statement> {
+    statements
+        .iter()
+        .filter(|x| !matches!(x, Statement::Cover(_)))
+        .map(|x| match x {
+            Statement::If(branches, otherwise) => Statement::If(
+                branches
+                    .iter()
+                    .map(|(x, y)| (x.clone(), strip_coverage(y)))
+                    .collect(),
+                strip_coverage(otherwise),
+            ),
+            Statement::Case(x) => Statement::Case(CaseStatement {
+                arms: x
+                    .arms
+                    .iter()
+                    .map(|(x, y)| (x.clone(), strip_coverage(y)))
+                    .collect(),
+                otherwise: strip_coverage(&x.otherwise),
+                ..x.clone()
+            }),
+            x => x.clone(),
+        })
+        .collect()
+}
+
+// アサーションの引数から最初の文字列リテラルを取り出す
+fn assert_message(group: &syntax_tree::IdentifierStatementGroup) -> Option<String> {
+    let syntax_tree::IdentifierStatementGroup::FunctionCall(x) = group else {
+        return None;
+    };
+    let list = &x.function_call.function_call_opt.as_ref()?.argument_list;
+    std::iter::once(&list.argument_item)
+        .chain(list.argument_list_list.iter().map(|x| &x.argument_item))
+        .find_map(|x| {
+            let text = TokenRange::from(&*x.argument_expression.expression)
+                .beg
+                .to_string();
+            text.strip_prefix('"')
+                .and_then(|x| x.strip_suffix('"'))
+                .map(|x| x.to_string())
+        })
+}
+
+// x / z を含む数値リテラルの (値, 比較するビットのマスク)
+fn wildcard_factor(factor: &syntax_tree::Factor) -> Option<(usize, usize)> {
+    if let syntax_tree::Factor::Number(x) = factor
+        && let syntax_tree::Number::IntegralNumber(x) = &*x.number
+        && let syntax_tree::IntegralNumber::Based(x) = &*x.integral_number
+        && let Some((value, mask)) = parse_based(&x.based.based_token.to_string())
+        && mask != usize::MAX
+    {
+        Some((value, mask))
+    } else {
+        None
+    }
+}
+
+// 等価演算子の右辺が x / z を含む数値リテラルだけの場合の (値, 比較するビットのマスク)
+fn wildcard_number(expr: &syntax_tree::Expression07) -> Option<(usize, usize)> {
+    let empty = expr.expression07_list.is_empty()
+        && expr.expression08.expression08_list.is_empty()
+        && expr.expression08.expression09.expression09_list.is_empty()
+        && expr
+            .expression08
+            .expression09
+            .expression10
+            .expression10_list
+            .is_empty();
+    let expr = &expr.expression08.expression09.expression10.expression11;
+    if !empty
+        || !expr.expression11_list.is_empty()
+        || expr.expression12.expression12_opt.is_some()
+        || !expr.expression12.expression13.expression13_list.is_empty()
+    {
+        return None;
+    }
+    wildcard_factor(&expr.expression12.expression13.factor)
+}
+
+// 基数指定の数値（例：8'b10xx_0000）を (値, 比較するビットのマスク) に変換する
+// x / z の桁はマスクから除き、先頭が x / z の場合は指定されたビット幅まで拡張する
+fn parse_based(s: &str) -> Option<(usize, usize)> {
+    let pos = s.rfind('\'')?;
+    let width = s[..pos].replace('_', "").parse::<usize>().ok();
+    let mut chars = s[pos + 1..].trim_start_matches(['s', 'S']).chars();
+    let bits = match chars.next()? {
+        'b' | 'B' => 1,
+        'o' | 'O' => 3,
+        'h' | 'H' => 4,
+        'd' | 'D' => {
+            let digits: String = chars.filter(|x| *x != '_').collect();
+            return digits.parse().ok().map(|x| (x, usize::MAX));
         }
+        _ => return None,
+    };
+
+    let digits: Vec<char> = chars.filter(|x| *x != '_').collect();
+    let is_wildcard = |x: &char| matches!(x, 'x' | 'X' | 'z' | 'Z');
+    let mut value = 0usize;
+    let mut mask = 0usize;
+    for x in &digits {
+        let (digit, digit_mask) = if is_wildcard(x) {
+            (0, 0)
+        } else {
+            (x.to_digit(1 << bits)? as usize, bit_mask(bits))
+        };
+        value = value.checked_shl(bits as u32).unwrap_or(0) | digit;
+        mask = mask.checked_shl(bits as u32).unwrap_or(0) | digit_mask;
+    }
+
+    let digits_width = digits.len() * bits;
+    mask |= !bit_mask(digits_width);
+    if let Some(width) = width
+        && digits.first().is_some_and(is_wildcard)
+    {
+        mask &= !(bit_mask(width) & !bit_mask(digits_width));
+    }
+    Some((value, mask))
+}
 
-        // 出力ポートの値を追加
-        for (name, value) in &self.outputs {
-            variables.insert(name.clone(), *value);
+// 関数やモジュールの名前（識別子を :: で繋ぎ、$sv:: は除く）
+// $sv 以外のシステム関数は None
+fn scoped_name(scoped: &syntax_tree::ScopedIdentifier) -> Option<String> {
+    let mut path = Vec::new();
+    match &*scoped.scoped_identifier_group {
+        syntax_tree::ScopedIdentifierGroup::IdentifierScopedIdentifierOpt(x) => {
+            path.push(x.identifier.identifier_token.to_string());
         }
+        syntax_tree::ScopedIdentifierGroup::DollarIdentifier(x) => {
+            if x.dollar_identifier.dollar_identifier_token.to_string() != "$sv" {
+                return None;
+            }
+        }
+    }
+    path.extend(
+        scoped
+            .scoped_identifier_list
+            .iter()
+            .map(|x| x.identifier.identifier_token.to_string()),
+    );
+    (!path.is_empty()).then(|| path.join("::"))
+}
 
-        // 内部信号の値を追加
-        for (name, value) in &self.internals {
-            variables.insert(name.clone(), *value);
+// インスタンスのポート接続を並べる（{ } でまとめたものは展開する）
+fn collect_inst_ports<'a>(
+    list: &'a syntax_tree::InstPortList,
+    items: &mut Vec<&'a syntax_tree::InstPortItem>,
+) {
+    let groups = std::iter::once(&list.inst_port_group)
+        .chain(list.inst_port_list_list.iter().map(|x| &x.inst_port_group));
+    for group in groups {
+        match &*group.inst_port_group_group {
+            syntax_tree::InstPortGroupGroup::LBraceInstPortListRBrace(x) => {
+                collect_inst_ports(&x.inst_port_list, items);
+            }
+            syntax_tree::InstPortGroupGroup::InstPortItem(x) => items.push(&x.inst_port_item),
         }
+    }
+}
+
+// 文が代入する信号の名前を集める
+fn collect_assigned(statements: &[Statement], names: &mut HashSet<String>) {
+    for statement in statements {
+        match statement {
+            Statement::Assign(x) => names.extend(x.targets.iter().map(|x| x.name.clone())),
+            Statement::If(branches, otherwise) => {
+                for (_, statements) in branches {
+                    collect_assigned(statements, names);
+                }
+                collect_assigned(otherwise, names);
+            }
+            Statement::Case(x) => {
+                for (_, statements) in &x.arms {
+                    collect_assigned(statements, names);
+                }
+                collect_assigned(&x.otherwise, names);
+            }
+            Statement::Instance(x) => names.extend(x.outputs.iter().map(|x| x.name.clone())),
+            Statement::Assert(_) | Statement::Cover(_) => {}
+        }
+    }
+}
+
+// 警告に表示する Factor の種類
+fn factor_name(factor: &syntax_tree::Factor) -> &'static str {
+    match factor {
+        syntax_tree::Factor::Number(_) => "number",
+        syntax_tree::Factor::BooleanLiteral(_) => "boolean literal",
+        syntax_tree::Factor::IdentifierFactor(_) => "identifier",
+        syntax_tree::Factor::LParenExpressionRParen(_) => "parenthesized expression",
+        syntax_tree::Factor::LBraceConcatenationListRBrace(_) => "concatenation",
+        syntax_tree::Factor::QuoteLBraceArrayLiteralListRBrace(_) => "array literal",
+        syntax_tree::Factor::CaseExpression(_) => "case expression",
+        syntax_tree::Factor::SwitchExpression(_) => "switch expression",
+        syntax_tree::Factor::StringLiteral(_) => "string literal",
+        syntax_tree::Factor::FactorGroup(_) => "msb / lsb",
+        syntax_tree::Factor::InsideExpression(_) => "inside expression",
+        syntax_tree::Factor::OutsideExpression(_) => "outside expression",
+        syntax_tree::Factor::TypeExpression(_) => "type expression",
+        syntax_tree::Factor::FactorTypeFactor(_) => "type",
+    }
+}
+
+// clock 型の有効エッジを解決する（clock は build 設定に従う）
+fn resolve_clock_type(kind: &TypeKind, default: ClockType) -> Option<ClockType> {
+    match kind {
+        TypeKind::Clock => Some(default),
+        TypeKind::ClockPosedge => Some(ClockType::PosEdge),
+        TypeKind::ClockNegedge => Some(ClockType::NegEdge),
+        _ => None,
+    }
+}
+
+// reset 型の極性・同期/非同期を解決する（reset は build 設定に従う）
+fn resolve_reset_type(kind: &TypeKind, default: ResetType) -> Option<ResetType> {
+    match kind {
+        TypeKind::Reset => Some(default),
+        TypeKind::ResetAsyncHigh => Some(ResetType::AsyncHigh),
+        TypeKind::ResetAsyncLow => Some(ResetType::AsyncLow),
+        TypeKind::ResetSyncHigh => Some(ResetType::SyncHigh),
+        TypeKind::ResetSyncLow => Some(ResetType::SyncLow),
+        _ => None,
+    }
+}
+
+// リセットのアサート・デアサート時の値
+pub(crate) fn reset_level(reset_type: ResetType, active: bool) -> usize {
+    let active_high = matches!(reset_type, ResetType::AsyncHigh | ResetType::SyncHigh);
+    (active == active_high) as usize
+}
+
+// 型のビット幅を求める（評価できない場合は usize の幅とみなす）
+fn type_width(r#type: &Type) -> usize {
+    let mut evaluator = Evaluator::new(&[]);
+    evaluator
+        .type_width(r#type.clone())
+        .map(|x| x.iter().product())
+        .unwrap_or(usize::BITS as usize)
+}
+
+// 型の配列要素数を求める（配列でない場合は None）
+fn array_size(r#type: &Type) -> Option<usize> {
+    if r#type.array.is_empty() {
+        return None;
+    }
+    let mut evaluator = Evaluator::new(&[]);
+    evaluator
+        .type_array(r#type.clone())
+        .map(|x| x.iter().product())
+}
+
+// 代入先に値を書き込む
+// 連接の場合は LSB 側の代入先から順に各代入先の幅で値を分割する
+fn store(
+    values: &mut [usize],
+    layout: &Layout,
+    targets: &[Target],
+    value: usize,
+    env: &HashMap<String, usize>,
+) {
+    if let [target] = targets {
+        store_target(values, layout, target, value, env);
+        return;
+    }
+
+    let mut offset = 0;
+    for target in targets.iter().rev() {
+        let width = target.width(env);
+        let part = value.checked_shr(offset as u32).unwrap_or(0) & bit_mask(width);
+        store_target(values, layout, target, part, env);
+        offset += width;
+    }
+}
 
-        variables
+// 代入先に値を書き込む（ビット選択の場合は読み出し・変更・書き込みを行う）
+fn store_target(
+    values: &mut [usize],
+    layout: &Layout,
+    target: &Target,
+    value: usize,
+    env: &HashMap<String, usize>,
+) {
+    let name = target.resolve(env);
+
+    // 出力ポートと内部信号に値を設定（入力ポートには代入しない）
+    if let Some(slot) = layout.slot(&name)
+        && layout.direction(slot) != Direction::Input
+    {
+        values[slot] = target.merge(values[slot], value, env);
     }
 }
@@ -0,0 +1,163 @@
+use crate::hooks::HookError;
+use crate::model::Span;
+use crate::testbench::ExpectFailure;
+use crate::vectors::VectorMismatch;
+use miette::{self, Diagnostic};
+use thiserror::Error;
+
+#[derive(Error, Diagnostic, Debug, Clone, PartialEq, Eq)]
+pub enum ModelError {
+    #[diagnostic(
+        code(ModelError::TopNotFound),
+        help("check the name of the top module")
+    )]
+    #[error("top module \"{0}\" is not found")]
+    TopNotFound(String),
+
+    #[diagnostic(code(ModelError::DefinitionNotFound), help(""))]
+    #[error("definition of module \"{0}\" is not found")]
+    DefinitionNotFound(String),
+
+    #[diagnostic(
+        code(ModelError::UnsupportedConstruct),
+        help("the simulator doesn't support this construct yet")
+    )]
+    #[error("unsupported construct \"{construct}\" at {span}")]
+    UnsupportedConstruct { construct: String, span: Span },
+
+    #[diagnostic(
+        code(ModelError::NoClockFound),
+        help("always_ff requires an input port of clock type")
+    )]
+    #[error("module \"{0}\" has always_ff but no clock input")]
+    NoClockFound(String),
+
+    #[diagnostic(code(ModelError::UnknownPort), help("check the name of the port"))]
+    #[error("port \"{0}\" is not found")]
+    UnknownPort(String),
+
+    #[diagnostic(code(ModelError::SignalNotFound), help("check the path of the signal"))]
+    #[error("signal \"{0}\" is not found")]
+    SignalNotFound(String),
+
+    #[diagnostic(
+        code(ModelError::UnknownSignal),
+        help("declare the signal, or relax the policy by Model::set_unknown_policy")
+    )]
+    #[error("unknown signal \"{name}\" is referenced at {locations}")]
+    UnknownSignal { name: String, locations: String },
+
+    #[diagnostic(code(ModelError::PathNotFound), help("{hint}"))]
+    #[error("no signal matches \"{path}\"")]
+    PathNotFound { path: String, hint: String },
+
+    #[diagnostic(
+        code(ModelError::InstanceNotFound),
+        help("behavioral models replace instances of modules without a definition")
+    )]
+    #[error("instance of module \"{0}\" is not found")]
+    InstanceNotFound(String),
+
+    #[diagnostic(
+        code(ModelError::StateMismatch),
+        help("restore a state taken from the same module")
+    )]
+    #[error("state of module \"{state}\" doesn't match module \"{model}\"")]
+    StateMismatch { model: String, state: String },
+
+    #[diagnostic(
+        code(ModelError::ReadFailed),
+        help("check the path of the source file")
+    )]
+    #[error("failed to read \"{path}\": {cause}")]
+    ReadFailed { path: String, cause: String },
+
+    #[diagnostic(code(ModelError::ParseFailed), help("fix the syntax error"))]
+    #[error("failed to parse \"{path}\": {cause}")]
+    ParseFailed { path: String, cause: String },
+
+    #[diagnostic(
+        code(ModelError::AnalyzeFailed),
+        help("fix the error reported by the analyzer")
+    )]
+    #[error("analysis failed: {0}")]
+    AnalyzeFailed(String),
+
+    #[diagnostic(code(ModelError::InvalidClock), help("{reason}"))]
+    #[error("clock \"{name}\" is invalid")]
+    InvalidClock { name: String, reason: String },
+
+    #[diagnostic(
+        code(ModelError::InvalidMetadata),
+        help("check the [simulate] section of Veryl.toml")
+    )]
+    #[error("simulation settings are invalid: {0}")]
+    InvalidMetadata(String),
+
+    #[diagnostic(
+        code(ModelError::Timeout),
+        help("increase the timeout or check the design")
+    )]
+    #[error("timed out waiting for {condition} at {time}ns")]
+    Timeout { condition: String, time: u64 },
+
+    #[diagnostic(
+        code(ModelError::SimulationStopped),
+        help("the simulator has no events, or a hook aborted the simulation")
+    )]
+    #[error("simulation stopped at {0}ns")]
+    SimulationStopped(u64),
+
+    #[diagnostic(
+        code(ModelError::WatchdogTimeout),
+        help("the simulation may not terminate, add a finish condition or increase the limit")
+    )]
+    #[error("watchdog expired after {limit_ms}ms at {time}ns")]
+    WatchdogTimeout { limit_ms: u64, time: u64 },
+
+    #[diagnostic(code(ModelError::HookNotFound), help("the hook may have been removed"))]
+    #[error("hook #{0} is not found")]
+    HookNotFound(usize),
+
+    #[diagnostic(
+        code(ModelError::JitFailed),
+        help("use EvalMode::Compiled on this platform")
+    )]
+    #[error("failed to compile the model to native code: {0}")]
+    JitFailed(String),
+
+    #[diagnostic(
+        code(ModelError::ReferenceFailed),
+        help("check that the SystemVerilog simulator is installed and accepts the design")
+    )]
+    #[error("reference simulator failed: {0}")]
+    ReferenceFailed(String),
+
+    #[diagnostic(
+        code(ModelError::HookFailed),
+        help("check the error reported by the hook")
+    )]
+    #[error("hook failed at {time}ns: {error}")]
+    HookFailed { time: u64, error: HookError },
+
+    #[diagnostic(
+        code(ModelError::VectorMismatch),
+        help("check the expected values of the row or the design")
+    )]
+    #[error("test vectors mismatch at {0}")]
+    VectorMismatch(VectorMismatch),
+
+    #[diagnostic(
+        code(ModelError::TimeTravelFailed),
+        help("enable time travel by Simulator::enable_time_travel before the point to go back")
+    )]
+    #[error("failed to go back: {0}")]
+    TimeTravelFailed(String),
+
+    #[diagnostic(
+        code(ModelError::ExpectFailed),
+        help("check the observed values of the signal or the design")
+    )]
+    #[error("expectation failed: {0}")]
+    ExpectFailed(ExpectFailure),
+}
@@ -0,0 +1,95 @@
+use crate::model::Direction;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Values of all signals of a model at a point in time
+/// registers are included in `outputs` and `internals`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelState {
+    pub module: String,
+    pub inputs: BTreeMap<String, usize>,
+    pub outputs: BTreeMap<String, usize>,
+    pub internals: BTreeMap<String, usize>,
+}
+
+impl ModelState {
+    /// Value of the signal
+    pub fn get(&self, name: &str) -> Option<usize> {
+        self.inputs
+            .get(name)
+            .or_else(|| self.outputs.get(name))
+            .or_else(|| self.internals.get(name))
+            .copied()
+    }
+
+    /// Signals whose values differ from `other`, sorted by name
+    /// `before` is the value in self and `after` is the value in `other`
+    pub fn diff(&self, other: &ModelState) -> Vec<SignalDelta> {
+        let mut names: Vec<&String> = self.signals().chain(other.signals()).collect();
+        names.sort();
+        names.dedup();
+
+        names
+            .into_iter()
+            .filter_map(|name| {
+                let before = self.get(name);
+                let after = other.get(name);
+                (before != after).then(|| SignalDelta {
+                    name: name.clone(),
+                    before,
+                    after,
+                })
+            })
+            .collect()
+    }
+
+    // 方向ごとの信号
+    pub(crate) fn by_direction(&self, direction: Direction) -> &BTreeMap<String, usize> {
+        match direction {
+            Direction::Input => &self.inputs,
+            Direction::Output => &self.outputs,
+            Direction::Internal => &self.internals,
+        }
+    }
+
+    pub(crate) fn by_direction_mut(
+        &mut self,
+        direction: Direction,
+    ) -> &mut BTreeMap<String, usize> {
+        match direction {
+            Direction::Input => &mut self.inputs,
+            Direction::Output => &mut self.outputs,
+            Direction::Internal => &mut self.internals,
+        }
+    }
+
+    fn signals(&self) -> impl Iterator<Item = &String> {
+        self.inputs
+            .keys()
+            .chain(self.outputs.keys())
+            .chain(self.internals.keys())
+    }
+}
+
+/// Change of a signal between two states
+/// the value is None if the signal doesn't exist in the state
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignalDelta {
+    pub name: String,
+    pub before: Option<usize>,
+    pub after: Option<usize>,
+}
+
+impl fmt::Display for SignalDelta {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = |x: Option<usize>| x.map(|x| x.to_string()).unwrap_or("-".to_string());
+        write!(
+            f,
+            "{}: {} -> {}",
+            self.name,
+            value(self.before),
+            value(self.after)
+        )
+    }
+}
@@ -0,0 +1,91 @@
+// 信号のパスの解決
+// パスは "top.u_core.regs[3]" のようにドットで区切った階層名で、先頭のトップモジュール名は省略できる
+// "*" は任意の文字列、"?" は任意の 1 文字に一致し、"u_core.regs[*]" のように複数の信号を選べる
+
+// 提案する信号名との編集距離の上限
+const MAX_DISTANCE: usize = 2;
+
+pub(crate) fn is_glob(path: &str) -> bool {
+    path.contains(['*', '?'])
+}
+
+// 先頭のトップモジュール名を取り除いた信号名
+pub(crate) fn strip_top<'a>(module: &str, path: &'a str) -> &'a str {
+    match path.split_once('.') {
+        Some((top, name)) if top == module => name,
+        _ => path,
+    }
+}
+
+// パターンが信号名、またはトップモジュール名を付けたパスに一致するか
+pub(crate) fn matches(module: &str, pattern: &str, name: &str) -> bool {
+    glob_match(pattern, name) || glob_match(pattern, &format!("{module}.{name}"))
+}
+
+// 見つからなかったパスの代わりに試すべきことの説明
+pub(crate) fn hint<'a>(module: &str, path: &str, names: impl Iterator<Item = &'a str>) -> String {
+    let name = strip_top(module, path);
+    if !is_glob(name)
+        && let Some(x) = suggest(name, names)
+    {
+        return format!("did you mean \"{x}\"?");
+    }
+    match path.split_once('.') {
+        Some((top, _)) if top != module && !is_glob(top) => {
+            format!("the top module is \"{module}\"")
+        }
+        _ => "check the path of the signal".to_string(),
+    }
+}
+
+// 編集距離が最も近い信号名
+fn suggest<'a>(name: &str, names: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    names
+        .map(|x| (distance(name, x), x))
+        .filter(|(d, _)| *d <= MAX_DISTANCE && *d < name.chars().count())
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, x)| x)
+}
+
+// レーベンシュタイン距離
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, x) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, y) in b.iter().enumerate() {
+            let next = (diagonal + usize::from(x != *y))
+                .min(row[j] + 1)
+                .min(row[j + 1] + 1);
+            diagonal = row[j + 1];
+            row[j + 1] = next;
+        }
+    }
+    row[b.len()]
+}
+
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    // 最後の '*' の位置から照合し直すバックトラック方式
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((sp, st)) = star {
+            p = sp + 1;
+            t = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|x| *x == '*')
+}
@@ -0,0 +1,524 @@
+use crate::hooks::CoverageHook;
+use crate::time::Instant;
+use crate::{AssertSeverity, CoverageDb, Model, ModelError, Simulator, Stimulus, StopReason};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+// 周期が指定されていないクロックの周期 [ns]
+const DEFAULT_PERIOD: u64 = 10;
+
+// 子プロセスで実行するシナリオの番号と結果の出力先
+const SCENARIO_ENV: &str = "VERYL_REGRESSION_SCENARIO";
+const RESULT_ENV: &str = "VERYL_REGRESSION_RESULT";
+
+// 同じプロセスの中で結果のファイル名が重ならないようにする連番
+static RESULT_ID: AtomicUsize = AtomicUsize::new(0);
+
+type Setup = Box<dyn Fn(&mut Simulator, u64) -> Result<(), ModelError> + Send + Sync>;
+type Check = Box<dyn Fn(&Simulator) -> Result<(), String> + Send + Sync>;
+
+enum Design {
+    Source(String),
+    Files(Vec<PathBuf>),
+}
+
+/// Named simulation run of a regression
+///
+/// The design is elaborated from the source text or files in each run, so scenarios can run
+/// in parallel. The simulation is reset, then the stimulus is applied and run for `time`.
+/// The scenario fails if a check returns an error, an assertion fails, or the simulation
+/// fails or is aborted.
+pub struct Scenario {
+    name: String,
+    top: String,
+    design: Option<Design>,
+    clocks: HashMap<String, u64>,
+    reset_cycles: usize,
+    time: u64,
+    stimulus: Option<Stimulus>,
+    seed: Option<u64>,
+    setups: Vec<Setup>,
+    checks: Vec<Check>,
+}
+
+impl Scenario {
+    pub fn new(name: &str, top: &str) -> Self {
+        Scenario {
+            name: name.to_string(),
+            top: top.to_string(),
+            design: None,
+            clocks: HashMap::new(),
+            reset_cycles: 0,
+            time: 0,
+            stimulus: None,
+            seed: None,
+            setups: Vec::new(),
+            checks: Vec::new(),
+        }
+    }
+
+    /// Design in Veryl source text
+    pub fn source(mut self, source: &str) -> Self {
+        self.design = Some(Design::Source(source.to_string()));
+        self
+    }
+
+    /// Design in Veryl source files
+    pub fn files<T: AsRef<Path>>(mut self, paths: &[T]) -> Self {
+        let paths = paths.iter().map(|x| x.as_ref().to_path_buf()).collect();
+        self.design = Some(Design::Files(paths));
+        self
+    }
+
+    /// Period of the clock, the other clocks are driven at 10ns
+    pub fn clock(mut self, name: &str, period_ns: u64) -> Self {
+        self.clocks.insert(name.to_string(), period_ns);
+        self
+    }
+
+    pub fn reset_cycles(mut self, cycles: usize) -> Self {
+        self.reset_cycles = cycles;
+        self
+    }
+
+    /// Simulation time after the reset
+    pub fn time(mut self, duration_ns: u64) -> Self {
+        self.time = duration_ns;
+        self
+    }
+
+    pub fn stimulus(mut self, stimulus: Stimulus) -> Self {
+        self.stimulus = Some(stimulus);
+        self
+    }
+
+    /// Seed passed to the setups, the seed of the regression is used if it's not specified
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Prepare the simulator before the reset, e.g. add a `RandomDriver` with the seed
+    pub fn setup<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&mut Simulator, u64) -> Result<(), ModelError> + Send + Sync + 'static,
+    {
+        self.setups.push(Box::new(f));
+        self
+    }
+
+    /// Check the simulator after the run, an error is reported as a failure of the scenario
+    pub fn check<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&Simulator) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.checks.push(Box::new(f));
+        self
+    }
+
+    fn result(&self, seed: u64) -> ScenarioResult {
+        ScenarioResult {
+            name: self.name.clone(),
+            passed: false,
+            seed,
+            time: 0,
+            runtime: Duration::ZERO,
+            failures: Vec::new(),
+            coverage: None,
+        }
+    }
+
+    fn run(&self, seed: u64, coverage: bool) -> ScenarioResult {
+        let started = Instant::now();
+        let mut result = self.result(seed);
+
+        // チェック中の panic も失敗として記録する
+        let run = panic::catch_unwind(AssertUnwindSafe(|| {
+            self.simulate(seed, coverage, &mut result)
+        }));
+        match run {
+            Ok(Ok(())) => (),
+            Ok(Err(x)) => result.failures.push(x.to_string()),
+            Err(x) => {
+                let message = x
+                    .downcast_ref::<&str>()
+                    .map(|x| x.to_string())
+                    .or_else(|| x.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "unknown panic".to_string());
+                result.failures.push(format!("panicked: {message}"));
+            }
+        }
+        result.passed = result.failures.is_empty();
+        result.runtime = started.elapsed();
+        result
+    }
+
+    fn simulate(
+        &self,
+        seed: u64,
+        coverage: bool,
+        result: &mut ScenarioResult,
+    ) -> Result<(), ModelError> {
+        let model = match &self.design {
+            Some(Design::Source(x)) => Model::from_source(x, &self.top, HashMap::new())?,
+            Some(Design::Files(x)) => Model::from_files(x, &self.top, HashMap::new())?,
+            None => {
+                return Err(ModelError::InvalidMetadata(format!(
+                    "design of scenario \"{}\" is not specified",
+                    self.name
+                )));
+            }
+        };
+
+        for name in self.clocks.keys() {
+            if !model.clocks().iter().any(|(x, _)| x == name) {
+                return Err(ModelError::InvalidClock {
+                    name: name.clone(),
+                    reason: "the signal is not a clock of the model".to_string(),
+                });
+            }
+        }
+        let clocks: Vec<_> = model
+            .clocks()
+            .iter()
+            .map(|(name, _)| {
+                let period = self.clocks.get(name).copied();
+                (name.clone(), period.unwrap_or(DEFAULT_PERIOD))
+            })
+            .collect();
+
+        let mut builder = Simulator::builder(model).reset_cycles(self.reset_cycles);
+        for (name, period) in clocks {
+            builder = builder.clock(&name, period);
+        }
+        let hook = CoverageHook::new();
+        let report = hook.report();
+        if coverage {
+            builder = builder.hook(Box::new(hook));
+        }
+        let mut simulator = builder.build()?;
+
+        for setup in &self.setups {
+            setup(&mut simulator, seed)?;
+        }
+        simulator.reset();
+        if let Some(x) = &self.stimulus {
+            simulator.apply_stimulus(x)?;
+        }
+        let reason = simulator.run(self.time);
+        result.time = simulator.time();
+
+        match reason {
+            StopReason::Aborted => result.failures.push("aborted".to_string()),
+            StopReason::Failed(x) => result.failures.push(x.to_string()),
+            _ => (),
+        }
+        for failure in simulator.model().assertion_failures() {
+            if failure.severity != AssertSeverity::Warning {
+                result.failures.push(failure.to_string());
+            }
+        }
+        for check in &self.checks {
+            if let Err(x) = check(&simulator) {
+                result.failures.push(x);
+            }
+        }
+
+        if coverage && let Ok(report) = report.lock() {
+            let mut db = CoverageDb::new(&self.name);
+            db.add_code(&report);
+            result.coverage = Some(db);
+        }
+        Ok(())
+    }
+}
+
+/// Result of a scenario
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScenarioResult {
+    pub name: String,
+    pub passed: bool,
+    pub seed: u64,
+    /// Simulation time at the end of the run
+    pub time: u64,
+    /// Wall clock time of the run including the elaboration
+    pub runtime: Duration,
+    /// Messages of the failed checks and assertions
+    pub failures: Vec<String>,
+    /// Code coverage if it's enabled in the regression
+    pub coverage: Option<CoverageDb>,
+}
+
+/// Aggregated results of a regression
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegressionReport {
+    /// Results in the order the scenarios were added
+    pub results: Vec<ScenarioResult>,
+    /// Wall clock time of the whole regression
+    pub runtime: Duration,
+    /// Coverage merged over the scenarios if it's enabled
+    pub coverage: Option<CoverageDb>,
+}
+
+impl RegressionReport {
+    pub fn passed(&self) -> usize {
+        self.results.iter().filter(|x| x.passed).count()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.results.len() - self.passed()
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.results.iter().all(|x| x.passed)
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &ScenarioResult> {
+        self.results.iter().filter(|x| !x.passed)
+    }
+}
+
+impl fmt::Display for RegressionReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let width = self.results.iter().map(|x| x.name.len()).max().unwrap_or(0);
+        for x in &self.results {
+            let status = if x.passed { "PASS" } else { "FAIL" };
+            writeln!(
+                f,
+                "{status} {:width$} seed={} {}ns {:.3}s",
+                x.name,
+                x.seed,
+                x.time,
+                x.runtime.as_secs_f64()
+            )?;
+            for failure in &x.failures {
+                writeln!(f, "     {failure}")?;
+            }
+        }
+        write!(
+            f,
+            "{} scenarios, {} passed, {} failed ({:.3}s)",
+            self.results.len(),
+            self.passed(),
+            self.failed(),
+            self.runtime.as_secs_f64()
+        )?;
+        if let Some(x) = &self.coverage {
+            let (statements, total_statements) = x.code.statements();
+            let (branches, total_branches) = x.code.branches();
+            let (toggles, total_toggles) = x.code.toggles();
+            write!(
+                f,
+                "\ncoverage: statements {statements}/{total_statements}, \
+                 branches {branches}/{total_branches}, toggles {toggles}/{total_toggles}"
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Runner of named scenarios, which aggregates the pass/fail, runtime, seed and coverage
+///
+/// Each scenario elaborates its own design. By default the scenarios run in worker threads
+/// of the current process, where a panic is caught but an abort or a stack overflow stops the
+/// whole regression. With `processes` each scenario runs in a child process instead.
+pub struct Regression {
+    scenarios: Vec<Scenario>,
+    jobs: usize,
+    seed: u64,
+    coverage: bool,
+    processes: bool,
+}
+
+impl Default for Regression {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Regression {
+    pub fn new() -> Self {
+        Regression {
+            scenarios: Vec::new(),
+            jobs: 1,
+            seed: 0,
+            coverage: false,
+            processes: false,
+        }
+    }
+
+    pub fn scenario(mut self, scenario: Scenario) -> Self {
+        self.scenarios.push(scenario);
+        self
+    }
+
+    /// Number of scenarios run at the same time
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.jobs = jobs.max(1);
+        self
+    }
+
+    /// Base seed, the scenario without its own seed gets the base seed plus its index
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Collect code coverage of each scenario and merge them into the report
+    pub fn coverage(mut self, enable: bool) -> Self {
+        self.coverage = enable;
+        self
+    }
+
+    /// Run each scenario in a child process, which re-executes the current executable
+    ///
+    /// The child runs the same code up to `run` and reports the result of its scenario there,
+    /// so the regression must be built the same way in each run. In a test, the child runs only
+    /// the current test, which is selected by the name of the test thread.
+    pub fn processes(mut self, enable: bool) -> Self {
+        self.processes = enable;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.scenarios.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scenarios.is_empty()
+    }
+
+    pub fn run(&self) -> RegressionReport {
+        // 子プロセスではシナリオを 1 つだけ実行して結果を書き出す
+        if let Some(index) = std::env::var(SCENARIO_ENV)
+            .ok()
+            .and_then(|x| x.parse::<usize>().ok())
+            && let Ok(path) = std::env::var(RESULT_ENV)
+        {
+            let code = match self.scenarios.get(index) {
+                Some(scenario) => {
+                    let result = scenario.run(self.seed_of(index), self.coverage);
+                    let json = serde_json::to_string(&result).unwrap_or_default();
+                    if std::fs::write(path, json).is_ok() {
+                        0
+                    } else {
+                        1
+                    }
+                }
+                None => 1,
+            };
+            std::process::exit(code);
+        }
+
+        let started = Instant::now();
+        let next = Mutex::new(0);
+        let results = Mutex::new(Vec::new());
+
+        // テストのスレッドにはテスト名が付いているので、子プロセスではそのテストだけを実行させる
+        let thread = std::thread::current();
+        let args: Vec<String> = match thread.name() {
+            Some(name) if name != "main" => vec![name.to_string(), "--exact".to_string()],
+            _ => std::env::args().skip(1).collect(),
+        };
+
+        let worker = || {
+            loop {
+                let index = {
+                    let mut next = next.lock().unwrap();
+                    *next += 1;
+                    *next - 1
+                };
+                let Some(scenario) = self.scenarios.get(index) else {
+                    break;
+                };
+                let seed = self.seed_of(index);
+                let result = if self.processes {
+                    self.run_process(index, seed, &args)
+                } else {
+                    scenario.run(seed, self.coverage)
+                };
+                results.lock().unwrap().push((index, result));
+            }
+        };
+        let jobs = self.jobs.min(self.scenarios.len());
+        if jobs > 1 {
+            std::thread::scope(|s| {
+                for _ in 0..jobs {
+                    s.spawn(worker);
+                }
+            });
+        } else {
+            worker();
+        }
+
+        let mut results = results.into_inner().unwrap();
+        results.sort_by_key(|(x, _)| *x);
+        let results: Vec<_> = results.into_iter().map(|(_, x)| x).collect();
+
+        let coverage = self.coverage.then(|| {
+            let mut db = CoverageDb::default();
+            for x in results.iter().filter_map(|x| x.coverage.as_ref()) {
+                db.merge(x);
+            }
+            db
+        });
+
+        RegressionReport {
+            results,
+            runtime: started.elapsed(),
+            coverage,
+        }
+    }
+
+    fn seed_of(&self, index: usize) -> u64 {
+        self.scenarios[index]
+            .seed
+            .unwrap_or_else(|| self.seed.wrapping_add(index as u64))
+    }
+
+    fn run_process(&self, index: usize, seed: u64, args: &[String]) -> ScenarioResult {
+        let started = Instant::now();
+        let mut result = self.scenarios[index].result(seed);
+        let path = std::env::temp_dir().join(format!(
+            "veryl-regression-{}-{}.json",
+            std::process::id(),
+            RESULT_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let status = std::env::current_exe().and_then(|exe| {
+            Command::new(exe)
+                .args(args)
+                .env(SCENARIO_ENV, index.to_string())
+                .env(RESULT_ENV, &path)
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+        });
+        let output = std::fs::read_to_string(&path);
+        let _ = std::fs::remove_file(&path);
+
+        match status {
+            Ok(x) if x.success() => {
+                match output.map(|x| serde_json::from_str::<ScenarioResult>(&x)) {
+                    Ok(Ok(x)) => return x,
+                    _ => result
+                        .failures
+                        .push("process exited without the result".to_string()),
+                }
+            }
+            Ok(x) => result.failures.push(format!("process exited with {x}")),
+            Err(x) => result
+                .failures
+                .push(format!("process failed to start: {x}")),
+        }
+        result.runtime = started.elapsed();
+        result
+    }
+}
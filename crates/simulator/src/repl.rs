@@ -0,0 +1,432 @@
+use crate::stimulus::parse_value;
+use crate::time::parse_time;
+use crate::{BreakHit, BreakPoint, Direction, HookId, SignalFilter, Simulator, StopReason};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+const HELP: &str = "\
+step [n]              process the next n events
+run <time>            run for the time, e.g. 100, 100ns, 2us
+cycles <n> [clock]    run n cycles of the clock
+set <port> <value>    drive an input port, e.g. 5, 0x1f, 0b101
+print [pattern...]    print signals matching glob patterns, e.g. top.*
+signals               list signals with direction and width
+break <condition>     break on q, q==3, q!=3, q<3, q<=3, q>3 or q>=3
+breaks                list breakpoints
+delete <n>            delete a breakpoint
+reset [cycles]        reset the design
+time                  print the current time
+history               list the command history, recall with !! or !n
+quit                  exit";
+
+// 比較演算子（長いものから照合する）
+const OPERATORS: [&str; 6] = ["==", "!=", "<=", ">=", "<", ">"];
+
+struct Break {
+    number: usize,
+    condition: String,
+    id: HookId,
+    hits: Arc<Mutex<Vec<BreakHit>>>,
+}
+
+/// Result of a command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    Continue,
+    Quit,
+}
+
+/// Interactive command line to poke at a design without writing a test, e.g.
+///
+/// ```text
+/// > set d 5
+/// > break q==3
+/// > run 100ns
+/// > print top.*
+/// ```
+///
+/// Type `help` for the commands. Breakpoints are checked at clock edges like `BreakPoint`.
+/// The input is read line by line without line editing, so arrow keys don't move the cursor
+/// or recall commands. Use `!!` and `!n` to recall commands from the history, or run the
+/// REPL under a line editor wrapper such as `rlwrap`.
+pub struct Repl {
+    simulator: Simulator,
+    breaks: Vec<Break>,
+    next_break: usize,
+    history: Vec<String>,
+    history_file: Option<PathBuf>,
+    prompt: String,
+}
+
+impl Repl {
+    pub fn new(simulator: Simulator) -> Self {
+        Repl {
+            simulator,
+            breaks: Vec::new(),
+            next_break: 1,
+            history: Vec::new(),
+            history_file: None,
+            prompt: "> ".to_string(),
+        }
+    }
+
+    /// Load the history from the file, and append commands to it
+    /// the file is created if it doesn't exist
+    pub fn history_file<T: AsRef<Path>>(mut self, path: T) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        if path.exists() {
+            let file = io::BufReader::new(File::open(&path)?);
+            for line in file.lines() {
+                self.history.push(line?);
+            }
+        }
+        self.history_file = Some(path);
+        Ok(self)
+    }
+
+    /// Prompt printed before each command, "> " by default
+    pub fn prompt(mut self, prompt: &str) -> Self {
+        self.prompt = prompt.to_string();
+        self
+    }
+
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    pub fn simulator(&self) -> &Simulator {
+        &self.simulator
+    }
+
+    pub fn simulator_mut(&mut self) -> &mut Simulator {
+        &mut self.simulator
+    }
+
+    pub fn into_simulator(self) -> Simulator {
+        self.simulator
+    }
+
+    /// Read commands line by line until "quit" or the end of the input
+    pub fn run(&mut self, mut input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        loop {
+            write!(output, "{}", self.prompt)?;
+            output.flush()?;
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                return Ok(());
+            }
+            if self.execute(&line, &mut output)? == Control::Quit {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Execute a command line, errors of the command are printed to the output
+    pub fn execute(&mut self, line: &str, output: &mut impl Write) -> io::Result<Control> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(Control::Continue);
+        }
+
+        let line = match self.recall(line) {
+            Ok(x) => x,
+            Err(x) => {
+                writeln!(output, "error: {x}")?;
+                return Ok(Control::Continue);
+            }
+        };
+        if line != self.history.last().map(|x| x.as_str()).unwrap_or_default() {
+            self.push_history(&line)?;
+        }
+
+        let words: Vec<_> = line.split_whitespace().collect();
+        let result = match words[0] {
+            "quit" | "exit" | "q" => return Ok(Control::Quit),
+            "help" | "h" => {
+                writeln!(output, "{HELP}")?;
+                Ok(())
+            }
+            "step" | "s" => self.step(&words[1..], output),
+            "run" | "r" => self.run_for(&words[1..], output),
+            "cycles" | "c" => self.cycles(&words[1..], output),
+            "set" => self.set(&words[1..]),
+            "print" | "p" => self.print(&words[1..], output),
+            "signals" => self.signals(output),
+            "break" | "b" => self.add_break(&words[1..], output),
+            "breaks" => self.list_breaks(output),
+            "delete" | "d" => self.delete_break(&words[1..]),
+            "reset" => self.reset(&words[1..], output),
+            "time" | "t" => {
+                writeln!(output, "time {}ns", self.simulator.time())?;
+                Ok(())
+            }
+            "history" => {
+                for (i, x) in self.history.iter().enumerate() {
+                    writeln!(output, "{:4}  {x}", i + 1)?;
+                }
+                Ok(())
+            }
+            x => Err(format!("unknown command \"{x}\", see \"help\"")),
+        };
+        match result {
+            Ok(_) => {}
+            Err(x) => writeln!(output, "error: {x}")?,
+        }
+        Ok(Control::Continue)
+    }
+
+    // !! と !n を履歴の命令に置き換える
+    fn recall(&self, line: &str) -> Result<String, String> {
+        let Some(index) = line.strip_prefix('!') else {
+            return Ok(line.to_string());
+        };
+        let entry = if index == "!" {
+            self.history.last()
+        } else {
+            index
+                .parse::<usize>()
+                .ok()
+                .and_then(|x| x.checked_sub(1))
+                .and_then(|x| self.history.get(x))
+        };
+        entry
+            .cloned()
+            .ok_or_else(|| format!("history \"{line}\" is not found"))
+    }
+
+    fn push_history(&mut self, line: &str) -> io::Result<()> {
+        self.history.push(line.to_string());
+        if let Some(path) = &self.history_file {
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            writeln!(file, "{line}")?;
+        }
+        Ok(())
+    }
+
+    fn step(&mut self, args: &[&str], output: &mut impl Write) -> Result<(), String> {
+        let n = match args.first() {
+            Some(x) => parse_count(x)?,
+            None => 1,
+        };
+        for _ in 0..n {
+            let Some(event) = self.simulator.step() else {
+                self.report_stop(None, output)?;
+                return Ok(());
+            };
+            let clock = event.clock.as_deref().unwrap_or("stimulus");
+            writeln!(output, "time {}ns: {clock}", event.time).map_err(|x| x.to_string())?;
+            if self.report_hits(output)? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn run_for(&mut self, args: &[&str], output: &mut impl Write) -> Result<(), String> {
+        let [duration] = args else {
+            return Err("usage: run <time>".to_string());
+        };
+        let duration =
+            parse_time(duration).ok_or_else(|| format!("invalid time \"{duration}\""))?;
+        let reason = self.simulator.run(duration);
+        self.report_stop(Some(reason), output)
+    }
+
+    fn cycles(&mut self, args: &[&str], output: &mut impl Write) -> Result<(), String> {
+        let (n, clock) = match args {
+            [n] => {
+                let Some((clock, _)) = self.simulator.model().clocks().first() else {
+                    return Err("the design has no clocks".to_string());
+                };
+                (parse_count(n)?, clock.clone())
+            }
+            [n, clock] => (parse_count(n)?, clock.to_string()),
+            _ => return Err("usage: cycles <n> [clock]".to_string()),
+        };
+        let reason = self
+            .simulator
+            .run_cycles(&clock, n)
+            .map_err(|x| x.to_string())?;
+        self.report_stop(Some(reason), output)
+    }
+
+    fn set(&mut self, args: &[&str]) -> Result<(), String> {
+        let [port, value] = args else {
+            return Err("usage: set <port> <value>".to_string());
+        };
+        let value = parse_value(value).ok_or_else(|| format!("invalid value \"{value}\""))?;
+        self.simulator
+            .model_mut()
+            .try_input(port, value)
+            .map_err(|x| x.to_string())
+    }
+
+    fn print(&mut self, args: &[&str], output: &mut impl Write) -> Result<(), String> {
+        let model = self.simulator.model();
+        // 配列の要素も含めて名前で直接指定できる
+        if let [name] = args
+            && !name.contains(['*', '?', '!'])
+            && let Some(value) = model.peek(name)
+        {
+            writeln!(output, "{name} = {value} (0x{value:x})").map_err(|x| x.to_string())?;
+            return Ok(());
+        }
+
+        let filter = SignalFilter::new(args);
+        let mut found = false;
+        for signal in model.signals() {
+            if filter.matches(model.module_name(), &signal.name) {
+                let value = signal.value;
+                writeln!(output, "{} = {value} (0x{value:x})", signal.name)
+                    .map_err(|x| x.to_string())?;
+                found = true;
+            }
+        }
+        if !found {
+            return Err(format!("no signals match \"{}\"", args.join(" ")));
+        }
+        Ok(())
+    }
+
+    fn signals(&mut self, output: &mut impl Write) -> Result<(), String> {
+        for signal in self.simulator.model().signals() {
+            let direction = match signal.direction {
+                Direction::Input => "input",
+                Direction::Output => "output",
+                Direction::Internal => "internal",
+            };
+            writeln!(
+                output,
+                "{:8} {:24} {}bit",
+                direction, signal.name, signal.width
+            )
+            .map_err(|x| x.to_string())?;
+        }
+        Ok(())
+    }
+
+    fn add_break(&mut self, args: &[&str], output: &mut impl Write) -> Result<(), String> {
+        let condition = args.concat();
+        if condition.is_empty() {
+            return Err("usage: break <condition>".to_string());
+        }
+
+        let operator = OPERATORS
+            .iter()
+            .find_map(|x| condition.find(x).map(|i| (i, *x)));
+        let (signal, hook) = match operator {
+            None => (
+                condition.clone(),
+                BreakPoint::new().when_changes(&condition),
+            ),
+            Some((i, operator)) => {
+                let signal = &condition[..i];
+                let text = &condition[i + operator.len()..];
+                let value = parse_value(text).ok_or_else(|| format!("invalid value \"{text}\""))?;
+                let hook = match operator {
+                    "==" => BreakPoint::new().when_equals(signal, value),
+                    _ => {
+                        let compare = comparison(operator);
+                        BreakPoint::new().when(&[signal], move |x| compare(x[0], value))
+                    }
+                };
+                (signal.to_string(), hook)
+            }
+        };
+        if self.simulator.model().peek(&signal).is_none() {
+            return Err(format!("signal \"{signal}\" is not found"));
+        }
+
+        let hits = hook.hits();
+        let id = self.simulator.add_hook(Box::new(hook));
+        let number = self.next_break;
+        self.next_break += 1;
+        writeln!(output, "breakpoint {number}: {condition}").map_err(|x| x.to_string())?;
+        self.breaks.push(Break {
+            number,
+            condition,
+            id,
+            hits,
+        });
+        Ok(())
+    }
+
+    fn list_breaks(&mut self, output: &mut impl Write) -> Result<(), String> {
+        for x in &self.breaks {
+            writeln!(output, "{:4}  {}", x.number, x.condition).map_err(|x| x.to_string())?;
+        }
+        Ok(())
+    }
+
+    fn delete_break(&mut self, args: &[&str]) -> Result<(), String> {
+        let [number] = args else {
+            return Err("usage: delete <n>".to_string());
+        };
+        let number = parse_count(number)?;
+        let Some(index) = self.breaks.iter().position(|x| x.number == number) else {
+            return Err(format!("breakpoint {number} is not found"));
+        };
+        let entry = self.breaks.remove(index);
+        self.simulator.remove_hook(entry.id);
+        Ok(())
+    }
+
+    fn reset(&mut self, args: &[&str], output: &mut impl Write) -> Result<(), String> {
+        match args {
+            [] => self.simulator.reset(),
+            [n] => self.simulator.reset_for_cycles(parse_count(n)?),
+            _ => return Err("usage: reset [cycles]".to_string()),
+        }
+        writeln!(output, "time {}ns", self.simulator.time()).map_err(|x| x.to_string())
+    }
+
+    // 停止の理由と、成立したブレークポイントを表示する
+    fn report_stop(
+        &mut self,
+        reason: Option<StopReason>,
+        output: &mut impl Write,
+    ) -> Result<(), String> {
+        let time = self.simulator.time();
+        let message = match reason {
+            Some(StopReason::Completed) => format!("time {time}ns"),
+            Some(StopReason::Paused) => format!("paused at {time}ns"),
+            Some(StopReason::Aborted) => format!("aborted at {time}ns"),
+            Some(StopReason::Finished(x)) => format!("finished at {time}ns: {x}"),
+            Some(StopReason::Failed(x)) => format!("failed at {time}ns: {x}"),
+            None => format!("no events at {time}ns"),
+        };
+        writeln!(output, "{message}").map_err(|x| x.to_string())?;
+        self.report_hits(output)?;
+        Ok(())
+    }
+
+    // 成立したブレークポイントを表示する、成立していれば true
+    fn report_hits(&mut self, output: &mut impl Write) -> Result<bool, String> {
+        let mut hit = false;
+        for entry in &self.breaks {
+            let hits = std::mem::take(&mut *entry.hits.lock().unwrap());
+            for x in hits {
+                writeln!(output, "breakpoint {}: {x}", entry.number).map_err(|x| x.to_string())?;
+                hit = true;
+            }
+        }
+        Ok(hit)
+    }
+}
+
+fn comparison(operator: &str) -> fn(usize, usize) -> bool {
+    match operator {
+        "!=" => |x, y| x != y,
+        "<=" => |x, y| x <= y,
+        ">=" => |x, y| x >= y,
+        "<" => |x, y| x < y,
+        _ => |x, y| x > y,
+    }
+}
+
+fn parse_count(text: &str) -> Result<usize, String> {
+    text.parse()
+        .map_err(|_| format!("invalid number \"{text}\""))
+}
@@ -0,0 +1,166 @@
+use crate::stimulus::parse_value;
+use crate::{Direction, Model, ModelError};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+use std::path::Path;
+
+/// Input applied during a recorded run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayRow {
+    pub time: u64,
+    /// Clock events already processed at the time when the input was applied
+    /// 0 means the input was applied before the clock edges at the time
+    pub events: usize,
+    pub signal: String,
+    pub value: usize,
+}
+
+/// Inputs recorded by `Simulator::start_recording`, and re-applied by `Simulator::replay`
+/// the text format is CSV with `time, events, signal, value` columns
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Replay {
+    rows: Vec<ReplayRow>,
+}
+
+impl Replay {
+    /// Parse replay text
+    pub fn parse(text: &str) -> Result<Self, ModelError> {
+        Self::parse_with_path(text, "<replay>")
+    }
+
+    /// Read a replay file
+    pub fn from_file<T: AsRef<Path>>(path: T) -> Result<Self, ModelError> {
+        let path = path.as_ref().to_string_lossy().to_string();
+        let text = std::fs::read_to_string(&path).map_err(|x| ModelError::ReadFailed {
+            path: path.clone(),
+            cause: x.to_string(),
+        })?;
+        Self::parse_with_path(&text, &path)
+    }
+
+    /// Write the replay file
+    pub fn save<T: AsRef<Path>>(&self, path: T) -> std::io::Result<()> {
+        std::fs::write(path, self.to_csv())
+    }
+
+    /// Rows in the order of application
+    pub fn rows(&self) -> &[ReplayRow] {
+        &self.rows
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut text = String::from("time,events,signal,value\n");
+        for row in &self.rows {
+            let _ = writeln!(
+                text,
+                "{},{},{},{}",
+                row.time, row.events, row.signal, row.value
+            );
+        }
+        text
+    }
+
+    fn parse_with_path(text: &str, path: &str) -> Result<Self, ModelError> {
+        let error = |line: usize, cause: &str| ModelError::ParseFailed {
+            path: path.to_string(),
+            cause: format!("line {line}: {cause}"),
+        };
+
+        let mut rows = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields: Vec<_> = line
+                .split(|x: char| x == ',' || x.is_whitespace())
+                .filter(|x| !x.is_empty())
+                .collect();
+            if fields.len() != 4 {
+                return Err(error(i + 1, "expected `time, events, signal, value`"));
+            }
+            if rows.is_empty() && fields[0] == "time" {
+                continue;
+            }
+
+            let time = fields[0]
+                .parse()
+                .map_err(|_| error(i + 1, &format!("invalid time \"{}\"", fields[0])))?;
+            let events = fields[1]
+                .parse()
+                .map_err(|_| error(i + 1, &format!("invalid events \"{}\"", fields[1])))?;
+            let value = parse_value(fields[3])
+                .ok_or_else(|| error(i + 1, &format!("invalid value \"{}\"", fields[3])))?;
+            rows.push(ReplayRow {
+                time,
+                events,
+                signal: fields[2].to_string(),
+                value,
+            });
+        }
+
+        // sort is stable, so rows at the same point keep the order
+        rows.sort_by_key(|x| (x.time, x.events));
+        Ok(Replay { rows })
+    }
+}
+
+// 入力の変化を記録する
+// 最初の記録ではすべての入力の現在値を記録する
+pub(crate) struct InputRecorder {
+    replay: Replay,
+    last: HashMap<String, usize>,
+}
+
+impl InputRecorder {
+    pub(crate) fn new() -> Self {
+        InputRecorder {
+            replay: Replay::default(),
+            last: HashMap::new(),
+        }
+    }
+
+    // クロックを除く入力のうち、前回から変化したものを記録する
+    pub(crate) fn record(
+        &mut self,
+        time: u64,
+        events: usize,
+        model: &Model,
+        clocks: &HashSet<&String>,
+    ) {
+        let inputs: Vec<_> = model
+            .signals()
+            .filter(|x| x.direction == Direction::Input && !clocks.contains(&x.name))
+            .collect();
+        for input in inputs {
+            let Some(value) = model.peek(&input.name) else {
+                continue;
+            };
+            if self.last.get(&input.name) != Some(&value) {
+                self.last.insert(input.name.clone(), value);
+                self.replay.rows.push(ReplayRow {
+                    time,
+                    events,
+                    signal: input.name,
+                    value,
+                });
+            }
+        }
+    }
+
+    // 位置より後の記録を捨て、最後に記録した値をその時点の値に戻す
+    pub(crate) fn truncate(&mut self, position: (u64, usize)) {
+        self.replay.rows.retain(|x| (x.time, x.events) <= position);
+        self.last = self
+            .replay
+            .rows
+            .iter()
+            .map(|x| (x.signal.clone(), x.value))
+            .collect();
+    }
+
+    pub(crate) fn replay(&self) -> &Replay {
+        &self.replay
+    }
+}
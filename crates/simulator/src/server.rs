@@ -0,0 +1,428 @@
+use crate::{Hook, HookAction, HookError, Model, ModelError, RunResult, Simulator, StopReason};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use std::collections::{HashMap, HashSet};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+// JSON-RPC のエラーコード
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SIMULATION_ERROR: i64 = -32000;
+
+// 設計を読み込む前に使うクロックの周期 [ns]
+const DEFAULT_PERIOD: u64 = 10;
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<ModelError> for RpcError {
+    fn from(x: ModelError) -> Self {
+        RpcError::new(SIMULATION_ERROR, x.to_string())
+    }
+}
+
+#[derive(Deserialize)]
+struct LoadParams {
+    source: Option<String>,
+    #[serde(default)]
+    files: Vec<PathBuf>,
+    top: String,
+    #[serde(default)]
+    clocks: HashMap<String, u64>,
+    period: Option<u64>,
+    #[serde(default)]
+    init: HashMap<String, usize>,
+}
+
+#[derive(Deserialize)]
+struct SetParams {
+    port: String,
+    value: usize,
+}
+
+#[derive(Deserialize)]
+struct SignalParams {
+    signal: String,
+}
+
+#[derive(Deserialize)]
+struct ResetParams {
+    cycles: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct RunParams {
+    duration: u64,
+}
+
+#[derive(Deserialize)]
+struct RunCyclesParams {
+    clock: String,
+    cycles: usize,
+}
+
+#[derive(Deserialize)]
+struct RunUntilParams {
+    signal: String,
+    value: usize,
+    timeout: u64,
+}
+
+#[derive(Deserialize)]
+struct WatchParams {
+    signals: Vec<String>,
+}
+
+// 監視中の信号と、まだ通知していない変化
+#[derive(Default)]
+struct Watch {
+    signals: HashSet<String>,
+    changes: Vec<Value>,
+}
+
+// 監視中の信号の変化を記録するフック
+struct ChangeStream(Arc<Mutex<Watch>>);
+
+impl ChangeStream {
+    fn record(&self, time: u64, name: &str, old: usize, new: usize) {
+        if let Ok(mut watch) = self.0.lock()
+            && watch.signals.contains(name)
+        {
+            watch.changes.push(json!({
+                "time": time,
+                "signal": name,
+                "old": old,
+                "new": new,
+            }));
+        }
+    }
+}
+
+impl Hook for ChangeStream {
+    fn on_input_change(
+        &mut self,
+        time: u64,
+        name: &str,
+        old: usize,
+        new: usize,
+    ) -> Result<HookAction, HookError> {
+        self.record(time, name, old, new);
+        Ok(HookAction::Continue)
+    }
+
+    fn on_signal_change(
+        &mut self,
+        time: u64,
+        name: &str,
+        old: usize,
+        new: usize,
+    ) -> Result<HookAction, HookError> {
+        self.record(time, name, old, new);
+        Ok(HookAction::Continue)
+    }
+}
+
+// Remote control of a simulation with JSON-RPC 2.0, one request or response per line
+// methods:
+//   load      {source | files, top, clocks?, period?, init?} -> {top, signals}
+//   reset     {cycles?} -> {time}
+//   set       {port, value} -> null
+//   get       {signal} -> value
+//   signals   -> [{name, direction, width, value}]
+//   time      -> time [ns]
+//   step      -> {time, clock} or null if there are no events
+//   run       {duration} -> {time, reason}
+//   run_cycles {clock, cycles} -> {time, reason}
+//   run_until {signal, value, timeout} -> {time, reason}, reason is "satisfied" or "timeout" too
+//   watch     {signals} / unwatch {signals} -> null, signals are paths or globs like "regs[*]"
+//   shutdown  -> null, stops the server after the response
+// changes of the watched signals are sent as "change" notifications with {time, signal, old, new}
+// before the response of the request which caused them
+pub struct Server {
+    simulator: Option<Simulator>,
+    watch: Arc<Mutex<Watch>>,
+    shutdown: bool,
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Server {
+    /// The design is loaded by the client with the "load" method
+    pub fn new() -> Self {
+        Server {
+            simulator: None,
+            watch: Arc::new(Mutex::new(Watch::default())),
+            shutdown: false,
+        }
+    }
+
+    /// Serve the simulator built by the host
+    pub fn with_simulator(simulator: Simulator) -> Self {
+        let mut server = Self::new();
+        server.attach(simulator);
+        server
+    }
+
+    /// Accept connections one at a time until a client calls "shutdown"
+    pub fn listen(&mut self, listener: &TcpListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let reader = BufReader::new(stream.try_clone()?);
+            // 接続ごとのエラーでサーバーは止めない
+            let _ = self.serve(reader, stream);
+            if self.shutdown {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Serve requests from the reader until it's closed or a client calls "shutdown"
+    pub fn serve(&mut self, reader: impl BufRead, mut writer: impl Write) -> io::Result<()> {
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            for message in self.handle(&line) {
+                writeln!(writer, "{message}")?;
+            }
+            writer.flush()?;
+            if self.shutdown {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Handle a request line, and return the notifications and the response to be sent
+    /// notifications have no response
+    pub fn handle(&mut self, request: &str) -> Vec<String> {
+        let (id, result) = match serde_json::from_str::<Value>(request) {
+            Ok(request) => {
+                let id = request.get("id").cloned();
+                (id, self.dispatch(&request))
+            }
+            Err(x) => (
+                Some(Value::Null),
+                Err(RpcError::new(PARSE_ERROR, x.to_string())),
+            ),
+        };
+
+        let mut messages: Vec<_> = self
+            .take_changes()
+            .into_iter()
+            .map(|x| json!({"jsonrpc": "2.0", "method": "change", "params": x}).to_string())
+            .collect();
+        if let Some(id) = id {
+            let response = match result {
+                Ok(x) => json!({"jsonrpc": "2.0", "id": id, "result": x}),
+                Err(x) => json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": {"code": x.code, "message": x.message},
+                }),
+            };
+            messages.push(response.to_string());
+        }
+        messages
+    }
+
+    fn attach(&mut self, mut simulator: Simulator) {
+        simulator.add_hook(Box::new(ChangeStream(self.watch.clone())));
+        self.simulator = Some(simulator);
+    }
+
+    fn take_changes(&self) -> Vec<Value> {
+        self.watch
+            .lock()
+            .map(|mut x| std::mem::take(&mut x.changes))
+            .unwrap_or_default()
+    }
+
+    fn dispatch(&mut self, request: &Value) -> Result<Value, RpcError> {
+        let Some(method) = request.get("method").and_then(|x| x.as_str()) else {
+            return Err(RpcError::new(INVALID_REQUEST, "method is not specified"));
+        };
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+        match method {
+            "load" => self.load(parse(params)?),
+            "reset" => {
+                let params: Option<ResetParams> = parse(params)?;
+                let simulator = self.simulator()?;
+                match params.and_then(|x| x.cycles) {
+                    Some(cycles) => simulator.reset_for_cycles(cycles),
+                    None => simulator.reset(),
+                }
+                Ok(json!({"time": simulator.time()}))
+            }
+            "set" => {
+                let params: SetParams = parse(params)?;
+                self.simulator()?
+                    .model_mut()
+                    .try_input(&params.port, params.value)?;
+                Ok(Value::Null)
+            }
+            "get" => {
+                let params: SignalParams = parse(params)?;
+                let value = self.simulator()?.model().peek(&params.signal);
+                value.map(Value::from).ok_or_else(|| {
+                    RpcError::new(
+                        INVALID_PARAMS,
+                        format!("signal \"{}\" is not found", params.signal),
+                    )
+                })
+            }
+            "signals" => {
+                let signals: Vec<_> = self.simulator()?.model().signals().collect();
+                Ok(json!(signals))
+            }
+            "time" => Ok(json!(self.simulator()?.time())),
+            "step" => {
+                let event = self.simulator()?.step();
+                Ok(event.map_or(Value::Null, |x| json!({"time": x.time, "clock": x.clock})))
+            }
+            "run" => {
+                let params: RunParams = parse(params)?;
+                let simulator = self.simulator()?;
+                let reason = simulator.run(params.duration);
+                Ok(stop_result(simulator.time(), reason))
+            }
+            "run_cycles" => {
+                let params: RunCyclesParams = parse(params)?;
+                let simulator = self.simulator()?;
+                let reason = simulator.run_cycles(&params.clock, params.cycles)?;
+                Ok(stop_result(simulator.time(), reason))
+            }
+            "run_until" => {
+                let params: RunUntilParams = parse(params)?;
+                let simulator = self.simulator()?;
+                if simulator.model().peek(&params.signal).is_none() {
+                    return Err(RpcError::new(
+                        INVALID_PARAMS,
+                        format!("signal \"{}\" is not found", params.signal),
+                    ));
+                }
+                let result = simulator.run_until(params.timeout, |_, model| {
+                    model.peek(&params.signal) == Some(params.value)
+                });
+                Ok(run_result(simulator.time(), result))
+            }
+            "watch" | "unwatch" => {
+                let params: WatchParams = parse(params)?;
+                // パスとグロブはモデルの信号名に展開し、一致しないパスは書き間違いとして返す
+                let model = self.simulator()?.model();
+                let mut signals = Vec::new();
+                for path in &params.signals {
+                    match model.select(path) {
+                        Ok(x) => signals.extend(x),
+                        Err(ModelError::PathNotFound { path, hint }) => {
+                            return Err(RpcError::new(
+                                INVALID_PARAMS,
+                                format!("no signal matches \"{path}\", {hint}"),
+                            ));
+                        }
+                        Err(x) => return Err(x.into()),
+                    }
+                }
+                if let Ok(mut watch) = self.watch.lock() {
+                    for signal in signals {
+                        if method == "watch" {
+                            watch.signals.insert(signal);
+                        } else {
+                            watch.signals.remove(&signal);
+                        }
+                    }
+                }
+                Ok(Value::Null)
+            }
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("method \"{method}\" is not found"),
+            )),
+        }
+    }
+
+    fn load(&mut self, params: LoadParams) -> Result<Value, RpcError> {
+        let model = match (&params.source, params.files.is_empty()) {
+            (Some(source), true) => Model::from_source(source, &params.top, params.init)?,
+            (None, false) => Model::from_files(&params.files, &params.top, params.init)?,
+            _ => {
+                return Err(RpcError::new(
+                    INVALID_PARAMS,
+                    "either source or files should be specified",
+                ));
+            }
+        };
+
+        // 周期が指定されていないクロックは period で駆動する
+        let period = params.period.unwrap_or(DEFAULT_PERIOD);
+        let mut clocks = params.clocks;
+        for (name, _) in model.clocks() {
+            clocks.entry(name.clone()).or_insert(period);
+        }
+
+        let signals: Vec<_> = model.signals().collect();
+        self.attach(Simulator::new(model, clocks));
+        Ok(json!({"top": params.top, "signals": signals}))
+    }
+
+    fn simulator(&mut self) -> Result<&mut Simulator, RpcError> {
+        self.simulator
+            .as_mut()
+            .ok_or_else(|| RpcError::new(SIMULATION_ERROR, "design is not loaded"))
+    }
+}
+
+fn parse<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|x| RpcError::new(INVALID_PARAMS, x.to_string()))
+}
+
+fn stop_result(time: u64, reason: StopReason) -> Value {
+    let (reason, message) = match reason {
+        StopReason::Completed => ("completed", None),
+        StopReason::Paused => ("paused", None),
+        StopReason::Aborted => ("aborted", None),
+        StopReason::Finished(x) => ("finished", Some(x.to_string())),
+        StopReason::Failed(x) => ("failed", Some(x.to_string())),
+    };
+    json!({"time": time, "reason": reason, "message": message})
+}
+
+fn run_result(time: u64, result: RunResult) -> Value {
+    let (reason, message) = match result {
+        RunResult::Satisfied(_) => ("satisfied", None),
+        RunResult::TimedOut(_) => ("timeout", None),
+        RunResult::Paused(_) => ("paused", None),
+        RunResult::Aborted(_) => ("aborted", None),
+        RunResult::Finished(x) => ("finished", Some(x.to_string())),
+        RunResult::Failed(x) => ("failed", Some(x.to_string())),
+    };
+    json!({"time": time, "reason": reason, "message": message})
+}
@@ -1,138 +1,1475 @@
-use crate::Model;
-use crate::hooks::Hook;
-use std::collections::HashMap;
+use crate::event_queue::{Event, EventQueue};
+use crate::hooks::{Hook, HookAction, HookError, HookId, HookSet, VCDLoggerHook};
+use crate::jitter::{ClockJitter, JitterState};
+use crate::replay::{InputRecorder, Replay, ReplayRow};
+use crate::simulator_builder::SimulatorBuilder;
+use crate::time::Instant;
+use crate::time_travel::{Checkpoint, Position, TimeTravel};
+use crate::{
+    AssertSeverity, AssertionFailure, CoveragePoint, Direction, Model, ModelError, ModelState,
+    Stimulus,
+};
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::path::Path;
+use std::time::Duration;
+use veryl_metadata::Metadata;
 
 // シミュレータ
 // model をクロックに従い時間発展させていきます
+// クロックエッジと入力操作はイベントキューに時刻の順に並べ、1 ステップに 1 つずつ処理します
 pub struct Simulator {
     model: Model, // シミュレート対象のモデル
 
     clock_intervals: HashMap<String, u64>, // クロック入力信号名と周期 [ns]
+    clock_order: Vec<String>,              // クロックの順序（同時刻のエッジはこの順に処理する）
+    clock_offsets: HashMap<String, u64>,   // 位相による最初のエッジの遅れ [ns]
+    reset_cycles: usize,                   // リセット後にリセットを保持するサイクル数
+    jitters: HashMap<String, JitterState>, // クロックのジッタ・ドリフト
+    disabled_clocks: HashSet<String>,      // 停止中のクロック（Low に保持する）
 
-    simulation_time_ns: u64,                     // 現在のシミュレーション時間
-    time_to_next_clock_ns: HashMap<String, u64>, // 次のクロックまでの残り時間
-    clock_states: HashMap<String, bool>,         // クロックの現在の状態 (High/Low)
+    simulation_time_ns: u64,             // 現在のシミュレーション時間
+    events: EventQueue,                  // クロックエッジ・入力操作のイベント
+    time_events: Option<(u64, usize)>,   // 最後のイベントの時刻と、その時刻に処理した数
+    clock_states: HashMap<String, bool>, // クロックの位相 (High/Low)
+    clock_levels: HashMap<String, bool>, // モデルに与えたクロックの値（停止中は Low）
 
-    hooks: Vec<Box<dyn Hook>>, // 登録されたフック
+    hooks: HookSet,                      // 登録されたフック
+    error: Option<ModelError>,           // フックが返したエラー（reset / restore まで再開しない）
+    started: bool,                       // on_start を通知済み
+    changes: Vec<(usize, usize, usize)>, // 通知する信号の変化 (位置, 変化前, 変化後)
+
+    finish_conditions: Vec<FinishCondition>, // 終了条件
+    max_time: Option<u64>,                   // 終了時刻 [ns]
+    finished: Option<FinishReason>,          // 終了した理由（reset / restore まで再開しない）
+    checked_assertions: usize,               // 確認済みのアサーション失敗の数
+    watchdog: Option<Duration>,              // 1 回の実行に許す実時間
+    aborted: bool,         // フックにより中断された（reset / restore まで再開しない）
+    stats: SimulatorStats, // step の実行時間とイベント数
+
+    clock_events: (u64, usize), // 最後のクロックイベントの時刻と、その時刻に処理した数
+    recorder: Option<InputRecorder>, // 入力の記録
+    replay: VecDeque<ReplayRow>, // クロックイベントの後に再生する入力
+    plusargs: HashMap<String, String>, // テストベンチに渡すオプション
+    time_travel: Option<TimeTravel>, // 巻き戻しのためのチェックポイントと各ステップの位置
+}
+
+// 終了条件
+enum FinishCondition {
+    Equals(String, usize),
+    Condition(String, Box<dyn FnMut(&Model) -> bool + Send>),
+}
+
+/// Reason why the simulation finished
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FinishReason {
+    /// The signal had the value
+    Signal { signal: String, value: usize },
+    /// The condition registered with the label became true
+    Condition(String),
+    /// The simulation reached the time
+    MaxTime(u64),
+    /// `$fatal` in the design was executed
+    Fatal(AssertionFailure),
+}
+
+impl fmt::Display for FinishReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FinishReason::Signal { signal, value } => write!(f, "{signal} == {value}"),
+            FinishReason::Condition(x) => write!(f, "{x}"),
+            FinishReason::MaxTime(x) => write!(f, "time {x}ns"),
+            FinishReason::Fatal(x) => write!(f, "{x}"),
+        }
+    }
+}
+
+/// Edge of a clock signal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ClockEdge {
+    Rising,
+    Falling,
+}
+
+/// Event processed by `Simulator::step`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepEvent {
+    pub time: u64,
+    /// None if the event is a scheduled stimulus
+    pub clock: Option<String>,
+    /// None if the clock is gated
+    pub edge: Option<ClockEdge>,
+    /// Strongest action requested by the hooks at this event
+    pub action: HookAction,
+    /// Index of the event at its time, the number of events processed before it at the same time
+    pub index_at_time: usize,
+}
+
+// 実行統計で報告する実行回数の多い文の数
+const HOTTEST_STATEMENTS: usize = 10;
+
+/// Performance statistics of a simulator returned by `Simulator::stats`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SimulatorStats {
+    /// Wall-clock time spent in `step`
+    pub wall_time: Duration,
+    /// Simulated time in ns, accumulated over resets
+    pub simulated_ns: u64,
+    /// Number of processed events
+    pub steps: u64,
+    /// Number of processed events of each clock
+    pub clock_events: BTreeMap<String, u64>,
+    /// Number of iterations spent to settle combinational statements
+    pub settle_iterations: u64,
+    /// Statements executed most often and their execution counts
+    pub hottest: Vec<(CoveragePoint, u64)>,
+}
+
+impl SimulatorStats {
+    /// Simulated ns per wall-clock second
+    pub fn ns_per_second(&self) -> f64 {
+        let wall = self.wall_time.as_secs_f64();
+        if wall == 0.0 {
+            0.0
+        } else {
+            self.simulated_ns as f64 / wall
+        }
+    }
+}
+
+impl fmt::Display for SimulatorStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "simulated {}ns in {:?} ({:.0} ns/s), {} events, {} settle iterations",
+            self.simulated_ns,
+            self.wall_time,
+            self.ns_per_second(),
+            self.steps,
+            self.settle_iterations
+        )?;
+        for (clock, count) in &self.clock_events {
+            write!(f, "\n  {clock}: {count} events")?;
+        }
+        for (point, count) in &self.hottest {
+            write!(f, "\n  {point}: {count} executions")?;
+        }
+        Ok(())
+    }
+}
+
+/// Reason why a run stopped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StopReason {
+    /// The requested duration or cycles have passed, or there are no clocks
+    Completed,
+    /// A hook requested a pause, the run can be resumed
+    Paused,
+    /// A hook requested an abort, the simulation can't be resumed until reset or restore
+    Aborted,
+    /// A finish condition was met, the simulation can't be resumed until reset or restore
+    Finished(FinishReason),
+    /// The simulation failed, e.g. the watchdog expired
+    /// it can't be resumed until reset or restore
+    Failed(ModelError),
+}
+
+impl StopReason {
+    // フックの要求から停止理由を決める
+    fn from_action(action: HookAction) -> Option<Self> {
+        match action {
+            HookAction::Continue => None,
+            HookAction::Pause => Some(StopReason::Paused),
+            HookAction::Abort => Some(StopReason::Aborted),
+        }
+    }
+}
+
+/// Result of `Simulator::run_until`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RunResult {
+    /// The condition became true at the time
+    Satisfied(u64),
+    /// The condition didn't become true until the time
+    TimedOut(u64),
+    /// A hook requested a pause at the time
+    Paused(u64),
+    /// A hook requested an abort at the time
+    Aborted(u64),
+    /// A finish condition was met
+    Finished(FinishReason),
+    /// The simulation failed
+    Failed(ModelError),
+}
+
+impl RunResult {
+    fn from_stop(reason: StopReason, time: u64) -> Self {
+        match reason {
+            StopReason::Completed => RunResult::TimedOut(time),
+            StopReason::Paused => RunResult::Paused(time),
+            StopReason::Aborted => RunResult::Aborted(time),
+            StopReason::Finished(x) => RunResult::Finished(x),
+            StopReason::Failed(x) => RunResult::Failed(x),
+        }
+    }
+}
+
+/// Checkpoint of a simulation to resume it later
+/// hooks and scheduled stimuli are not included
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimulatorState {
+    pub time_ns: u64,
+    pub time_to_next_clock_ns: BTreeMap<String, u64>,
+    pub clock_states: BTreeMap<String, bool>,
+    #[serde(default)]
+    pub jitters: BTreeMap<String, JitterState>,
+    #[serde(default)]
+    pub clock_periods: BTreeMap<String, u64>,
+    #[serde(default)]
+    pub disabled_clocks: BTreeSet<String>,
+    #[serde(default)]
+    pub clock_levels: BTreeMap<String, bool>,
+    pub model: ModelState,
 }
 
 impl Simulator {
     pub fn new(model: Model, clocks: HashMap<String, u64>) -> Self {
-        // 各クロックの次の立ち上がりまでの時間を初期化（周期の半分）
-        let mut time_to_next_clock_ns = HashMap::new();
-        let mut clock_states = HashMap::new();
+        // 同時刻のエッジの処理順を決めるため名前順に並べる
+        let mut clocks: Vec<_> = clocks.into_iter().map(|(k, v)| (k, v, 0)).collect();
+        clocks.sort();
+        Self::with_config(model, clocks, Vec::new(), 0, Vec::new())
+    }
+
+    /// Create a simulator with a builder
+    pub fn builder(model: Model) -> SimulatorBuilder {
+        SimulatorBuilder::new(model)
+    }
+
+    /// Create a simulator configured by the `[simulate]` section of Veryl.toml
+    /// the model is built from the symbol table like `Model::with_build`,
+    /// and the paths in the section are relative to the project root
+    pub fn from_metadata(metadata: &Metadata) -> Result<Self, ModelError> {
+        let config = &metadata.simulate;
+        let Some(top) = &config.top else {
+            return Err(ModelError::InvalidMetadata(
+                "top module is not specified".to_string(),
+            ));
+        };
+        let model = Model::with_build(top, HashMap::new(), &metadata.build)?;
 
-        for (clock_name, interval) in &clocks {
-            // 最初は Low から始まり、周期の半分で High になる
-            time_to_next_clock_ns.insert(clock_name.clone(), interval / 2);
-            clock_states.insert(clock_name.clone(), false);
+        for name in config.clocks.keys() {
+            if !model.clocks().iter().any(|(x, _)| x == name) {
+                return Err(ModelError::InvalidClock {
+                    name: name.clone(),
+                    reason: "the signal is not a clock of the model".to_string(),
+                });
+            }
+        }
+        let clocks: Vec<_> = model
+            .clocks()
+            .iter()
+            .map(|(name, _)| {
+                let period = config.clocks.get(name).copied();
+                (name.clone(), period.unwrap_or(config.period))
+            })
+            .collect();
+
+        // Veryl.toml のないメタデータでは現在のディレクトリを基準にする
+        let root = metadata
+            .metadata_path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+
+        let mut builder = Simulator::builder(model).reset_cycles(config.reset_cycles);
+        for (name, period) in clocks {
+            builder = builder.clock(&name, period);
+        }
+        if let Some(path) = &config.waveform {
+            let patterns: Vec<_> = config.signals.iter().map(|x| x.as_str()).collect();
+            let hook = VCDLoggerHook::new(&root.join(path).to_string_lossy()).filter(&patterns);
+            builder = builder.hook(Box::new(hook));
         }
 
-        Simulator {
+        let mut simulator = builder.build()?;
+        if let Some(path) = &config.stimulus {
+            simulator.apply_stimulus(&Stimulus::from_file(root.join(path))?)?;
+        }
+        simulator.plusargs = config.plusargs.clone();
+        Ok(simulator)
+    }
+
+    // clocks は (クロック名, 周期, 最初のエッジの遅れ) の並び
+    pub(crate) fn with_config(
+        model: Model,
+        clocks: Vec<(String, u64, u64)>,
+        jitters: Vec<(String, ClockJitter)>,
+        reset_cycles: usize,
+        hooks: Vec<(Box<dyn Hook>, i32)>,
+    ) -> Self {
+        let mut simulator = Simulator {
             model,
-            clock_intervals: clocks,
+            clock_intervals: clocks.iter().map(|(k, v, _)| (k.clone(), *v)).collect(),
+            clock_order: clocks.iter().map(|(k, _, _)| k.clone()).collect(),
+            clock_offsets: clocks.iter().map(|(k, _, x)| (k.clone(), *x)).collect(),
+            reset_cycles,
+            jitters: jitters
+                .into_iter()
+                .map(|(k, v)| (k, JitterState::new(v)))
+                .collect(),
+            disabled_clocks: HashSet::new(),
             simulation_time_ns: 0,
-            time_to_next_clock_ns,
-            clock_states,
-            hooks: Vec::new(),
+            events: EventQueue::default(),
+            time_events: None,
+            clock_states: HashMap::new(),
+            clock_levels: HashMap::new(),
+            hooks: HookSet::default(),
+            error: None,
+            started: false,
+            changes: Vec::new(),
+            finish_conditions: Vec::new(),
+            max_time: None,
+            finished: None,
+            checked_assertions: 0,
+            watchdog: None,
+            aborted: false,
+            stats: SimulatorStats::default(),
+            clock_events: (0, 0),
+            recorder: None,
+            replay: VecDeque::new(),
+            plusargs: HashMap::new(),
+            time_travel: None,
+        };
+        for (hook, priority) in hooks {
+            simulator.hooks.add(hook, priority);
+        }
+        simulator.init_clocks();
+        simulator
+    }
+
+    // クロックの状態を初期化する
+    fn init_clocks(&mut self) {
+        // ジッタの乱数列も最初からやり直す
+        for jitter in self.jitters.values_mut() {
+            *jitter = JitterState::new(*jitter.config());
+        }
+        self.events.clear_clocks();
+        for (i, clock_name) in self.clock_order.clone().into_iter().enumerate() {
+            // 最初は Low から始まり、周期の半分（と位相の遅れ）で High になる
+            let offset = self.clock_offsets[&clock_name];
+            let half_period = self.next_half_period(&clock_name);
+            self.clock_states.insert(clock_name.clone(), false);
+            self.clock_levels.insert(clock_name.clone(), false);
+            let time = self.simulation_time_ns + half_period + offset;
+            self.events.push_clock(time, clock_name, i);
+        }
+    }
+
+    // 次のエッジまでの時間（周期の半分にジッタ・ドリフトを加えたもの）
+    fn next_half_period(&mut self, clock_name: &str) -> u64 {
+        let half_period = self.clock_intervals[clock_name] / 2;
+        match self.jitters.get_mut(clock_name) {
+            Some(jitter) => jitter.next_interval(half_period),
+            None => half_period,
+        }
+    }
+
+    /// Apply jitter and drift to the clock from the edge after the next
+    pub fn set_clock_jitter(&mut self, name: &str, jitter: ClockJitter) -> Result<(), ModelError> {
+        self.check_clock(name)?;
+        self.jitters
+            .insert(name.to_string(), JitterState::new(jitter));
+        Ok(())
+    }
+
+    /// Gate or ungate the clock, effective at the next edge
+    /// a gated clock is held low, and edges are kept in phase while gated
+    pub fn set_clock_enabled(&mut self, name: &str, enabled: bool) -> Result<(), ModelError> {
+        self.check_clock(name)?;
+        if enabled {
+            self.disabled_clocks.remove(name);
+        } else {
+            self.disabled_clocks.insert(name.to_string());
+        }
+        Ok(())
+    }
+
+    /// Change the period of the clock in nanoseconds
+    /// the next edge keeps its time, and the edges after it follow the new period
+    pub fn set_clock_period(&mut self, name: &str, period_ns: u64) -> Result<(), ModelError> {
+        self.check_clock(name)?;
+        if period_ns < 2 {
+            return Err(ModelError::InvalidClock {
+                name: name.to_string(),
+                reason: "the period must be at least 2 ns".to_string(),
+            });
+        }
+        self.clock_intervals.insert(name.to_string(), period_ns);
+        Ok(())
+    }
+
+    pub fn is_clock_enabled(&self, name: &str) -> bool {
+        self.clock_intervals.contains_key(name) && !self.disabled_clocks.contains(name)
+    }
+
+    /// Current period of the clock in nanoseconds
+    pub fn clock_period(&self, name: &str) -> Option<u64> {
+        self.clock_intervals.get(name).copied()
+    }
+
+    /// Set an option read by the testbench like a plusarg of SystemVerilog simulators
+    pub fn set_plusarg(&mut self, name: &str, value: &str) {
+        self.plusargs.insert(name.to_string(), value.to_string());
+    }
+
+    /// Value of the option set by `set_plusarg` or `plusargs` of Veryl.toml
+    pub fn plusarg(&self, name: &str) -> Option<&str> {
+        self.plusargs.get(name).map(|x| x.as_str())
+    }
+
+    pub(crate) fn check_clock(&self, name: &str) -> Result<(), ModelError> {
+        if self.clock_intervals.contains_key(name) {
+            Ok(())
+        } else {
+            Err(ModelError::InvalidClock {
+                name: name.to_string(),
+                reason: "the clock is not driven by the simulator".to_string(),
+            })
         }
     }
 
+    // 同時刻のエッジを最初に処理するクロック
+    pub(crate) fn first_clock(&self) -> Option<String> {
+        self.clock_order.first().cloned()
+    }
+
+    /// Reset the simulation
+    /// the reset is held for the cycles configured by `SimulatorBuilder::reset_cycles`
     pub fn reset(&mut self) {
+        self.reset_for_cycles(self.reset_cycles);
+    }
+
+    /// Reset the simulation, then hold the reset inputs asserted for `n` rising edges
+    /// of the first clock before deasserting them
+    /// unlike the instantaneous reset, this exercises the synchronous reset paths
+    /// if the first clock is gated, the reset is not held since it has no edges to count
+    pub fn reset_for_cycles(&mut self, n: usize) {
         self.simulation_time_ns = 0;
+        self.aborted = false;
+        self.finished = None;
+        self.error = None;
 
         // クロック状態をリセット
-        for (clock_name, _) in &self.clock_intervals {
-            self.clock_states.insert(clock_name.clone(), false);
-            // リセット後、次のクロックまでの時間を周期の半分に設定
-            let interval = self.clock_intervals[clock_name];
-            self.time_to_next_clock_ns
-                .insert(clock_name.clone(), interval / 2);
-        }
+        self.init_clocks();
 
         // モデルをリセット
         self.model.reset();
+        self.model.clear_changes();
 
         // フックに通知
-        for hook in &mut self.hooks {
-            hook.on_reset(self.simulation_time_ns, &self.model);
+        let time = self.simulation_time_ns;
+        let result = self.hooks.call(|hook| {
+            hook.on_reset(time, &self.model)
+                .map(|_| HookAction::Continue)
+        });
+        self.hook_result(result);
+        self.started = false;
+        self.clock_events = (0, 0);
+        self.time_events = None;
+        self.replay.clear();
+
+        // 指定されたサイクル数だけリセットを保持してクロックを進める
+        // 保持中はフックの一時停止要求を無視する
+        // ゲートされたクロックはエッジが来ないので、数えずに終える
+        if n > 0
+            && let Some(clock_name) = self.clock_order.first().cloned()
+            && !self.disabled_clocks.contains(&clock_name)
+        {
+            self.model.set_reset(true);
+            let mut count = 0;
+            while count < n {
+                let Some(event) = self.step() else {
+                    break;
+                };
+                if event.clock.as_deref() == Some(clock_name.as_str())
+                    && event.edge == Some(ClockEdge::Rising)
+                {
+                    count += 1;
+                }
+            }
+            self.model.set_reset(false);
+        }
+
+        // 記録はリセット後の状態からやり直す
+        if self.recorder.is_some() {
+            self.start_recording();
+        }
+        if let Some(interval) = self.time_travel.as_ref().map(|x| x.interval()) {
+            self.enable_time_travel(interval);
+        }
+    }
+
+    // 指定したクロックの立ち上がりエッジが n 回起こるまで進める
+    fn run_rising_edges(&mut self, clock_name: &str, n: usize) -> StopReason {
+        let started = Instant::now();
+        let mut count = 0;
+        while count < n {
+            match self.advance(started) {
+                Ok(event) => {
+                    if event.clock.as_deref() == Some(clock_name)
+                        && event.edge == Some(ClockEdge::Rising)
+                    {
+                        count += 1;
+                    }
+                }
+                Err(reason) => return reason,
+            }
+        }
+        StopReason::Completed
+    }
+
+    // 再開できない状態であればその理由
+    fn blocked(&self) -> Option<StopReason> {
+        if let Some(error) = &self.error {
+            Some(StopReason::Failed(error.clone()))
+        } else if let Some(reason) = &self.finished {
+            Some(StopReason::Finished(reason.clone()))
+        } else if self.aborted {
+            Some(StopReason::Aborted)
+        } else {
+            None
+        }
+    }
+
+    // 1 イベント進める
+    // 停止すべき場合はその理由を返す（イベントを処理した後に停止する場合もある）
+    fn advance(&mut self, started: Instant) -> Result<StepEvent, StopReason> {
+        if let Some(reason) = self.blocked() {
+            return Err(reason);
+        }
+        if let Some(limit) = self.watchdog
+            && started.elapsed() > limit
+        {
+            self.aborted = true;
+            return Err(StopReason::Failed(ModelError::WatchdogTimeout {
+                limit_ms: limit.as_millis() as u64,
+                time: self.simulation_time_ns,
+            }));
+        }
+
+        let Some(event) = self.step() else {
+            return Err(self.blocked().unwrap_or(StopReason::Completed));
+        };
+        if let Some(reason) = &self.finished {
+            return Err(StopReason::Finished(reason.clone()));
+        }
+        match StopReason::from_action(event.action) {
+            Some(reason) => Err(reason),
+            None => Ok(event),
         }
     }
 
     /// Run simulation for specified duration in nanoseconds
-    pub fn run(&mut self, duration_ns: u64) {
+    /// stops early if a hook requests a pause or an abort, or a finish condition is met
+    pub fn run(&mut self, duration_ns: u64) -> StopReason {
+        let started = Instant::now();
         let end_time = self.simulation_time_ns + duration_ns;
 
-        while self.simulation_time_ns < end_time {
-            self.step();
+        let mut reason = self.blocked().unwrap_or(StopReason::Completed);
+        while reason == StopReason::Completed && self.simulation_time_ns < end_time {
+            // クロックがなければ時間は進まない
+            match self.advance(started) {
+                Ok(_) => {}
+                Err(StopReason::Completed) => break,
+                Err(x) => reason = x,
+            }
         }
 
-        // シミュレーション終了をフックに通知
-        for hook in &mut self.hooks {
-            hook.on_finish(self.simulation_time_ns, &self.model);
+        self.notify_finish();
+        self.with_error(reason)
+    }
+
+    /// Run simulation until `n` rising edges of the clock have occurred
+    pub fn run_cycles(&mut self, clock_name: &str, n: usize) -> Result<StopReason, ModelError> {
+        self.check_clock(clock_name)?;
+        if n > 0 && self.disabled_clocks.contains(clock_name) {
+            return Err(ModelError::InvalidClock {
+                name: clock_name.to_string(),
+                reason: "the clock is gated".to_string(),
+            });
         }
+        let reason = self.run_rising_edges(clock_name, n);
+        self.notify_finish();
+        Ok(self.with_error(reason))
     }
 
-    fn step(&mut self) {
-        // 次のクロックイベントまでの最小時間を探す
-        let mut min_time_to_next = u64::MAX;
-        let mut next_clock = String::new();
+    /// Run simulation until the condition on the time and the model becomes true,
+    /// or `timeout_ns` nanoseconds have passed
+    /// the condition is checked before the first step and after every step
+    pub fn run_until<F>(&mut self, timeout_ns: u64, mut condition: F) -> RunResult
+    where
+        F: FnMut(u64, &Model) -> bool,
+    {
+        let started = Instant::now();
+        let end_time = self.simulation_time_ns + timeout_ns;
+        let result = loop {
+            if condition(self.simulation_time_ns, &self.model) {
+                break RunResult::Satisfied(self.simulation_time_ns);
+            }
+            if let Some(reason) = self.blocked() {
+                break RunResult::from_stop(reason, self.simulation_time_ns);
+            }
+            if self.simulation_time_ns >= end_time {
+                break RunResult::TimedOut(self.simulation_time_ns);
+            }
+            if let Err(reason) = self.advance(started) {
+                // 停止した時点の値で条件を確認する
+                if condition(self.simulation_time_ns, &self.model) {
+                    break RunResult::Satisfied(self.simulation_time_ns);
+                }
+                break RunResult::from_stop(reason, self.simulation_time_ns);
+            }
+        };
+        self.notify_finish();
+        match &self.error {
+            Some(x) => RunResult::Failed(x.clone()),
+            None => result,
+        }
+    }
 
-        for (clock_name, time_to_next) in &self.time_to_next_clock_ns {
-            if *time_to_next < min_time_to_next {
-                min_time_to_next = *time_to_next;
-                next_clock = clock_name.clone();
+    /// Finish the simulation when the signal has the value
+    pub fn finish_when_equals(&mut self, signal: &str, value: usize) {
+        self.finish_conditions
+            .push(FinishCondition::Equals(signal.to_string(), value));
+    }
+
+    /// Finish the simulation when the condition becomes true
+    /// the label is reported by `FinishReason::Condition`
+    pub fn finish_when<F>(&mut self, label: &str, condition: F)
+    where
+        F: FnMut(&Model) -> bool + Send + 'static,
+    {
+        self.finish_conditions.push(FinishCondition::Condition(
+            label.to_string(),
+            Box::new(condition),
+        ));
+    }
+
+    /// Finish the simulation at the time, events after it are not processed
+    pub fn finish_at(&mut self, time_ns: u64) {
+        self.max_time = Some(time_ns);
+    }
+
+    /// Limit the real time spent by a single run, runaway simulations are aborted
+    /// with `ModelError::WatchdogTimeout`
+    pub fn set_watchdog(&mut self, limit: Option<Duration>) {
+        self.watchdog = limit;
+    }
+
+    /// Reason why the simulation finished, if it did
+    pub fn finish_reason(&self) -> Option<&FinishReason> {
+        self.finished.as_ref()
+    }
+
+    // 終了条件を確認する
+    fn check_finish(&mut self) {
+        // 未知の信号の参照でモデルが失敗していればエラーで停止する
+        if let Some(error) = self.model.unknown_failure()
+            && self.error.is_none()
+        {
+            self.error = Some(error.clone());
+            return;
+        }
+        // 設計中の $fatal が実行されれば終了する
+        let failures = &self.model.assertion_failures()[self.checked_assertions..];
+        self.checked_assertions += failures.len();
+        if let Some(x) = failures
+            .iter()
+            .find(|x| x.severity == AssertSeverity::Fatal)
+        {
+            self.finished = Some(FinishReason::Fatal(x.clone()));
+            return;
+        }
+        for condition in &mut self.finish_conditions {
+            let reason = match condition {
+                FinishCondition::Equals(signal, value) => (self.model.peek(signal) == Some(*value))
+                    .then(|| FinishReason::Signal {
+                        signal: signal.clone(),
+                        value: *value,
+                    }),
+                FinishCondition::Condition(label, f) => {
+                    f(&self.model).then(|| FinishReason::Condition(label.clone()))
+                }
+            };
+            if reason.is_some() {
+                self.finished = reason;
+                return;
             }
         }
+    }
+
+    // シミュレーション終了をフックに通知
+    fn notify_finish(&mut self) {
+        let time = self.simulation_time_ns;
+        let result = self.hooks.call(|hook| {
+            hook.on_finish(time, &self.model)
+                .map(|_| HookAction::Continue)
+        });
+        self.hook_result(result);
+    }
 
-        // 時間が見つからない場合は終了
-        if min_time_to_next == u64::MAX {
+    // 最初のステップの前に on_start を通知する
+    fn notify_start(&mut self) {
+        if self.started {
             return;
         }
+        self.started = true;
+        let result = self
+            .hooks
+            .call(|hook| hook.on_start(&self.model).map(|_| HookAction::Continue));
+        self.hook_result(result);
+        // 開始前の変化は通知しない
+        self.model
+            .record_changes(self.simulation_time_ns, &mut self.changes);
+        self.changes.clear();
+    }
+
+    // 前回の通知からの信号の変化をフックに通知する
+    fn notify_changes(&mut self) -> HookAction {
+        // 変化はモデルが記録した位置の値から求め、モデル全体は比較しない
+        let mut changes = std::mem::take(&mut self.changes);
+        self.model
+            .record_changes(self.simulation_time_ns, &mut changes);
+        self.record_inputs();
+        if self.hooks.is_empty() {
+            changes.clear();
+            self.changes = changes;
+            return HookAction::Continue;
+        }
 
-        // シミュレーション時間を進める
-        self.simulation_time_ns += min_time_to_next;
+        // 入力の変化を先に通知する（変化は信号名の順に並んでいる）
+        let time = self.simulation_time_ns;
+        let mut action = HookAction::Continue;
+        for input in [true, false] {
+            for &(slot, old, new) in &changes {
+                let (name, direction) = self.model.signal_at(slot);
+                // シミュレータが駆動するクロックは除く
+                if (direction == Direction::Input) != input
+                    || input && self.clock_intervals.contains_key(name)
+                {
+                    continue;
+                }
+                let result = if input {
+                    self.hooks
+                        .call(|hook| hook.on_input_change(time, name, old, new))
+                } else {
+                    self.hooks
+                        .call(|hook| hook.on_signal_change(time, name, old, new))
+                };
+                action = action.max(self.hook_result(result));
+            }
+        }
+        changes.clear();
+        self.changes = changes;
+        action
+    }
 
-        // すべてのクロックの残り時間を更新
-        for (_clock_name, time_to_next) in self.time_to_next_clock_ns.iter_mut() {
-            *time_to_next -= min_time_to_next;
+    // フックのエラーを記録し、シミュレーションを中断する
+    fn hook_result(&mut self, result: Result<HookAction, HookError>) -> HookAction {
+        match result {
+            Ok(x) => x,
+            Err(error) => {
+                if self.error.is_none() {
+                    self.error = Some(ModelError::HookFailed {
+                        time: self.simulation_time_ns,
+                        error,
+                    });
+                }
+                self.aborted = true;
+                HookAction::Abort
+            }
         }
+    }
 
-        // ステップフックを呼ぶ
-        for hook in &mut self.hooks {
-            hook.on_step(self.simulation_time_ns, &self.model);
+    // フックのエラーがあれば停止理由をエラーにする
+    fn with_error(&self, reason: StopReason) -> StopReason {
+        match &self.error {
+            Some(x) => StopReason::Failed(x.clone()),
+            None => reason,
+        }
+    }
+
+    /// Current simulation time in nanoseconds
+    pub fn time(&self) -> u64 {
+        self.simulation_time_ns
+    }
+
+    pub fn model(&self) -> &Model {
+        &self.model
+    }
+
+    /// Mutable access to the model to drive inputs between steps
+    pub fn model_mut(&mut self) -> &mut Model {
+        &mut self.model
+    }
+
+    /// Advance to the time, processing all clock events up to and including it
+    /// unlike `run`, the simulation time becomes exactly `time_ns`
+    /// if a hook requests a pause or an abort, the time stays at the event
+    pub fn advance_to(&mut self, time_ns: u64) -> StopReason {
+        let started = Instant::now();
+        if let Some(reason) = self.blocked() {
+            return reason;
+        }
+        while self.next_time().is_some_and(|x| x <= time_ns) {
+            if let Err(reason) = self.advance(started) {
+                return reason;
+            }
+        }
+
+        if let Some(max_time) = self.max_time
+            && time_ns > max_time
+        {
+            self.skip_to(max_time);
+            self.finished = Some(FinishReason::MaxTime(max_time));
+            return StopReason::Finished(FinishReason::MaxTime(max_time));
+        }
+        self.skip_to(time_ns);
+        StopReason::Completed
+    }
+
+    // 次のイベントの手前まで時間を進める
+    fn skip_to(&mut self, time_ns: u64) {
+        self.simulation_time_ns = self.simulation_time_ns.max(time_ns);
+    }
+
+    // 次のイベントの時刻
+    pub(crate) fn next_time(&self) -> Option<u64> {
+        self.events.next_time()
+    }
+
+    /// Schedule a stimulus at the time
+    /// it is applied before the clock edges at the same time
+    pub fn at<F>(&mut self, time_ns: u64, action: F)
+    where
+        F: FnMut(&mut Model) + Send + 'static,
+    {
+        self.events
+            .push(time_ns, Event::Stimulus(Box::new(action), None));
+    }
+
+    /// Schedule a stimulus repeated every `period_ns` nanoseconds from now
+    /// a period of 0 is treated as 1
+    pub fn every<F>(&mut self, period_ns: u64, action: F)
+    where
+        F: FnMut(&mut Model) + Send + 'static,
+    {
+        let period = period_ns.max(1);
+        self.events.push(
+            self.simulation_time_ns + period,
+            Event::Stimulus(Box::new(action), Some(period)),
+        );
+    }
+
+    /// Schedule an input change after `delay_ns` nanoseconds
+    /// with no delay, it is applied by the next step at the current time as a new event
+    pub fn schedule_input(
+        &mut self,
+        signal: &str,
+        value: usize,
+        delay_ns: u64,
+    ) -> Result<(), ModelError> {
+        self.check_input(signal)?;
+        self.events.push(
+            self.simulation_time_ns + delay_ns,
+            Event::Input(signal.to_string(), value),
+        );
+        Ok(())
+    }
+
+    fn check_input(&self, signal: &str) -> Result<(), ModelError> {
+        if self
+            .model
+            .signals()
+            .any(|x| x.name == signal && x.direction == Direction::Input)
+        {
+            Ok(())
+        } else {
+            Err(ModelError::UnknownPort(signal.to_string()))
+        }
+    }
+
+    /// Schedule all rows of the stimulus
+    /// returns an error without scheduling anything if a signal isn't an input port
+    pub fn apply_stimulus(&mut self, stimulus: &Stimulus) -> Result<(), ModelError> {
+        for row in stimulus.rows() {
+            self.check_input(&row.signal)?;
+        }
+
+        for row in stimulus.rows() {
+            self.events
+                .push(row.time, Event::Input(row.signal.clone(), row.value));
+        }
+        Ok(())
+    }
+
+    // 現在時刻までに予約された入力操作を実行する
+    fn apply_scheduled(&mut self) {
+        let now = self.simulation_time_ns;
+        while let Some(event) = self.events.pop_stimulus(now) {
+            match event {
+                Event::Stimulus(mut action, period) => {
+                    action(&mut self.model);
+                    if let Some(period) = period {
+                        self.events
+                            .push(now + period, Event::Stimulus(action, Some(period)));
+                    }
+                }
+                Event::Input(signal, value) => self.model.input(&signal, value),
+                _ => {}
+            }
+        }
+    }
+
+    /// Start recording the inputs applied to the model, including the inputs driven by hooks
+    /// the current values of all inputs are recorded first, and a reset restarts the recording
+    pub fn start_recording(&mut self) {
+        self.recorder = Some(InputRecorder::new());
+        self.record_inputs();
+    }
+
+    /// Inputs recorded since `start_recording`
+    pub fn recorded_inputs(&mut self) -> Option<Replay> {
+        self.record_inputs();
+        self.recorder.as_ref().map(|x| x.replay().clone())
+    }
+
+    /// Write the recorded inputs to the replay file
+    pub fn save_replay<T: AsRef<Path>>(&mut self, path: T) -> std::io::Result<()> {
+        self.recorded_inputs().unwrap_or_default().save(path)
+    }
+
+    /// Re-apply the inputs recorded in the replay file
+    /// it should be called at the point where the recording was started, e.g. just after reset
+    pub fn replay<T: AsRef<Path>>(&mut self, path: T) -> Result<(), ModelError> {
+        let replay = Replay::from_file(path)?;
+        self.apply_replay(&replay)
+    }
+
+    /// Re-apply the recorded inputs at the same points of the run
+    /// returns an error without applying anything if a signal isn't an input port
+    pub fn apply_replay(&mut self, replay: &Replay) -> Result<(), ModelError> {
+        for row in replay.rows() {
+            self.check_input(&row.signal)?;
         }
 
+        // クロックエッジの前の入力は予約された入力操作として、後の入力はクロックイベントの後に再生する
+        let now = (self.simulation_time_ns, self.events_now());
+        for row in replay.rows() {
+            if (row.time, row.events) <= now {
+                self.model.input(&row.signal, row.value);
+            } else if row.events == 0 {
+                self.events
+                    .push(row.time, Event::Input(row.signal.clone(), row.value));
+            } else {
+                self.replay.push_back(row.clone());
+            }
+        }
+        Ok(())
+    }
+
+    // 現在時刻に処理したクロックイベントの数
+    fn events_now(&self) -> usize {
+        match self.clock_events {
+            (time, n) if time == self.simulation_time_ns => n,
+            _ => 0,
+        }
+    }
+
+    fn record_inputs(&mut self) {
+        let events = self.events_now();
+        if let Some(recorder) = &mut self.recorder {
+            let clocks = self.clock_intervals.keys().collect();
+            recorder.record(self.simulation_time_ns, events, &self.model, &clocks);
+        }
+    }
+
+    fn apply_replay_queue(&mut self) {
+        let now = (self.simulation_time_ns, self.events_now());
+        while let Some(row) = self.replay.front() {
+            if (row.time, row.events) > now {
+                break;
+            }
+            self.model.input(&row.signal, row.value);
+            self.replay.pop_front();
+        }
+    }
+
+    /// Performance statistics accumulated since the simulator was built
+    pub fn stats(&self) -> SimulatorStats {
+        // 実行回数の多い文を選ぶ
+        let mut hottest: Vec<_> = self
+            .model
+            .coverage_points()
+            .iter()
+            .zip(self.model.execution_counts())
+            .filter(|(x, count)| !x.kind.is_branch() && **count > 0)
+            .map(|(x, count)| (x.clone(), *count))
+            .collect();
+        hottest.sort_by(|x, y| y.1.cmp(&x.1).then(x.0.location.cmp(&y.0.location)));
+        hottest.truncate(HOTTEST_STATEMENTS);
+
+        SimulatorStats {
+            settle_iterations: self.model.settle_iterations(),
+            hottest,
+            ..self.stats.clone()
+        }
+    }
+
+    /// Advance to the next clock event or scheduled stimulus and process it
+    /// returns None if there are no events, or the simulation was aborted or finished
+    pub fn step(&mut self) -> Option<StepEvent> {
+        let started = Instant::now();
+        let time = self.simulation_time_ns;
+        let event = self.process_event();
+        self.stats.wall_time += started.elapsed();
+        self.stats.simulated_ns += self.simulation_time_ns.saturating_sub(time);
+        if let Some(event) = &event {
+            self.record_step();
+            self.stats.steps += 1;
+            if let Some(clock) = &event.clock {
+                *self.stats.clock_events.entry(clock.clone()).or_default() += 1;
+            }
+        }
+        event
+    }
+
+    // 次のイベントを処理する
+    fn process_event(&mut self) -> Option<StepEvent> {
+        if self.blocked().is_some() {
+            return None;
+        }
+        self.notify_start();
+        if self.blocked().is_some() {
+            return None;
+        }
+
+        // 終了時刻を超えるイベントは処理しない
+        if let Some(max_time) = self.max_time
+            && self.next_time().is_none_or(|x| x > max_time)
+        {
+            self.skip_to(max_time);
+            self.finished = Some(FinishReason::MaxTime(max_time));
+            return None;
+        }
+
+        // 予約された入力操作は同時刻のクロックより先に処理する
+        if let Some(time) = self.events.next_stimulus() {
+            // ステップ間の入力の変化を通知する
+            let action = self.notify_changes();
+            self.skip_to(time);
+
+            let time = self.simulation_time_ns;
+            let result = self.hooks.call(|hook| hook.on_step(time, &self.model));
+            let action = action.max(self.hook_result(result));
+            self.apply_scheduled();
+            let action = action.max(self.notify_changes());
+            self.check_finish();
+
+            if action == HookAction::Abort {
+                self.aborted = true;
+            }
+            return Some(StepEvent {
+                time: self.simulation_time_ns,
+                clock: None,
+                edge: None,
+                action,
+                index_at_time: self.next_index_at_time(),
+            });
+        }
+
+        // 次のクロックエッジを取り出す
+        let (time, next_clock) = self.events.pop_clock()?;
+
+        // ステップ間の入力の変化を通知する
+        let action = self.notify_changes();
+
+        // シミュレーション時間を進める
+        self.skip_to(time);
+
+        // ステップフックを呼ぶ
+        let time = self.simulation_time_ns;
+        let result = self.hooks.call(|hook| hook.on_step(time, &self.model));
+        let mut action = action.max(self.hook_result(result));
+
         // クロックイベントを処理
-        if !next_clock.is_empty() {
-            let current_state = self.clock_states[&next_clock];
-            let new_state = !current_state;
-            self.clock_states.insert(next_clock.clone(), new_state);
+        let phase = !self.clock_states[&next_clock];
+        self.clock_states.insert(next_clock.clone(), phase);
 
-            // クロックの立ち上がりエッジの場合
-            if new_state {
+        // 停止中のクロックは位相のみ進め、Low に保持する
+        let level = phase && !self.disabled_clocks.contains(&next_clock);
+        let edge = match self.clock_levels.insert(next_clock.clone(), level) {
+            Some(x) if x == level => None,
+            _ if level => Some(ClockEdge::Rising),
+            _ => Some(ClockEdge::Falling),
+        };
+
+        match edge {
+            Some(ClockEdge::Rising) => {
                 // pre_clockフックを呼ぶ
-                for hook in &mut self.hooks {
-                    hook.pre_clock(self.simulation_time_ns, &next_clock, &self.model);
-                }
+                let result = self
+                    .hooks
+                    .call(|hook| hook.pre_clock(time, &next_clock, &self.model));
+                action = action.max(self.hook_result(result));
 
-                // モデルのクロックを進める
-                self.model.clock();
+                // モデルのクロックを進める（このクロックで駆動されるブロックのみ）
+                self.model.clock_rise(&next_clock);
 
                 // post_clockフックを呼ぶ
-                for hook in &mut self.hooks {
-                    hook.post_clock(self.simulation_time_ns, &next_clock, &self.model);
+                let result = self
+                    .hooks
+                    .call(|hook| hook.post_clock(time, &next_clock, &self.model));
+                action = action.max(self.hook_result(result));
+
+                // エッジ後の入力を駆動するフックを呼ぶ
+                let result = self
+                    .hooks
+                    .call(|hook| hook.drive(time, &next_clock, &mut self.model));
+                action = action.max(self.hook_result(result));
+            }
+            Some(ClockEdge::Falling) => {
+                let result = self
+                    .hooks
+                    .call(|hook| hook.pre_clock_fall(time, &next_clock, &self.model));
+                action = action.max(self.hook_result(result));
+
+                // 立ち下がりで駆動されるブロックを評価
+                self.model.clock_fall(&next_clock);
+
+                let result = self
+                    .hooks
+                    .call(|hook| hook.post_clock_fall(time, &next_clock, &self.model));
+                action = action.max(self.hook_result(result));
+            }
+            None => {}
+        }
+
+        // 同時刻のクロックイベントを数え、その後に記録された入力を再生する
+        self.clock_events = match self.clock_events {
+            (x, n) if x == time => (x, n + 1),
+            _ => (time, 1),
+        };
+        self.apply_replay_queue();
+
+        // 次のクロックイベントを追加（周期の半分後）
+        let half_period = self.next_half_period(&next_clock);
+        let order = self
+            .clock_order
+            .iter()
+            .position(|x| *x == next_clock)
+            .unwrap_or_default();
+        self.events
+            .push_clock(time + half_period, next_clock.clone(), order);
+
+        action = action.max(self.notify_changes());
+        self.check_finish();
+
+        // 中断された場合は reset / restore まで再開しない
+        if action == HookAction::Abort {
+            self.aborted = true;
+        }
+
+        Some(StepEvent {
+            time: self.simulation_time_ns,
+            clock: Some(next_clock),
+            edge,
+            action,
+            index_at_time: self.next_index_at_time(),
+        })
+    }
+
+    // 同時刻に処理したイベントを数え、このイベントの同時刻での番号を返す
+    fn next_index_at_time(&mut self) -> usize {
+        let time = self.simulation_time_ns;
+        let index = match self.time_events {
+            Some((x, n)) if x == time => n + 1,
+            _ => 0,
+        };
+        self.time_events = Some((time, index));
+        index
+    }
+
+    /// Checkpoint of the current time, clock phases and model state
+    pub fn checkpoint(&self) -> SimulatorState {
+        SimulatorState {
+            time_ns: self.simulation_time_ns,
+            time_to_next_clock_ns: self
+                .clock_order
+                .iter()
+                .filter_map(|x| {
+                    let time = self.events.clock_time(x)?;
+                    Some((x.clone(), time - self.simulation_time_ns))
+                })
+                .collect(),
+            clock_states: self.clock_states.clone().into_iter().collect(),
+            clock_levels: self.clock_levels.clone().into_iter().collect(),
+            jitters: self.jitters.clone().into_iter().collect(),
+            clock_periods: self.clock_intervals.clone().into_iter().collect(),
+            disabled_clocks: self.disabled_clocks.clone().into_iter().collect(),
+            model: self.model.snapshot(),
+        }
+    }
+
+    /// Resume the simulation from a checkpoint
+    /// the simulator must be created with the same module and clocks
+    pub fn restore(&mut self, state: &SimulatorState) -> Result<(), ModelError> {
+        self.restore_state(state)?;
+        // 履歴は復元した時点からやり直す
+        if let Some(interval) = self.time_travel.as_ref().map(|x| x.interval()) {
+            self.start_recording();
+            self.enable_time_travel(interval);
+        }
+        Ok(())
+    }
+
+    fn restore_state(&mut self, state: &SimulatorState) -> Result<(), ModelError> {
+        // 全てのクロックの位相が揃っている必要がある
+        for name in self.clock_intervals.keys() {
+            if !state.clock_states.contains_key(name)
+                || !state.time_to_next_clock_ns.contains_key(name)
+            {
+                return Err(ModelError::SignalNotFound(name.clone()));
+            }
+        }
+        for name in state
+            .clock_states
+            .keys()
+            .chain(state.jitters.keys())
+            .chain(state.clock_periods.keys())
+            .chain(state.disabled_clocks.iter())
+            .chain(state.clock_levels.keys())
+        {
+            if !self.clock_intervals.contains_key(name) {
+                return Err(ModelError::SignalNotFound(name.clone()));
+            }
+        }
+        self.model.restore(&state.model)?;
+        self.model.clear_changes();
+        self.simulation_time_ns = state.time_ns;
+        self.aborted = false;
+        self.finished = None;
+        self.error = None;
+        self.time_events = None;
+        self.events.clear_clocks();
+        for (i, clock_name) in self.clock_order.iter().enumerate() {
+            let time = state.time_ns + state.time_to_next_clock_ns[clock_name];
+            self.events.push_clock(time, clock_name.clone(), i);
+        }
+        self.clock_states = state.clock_states.clone().into_iter().collect();
+        self.jitters = state.jitters.clone().into_iter().collect();
+        self.clock_intervals.extend(state.clock_periods.clone());
+        self.disabled_clocks = state.disabled_clocks.iter().cloned().collect();
+        // 値がなければ位相と同じとみなす
+        self.clock_levels = self
+            .clock_states
+            .iter()
+            .map(|(k, v)| (k.clone(), state.clock_levels.get(k).copied().unwrap_or(*v)))
+            .collect();
+        Ok(())
+    }
+
+    /// Keep the history of the simulation to go back by `step_back` and `rewind_to`
+    /// a checkpoint is taken every `interval` steps and the inputs are recorded,
+    /// the history starts at the current point and restarts at reset or restore
+    pub fn enable_time_travel(&mut self, interval: usize) {
+        if self.recorder.is_none() {
+            self.start_recording();
+        }
+        let start = self.history_checkpoint();
+        self.time_travel = Some(TimeTravel::new(interval, start));
+    }
+
+    pub fn disable_time_travel(&mut self) {
+        self.time_travel = None;
+    }
+
+    /// Go back to the point before the last step, and return the time of the point
+    pub fn step_back(&mut self) -> Result<u64, ModelError> {
+        let Some(history) = &mut self.time_travel else {
+            return Err(time_travel_disabled());
+        };
+        let Some(position) = history.pop() else {
+            return Err(ModelError::TimeTravelFailed(format!(
+                "there are no steps to go back at {}ns",
+                self.simulation_time_ns
+            )));
+        };
+        self.travel_to(position)?;
+        Ok(self.simulation_time_ns)
+    }
+
+    /// Go back to the time, the events at the time are processed
+    /// hooks are not called for the re-executed steps, and running forward again
+    /// applies the recorded inputs after the time
+    pub fn rewind_to(&mut self, time_ns: u64) -> Result<(), ModelError> {
+        if time_ns > self.simulation_time_ns {
+            return Err(ModelError::TimeTravelFailed(format!(
+                "{time_ns}ns is after the current time {}ns",
+                self.simulation_time_ns
+            )));
+        }
+        self.travel_to((time_ns, usize::MAX))
+    }
+
+    // 直前のチェックポイントから、記録した入力でフックを呼ばずに位置まで再実行する
+    fn travel_to(&mut self, position: Position) -> Result<(), ModelError> {
+        let Some(mut history) = self.time_travel.take() else {
+            return Err(time_travel_disabled());
+        };
+        let Some(checkpoint) = history.latest(position).cloned() else {
+            let start = history.start().0;
+            self.time_travel = Some(history);
+            return Err(ModelError::TimeTravelFailed(format!(
+                "{}ns is before the start of the history at {start}ns",
+                position.0
+            )));
+        };
+
+        let replay = self.recorded_inputs().unwrap_or_default();
+        let hooks = std::mem::take(&mut self.hooks);
+        let result = self.reexecute(&checkpoint, &replay, position);
+        self.hooks = hooks;
+
+        let position = self.position();
+        if let Some(recorder) = &mut self.recorder {
+            recorder.truncate(position);
+        }
+        history.truncate(position);
+        self.time_travel = Some(history);
+        result
+    }
+
+    fn reexecute(
+        &mut self,
+        checkpoint: &Checkpoint,
+        replay: &Replay,
+        position: Position,
+    ) -> Result<(), ModelError> {
+        self.restore_state(&checkpoint.state)?;
+        self.clock_events = checkpoint.position;
+        self.model
+            .truncate_assertion_failures(checkpoint.assertions);
+        self.checked_assertions = checkpoint.assertions;
+        self.replay.clear();
+        self.apply_replay(replay)?;
+
+        while self.position() < position {
+            match self.next_time() {
+                Some(x) if x <= position.0 => {
+                    if self.step().is_none() {
+                        break;
+                    }
                 }
+                _ => {
+                    self.skip_to(position.0);
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn position(&self) -> Position {
+        (self.simulation_time_ns, self.events_now())
+    }
+
+    fn history_checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            position: self.position(),
+            state: self.checkpoint(),
+            assertions: self.model.assertion_failures().len(),
+        }
+    }
+
+    // ステップ後の位置を履歴に追加する
+    fn record_step(&mut self) {
+        let position = self.position();
+        let Some(history) = &mut self.time_travel else {
+            return;
+        };
+        if history.push(position) {
+            let checkpoint = self.history_checkpoint();
+            if let Some(history) = &mut self.time_travel {
+                history.add_checkpoint(checkpoint);
             }
+        }
+    }
+
+    /// Add a hook to the simulator with priority 0
+    pub fn add_hook(&mut self, hook: Box<dyn Hook>) -> HookId {
+        self.hooks.add(hook, 0)
+    }
+
+    /// Add a hook with the priority
+    /// hooks are called in ascending order of priority, and in the order of registration
+    /// for the same priority, so a scoreboard with a higher priority than a driver
+    /// always observes the values after the driver at the same event
+    pub fn add_hook_with_priority(&mut self, hook: Box<dyn Hook>, priority: i32) -> HookId {
+        self.hooks.add(hook, priority)
+    }
 
-            // 次のクロックイベントまでの時間を設定（周期の半分）
-            let interval = self.clock_intervals[&next_clock];
-            self.time_to_next_clock_ns.insert(next_clock, interval / 2);
+    /// Change the priority of the hook
+    /// the hook is called after the other hooks of the same priority
+    pub fn set_hook_priority(&mut self, id: HookId, priority: i32) -> Result<(), ModelError> {
+        if self.hooks.set_priority(id, priority) {
+            Ok(())
+        } else {
+            Err(ModelError::HookNotFound(id.0))
         }
     }
 
-    /// Add a hook to the simulator
-    pub fn add_hook(&mut self, hook: Box<dyn Hook>) {
-        self.hooks.push(hook);
+    pub fn hook_priority(&self, id: HookId) -> Option<i32> {
+        self.hooks.priority(id)
+    }
+
+    /// Remove the hook and give it back
+    pub fn remove_hook(&mut self, id: HookId) -> Option<Box<dyn Hook>> {
+        self.hooks.remove(id)
+    }
+
+    /// Ids of the registered hooks in the order of calls
+    /// hooks added by `SimulatorBuilder::hook` come first in the same priority
+    pub fn hook_ids(&self) -> Vec<HookId> {
+        self.hooks.ids()
     }
+
+    /// Enable or disable the hook, disabled hooks are not called
+    pub fn set_hook_enabled(&mut self, id: HookId, enabled: bool) -> Result<(), ModelError> {
+        if self.hooks.set_enabled(id, enabled) {
+            Ok(())
+        } else {
+            Err(ModelError::HookNotFound(id.0))
+        }
+    }
+
+    pub fn is_hook_enabled(&self, id: HookId) -> bool {
+        self.hooks.is_enabled(id)
+    }
+
+    /// Access the hook as the concrete type
+    /// returns None if the hook is not found or has another type
+    pub fn with_hook_mut<T: Hook, R>(
+        &mut self,
+        id: HookId,
+        f: impl FnOnce(&mut T) -> R,
+    ) -> Option<R> {
+        let hook: &mut dyn Any = self.hooks.get_mut(id)?.as_mut();
+        hook.downcast_mut::<T>().map(f)
+    }
+}
+
+fn time_travel_disabled() -> ModelError {
+    ModelError::TimeTravelFailed("time travel is not enabled".to_string())
 }
@@ -0,0 +1,104 @@
+use crate::hooks::Hook;
+use crate::jitter::ClockJitter;
+use crate::{Model, ModelError, Simulator};
+
+/// Builder of `Simulator`
+/// clocks keep the order of registration, and edges at the same time are processed in this order
+pub struct SimulatorBuilder {
+    model: Model,
+    clocks: Vec<(String, u64)>, // (name, period [ns])
+    phases: Vec<(String, u64)>, // (name, phase [degree])
+    jitters: Vec<(String, ClockJitter)>,
+    reset_cycles: usize,
+    hooks: Vec<(Box<dyn Hook>, i32)>, // (hook, priority)
+}
+
+impl SimulatorBuilder {
+    pub(crate) fn new(model: Model) -> Self {
+        SimulatorBuilder {
+            model,
+            clocks: Vec::new(),
+            phases: Vec::new(),
+            jitters: Vec::new(),
+            reset_cycles: 0,
+            hooks: Vec::new(),
+        }
+    }
+
+    /// Drive the clock with the period in nanoseconds
+    pub fn clock(mut self, name: &str, period_ns: u64) -> Self {
+        self.clocks.push((name.to_string(), period_ns));
+        self
+    }
+
+    /// Delay the clock by the phase in degrees
+    pub fn clock_phase(mut self, name: &str, degree: u64) -> Self {
+        self.phases.push((name.to_string(), degree));
+        self
+    }
+
+    /// Apply jitter and drift to the clock
+    pub fn clock_jitter(mut self, name: &str, jitter: ClockJitter) -> Self {
+        self.jitters.push((name.to_string(), jitter));
+        self
+    }
+
+    /// Hold reset for `cycles` cycles of the first clock after `Simulator::reset`
+    pub fn reset_cycles(mut self, cycles: usize) -> Self {
+        self.reset_cycles = cycles;
+        self
+    }
+
+    pub fn hook(self, hook: Box<dyn Hook>) -> Self {
+        self.hook_with_priority(hook, 0)
+    }
+
+    /// Add a hook called in ascending order of priority
+    /// see `Simulator::add_hook_with_priority`
+    pub fn hook_with_priority(mut self, hook: Box<dyn Hook>, priority: i32) -> Self {
+        self.hooks.push((hook, priority));
+        self
+    }
+
+    pub fn build(self) -> Result<Simulator, ModelError> {
+        let invalid = |name: &str, reason: &str| ModelError::InvalidClock {
+            name: name.to_string(),
+            reason: reason.to_string(),
+        };
+
+        let mut clocks: Vec<(String, u64, u64)> = Vec::new();
+        for (name, period) in self.clocks {
+            if !self.model.clocks().iter().any(|(x, _)| *x == name) {
+                return Err(invalid(&name, "the signal is not a clock of the model"));
+            }
+            if clocks.iter().any(|(x, _, _)| *x == name) {
+                return Err(invalid(&name, "the period is specified more than once"));
+            }
+            if period < 2 {
+                return Err(invalid(&name, "the period must be at least 2 ns"));
+            }
+            clocks.push((name, period, 0));
+        }
+
+        for (name, degree) in self.phases {
+            let Some((_, period, offset)) = clocks.iter_mut().find(|(x, _, _)| *x == name) else {
+                return Err(invalid(&name, "the phase is specified without the period"));
+            };
+            *offset = *period * (degree % 360) / 360;
+        }
+
+        for (name, _) in &self.jitters {
+            if !clocks.iter().any(|(x, _, _)| x == name) {
+                return Err(invalid(name, "the jitter is specified without the period"));
+            }
+        }
+
+        Ok(Simulator::with_config(
+            self.model,
+            clocks,
+            self.jitters,
+            self.reset_cycles,
+            self.hooks,
+        ))
+    }
+}
@@ -0,0 +1,69 @@
+use similar::TextDiff;
+use std::fs;
+use std::path::Path;
+
+// スナップショットを更新する環境変数
+const UPDATE_VAR: &str = "VERYL_UPDATE_SNAPSHOTS";
+
+/// Compare a waveform with the snapshot recorded in `tests/snapshots/<name>.wave`
+///
+/// The name is the enclosing function by default, and can be given as the first argument.
+/// The snapshot is recorded if it doesn't exist, and updated if `VERYL_UPDATE_SNAPSHOTS`
+/// is set. Otherwise, the assertion fails with the diff from the snapshot.
+#[macro_export]
+macro_rules! assert_wave_snapshot {
+    ($logger:expr) => {{
+        fn f() {}
+        let name = $crate::snapshot_name(std::any::type_name_of_val(&f));
+        $crate::assert_wave_snapshot!(name, $logger)
+    }};
+    ($name:expr, $logger:expr) => {{
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("snapshots")
+            .join(format!("{}.wave", $name));
+        if let Err(x) = $crate::check_snapshot(&path, &$logger.snapshot()) {
+            panic!("{x}");
+        }
+    }};
+}
+
+// 関数の型名（"tests::test_wave::f"）から囲んでいる関数の名前を取り出す
+#[doc(hidden)]
+pub fn snapshot_name(type_name: &str) -> &str {
+    type_name
+        .rsplit("::")
+        .find(|x| *x != "f" && *x != "{{closure}}")
+        .unwrap_or("snapshot")
+}
+
+/// Compare the text with the snapshot file, and return the diff if they differ
+/// the file is written if it doesn't exist or `VERYL_UPDATE_SNAPSHOTS` is set
+#[doc(hidden)]
+pub fn check_snapshot(path: &Path, actual: &str) -> Result<(), String> {
+    let update = std::env::var_os(UPDATE_VAR).is_some();
+    let expected = match fs::read_to_string(path) {
+        Ok(x) if !update => x,
+        _ => return write_snapshot(path, actual),
+    };
+    if expected == actual {
+        return Ok(());
+    }
+
+    let diff = TextDiff::from_lines(expected.as_str(), actual)
+        .unified_diff()
+        .header("snapshot", "actual")
+        .to_string();
+    Err(format!(
+        "waveform doesn't match the snapshot \"{}\"\n{diff}set {UPDATE_VAR}=1 to update the snapshot",
+        path.to_string_lossy()
+    ))
+}
+
+fn write_snapshot(path: &Path, actual: &str) -> Result<(), String> {
+    let error = |x: std::io::Error| format!("failed to write \"{}\": {x}", path.to_string_lossy());
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(error)?;
+    }
+    fs::write(path, actual).map_err(error)
+}
@@ -0,0 +1,94 @@
+use crate::ModelError;
+use std::path::Path;
+
+/// Row of a stimulus file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StimulusRow {
+    pub time: u64,
+    pub signal: String,
+    pub value: usize,
+}
+
+/// Input vectors read from a stimulus file
+///
+/// Each line is a `time, signal, value` row separated by commas, tabs or spaces.
+/// Times are in nanoseconds, and values are decimal or prefixed by `0x`, `0o` or `0b`.
+/// Empty lines, lines starting with `#` and a `time, signal, value` header are skipped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stimulus {
+    rows: Vec<StimulusRow>,
+}
+
+impl Stimulus {
+    /// Parse stimulus text
+    pub fn parse(text: &str) -> Result<Self, ModelError> {
+        Self::parse_with_path(text, "<stimulus>")
+    }
+
+    /// Read a stimulus file
+    pub fn from_file<T: AsRef<Path>>(path: T) -> Result<Self, ModelError> {
+        let path = path.as_ref().to_string_lossy().to_string();
+        let text = std::fs::read_to_string(&path).map_err(|x| ModelError::ReadFailed {
+            path: path.clone(),
+            cause: x.to_string(),
+        })?;
+        Self::parse_with_path(&text, &path)
+    }
+
+    /// Rows ordered by time, rows at the same time keep the order in the file
+    pub fn rows(&self) -> &[StimulusRow] {
+        &self.rows
+    }
+
+    fn parse_with_path(text: &str, path: &str) -> Result<Self, ModelError> {
+        let error = |line: usize, cause: &str| ModelError::ParseFailed {
+            path: path.to_string(),
+            cause: format!("line {line}: {cause}"),
+        };
+
+        let mut rows = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields: Vec<_> = line
+                .split(|x: char| x == ',' || x.is_whitespace())
+                .filter(|x| !x.is_empty())
+                .collect();
+            if fields.len() != 3 {
+                return Err(error(i + 1, "expected `time, signal, value`"));
+            }
+            if rows.is_empty() && fields[0] == "time" {
+                continue;
+            }
+
+            let time = fields[0]
+                .parse()
+                .map_err(|_| error(i + 1, &format!("invalid time \"{}\"", fields[0])))?;
+            let value = parse_value(fields[2])
+                .ok_or_else(|| error(i + 1, &format!("invalid value \"{}\"", fields[2])))?;
+            rows.push(StimulusRow {
+                time,
+                signal: fields[1].to_string(),
+                value,
+            });
+        }
+
+        // sort is stable, so rows at the same time keep the order
+        rows.sort_by_key(|x| x.time);
+        Ok(Stimulus { rows })
+    }
+}
+
+pub(crate) fn parse_value(text: &str) -> Option<usize> {
+    let text = text.replace('_', "");
+    let (digits, radix) = match text.get(..2) {
+        Some("0x" | "0X") => (&text[2..], 16),
+        Some("0o" | "0O") => (&text[2..], 8),
+        Some("0b" | "0B") => (&text[2..], 2),
+        _ => (text.as_str(), 10),
+    };
+    usize::from_str_radix(digits, radix).ok()
+}
@@ -0,0 +1,227 @@
+use crate::{ClockEdge, Model, ModelError, Simulator, StopReason};
+use std::fmt;
+
+/// Failure of `TestBench::expect` or `TestBench::expect_sequence`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpectFailure {
+    pub signal: String,
+    pub clock: String,
+    /// Expected value, or the expected values at the consecutive edges of a sequence
+    pub expected: Vec<usize>,
+    /// Cycles waited for the value, None for a sequence
+    pub cycles: Option<usize>,
+    /// (time, value) of the signal when it was checked
+    pub observed: Vec<(u64, usize)>,
+}
+
+impl fmt::Display for ExpectFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.cycles {
+            Some(cycles) => write!(
+                f,
+                "{} != {} within {cycles} cycles of {}",
+                self.signal, self.expected[0], self.clock
+            )?,
+            None => {
+                let expected: Vec<_> = self.expected.iter().map(|x| x.to_string()).collect();
+                write!(
+                    f,
+                    "{} != [{}] at edges of {}",
+                    self.signal,
+                    expected.join(", "),
+                    self.clock
+                )?;
+            }
+        }
+        for (time, value) in &self.observed {
+            write!(f, "\n  {time}ns: {value} ({value:#x})")?;
+        }
+        Ok(())
+    }
+}
+
+/// Blocking-style helpers over `Simulator` for writing tests
+/// pauses requested by hooks are ignored while waiting
+pub struct TestBench {
+    simulator: Simulator,
+    clock: Option<String>,
+}
+
+impl TestBench {
+    pub fn new(simulator: Simulator) -> Self {
+        TestBench {
+            simulator,
+            clock: None,
+        }
+    }
+
+    /// Clock of `expect` and `expect_sequence`, the first clock of the simulator by default
+    pub fn clock(mut self, name: &str) -> Self {
+        self.clock = Some(name.to_string());
+        self
+    }
+
+    pub fn simulator(&self) -> &Simulator {
+        &self.simulator
+    }
+
+    pub fn simulator_mut(&mut self) -> &mut Simulator {
+        &mut self.simulator
+    }
+
+    pub fn into_inner(self) -> Simulator {
+        self.simulator
+    }
+
+    pub fn model(&self) -> &Model {
+        self.simulator.model()
+    }
+
+    /// Current simulation time in nanoseconds
+    pub fn time(&self) -> u64 {
+        self.simulator.time()
+    }
+
+    /// Drive the input port
+    pub fn drive(&mut self, port: &str, value: usize) -> Result<(), ModelError> {
+        self.simulator.model_mut().try_input(port, value)
+    }
+
+    /// Read any signal including internal signals
+    pub fn read(&self, path: &str) -> Result<usize, ModelError> {
+        self.model()
+            .peek(path)
+            .ok_or_else(|| ModelError::SignalNotFound(path.to_string()))
+    }
+
+    /// Wait for the next rising edge of the clock
+    pub fn wait_posedge(&mut self, clock: &str) -> Result<(), ModelError> {
+        self.wait_edge(clock, ClockEdge::Rising)
+    }
+
+    /// Wait for the next falling edge of the clock
+    pub fn wait_negedge(&mut self, clock: &str) -> Result<(), ModelError> {
+        self.wait_edge(clock, ClockEdge::Falling)
+    }
+
+    /// Wait for the nanoseconds
+    pub fn wait(&mut self, duration_ns: u64) -> Result<(), ModelError> {
+        let end_time = self.time() + duration_ns;
+        loop {
+            match self.simulator.advance_to(end_time) {
+                StopReason::Completed => return Ok(()),
+                StopReason::Paused => {}
+                StopReason::Failed(x) => return Err(x),
+                StopReason::Aborted | StopReason::Finished(_) => {
+                    return Err(ModelError::SimulationStopped(self.time()));
+                }
+            }
+        }
+    }
+
+    /// Wait until the signal has the value, or `timeout_ns` nanoseconds have passed
+    /// returns immediately if the signal already has the value
+    pub fn wait_until(
+        &mut self,
+        path: &str,
+        value: usize,
+        timeout_ns: u64,
+    ) -> Result<(), ModelError> {
+        let end_time = self.time() + timeout_ns;
+        loop {
+            if self.read(path)? == value {
+                return Ok(());
+            }
+            let next = self.simulator.next_time();
+            if next.is_none_or(|x| x > end_time) {
+                return Err(ModelError::Timeout {
+                    condition: format!("{path} == {value}"),
+                    time: self.time(),
+                });
+            }
+            if self.simulator.step().is_none() {
+                return Err(ModelError::SimulationStopped(self.time()));
+            }
+        }
+    }
+
+    /// Run the clock until the signal has the value after a rising edge
+    /// the current value is checked first, and the history of the checked values is
+    /// reported by `ModelError::ExpectFailed` if the value isn't seen within the cycles
+    pub fn expect(
+        &mut self,
+        path: &str,
+        value: usize,
+        within_cycles: usize,
+    ) -> Result<(), ModelError> {
+        let clock = self.expect_clock()?;
+        let mut observed = vec![(self.time(), self.read(path)?)];
+        for _ in 0..within_cycles {
+            if observed.last().is_some_and(|x| x.1 == value) {
+                break;
+            }
+            self.wait_posedge(&clock)?;
+            observed.push((self.time(), self.read(path)?));
+        }
+        if observed.last().is_some_and(|x| x.1 == value) {
+            return Ok(());
+        }
+        Err(ModelError::ExpectFailed(ExpectFailure {
+            signal: path.to_string(),
+            clock,
+            expected: vec![value],
+            cycles: Some(within_cycles),
+            observed,
+        }))
+    }
+
+    /// Run the clock for the values, and check the signal has each value
+    /// after the consecutive rising edges
+    pub fn expect_sequence(&mut self, path: &str, values: &[usize]) -> Result<(), ModelError> {
+        let clock = self.expect_clock()?;
+        let mut observed = Vec::new();
+        for expected in values {
+            self.wait_posedge(&clock)?;
+            let value = self.read(path)?;
+            observed.push((self.time(), value));
+            if value != *expected {
+                return Err(ModelError::ExpectFailed(ExpectFailure {
+                    signal: path.to_string(),
+                    clock,
+                    expected: values.to_vec(),
+                    cycles: None,
+                    observed,
+                }));
+            }
+        }
+        Ok(())
+    }
+
+    fn expect_clock(&self) -> Result<String, ModelError> {
+        self.clock
+            .clone()
+            .or_else(|| self.simulator.first_clock())
+            .ok_or_else(|| ModelError::InvalidClock {
+                name: String::new(),
+                reason: "the simulator has no clocks".to_string(),
+            })
+    }
+
+    fn wait_edge(&mut self, clock: &str, edge: ClockEdge) -> Result<(), ModelError> {
+        self.simulator.check_clock(clock)?;
+        if !self.simulator.is_clock_enabled(clock) {
+            return Err(ModelError::InvalidClock {
+                name: clock.to_string(),
+                reason: "the clock is gated".to_string(),
+            });
+        }
+        loop {
+            let Some(event) = self.simulator.step() else {
+                return Err(ModelError::SimulationStopped(self.time()));
+            };
+            if event.clock.as_deref() == Some(clock) && event.edge == Some(edge) {
+                return Ok(());
+            }
+        }
+    }
+}
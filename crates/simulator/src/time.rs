@@ -0,0 +1,80 @@
+/// Time literals in nanoseconds, which is the time unit of the simulator
+/// e.g. `10.ns()`, `2.us()`
+pub trait TimeUnit {
+    fn ns(self) -> u64;
+    fn us(self) -> u64;
+    fn ms(self) -> u64;
+}
+
+impl TimeUnit for u64 {
+    fn ns(self) -> u64 {
+        self
+    }
+
+    fn us(self) -> u64 {
+        self * 1_000
+    }
+
+    fn ms(self) -> u64 {
+        self * 1_000_000
+    }
+}
+
+/// Parse a time like `100`, `100ns`, `2us`, `1ms` or `1s` in nanoseconds
+/// the unit is nanoseconds if omitted
+pub fn parse_time(text: &str) -> Option<u64> {
+    let (digits, scale) = if let Some(x) = text.strip_suffix("ns") {
+        (x, 1)
+    } else if let Some(x) = text.strip_suffix("us") {
+        (x, 1_000)
+    } else if let Some(x) = text.strip_suffix("ms") {
+        (x, 1_000_000)
+    } else if let Some(x) = text.strip_suffix('s') {
+        (x, 1_000_000_000)
+    } else {
+        (text, 1)
+    };
+    digits.trim().parse::<u64>().ok()?.checked_mul(scale)
+}
+
+// Wall clock used for the watchdog, statistics and dates in the output files
+// std::time panics on wasm32-unknown-unknown, so the clock is stopped at the UNIX epoch there
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub(crate) use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub(crate) use stopped::{Instant, SystemTime, UNIX_EPOCH};
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+mod stopped {
+    use std::convert::Infallible;
+    use std::time::Duration;
+
+    #[derive(Debug, Clone, Copy)]
+    pub(crate) struct Instant;
+
+    impl Instant {
+        pub(crate) fn now() -> Self {
+            Instant
+        }
+
+        pub(crate) fn elapsed(&self) -> Duration {
+            Duration::ZERO
+        }
+    }
+
+    #[derive(Debug, Clone, Copy)]
+    pub(crate) struct SystemTime;
+
+    pub(crate) const UNIX_EPOCH: SystemTime = SystemTime;
+
+    impl SystemTime {
+        pub(crate) fn now() -> Self {
+            SystemTime
+        }
+
+        pub(crate) fn duration_since(&self, _earlier: SystemTime) -> Result<Duration, Infallible> {
+            Ok(Duration::ZERO)
+        }
+    }
+}
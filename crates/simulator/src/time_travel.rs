@@ -0,0 +1,68 @@
+use crate::SimulatorState;
+
+// シミュレーション中の位置（時刻と、その時刻に処理したクロックイベントの数）
+pub(crate) type Position = (u64, usize);
+
+#[derive(Clone)]
+pub(crate) struct Checkpoint {
+    pub(crate) position: Position,
+    pub(crate) state: SimulatorState,
+    pub(crate) assertions: usize, // この時点までのアサーション失敗の数
+}
+
+// History of a simulation to go back to a previous point
+// checkpoints are taken every `interval` steps, and a point between them is reached
+// by restoring the previous checkpoint and re-executing the steps with the recorded inputs
+pub(crate) struct TimeTravel {
+    interval: usize,
+    steps: Vec<Position>,         // 各ステップ後の位置
+    checkpoints: Vec<Checkpoint>, // 位置の順に並んだチェックポイント
+}
+
+impl TimeTravel {
+    pub(crate) fn new(interval: usize, start: Checkpoint) -> Self {
+        TimeTravel {
+            interval: interval.max(1),
+            steps: Vec::new(),
+            checkpoints: vec![start],
+        }
+    }
+
+    pub(crate) fn interval(&self) -> usize {
+        self.interval
+    }
+
+    // ステップを記録し、チェックポイントを取るべきであれば true を返す
+    pub(crate) fn push(&mut self, position: Position) -> bool {
+        self.steps.push(position);
+        self.steps.len().is_multiple_of(self.interval)
+    }
+
+    pub(crate) fn add_checkpoint(&mut self, checkpoint: Checkpoint) {
+        self.checkpoints.push(checkpoint);
+    }
+
+    pub(crate) fn start(&self) -> Position {
+        self.checkpoints[0].position
+    }
+
+    // 最後のステップを取り除き、その前の位置を返す
+    pub(crate) fn pop(&mut self) -> Option<Position> {
+        self.steps.pop()?;
+        Some(self.steps.last().copied().unwrap_or(self.start()))
+    }
+
+    // 位置より前の最後のチェックポイント
+    pub(crate) fn latest(&self, position: Position) -> Option<&Checkpoint> {
+        self.checkpoints
+            .iter()
+            .rev()
+            .find(|x| x.position <= position)
+    }
+
+    // 位置より後の履歴を捨てる
+    pub(crate) fn truncate(&mut self, position: Position) {
+        self.steps.retain(|x| *x <= position);
+        self.checkpoints.retain(|x| x.position <= position);
+    }
+}
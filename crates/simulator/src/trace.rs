@@ -0,0 +1,236 @@
+use std::collections::{BTreeMap, VecDeque};
+
+// Multi-resolution trace storage
+// recent samples are kept at full resolution, and older samples are folded into
+// summary buckets (min/max/first/last) so that memory stays bounded on long runs
+pub struct TraceStorage {
+    window: usize,      // number of full-resolution samples kept per signal
+    bucket_span: u64,   // initial time span of a summary bucket [ns]
+    max_buckets: usize, // number of summary buckets kept per signal
+    signals: BTreeMap<String, SignalTrace>,
+}
+
+/// Summary of the values of a signal in [start, end)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceBucket {
+    pub start: u64,
+    pub end: u64,
+    pub min: usize,
+    pub max: usize,
+    pub first: usize,
+    pub last: usize,
+    pub count: usize,
+}
+
+impl TraceBucket {
+    fn new(start: u64, end: u64, value: usize) -> Self {
+        TraceBucket {
+            start,
+            end,
+            min: value,
+            max: value,
+            first: value,
+            last: value,
+            count: 1,
+        }
+    }
+
+    fn push(&mut self, value: usize) {
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.last = value;
+        self.count += 1;
+    }
+
+    // The time range of self is kept as is
+    fn merge(&mut self, other: &TraceBucket) {
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.last = other.last;
+        self.count += other.count;
+    }
+}
+
+struct SignalTrace {
+    recent: VecDeque<(u64, usize)>, // (time, value)
+    summary: Vec<TraceBucket>,
+    bucket_span: u64,
+}
+
+impl SignalTrace {
+    fn fold(&mut self, time: u64, value: usize, max_buckets: usize) {
+        if let Some(last) = self.summary.last_mut()
+            && time < last.end
+        {
+            last.push(value);
+            return;
+        }
+
+        let start = time - time % self.bucket_span;
+        self.summary
+            .push(TraceBucket::new(start, start + self.bucket_span, value));
+
+        // Halve the resolution of the whole summary when it grows too large
+        if self.summary.len() > max_buckets {
+            let mut merged: Vec<TraceBucket> = Vec::with_capacity(self.summary.len() / 2 + 1);
+            let span = self.bucket_span * 2;
+            for bucket in &self.summary {
+                let start = bucket.start - bucket.start % span;
+                match merged.last_mut() {
+                    Some(last) if last.start == start => last.merge(bucket),
+                    _ => {
+                        let mut new = *bucket;
+                        new.start = start;
+                        new.end = start + span;
+                        merged.push(new);
+                    }
+                }
+            }
+            self.summary = merged;
+            self.bucket_span = span;
+        }
+    }
+}
+
+impl TraceStorage {
+    /// Create a storage keeping `window` samples per signal at full resolution,
+    /// and summarizing older samples into buckets of `bucket_span` nanoseconds
+    pub fn new(window: usize, bucket_span: u64) -> Self {
+        TraceStorage {
+            window: window.max(1),
+            bucket_span: bucket_span.max(1),
+            max_buckets: 1024,
+            signals: BTreeMap::new(),
+        }
+    }
+
+    /// Limit the number of summary buckets per signal
+    /// older history is coarsened when the limit is exceeded
+    pub fn with_max_buckets(mut self, max_buckets: usize) -> Self {
+        self.max_buckets = max_buckets.max(2);
+        self
+    }
+
+    /// Record a sample of a signal
+    pub fn record(&mut self, time: u64, name: &str, value: usize) {
+        let bucket_span = self.bucket_span;
+        let trace = self
+            .signals
+            .entry(name.to_string())
+            .or_insert_with(|| SignalTrace {
+                recent: VecDeque::new(),
+                summary: Vec::new(),
+                bucket_span,
+            });
+
+        trace.recent.push_back((time, value));
+        while trace.recent.len() > self.window {
+            if let Some((time, value)) = trace.recent.pop_front() {
+                trace.fold(time, value, self.max_buckets);
+            }
+        }
+    }
+
+    /// Names of all recorded signals
+    pub fn signals(&self) -> impl Iterator<Item = &str> {
+        self.signals.keys().map(|x| x.as_str())
+    }
+
+    /// Full-resolution samples of a signal
+    pub fn recent(&self, name: &str) -> Vec<(u64, usize)> {
+        self.signals
+            .get(name)
+            .map(|x| x.recent.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Summarized samples of a signal older than the full-resolution window
+    pub fn summary(&self, name: &str) -> &[TraceBucket] {
+        self.signals
+            .get(name)
+            .map(|x| x.summary.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Value of a signal at the given time
+    /// for summarized history, the last value of the bucket is returned
+    pub fn value_at(&self, name: &str, time: u64) -> Option<usize> {
+        let trace = self.signals.get(name)?;
+        if let Some((_, value)) = trace.recent.iter().rev().find(|(t, _)| *t <= time) {
+            return Some(*value);
+        }
+        trace
+            .summary
+            .iter()
+            .rev()
+            .find(|x| x.start <= time)
+            .map(|x| x.last)
+    }
+
+    /// Overview of a signal in [start, end) divided into `columns` buckets
+    /// this is the view used to display long runs end-to-end
+    /// columns without samples hold the value before them, with `count` of 0
+    pub fn overview(
+        &self,
+        name: &str,
+        start: u64,
+        end: u64,
+        columns: usize,
+    ) -> Vec<Option<TraceBucket>> {
+        let mut ret = vec![None; columns];
+        let Some(trace) = self.signals.get(name) else {
+            return ret;
+        };
+        if columns == 0 || end <= start {
+            return ret;
+        }
+
+        let span = (end - start).div_ceil(columns as u64);
+        let column = |time: u64| ((time - start) / span) as usize;
+
+        let mut put = |index: usize, bucket: &TraceBucket| {
+            let x: &mut Option<TraceBucket> = &mut ret[index];
+            match x {
+                Some(x) => x.merge(bucket),
+                None => {
+                    let mut new = *bucket;
+                    new.start = start + span * index as u64;
+                    new.end = new.start + span;
+                    *x = Some(new);
+                }
+            }
+        };
+
+        for bucket in &trace.summary {
+            if bucket.end <= start || bucket.start >= end {
+                continue;
+            }
+            put(column(bucket.start.max(start)), bucket);
+        }
+        for (time, value) in &trace.recent {
+            if *time < start || *time >= end {
+                continue;
+            }
+            put(column(*time), &TraceBucket::new(*time, *time + 1, *value));
+        }
+
+        // サンプルのない列は直前の値を保持する
+        for index in 0..columns {
+            if ret[index].is_some() {
+                continue;
+            }
+            let held = match index {
+                0 => self.value_at(name, start),
+                _ => ret[index - 1].map(|x| x.last),
+            };
+            if let Some(value) = held {
+                let start = start + span * index as u64;
+                let mut bucket = TraceBucket::new(start, start + span, value);
+                bucket.count = 0;
+                ret[index] = Some(bucket);
+            }
+        }
+
+        ret
+    }
+}
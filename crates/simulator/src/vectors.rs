@@ -0,0 +1,203 @@
+use crate::stimulus::parse_value;
+use crate::{Direction, Model, ModelError};
+use std::fmt;
+use std::path::Path;
+
+/// First row of test vectors which didn't match the model
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VectorMismatch {
+    /// Index of the row from 0
+    pub row: usize,
+    /// Line of the row in the CSV
+    pub line: Option<usize>,
+    /// Values driven to the input ports
+    pub inputs: Vec<(String, usize)>,
+    /// (signal, expected, actual) of the signals which differ
+    pub diffs: Vec<(String, usize, usize)>,
+}
+
+impl fmt::Display for VectorMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "row {}", self.row)?;
+        if let Some(line) = self.line {
+            write!(f, " (line {line})")?;
+        }
+        let inputs: Vec<_> = self
+            .inputs
+            .iter()
+            .map(|(x, y)| format!("{x}={y}"))
+            .collect();
+        write!(f, ": {}", inputs.join(", "))?;
+        for (signal, expected, actual) in &self.diffs {
+            write!(
+                f,
+                "\n  {signal}: expected {expected} ({expected:#x}), actual {actual} ({actual:#x})"
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Table of input vectors and expected values run through a `Model`
+///
+/// Columns are signals of the model, input ports are driven and the other signals are compared.
+/// In CSV, the first line is the header of the signal names and the following lines are rows
+/// separated by commas, tabs or spaces. Values are decimal or prefixed by `0x`, `0o` or `0b`,
+/// and `-` or `x` is a value which is not driven or compared.
+/// Empty lines and lines starting with `#` are skipped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TestVectors {
+    columns: Vec<String>,
+    rows: Vec<(Option<usize>, Vec<Option<usize>>)>, // (CSV の行番号, 値)
+    clock: Option<String>,
+}
+
+impl TestVectors {
+    pub fn new(columns: &[&str]) -> Self {
+        TestVectors {
+            columns: columns.iter().map(|x| x.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Add a row of the values in the order of the columns
+    pub fn row(mut self, values: &[usize]) -> Self {
+        self.rows
+            .push((None, values.iter().map(|x| Some(*x)).collect()));
+        self
+    }
+
+    /// Parse test vectors in CSV
+    pub fn parse(text: &str) -> Result<Self, ModelError> {
+        Self::parse_with_path(text, "<vectors>")
+    }
+
+    /// Read test vectors from a CSV file
+    pub fn from_file<T: AsRef<Path>>(path: T) -> Result<Self, ModelError> {
+        let path = path.as_ref().to_string_lossy().to_string();
+        let text = std::fs::read_to_string(&path).map_err(|x| ModelError::ReadFailed {
+            path: path.clone(),
+            cause: x.to_string(),
+        })?;
+        Self::parse_with_path(&text, &path)
+    }
+
+    /// Pulse the clock after driving the inputs of each row, and compare the values after the edge
+    /// without a clock, the values are compared after the combinational logic settles
+    pub fn clock(mut self, name: &str) -> Self {
+        self.clock = Some(name.to_string());
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Run the rows in order, and return the number of the rows
+    /// stops at the first row which doesn't match with `ModelError::VectorMismatch`
+    pub fn run(&self, model: &mut Model) -> Result<usize, ModelError> {
+        let inputs: Vec<bool> = self
+            .columns
+            .iter()
+            .map(|name| {
+                let signal = model.signals().find(|x| x.name == *name);
+                match signal {
+                    Some(x) => Ok(x.direction == Direction::Input),
+                    None => Err(ModelError::SignalNotFound(name.clone())),
+                }
+            })
+            .collect::<Result<_, _>>()?;
+
+        for (i, (line, values)) in self.rows.iter().enumerate() {
+            if values.len() != self.columns.len() {
+                return Err(ModelError::ParseFailed {
+                    path: "<vectors>".to_string(),
+                    cause: format!(
+                        "row {i} has {} values for {} columns",
+                        values.len(),
+                        self.columns.len()
+                    ),
+                });
+            }
+
+            let mut driven = Vec::new();
+            for ((name, value), input) in self.columns.iter().zip(values).zip(&inputs) {
+                if let (Some(value), true) = (value, input) {
+                    model.try_input(name, *value)?;
+                    driven.push((name.clone(), *value));
+                }
+            }
+            if let Some(clock) = &self.clock {
+                model.clock_rise(clock);
+                model.clock_fall(clock);
+            }
+
+            let mut diffs = Vec::new();
+            for ((name, value), input) in self.columns.iter().zip(values).zip(&inputs) {
+                if let (Some(expected), false) = (value, input) {
+                    let actual = model.peek(name).unwrap_or_default();
+                    if actual != *expected {
+                        diffs.push((name.clone(), *expected, actual));
+                    }
+                }
+            }
+            if !diffs.is_empty() {
+                return Err(ModelError::VectorMismatch(VectorMismatch {
+                    row: i,
+                    line: *line,
+                    inputs: driven,
+                    diffs,
+                }));
+            }
+        }
+        Ok(self.rows.len())
+    }
+
+    fn parse_with_path(text: &str, path: &str) -> Result<Self, ModelError> {
+        let error = |line: usize, cause: &str| ModelError::ParseFailed {
+            path: path.to_string(),
+            cause: format!("line {line}: {cause}"),
+        };
+
+        let mut vectors = TestVectors::default();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields: Vec<_> = line
+                .split(|x: char| x == ',' || x.is_whitespace())
+                .filter(|x| !x.is_empty())
+                .collect();
+            if vectors.columns.is_empty() {
+                vectors.columns = fields.iter().map(|x| x.to_string()).collect();
+                continue;
+            }
+            if fields.len() != vectors.columns.len() {
+                return Err(error(
+                    i + 1,
+                    &format!("expected {} values", vectors.columns.len()),
+                ));
+            }
+
+            let mut values = Vec::new();
+            for field in fields {
+                let value = match field {
+                    "-" | "x" | "X" => None,
+                    _ => Some(
+                        parse_value(field)
+                            .ok_or_else(|| error(i + 1, &format!("invalid value \"{field}\"")))?,
+                    ),
+                };
+                values.push(value);
+            }
+            vectors.rows.push((Some(i + 1), values));
+        }
+        Ok(vectors)
+    }
+}
@@ -0,0 +1,122 @@
+use crate::{HookId, Model, Simulator, VCDLoggerHook};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use wasm_bindgen::prelude::*;
+
+// VCD の出力先、JavaScript から読み出した分は取り除く
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    fn take(&self) -> Vec<u8> {
+        self.0
+            .lock()
+            .map(|mut x| std::mem::take(&mut *x))
+            .unwrap_or_default()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Ok(mut x) = self.0.lock() {
+            x.extend_from_slice(buf);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// JavaScript API of the simulator for the playground
+// signal values are usize, so they are limited to 32 bits on wasm32
+#[wasm_bindgen(js_name = Simulator)]
+pub struct WasmSimulator {
+    simulator: Simulator,
+    vcd: Option<(HookId, SharedBuffer)>,
+}
+
+#[wasm_bindgen(js_class = Simulator)]
+impl WasmSimulator {
+    /// Create a simulator of the top module from Veryl source text
+    /// all clocks of the module are driven with the same period [ns]
+    #[wasm_bindgen(constructor)]
+    pub fn new(source: &str, top: &str, period: u64) -> Result<WasmSimulator, JsError> {
+        let model = Model::from_source(source, top, HashMap::new())?;
+        let clocks = model
+            .clocks()
+            .iter()
+            .map(|(name, _)| (name.clone(), period))
+            .collect();
+        Ok(WasmSimulator {
+            simulator: Simulator::new(model, clocks),
+            vcd: None,
+        })
+    }
+
+    pub fn reset(&mut self) {
+        self.simulator.reset();
+    }
+
+    /// Process the next event, returns false if there are no events
+    pub fn step(&mut self) -> bool {
+        self.simulator.step().is_some()
+    }
+
+    pub fn run(&mut self, duration: u64) {
+        self.simulator.run(duration);
+    }
+
+    #[wasm_bindgen(js_name = runCycles)]
+    pub fn run_cycles(&mut self, clock: &str, cycles: usize) -> Result<(), JsError> {
+        self.simulator.run_cycles(clock, cycles)?;
+        Ok(())
+    }
+
+    /// Current time [ns]
+    pub fn time(&self) -> u64 {
+        self.simulator.time()
+    }
+
+    pub fn set(&mut self, port: &str, value: usize) -> Result<(), JsError> {
+        self.simulator.model_mut().try_input(port, value)?;
+        Ok(())
+    }
+
+    /// Value of a port or an internal signal
+    pub fn get(&self, path: &str) -> Option<usize> {
+        self.simulator.model().peek(path)
+    }
+
+    /// All signals as a JSON array of {name, direction, width, value}
+    pub fn signals(&self) -> String {
+        let signals: Vec<_> = self.simulator.model().signals().collect();
+        serde_json::to_string(&signals).unwrap_or_default()
+    }
+
+    /// Start recording the waveform, it's read by `takeVcd`
+    #[wasm_bindgen(js_name = startVcd)]
+    pub fn start_vcd(&mut self) {
+        if self.vcd.is_some() {
+            return;
+        }
+        let buffer = SharedBuffer::default();
+        let hook = VCDLoggerHook::from_writer(buffer.clone());
+        let id = self.simulator.add_hook(Box::new(hook));
+        self.vcd = Some((id, buffer));
+    }
+
+    /// VCD text written since the last call
+    #[wasm_bindgen(js_name = takeVcd)]
+    pub fn take_vcd(&mut self) -> Result<String, JsError> {
+        let Some((id, buffer)) = &self.vcd else {
+            return Ok(String::new());
+        };
+        self.simulator
+            .with_hook_mut(*id, |x: &mut VCDLoggerHook| x.flush())
+            .transpose()?;
+        Ok(String::from_utf8_lossy(&buffer.take()).to_string())
+    }
+}
@@ -1,9 +1,21 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use veryl_analyzer::{Analyzer, AnalyzerError, symbol_table};
 use veryl_metadata::Metadata;
 use veryl_parser::Parser;
-use veryl_simulator::{BufLogger, Model, Simulator, VCDLoggerHook};
+use veryl_simulator::{
+    ActivityHook, ApbMaster, ApbSlave, ApbTransfer, AssertHook, AssertSeverity, AsyncTestBench,
+    BitVec, BreakPoint, BufLogger, CaseCheck, ClockEdge, ClockJitter, ClockType, CoverGroup,
+    CoverageDb, CoverageHook, Coverpoint, CoverpointReport, CsvLoggerHook, Direction, Distribution,
+    EvalMode, FinishReason, HandshakeChecker, HandshakeTransfer, Hook, HookAction, HookError,
+    I2cBus, I2cMaster, I2cSlave, I2cTransfer, Jitter, JsonLoggerHook, MemoryModel, Model,
+    ModelError, PropExpr, Property, RandomDriver, Regression, Replay, ResetType, RunResult,
+    Scenario, Scoreboard, ScoreboardMismatch, SignalDelta, SignalFilter, SignalStatsHook,
+    Simulator, SimulatorState, SpiMaster, SpiReport, SpiSlave, StepEvent, Stimulus, StimulusRow,
+    StopReason, TemporalHook, TestBench, TestVectors, TimeUnit, TraceStorage, Transaction,
+    TransactionRecorder, UartModel, UnknownPolicy, VCDLoggerHook, VcdCompareHook, VcdMismatch,
+    WatchEvent, WatchPoint, assert_wave_snapshot, check_snapshot,
+};
 
 #[track_caller]
 fn analyze(code: &str) -> Vec<AnalyzerError> {
@@ -31,7 +43,7 @@ fn test_comb() {
     let mut init = HashMap::new();
     init.insert("a".to_string(), 10);
     init.insert("b".to_string(), 20);
-    let mut model = Model::new("CombTest", init);
+    let mut model = Model::new("CombTest", init).unwrap();
     assert_eq!(model.get("c"), Some(30));
 
     model.input("a", 20);
@@ -43,7 +55,7 @@ fn test_comb() {
 fn test_ff() {
     let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
     analyze(&code);
-    let mut model = Model::new("FFTest", HashMap::new());
+    let mut model = Model::new("FFTest", HashMap::new()).unwrap();
 
     model.reset();
 
@@ -65,7 +77,7 @@ fn test_ff() {
 fn test_ff_simulator() {
     let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
     analyze(&code);
-    let model = Model::new("FFTest", HashMap::new());
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
 
     // Create clock intervals map
     let mut clocks = HashMap::new();
@@ -83,7 +95,7 @@ fn test_ff_simulator() {
 fn test_vcd_logger() {
     let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
     analyze(&code);
-    let model = Model::new("FFTest", HashMap::new());
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
 
     // Create clock intervals map
     let mut clocks = HashMap::new();
@@ -98,3 +110,4588 @@ fn test_vcd_logger() {
     simulator.reset();
     simulator.run(5000); // Run for 5000ns
 }
+
+#[test]
+fn test_select() {
+    let code = std::fs::read_to_string("tests/select.veryl").unwrap();
+    analyze(&code);
+
+    let mut init = HashMap::new();
+    init.insert("a".to_string(), 0b1011_0110);
+    let model = Model::new("SelectTest", init).unwrap();
+    assert_eq!(model.get("b"), Some(0b1011));
+    assert_eq!(model.get("c"), Some(1));
+    assert_eq!(model.get("d"), Some(0));
+    assert_eq!(model.get("e"), Some(8));
+    assert_eq!(model.get("f"), Some(0b1101));
+}
+
+#[test]
+fn test_vectors() {
+    let code = std::fs::read_to_string("tests/select.veryl").unwrap();
+    analyze(&code);
+    let mut model = Model::new("SelectTest", HashMap::new()).unwrap();
+    let vectors = TestVectors::new(&["a", "b", "c", "d", "f"])
+        .row(&[0b1011_0110, 0b1011, 1, 0, 0b1101])
+        .row(&[0b0000_0001, 0b0000, 0, 1, 0b0000])
+        .row(&[0b0111_1100, 0b0111, 0, 0, 0b1111]);
+    assert_eq!(vectors.run(&mut model), Ok(3));
+
+    // 順序回路はクロックのエッジ後の値を比較する
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+    let mut model = Model::new("FFTest", HashMap::new()).unwrap();
+    let vectors = TestVectors::parse(
+        "# reset, then count
+        rst, a, b
+        0,   0, 0
+        1,   1, 1
+        1,   0, 2
+        1,   -, 0x3
+        ",
+    )
+    .unwrap()
+    .clock("clk");
+    assert_eq!(vectors.run(&mut model), Ok(4));
+
+    // 最初に一致しなかった行を報告する
+    let code = std::fs::read_to_string("tests/comb.veryl").unwrap();
+    analyze(&code);
+    let mut model = Model::new("CombTest", HashMap::new()).unwrap();
+    let vectors = TestVectors::parse("a b c\n1 2 3\n2 2 5\n3 3 7\n").unwrap();
+    let Err(ModelError::VectorMismatch(mismatch)) = vectors.run(&mut model) else {
+        panic!("mismatch is not reported");
+    };
+    assert_eq!(mismatch.row, 1);
+    assert_eq!(mismatch.line, Some(3));
+    assert_eq!(mismatch.diffs, [("c".to_string(), 5, 4)]);
+    assert_eq!(
+        mismatch.to_string(),
+        "row 1 (line 3): a=2, b=2\n  c: expected 5 (0x5), actual 4 (0x4)"
+    );
+
+    let vectors = TestVectors::new(&["a", "z"]).row(&[1, 1]);
+    assert_eq!(
+        vectors.run(&mut model),
+        Err(ModelError::SignalNotFound("z".to_string()))
+    );
+    assert!(TestVectors::parse("a b\n1\n").is_err());
+}
+
+#[test]
+fn test_trace_storage() {
+    let mut storage = TraceStorage::new(10, 100).with_max_buckets(4);
+    for i in 0..100 {
+        storage.record(i * 10, "a", i as usize);
+    }
+
+    // Recent samples are kept at full resolution
+    let recent = storage.recent("a");
+    assert_eq!(recent.len(), 10);
+    assert_eq!(recent.first(), Some(&(900, 90)));
+    assert_eq!(storage.value_at("a", 955), Some(95));
+
+    // Older samples are summarized into a bounded number of buckets
+    let summary = storage.summary("a");
+    assert!(summary.len() <= 4);
+    assert_eq!(summary.first().unwrap().first, 0);
+    assert_eq!(summary.iter().map(|x| x.count).sum::<usize>(), 90);
+    assert_eq!(summary.iter().map(|x| x.max).max(), Some(89));
+
+    // The whole run can be viewed at once
+    let overview = storage.overview("a", 0, 1000, 2);
+    let first = overview[0].unwrap();
+    let second = overview[1].unwrap();
+    assert_eq!(first.min, 0);
+    assert_eq!(second.max, 99);
+    assert_eq!(first.count + second.count, 100);
+
+    // Columns after the last sample hold its value
+    let overview = storage.overview("a", 500, 2000, 3);
+    assert_eq!(overview[0].unwrap().max, 99);
+    assert_eq!(overview[1].unwrap().count, 0);
+    assert_eq!(overview[1].unwrap().last, 99);
+    assert_eq!(overview[2].unwrap().first, 99);
+}
+
+#[test]
+fn test_slice_assignment() {
+    let code = std::fs::read_to_string("tests/slice.veryl").unwrap();
+    analyze(&code);
+
+    let mut init = HashMap::new();
+    init.insert("a".to_string(), 0x5);
+    init.insert("i".to_string(), 2);
+    let mut model = Model::new("SliceTest", init).unwrap();
+    assert_eq!(model.get("b"), Some(0xf5));
+    assert_eq!(model.get("c"), Some(0x50));
+
+    model.reset();
+    model.clock();
+    assert_eq!(model.get("d"), Some(0x5));
+
+    model.input("i", 1);
+    assert_eq!(model.get("d"), Some(0x0));
+
+    model.input("a", 0x3);
+    model.clock();
+    assert_eq!(model.get("d"), Some(0x3));
+    model.input("i", 2);
+    assert_eq!(model.get("d"), Some(0x5));
+}
+
+#[test]
+fn test_unknown_signal() {
+    let code = std::fs::read_to_string("tests/unknown.veryl").unwrap();
+    analyze(&code);
+
+    let mut model = Model::new("UnknownTest", HashMap::new()).unwrap();
+    model.input("a", 1);
+    assert_eq!(model.get("b"), Some(1));
+
+    let unknowns = model.unknown_signals();
+    assert_eq!(unknowns.len(), 1);
+    assert_eq!(unknowns[0].name, "c");
+    // initial evaluation and input change settle in a single pass each
+    assert_eq!(unknowns[0].count, 2);
+    assert_eq!(unknowns[0].locations[0].line, 5);
+}
+
+#[test]
+fn test_unknown_signal_error() {
+    let code = std::fs::read_to_string("tests/unknown.veryl").unwrap();
+    analyze(&code);
+
+    let mut model = Model::new("UnknownTest", HashMap::new()).unwrap();
+    model.set_unknown_policy(UnknownPolicy::Error);
+    let Err(ModelError::UnknownSignal { name, locations }) = model.try_input("a", 1) else {
+        panic!("unknown signal is not reported");
+    };
+    assert_eq!(name, "c");
+    assert!(locations.starts_with("5:"));
+    // the signal evaluates as 0, and the failure is kept until reset
+    assert_eq!(model.get("b"), Some(1));
+    assert!(model.try_input("a", 2).is_err());
+    model.set_unknown_policy(UnknownPolicy::Zero);
+    model.reset();
+    assert_eq!(model.unknown_failure(), None);
+
+    let mut model = Model::new("UnknownTest", HashMap::new()).unwrap();
+    model.set_unknown_policy(UnknownPolicy::WarnOnce);
+    assert_eq!(model.try_input("a", 1), Ok(()));
+
+    let mut model = Model::new("UnknownClockTest", HashMap::new()).unwrap();
+    model.set_unknown_policy(UnknownPolicy::Error);
+    let mut simulator = Simulator::builder(model).clock("clk", 10).build().unwrap();
+    simulator.reset();
+    let StopReason::Failed(ModelError::UnknownSignal { name, .. }) = simulator.run(100) else {
+        panic!("unknown signal is not reported");
+    };
+    assert_eq!(name, "d");
+    assert!(simulator.time() < 100);
+}
+
+#[test]
+fn test_foreign_function() {
+    let code = std::fs::read_to_string("tests/dpi.veryl").unwrap();
+    analyze(&code);
+
+    for mode in [EvalMode::Compiled, EvalMode::Interpreted] {
+        let mut model = Model::new("DpiTest", HashMap::new()).unwrap();
+        model.set_eval_mode(mode).unwrap();
+
+        // 登録前の呼び出しは 0 を返し、未知の名前として記録する
+        model.input("a", 3);
+        assert_eq!(model.get("y"), Some(0));
+        assert!(model.unknown_signals().iter().any(|x| x.name == "dsp_mul"));
+
+        model.register_function("dsp_mul", |args| args[0] * args[1]);
+        assert_eq!(model.get("y"), Some(0));
+        model.input("b", 5);
+        assert_eq!(model.get("y"), Some(15));
+
+        let mut sum = 0;
+        model.register_function("dpi::accumulate", move |args| {
+            sum += args[0];
+            sum
+        });
+        let traced = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let log = traced.clone();
+        model.register_function("dpi::trace", move |args| {
+            log.lock().unwrap().push((args[0], args[1]));
+            0
+        });
+
+        model.reset();
+        model.clock();
+        model.input("a", 4);
+        model.clock();
+        assert_eq!(model.get("acc"), Some(35), "{mode:?}");
+        assert_eq!(*traced.lock().unwrap(), vec![(3, 5), (4, 5)]);
+    }
+}
+
+#[test]
+fn test_behavioral_module() {
+    use veryl_simulator::{BehavioralModule, Ports};
+
+    // 読み出しがレジスタ出力のメモリ
+    struct Ram {
+        memory: [usize; 16],
+    }
+
+    impl BehavioralModule for Ram {
+        fn eval(&mut self, _ports: &mut Ports) {}
+
+        fn clock(&mut self, ports: &mut Ports) {
+            let addr = ports.get("addr").unwrap();
+            ports.set("rdata", self.memory[addr]);
+            if ports.get("we") == Some(1) {
+                self.memory[addr] = ports.get("wdata").unwrap();
+            }
+        }
+
+        fn reset(&mut self, ports: &mut Ports) {
+            self.memory = [0; 16];
+            ports.set("rdata", 0);
+        }
+    }
+
+    struct Adder;
+
+    impl BehavioralModule for Adder {
+        fn eval(&mut self, ports: &mut Ports) {
+            let y = ports.get("a").unwrap() + ports.get("b").unwrap();
+            assert!(ports.set("y", y & 0xff));
+            assert!(!ports.set("a", 0));
+        }
+    }
+
+    let code = std::fs::read_to_string("tests/behavioral.veryl").unwrap();
+    analyze(&code);
+
+    for mode in [EvalMode::Compiled, EvalMode::Interpreted] {
+        let mut model = Model::new("BehavioralTest", HashMap::new()).unwrap();
+        model.set_eval_mode(mode).unwrap();
+
+        // 登録前のインスタンスは出力を保持し、未知の名前として記録する
+        model.input("wdata", 1);
+        assert_eq!(model.get("sum"), Some(0));
+        assert!(model.unknown_signals().iter().any(|x| x.name == "u_add"));
+
+        assert!(matches!(
+            model.register_module("Ram", |_| Adder),
+            Err(ModelError::InstanceNotFound(x)) if x == "Ram"
+        ));
+        model
+            .register_module("vendor::Ram", |_| Ram { memory: [0; 16] })
+            .unwrap();
+        model.register_module("Adder", |_| Adder).unwrap();
+        assert_eq!(model.get("sum"), Some(1));
+
+        model.reset();
+        model.input("addr", 3);
+        model.input("wdata", 0x42);
+        model.input("we", 1);
+        model.clock();
+        model.input("we", 0);
+        model.clock();
+        // メモリの出力はクロックエッジの後に反映されるので、rdata は 1 サイクル遅れる
+        assert_eq!(model.get("sum"), Some(0x84), "{mode:?}");
+        assert_eq!(model.get("rdata"), Some(0));
+        model.clock_rise("clk");
+        model.clock_fall("clk");
+        assert_eq!(model.get("rdata"), Some(0x42));
+    }
+
+    // proto モジュールのポートの方向は宣言に従う
+    struct Double;
+
+    impl BehavioralModule for Double {
+        fn eval(&mut self, ports: &mut Ports) {
+            ports.set("y", ports.get("a").unwrap() * 2);
+        }
+    }
+
+    let mut model = Model::new("BehavioralProtoTest", HashMap::new()).unwrap();
+    model
+        .register_module("BehavioralProto", |name| {
+            assert_eq!(name, "u_proto");
+            Double
+        })
+        .unwrap();
+    model.input("a", 21);
+    assert_eq!(model.get("y"), Some(42));
+}
+
+#[test]
+fn test_concat_assignment() {
+    let code = std::fs::read_to_string("tests/concat.veryl").unwrap();
+    analyze(&code);
+
+    let mut init = HashMap::new();
+    init.insert("bus".to_string(), 0xa5);
+    let model = Model::new("ConcatTest", init).unwrap();
+    assert_eq!(model.get("hi"), Some(0xa));
+    assert_eq!(model.get("lo"), Some(0x5));
+    assert_eq!(model.get("x"), Some(0b10));
+    assert_eq!(model.get("y"), Some(0b100110));
+}
+
+#[test]
+fn test_latch() {
+    let code = std::fs::read_to_string("tests/latch.veryl").unwrap();
+    analyze(&code);
+
+    let mut model = Model::new("LatchTest", HashMap::new()).unwrap();
+
+    // transparent while en is high
+    model.input("en", 1);
+    model.input("d", 5);
+    assert_eq!(model.get("q"), Some(5));
+    model.input("d", 7);
+    assert_eq!(model.get("q"), Some(7));
+
+    // hold while en is low
+    model.input("en", 0);
+    model.input("d", 3);
+    assert_eq!(model.get("q"), Some(7));
+
+    // assignments in reverse dependency order settle
+    assert_eq!(model.get("r"), Some(9));
+    model.input("en", 1);
+    assert_eq!(model.get("q"), Some(3));
+    assert_eq!(model.get("r"), Some(5));
+}
+
+#[test]
+fn test_case() {
+    let code = std::fs::read_to_string("tests/case.veryl").unwrap();
+    analyze(&code);
+
+    let mut model = Model::new("CaseTest", HashMap::new()).unwrap();
+
+    // default is taken only when no arm matches, regardless of its position
+    let expected = [
+        (0, 1),
+        (1, 1),
+        (3, 2),
+        (5, 2),
+        (6, 4),
+        (7, 4),
+        (8, 9),
+        (10, 3),
+        (14, 3),
+        (15, 3),
+    ];
+    for (sel, a) in expected {
+        model.input("sel", sel);
+        assert_eq!(model.get("a"), Some(a), "sel = {sel}");
+    }
+
+    model.input("sel", 2);
+    assert_eq!(model.get("b"), Some(1));
+    model.input("sel", 6);
+    assert_eq!(model.get("b"), Some(2));
+
+    let overlaps: Vec<_> = model
+        .case_overlaps()
+        .iter()
+        .map(|x| (x.location.line, x.previous.map(|x| x.line), x.unreachable))
+        .collect();
+    assert_eq!(
+        overlaps,
+        vec![
+            (13, Some(12), true),
+            (14, Some(11), true),
+            (21, Some(20), false),
+        ]
+    );
+}
+
+#[test]
+fn test_model_error() {
+    let code = std::fs::read_to_string("tests/error.veryl").unwrap();
+    analyze(&code);
+
+    assert!(matches!(
+        Model::new("Missing", HashMap::new()),
+        Err(ModelError::TopNotFound(x)) if x == "Missing"
+    ));
+    assert!(matches!(
+        Model::new("InstTest", HashMap::new()),
+        Err(ModelError::UnsupportedConstruct { construct, span }) if construct == "inst" && span.line == 12
+    ));
+    assert!(matches!(
+        Model::new("NoClockTest", HashMap::new()),
+        Err(ModelError::NoClockFound(x)) if x == "NoClockTest"
+    ));
+    assert!(matches!(
+        Model::new("ShiftTest", HashMap::new()),
+        Err(ModelError::UnsupportedConstruct { construct, span })
+            if construct == "operator `>>>=`" && span.line == 33
+    ));
+    assert!(Model::new("ErrorChild", HashMap::new()).is_ok());
+}
+
+#[test]
+fn test_compare() {
+    let code = std::fs::read_to_string("tests/compare.veryl").unwrap();
+    analyze(&code);
+
+    let mut model = Model::new("CompareTest", HashMap::new()).unwrap();
+    assert!(model.warnings().is_empty());
+
+    for (a, b) in [(3, 3), (3, 5), (0, 15)] {
+        model.input("a", a);
+        model.input("b", b);
+        assert_eq!(model.get("eq"), Some((a == b) as usize), "{a} == {b}");
+        assert_eq!(model.get("ne"), Some((a != b) as usize), "{a} != {b}");
+    }
+
+    // x bits of the right operand are not compared
+    let expected = [
+        (0b0000_0001, (1, 0, 0)),
+        (0b0000_0111, (1, 0, 0)),
+        (0b0000_0110, (0, 0, 0)),
+        (0b0010_0101, (0, 1, 1)),
+        (0b1000_1001, (0, 0, 0)),
+        (0b0101_0011, (0, 0, 1)),
+    ];
+    for (op, (ld, st, alu)) in expected {
+        model.input("op", op);
+        assert_eq!(model.get("ld"), Some(ld), "op = {op:#b}");
+        assert_eq!(model.get("st"), Some(st), "op = {op:#b}");
+        assert_eq!(model.get("alu"), Some(alu), "op = {op:#b}");
+    }
+}
+
+#[test]
+fn test_compound_assignment() {
+    let code = std::fs::read_to_string("tests/compound.veryl").unwrap();
+    analyze(&code);
+
+    let mut model = Model::new("CompoundTest", HashMap::new()).unwrap();
+    assert!(model.warnings().is_empty());
+
+    for (a, b) in [(3, 2), (100, 60), (200, 17), (2, 5)] {
+        model.input("a", a);
+        model.input("b", b);
+        assert_eq!(
+            model.get("c"),
+            Some(((a + b) << 1) & 0xff),
+            "a = {a}, b = {b}"
+        );
+        assert_eq!(
+            model.get("d"),
+            Some((a.wrapping_sub(b) & 0xff) >> 2),
+            "a = {a}, b = {b}"
+        );
+        assert_eq!(model.get("e"), Some(((a + 1) & 0xf) << 4), "a = {a}");
+        assert_eq!(
+            model.get("f"),
+            Some(((a & b) | 1) ^ 0xf0),
+            "a = {a}, b = {b}"
+        );
+        assert_eq!(model.get("g"), Some(a % b), "a = {a}, b = {b}");
+    }
+    model.input("a", 15);
+    assert_eq!(model.get("e"), Some(0));
+
+    // the result is truncated to the width of the target
+    model.input("a", 255);
+    model.input("b", 1);
+    assert_eq!(model.get("c"), Some(0));
+
+    model.reset();
+    assert_eq!(model.get("cnt"), Some(1));
+    assert_eq!(model.get("dn"), Some(2));
+    for i in 1..100 {
+        model.clock();
+        assert_eq!(model.get("cnt"), Some((1 + 3 * i) & 0xff));
+        // the down-counter wraps around from 0 to 0xff
+        assert_eq!(model.get("dn"), Some((258 - i) & 0xff));
+    }
+}
+
+#[test]
+fn test_inside() {
+    let code = std::fs::read_to_string("tests/inside.veryl").unwrap();
+    analyze(&code);
+
+    let mut model = Model::new("InsideTest", HashMap::new()).unwrap();
+    assert!(model.warnings().is_empty());
+
+    model.input("lo", 3);
+    model.input("hi", 6);
+    let expected = [
+        (0, (0, 0, 0)),
+        (1, (1, 0, 0)),
+        (2, (0, 1, 0)),
+        (3, (0, 1, 1)),
+        (4, (1, 1, 1)),
+        (6, (1, 1, 0)),
+        (7, (1, 1, 0)),
+        (10, (0, 0, 0)),
+        (12, (0, 1, 0)),
+        (15, (0, 0, 1)),
+    ];
+    for (op, (a, b, c)) in expected {
+        model.input("op", op);
+        assert_eq!(model.get("a"), Some(a), "op = {op}");
+        assert_eq!(model.get("b"), Some(b), "op = {op}");
+        assert_eq!(model.get("c"), Some(c), "op = {op}");
+    }
+}
+
+#[test]
+fn test_case_violation() {
+    let code = std::fs::read_to_string("tests/cond_type.veryl").unwrap();
+    analyze(&code);
+
+    let mut model = Model::new("CondTypeTest", HashMap::new()).unwrap();
+    model.input("sel", 1);
+    assert!(model.case_violations().is_empty());
+
+    // multiple arms match
+    model.input("sel", 2);
+    assert_eq!(model.get("a"), Some(2));
+    // no arm matches
+    model.input("sel", 3);
+    assert_eq!(model.get("b"), Some(0));
+
+    let violations = model.case_violations();
+    assert_eq!(violations.len(), 2);
+    assert_eq!(violations[0].check, CaseCheck::Unique);
+    assert_eq!(violations[0].location.line, 8);
+    assert_eq!(violations[0].value, 2);
+    assert_eq!(
+        violations[0]
+            .matched
+            .iter()
+            .map(|x| x.line)
+            .collect::<Vec<_>>(),
+        vec![10, 11]
+    );
+    assert_eq!(violations[1].value, 3);
+    assert!(violations[1].matched.is_empty());
+}
+
+#[test]
+fn test_model_warning() {
+    let code = std::fs::read_to_string("tests/warning.veryl").unwrap();
+    analyze(&code);
+
+    let model = Model::new("WarningTest", HashMap::new()).unwrap();
+    let warnings: Vec<_> = model
+        .warnings()
+        .iter()
+        .map(|x| (x.construct.as_str(), x.span.line, x.span.column))
+        .collect();
+    assert_eq!(warnings, vec![("operator `**`", 7, 18)]);
+}
+
+#[test]
+fn test_lint() {
+    use veryl_simulator::capability::{self, Support};
+
+    assert_eq!(capability::support("operator `+`"), Support::Approximated);
+    assert_eq!(capability::support("case"), Support::Supported);
+    assert_eq!(
+        capability::support("system function `$display`"),
+        Support::Unsupported
+    );
+
+    let code = std::fs::read_to_string("tests/warning.veryl").unwrap();
+    analyze(&code);
+
+    let model = Model::new("WarningTest", HashMap::new()).unwrap();
+    let lint = model.lint();
+    let entries: Vec<_> = lint
+        .entries
+        .iter()
+        .map(|x| (x.construct.as_str(), x.support, x.locations.len()))
+        .collect();
+    assert_eq!(
+        entries,
+        vec![
+            ("operator `**`", Support::Unsupported, 1),
+            ("operator `/=`", Support::Approximated, 1),
+        ]
+    );
+    assert!(!lint.is_supported());
+    assert!(
+        lint.to_string()
+            .contains("unsupported: operator `**` (the left operand is used) at 7:18")
+    );
+}
+
+#[test]
+fn test_signals() {
+    let code = std::fs::read_to_string("tests/slice.veryl").unwrap();
+    analyze(&code);
+
+    let mut init = HashMap::new();
+    init.insert("a".to_string(), 0x5a);
+    let model = Model::new("SliceTest", init).unwrap();
+    let signals: Vec<_> = model.signals().collect();
+
+    let a = signals.iter().find(|x| x.name == "a").unwrap();
+    assert_eq!(a.direction, Direction::Input);
+    assert_eq!(a.value, 0x5a);
+
+    let mem = signals.iter().find(|x| x.name == "mem[3]").unwrap();
+    assert_eq!(mem.direction, Direction::Internal);
+    assert_eq!(mem.width, 8);
+
+    assert!(signals.iter().any(|x| x.direction == Direction::Output));
+    assert!(signals.windows(2).all(|x| x[0].name < x[1].name));
+}
+
+#[test]
+fn test_typed_access() {
+    let code = std::fs::read_to_string("tests/select.veryl").unwrap();
+    analyze(&code);
+
+    let mut model = Model::new("SelectTest", HashMap::new()).unwrap();
+    model.input_u64("a", 0b1011_0110);
+    assert_eq!(model.get_u64("b"), Some(0b1011));
+    assert_eq!(model.get_bool("c"), Some(true));
+    assert_eq!(model.get_bool("d"), Some(false));
+
+    let bits = model.get_bits("b").unwrap();
+    assert_eq!(bits.width(), 4);
+    assert_eq!(bits.to_string(), "4'b1011");
+    assert_eq!(model.get_bits("SelectTest.b"), Some(bits));
+
+    let mut bits = BitVec::new(8);
+    bits.set_bit(2, true);
+    model.input_bits("a", &bits);
+    assert_eq!(model.get_u64("b"), Some(0));
+    assert_eq!(model.get_u64("f"), Some(0b0001));
+
+    assert!(model.try_input("a", 0).is_ok());
+    assert!(matches!(
+        model.try_input("x", 0),
+        Err(ModelError::UnknownPort(x)) if x == "x"
+    ));
+
+    let bits = BitVec::from_u64(70, u64::MAX);
+    assert_eq!(bits.try_to_u64(), Some(u64::MAX));
+    assert!(!bits.bit(64));
+}
+
+#[test]
+fn test_peek_poke() {
+    let code = std::fs::read_to_string("tests/slice.veryl").unwrap();
+    analyze(&code);
+
+    let mut model = Model::new("SliceTest", HashMap::new()).unwrap();
+
+    // backdoor-initialize a register
+    model.poke("mem[2]", 0x1ab).unwrap();
+    assert_eq!(model.peek("mem[2]"), Some(0xab));
+    assert_eq!(model.peek("SliceTest.mem[2]"), Some(0xab));
+    model.input("i", 2);
+    assert_eq!(model.get("d"), Some(0xab));
+
+    assert_eq!(model.peek("i"), Some(2));
+    assert_eq!(model.peek("Other.mem[2]"), None);
+    assert!(matches!(
+        model.poke("mem[4]", 0),
+        Err(ModelError::SignalNotFound(x)) if x == "mem[4]"
+    ));
+}
+
+#[test]
+fn test_signal_path() {
+    let code = std::fs::read_to_string("tests/slice.veryl").unwrap();
+    analyze(&code);
+
+    let mut model = Model::new("SliceTest", HashMap::new()).unwrap();
+    let mem = ["mem[0]", "mem[1]", "mem[2]", "mem[3]"];
+    assert_eq!(model.select("mem[*]").unwrap(), mem);
+    assert_eq!(model.select("SliceTest.mem[?]").unwrap(), mem);
+    assert_eq!(model.select("SliceTest.d").unwrap(), ["d"]);
+
+    // グロブで選んだ信号をまとめて操作する
+    model.poke("mem[*]", 5).unwrap();
+    let values: Vec<_> = model.peek_all("mem[*]").into_iter().map(|x| x.1).collect();
+    assert_eq!(values, [5, 5, 5, 5]);
+    model.force("SliceTest.mem[*]", 7).unwrap();
+    assert!(model.is_forced("mem[1]"));
+    model.release("mem[*]").unwrap();
+    assert!(!model.is_forced("mem[1]"));
+    model.input("?", 3);
+    assert_eq!((model.peek("a"), model.peek("i")), (Some(3), Some(3)));
+    model.try_input("SliceTest.a", 1).unwrap();
+    assert_eq!(model.get("SliceTest.b"), Some(0xf1));
+
+    // 書き間違いには近い信号名を提案する
+    let hint = |x: ModelError| match x {
+        ModelError::PathNotFound { hint, .. } => hint,
+        _ => panic!("unexpected error"),
+    };
+    assert_eq!(
+        hint(model.select("men[2]").unwrap_err()),
+        "did you mean \"mem[2]\"?"
+    );
+    assert_eq!(
+        hint(model.select("Other.mem[2]").unwrap_err()),
+        "the top module is \"SliceTest\""
+    );
+    assert_eq!(
+        hint(model.try_input("x*", 0).unwrap_err()),
+        "check the path of the signal"
+    );
+    assert!(model.peek_all("x*").is_empty());
+
+    // ブレークポイントの条件にもパスとグロブを使える
+    let breakpoint = BreakPoint::new().when_equals("SliceTest.mem[*]", 9);
+    let hits = breakpoint.hits();
+    let model = Model::new("SliceTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook(Box::new(breakpoint))
+        .build()
+        .unwrap();
+    simulator.reset();
+    simulator.model_mut().input("a", 9);
+    simulator.model_mut().input("i", 3);
+    assert_eq!(simulator.run(20), StopReason::Paused);
+    let hit = hits.lock().unwrap()[0].clone();
+    assert_eq!(hit.time, 5);
+    assert_eq!(hit.values[3], ("mem[3]".to_string(), 9));
+
+    let model = Model::new("SliceTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook(Box::new(BreakPoint::new().when_changes("dd")))
+        .build()
+        .unwrap();
+    let StopReason::Failed(error) = simulator.run(20) else {
+        panic!("typo is not reported");
+    };
+    assert!(
+        error
+            .to_string()
+            .contains("breakpoint on \"dd\" matches no signal, did you mean \"d\"?")
+    );
+}
+
+#[test]
+fn test_force_release() {
+    let code = std::fs::read_to_string("tests/comb.veryl").unwrap();
+    analyze(&code);
+
+    let mut model = Model::new("CombTest", HashMap::new()).unwrap();
+    model.input("a", 1);
+    model.input("b", 2);
+    assert_eq!(model.get("c"), Some(3));
+
+    // forced output ignores its driver
+    model.force("c", 10).unwrap();
+    assert!(model.is_forced("c"));
+    model.input("a", 5);
+    assert_eq!(model.get("c"), Some(10));
+    model.release("c").unwrap();
+    assert!(!model.is_forced("c"));
+    assert_eq!(model.get("c"), Some(7));
+
+    // forced input ignores `input` until released
+    model.force("CombTest.a", 0).unwrap();
+    assert_eq!(model.get("c"), Some(2));
+    model.input("a", 8);
+    assert_eq!(model.get("c"), Some(2));
+    model.release("a").unwrap();
+    assert_eq!(model.get("c"), Some(10));
+
+    assert!(matches!(
+        model.force("x", 0),
+        Err(ModelError::SignalNotFound(x)) if x == "x"
+    ));
+
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    // forced register holds its value until the next assignment after release
+    let mut model = Model::new("FFTest", HashMap::new()).unwrap();
+    model.reset();
+    model.force("b", 5).unwrap();
+    model.clock();
+    model.clock();
+    assert_eq!(model.get("b"), Some(5));
+    model.release("b").unwrap();
+    assert_eq!(model.get("b"), Some(5));
+    model.clock();
+    assert_eq!(model.get("b"), Some(6));
+}
+
+#[test]
+fn test_reset_type() {
+    let code = std::fs::read_to_string("tests/reset.veryl").unwrap();
+    analyze(&code);
+
+    let mut model = Model::new("ResetTest", HashMap::new()).unwrap();
+    assert_eq!(model.clocks(), &[("clk".to_string(), ClockType::NegEdge)]);
+    assert_eq!(
+        model.resets(),
+        &[
+            ("srst".to_string(), ResetType::SyncHigh),
+            ("arst".to_string(), ResetType::AsyncLow),
+        ]
+    );
+
+    // resets start deasserted
+    assert_eq!(model.peek("srst"), Some(0));
+    assert_eq!(model.peek("arst"), Some(1));
+
+    model.reset();
+    assert_eq!(model.peek("srst"), Some(0));
+    assert_eq!(model.peek("arst"), Some(1));
+    model.clock();
+    model.clock();
+    assert_eq!(model.get("a"), Some(2));
+    assert_eq!(model.get("b"), Some(2));
+
+    // synchronous reset waits for the clock
+    model.input("srst", 1);
+    assert_eq!(model.get("a"), Some(2));
+    model.clock();
+    assert_eq!(model.get("a"), Some(0));
+    assert_eq!(model.get("b"), Some(3));
+    model.input("srst", 0);
+
+    // asynchronous reset is applied immediately, and held while asserted
+    model.input("arst", 0);
+    assert_eq!(model.get("b"), Some(0));
+    model.clock();
+    assert_eq!(model.get("a"), Some(1));
+    assert_eq!(model.get("b"), Some(0));
+    model.input("arst", 1);
+    model.clock();
+    assert_eq!(model.get("b"), Some(1));
+}
+
+#[test]
+fn test_snapshot_restore() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let mut model = Model::new("FFTest", HashMap::new()).unwrap();
+    model.reset();
+    model.clock();
+    let state = model.snapshot();
+    assert_eq!(state.get("b"), Some(1));
+
+    // fork a what-if simulation and backtrack
+    model.clock();
+    model.clock();
+    assert_eq!(model.get("b"), Some(3));
+    model.restore(&state).unwrap();
+    assert_eq!(model.get("b"), Some(1));
+    assert_eq!(model.snapshot(), state);
+    model.clock();
+    assert_eq!(model.get("b"), Some(2));
+
+    let code = std::fs::read_to_string("tests/comb.veryl").unwrap();
+    analyze(&code);
+
+    let mut model = Model::new("CombTest", HashMap::new()).unwrap();
+    assert!(matches!(
+        model.restore(&state),
+        Err(ModelError::StateMismatch { model, state }) if model == "CombTest" && state == "FFTest"
+    ));
+}
+
+#[test]
+fn test_checkpoint() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let mut clocks = HashMap::new();
+    clocks.insert("clk".to_string(), 1000);
+
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::new(model, clocks.clone());
+    simulator.reset();
+    simulator.run(2500);
+
+    let json = serde_json::to_string(&simulator.checkpoint()).unwrap();
+    let state: SimulatorState = serde_json::from_str(&json).unwrap();
+    assert_eq!(state.time_ns, 2500);
+    assert_eq!(state.model.get("b"), Some(3));
+
+    // resume from the checkpoint in another simulator
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut resumed = Simulator::new(model, clocks);
+    resumed.restore(&state).unwrap();
+    simulator.run(3000);
+    resumed.run(3000);
+    assert_eq!(resumed.checkpoint(), simulator.checkpoint());
+
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut other = Simulator::new(model, HashMap::new());
+    assert!(matches!(
+        other.restore(&state),
+        Err(ModelError::SignalNotFound(x)) if x == "clk"
+    ));
+}
+
+#[test]
+fn test_last_change() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model).clock("clk", 10).build().unwrap();
+    simulator.reset();
+    assert_eq!(simulator.model().last_change("b"), None);
+
+    simulator.run(30);
+    assert_eq!(simulator.model().last_change("a"), Some(25));
+    assert_eq!(simulator.model().last_change("b"), Some(25));
+    assert_eq!(simulator.model().last_change("rst"), None);
+    assert_eq!(simulator.model().last_change("x"), None);
+
+    // ステップ間の入力の変化は直前の時刻に記録する
+    simulator.model_mut().input("rst", 0);
+    simulator.run(3);
+    assert_eq!(simulator.model().last_change("rst"), Some(30));
+    assert_eq!(simulator.model().last_change("b"), Some(30));
+    simulator.run(20);
+    assert_eq!(simulator.model().last_change("b"), Some(30));
+
+    simulator.reset();
+    assert_eq!(simulator.model().last_change("rst"), None);
+}
+
+#[test]
+fn test_state_diff() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let mut model = Model::new("FFTest", HashMap::new()).unwrap();
+    model.reset();
+    let before = model.snapshot();
+    model.clock();
+    let after = model.snapshot();
+
+    let diff = before.diff(&after);
+    assert_eq!(
+        diff,
+        vec![
+            SignalDelta {
+                name: "a".to_string(),
+                before: Some(0),
+                after: Some(1),
+            },
+            SignalDelta {
+                name: "b".to_string(),
+                before: Some(0),
+                after: Some(1),
+            },
+        ]
+    );
+    assert_eq!(diff[1].to_string(), "b: 0 -> 1");
+    assert!(after.diff(&after).is_empty());
+
+    let mut other = after.clone();
+    other.outputs.remove("b");
+    assert_eq!(after.diff(&other)[0].to_string(), "b: 1 -> -");
+}
+
+#[test]
+fn test_export_json() {
+    let code = std::fs::read_to_string("tests/comb.veryl").unwrap();
+    analyze(&code);
+
+    let model = Model::new("CombTest", HashMap::new()).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&model.export_json()).unwrap();
+    assert_eq!(json["module"], "CombTest");
+    assert_eq!(json["signals"][2]["name"], "c");
+    assert_eq!(json["signals"][2]["direction"], "output");
+    assert_eq!(json["signals"][2]["width"], 32);
+
+    let assign = &json["combinational"][0]["assign"];
+    assert_eq!(assign["targets"][0]["name"], "c");
+    assert_eq!(
+        assign["expression"],
+        serde_json::json!({"add": [{"var": "a"}, {"var": "b"}]})
+    );
+
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&model.export_json()).unwrap();
+    assert_eq!(json["clocks"][0], serde_json::json!(["clk", "posedge"]));
+    assert_eq!(json["resets"][0], serde_json::json!(["rst", "async_low"]));
+    assert_eq!(json["sequential"][0]["reset_signal"], "rst");
+    assert_eq!(json["sequential"][0]["reset"].as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn test_from_files() {
+    let mut model = Model::from_files(&["tests/ff.veryl"], "FFTest", HashMap::new()).unwrap();
+    model.reset();
+    model.clock();
+    assert_eq!(model.get("b"), Some(1));
+
+    // multiple files
+    let paths = ["tests/comb.veryl", "tests/slice.veryl"];
+    let mut model = Model::from_files(&paths, "CombTest", HashMap::new()).unwrap();
+    model.input("a", 1);
+    model.input("b", 2);
+    assert_eq!(model.get("c"), Some(3));
+    assert!(Model::from_files(&paths, "SliceTest", HashMap::new()).is_ok());
+    assert!(matches!(
+        Model::from_files(&paths, "FFTest", HashMap::new()),
+        Err(ModelError::TopNotFound(_))
+    ));
+
+    assert!(matches!(
+        Model::from_files(&["tests/none.veryl"], "FFTest", HashMap::new()),
+        Err(ModelError::ReadFailed { path, .. }) if path == "tests/none.veryl"
+    ));
+}
+
+#[test]
+fn test_from_source() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    let mut model = Model::from_source(&code, "FFTest", HashMap::new()).unwrap();
+    model.reset();
+    model.clock();
+    assert_eq!(model.get("b"), Some(1));
+
+    assert!(matches!(
+        Model::from_source("module FFTest (", "FFTest", HashMap::new()),
+        Err(ModelError::ParseFailed { .. })
+    ));
+}
+
+#[test]
+fn test_vcd_to_writer() {
+    // ファイルの代わりにメモリ上のバッファに書き出す
+    #[derive(Clone, Default)]
+    struct Buffer(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let model = Model::from_files(&["tests/ff.veryl"], "FFTest", HashMap::new()).unwrap();
+    let buffer = Buffer::default();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook(Box::new(VCDLoggerHook::from_writer(buffer.clone())))
+        .build()
+        .unwrap();
+    simulator.reset();
+    simulator.run_cycles("clk", 3).unwrap();
+    drop(simulator);
+
+    let vcd = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    assert!(vcd.contains("$enddefinitions $end"));
+    assert!(vcd.contains("$var wire 32 "));
+    assert!(vcd.contains("b11 "));
+}
+
+#[test]
+fn test_isolated_models() {
+    // the symbol table of this thread is kept
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+    let comb = Model::from_files(&["tests/comb.veryl"], "CombTest", HashMap::new()).unwrap();
+    assert!(Model::new("FFTest", HashMap::new()).is_ok());
+    assert!(matches!(
+        Model::new("CombTest", HashMap::new()),
+        Err(ModelError::TopNotFound(_))
+    ));
+
+    // independent designs are simulated concurrently
+    let ff = std::thread::spawn(|| {
+        let mut model = Model::from_files(&["tests/ff.veryl"], "FFTest", HashMap::new()).unwrap();
+        model.reset();
+        for _ in 0..10 {
+            model.clock();
+        }
+        model.get("b")
+    });
+    let comb = std::thread::spawn(move || {
+        let mut model = comb;
+        model.input("a", 3);
+        model.input("b", 4);
+        model.get("c")
+    });
+    assert_eq!(ff.join().unwrap(), Some(10));
+    assert_eq!(comb.join().unwrap(), Some(7));
+}
+
+#[test]
+fn test_simulator_builder() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    // reset is held for 4 cycles
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10.ns())
+        .reset_cycles(4)
+        .hook(Box::new(BufLogger::new()))
+        .build()
+        .unwrap();
+    simulator.reset();
+    let state = simulator.checkpoint();
+    assert_eq!(state.time_ns, 35);
+    assert_eq!(state.model.get("b"), Some(0));
+    assert_eq!(state.model.get("rst"), Some(1));
+    simulator.run(10);
+    assert_eq!(simulator.checkpoint().model.get("b"), Some(1));
+
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    assert!(matches!(
+        Simulator::builder(model).clock("rst", 10).build(),
+        Err(ModelError::InvalidClock { name, .. }) if name == "rst"
+    ));
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    assert!(matches!(
+        Simulator::builder(model).clock_phase("clk", 90).build(),
+        Err(ModelError::InvalidClock { name, .. }) if name == "clk"
+    ));
+
+    let code = std::fs::read_to_string("tests/clocks.veryl").unwrap();
+    analyze(&code);
+
+    // clk2 is delayed by a quarter of the period
+    let model = Model::new("ClocksTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 8.ns())
+        .clock("clk2", 8.ns())
+        .clock_phase("clk2", 90)
+        .build()
+        .unwrap();
+    simulator.reset();
+    simulator.run(4);
+    let state = simulator.checkpoint();
+    assert_eq!(
+        (state.model.get("a"), state.model.get("b")),
+        (Some(1), Some(0))
+    );
+    simulator.run(2);
+    let state = simulator.checkpoint();
+    assert_eq!(state.time_ns, 6);
+    assert_eq!(
+        (state.model.get("a"), state.model.get("b")),
+        (Some(1), Some(1))
+    );
+}
+
+// Records the times of rising edges
+struct EdgeRecorder(std::sync::Arc<std::sync::Mutex<Vec<u64>>>);
+
+impl Hook for EdgeRecorder {
+    fn post_clock(
+        &mut self,
+        time: u64,
+        _clock_name: &str,
+        _model: &Model,
+    ) -> Result<HookAction, HookError> {
+        self.0.lock().unwrap().push(time);
+        Ok(HookAction::Continue)
+    }
+}
+
+#[test]
+fn test_clock_jitter() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let run = |jitter: ClockJitter| {
+        let edges = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let model = Model::new("FFTest", HashMap::new()).unwrap();
+        let mut simulator = Simulator::builder(model)
+            .clock("clk", 10)
+            .clock_jitter("clk", jitter)
+            .hook(Box::new(EdgeRecorder(edges.clone())))
+            .build()
+            .unwrap();
+        simulator.reset();
+        simulator.run(200);
+        edges.lock().unwrap().clone()
+    };
+
+    // edges deviate from the ideal time without accumulating
+    let jitter = ClockJitter {
+        jitter: Jitter::Uniform { max_ns: 2 },
+        seed: 1,
+        ..Default::default()
+    };
+    let edges = run(jitter);
+    assert!(edges.len() >= 19);
+    for (i, time) in edges.iter().enumerate() {
+        assert!(time.abs_diff(5 + 10 * i as u64) <= 2);
+    }
+    assert!(
+        edges
+            .iter()
+            .enumerate()
+            .any(|(i, x)| *x != 5 + 10 * i as u64)
+    );
+
+    // reproducible from the seed
+    assert_eq!(run(jitter), edges);
+    let jitter = ClockJitter { seed: 2, ..jitter };
+    assert_ne!(run(jitter), edges);
+
+    // 10% slower clock
+    let drift = ClockJitter {
+        drift_ppm: 100_000,
+        ..Default::default()
+    };
+    let edges = run(drift);
+    assert_eq!(edges[0], 5);
+    assert_eq!(edges[1], 16);
+    assert_eq!(edges[18], 203);
+}
+
+#[test]
+fn test_clock_control() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let edges = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook(Box::new(EdgeRecorder(edges.clone())))
+        .build()
+        .unwrap();
+    simulator.reset();
+    simulator.run(20);
+    assert_eq!(*edges.lock().unwrap(), vec![5, 15]);
+
+    // gated clock keeps its phase
+    simulator.set_clock_enabled("clk", false).unwrap();
+    assert!(!simulator.is_clock_enabled("clk"));
+    simulator.run(30);
+    assert_eq!(simulator.checkpoint().model.get("b"), Some(2));
+    simulator.set_clock_enabled("clk", true).unwrap();
+    simulator.run(10);
+    assert_eq!(*edges.lock().unwrap(), vec![5, 15, 55]);
+
+    // new period is effective after the next edge
+    simulator.set_clock_period("clk", 4).unwrap();
+    assert_eq!(simulator.clock_period("clk"), Some(4));
+    simulator.run(10);
+    assert_eq!(*edges.lock().unwrap(), vec![5, 15, 55, 65, 69]);
+    assert_eq!(simulator.checkpoint().model.get("b"), Some(5));
+
+    assert!(matches!(
+        simulator.set_clock_period("clk", 1),
+        Err(ModelError::InvalidClock { .. })
+    ));
+    assert!(matches!(
+        simulator.set_clock_enabled("rst", false),
+        Err(ModelError::InvalidClock { name, .. }) if name == "rst"
+    ));
+}
+
+// Records the times of falling edges
+struct FallRecorder(std::sync::Arc<std::sync::Mutex<Vec<u64>>>);
+
+impl Hook for FallRecorder {
+    fn post_clock_fall(
+        &mut self,
+        time: u64,
+        _clock_name: &str,
+        _model: &Model,
+    ) -> Result<HookAction, HookError> {
+        self.0.lock().unwrap().push(time);
+        Ok(HookAction::Continue)
+    }
+}
+
+#[test]
+fn test_falling_edge() {
+    let code = std::fs::read_to_string("tests/reset.veryl").unwrap();
+    analyze(&code);
+
+    // negedge clock is evaluated at falling edges
+    let falls = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let model = Model::new("ResetTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook(Box::new(FallRecorder(falls.clone())))
+        .build()
+        .unwrap();
+    simulator.reset();
+    simulator.run(5);
+    let state = simulator.checkpoint();
+    assert_eq!(state.model.get("clk"), Some(1));
+    assert_eq!(state.model.get("a"), Some(0));
+    simulator.run(5);
+    let state = simulator.checkpoint();
+    assert_eq!(state.model.get("clk"), Some(0));
+    assert_eq!(state.model.get("a"), Some(1));
+    simulator.run(20);
+    assert_eq!(*falls.lock().unwrap(), vec![10, 20, 30]);
+
+    // gating while high lets the clock fall, and no edge occurs until the next rising edge
+    simulator.run(5);
+    simulator.set_clock_enabled("clk", false).unwrap();
+    simulator.run(15);
+    simulator.set_clock_enabled("clk", true).unwrap();
+    simulator.run(20);
+    assert_eq!(*falls.lock().unwrap(), vec![10, 20, 30, 40, 60, 70]);
+}
+
+#[test]
+fn test_run_cycles() {
+    let code = std::fs::read_to_string("tests/clocks.veryl").unwrap();
+    analyze(&code);
+
+    let model = Model::new("ClocksTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .clock("clk2", 30)
+        .build()
+        .unwrap();
+    simulator.reset();
+    simulator.run_cycles("clk", 3).unwrap();
+    let state = simulator.checkpoint();
+    assert_eq!(state.time_ns, 25);
+    assert_eq!(
+        (state.model.get("a"), state.model.get("b")),
+        (Some(3), Some(1))
+    );
+
+    simulator.run_cycles("clk2", 2).unwrap();
+    let state = simulator.checkpoint();
+    assert_eq!(state.time_ns, 75);
+    assert_eq!(
+        (state.model.get("a"), state.model.get("b")),
+        (Some(8), Some(3))
+    );
+
+    simulator.set_clock_enabled("clk", false).unwrap();
+    assert!(simulator.run_cycles("clk", 1).is_err());
+    assert!(simulator.run_cycles("a", 1).is_err());
+}
+
+#[test]
+fn test_run_until() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model).clock("clk", 10).build().unwrap();
+    simulator.reset();
+
+    let result = simulator.run_until(1000, |_, model| model.get("b") == Some(4));
+    assert_eq!(result, RunResult::Satisfied(35));
+
+    // already satisfied
+    let result = simulator.run_until(1000, |_, model| model.get("b") == Some(4));
+    assert_eq!(result, RunResult::Satisfied(35));
+
+    let result = simulator.run_until(100, |_, model| model.get("b") == Some(100));
+    assert_eq!(result, RunResult::TimedOut(135));
+}
+
+#[test]
+fn test_step_advance_to() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model).clock("clk", 10).build().unwrap();
+    simulator.reset();
+
+    let event = simulator.step().unwrap();
+    assert_eq!(
+        event,
+        StepEvent {
+            time: 5,
+            clock: Some("clk".to_string()),
+            edge: Some(ClockEdge::Rising),
+            action: HookAction::Continue,
+            index_at_time: 0,
+        }
+    );
+    assert_eq!(simulator.step().unwrap().edge, Some(ClockEdge::Falling));
+    assert_eq!(simulator.model().get("b"), Some(1));
+
+    // exact time without overshooting
+    simulator.advance_to(27);
+    assert_eq!(simulator.time(), 27);
+    assert_eq!(simulator.model().get("b"), Some(3));
+    simulator.advance_to(35);
+    assert_eq!(simulator.time(), 35);
+    assert_eq!(simulator.model().get("b"), Some(4));
+    assert_eq!(simulator.step().unwrap().time, 40);
+
+    // no clocks
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::new(model, HashMap::new());
+    assert_eq!(simulator.step(), None);
+    simulator.advance_to(100);
+    assert_eq!(simulator.time(), 100);
+}
+
+// Requests the action when b reaches the value
+struct ActionAt(usize, HookAction);
+
+impl Hook for ActionAt {
+    fn post_clock(
+        &mut self,
+        _time: u64,
+        _clock_name: &str,
+        model: &Model,
+    ) -> Result<HookAction, HookError> {
+        if model.get("b") == Some(self.0) {
+            Ok(self.1)
+        } else {
+            Ok(HookAction::Continue)
+        }
+    }
+}
+
+#[test]
+fn test_event_queue() {
+    let code = std::fs::read_to_string("tests/clocks.veryl").unwrap();
+    analyze(&code);
+
+    // simultaneous edges are processed one after another in the clock order
+    let model = Model::new("ClocksTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .clock("clk2", 10)
+        .build()
+        .unwrap();
+    let events: Vec<_> = (0..3).map(|_| simulator.step().unwrap()).collect();
+    let events: Vec<_> = events
+        .iter()
+        .map(|x| (x.time, x.clock.as_deref(), x.index_at_time))
+        .collect();
+    assert_eq!(
+        events,
+        [
+            (5, Some("clk"), 0),
+            (5, Some("clk2"), 1),
+            (10, Some("clk"), 0)
+        ]
+    );
+
+    let code = std::fs::read_to_string("tests/comb.veryl").unwrap();
+    analyze(&code);
+
+    // delayed inputs, zero delay is a new event at the current time
+    let model = Model::new("CombTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::new(model, HashMap::new());
+    simulator.schedule_input("a", 3, 10).unwrap();
+    simulator.schedule_input("b", 4, 10).unwrap();
+    simulator.schedule_input("a", 1, 0).unwrap();
+    let event = simulator.step().unwrap();
+    assert_eq!((event.time, event.index_at_time), (0, 0));
+    assert_eq!(simulator.model().get("c"), Some(1));
+
+    simulator.schedule_input("b", 2, 0).unwrap();
+    let event = simulator.step().unwrap();
+    assert_eq!((event.time, event.index_at_time), (0, 1));
+    assert_eq!(simulator.model().get("c"), Some(3));
+
+    let event = simulator.step().unwrap();
+    assert_eq!((event.time, event.index_at_time), (10, 0));
+    assert_eq!(simulator.model().get("c"), Some(7));
+    assert_eq!(simulator.step(), None);
+
+    assert!(matches!(
+        simulator.schedule_input("c", 0, 0),
+        Err(ModelError::UnknownPort(x)) if x == "c"
+    ));
+}
+
+#[test]
+fn test_hook_action() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    // pause and resume
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook(Box::new(ActionAt(3, HookAction::Pause)))
+        .build()
+        .unwrap();
+    simulator.reset();
+    assert_eq!(simulator.run(100), StopReason::Paused);
+    assert_eq!(simulator.time(), 25);
+    assert_eq!(simulator.model().get("b"), Some(3));
+    assert_eq!(simulator.run(20), StopReason::Completed);
+    assert_eq!(simulator.time(), 45);
+    assert_eq!(simulator.model().get("b"), Some(5));
+
+    // pause in run_until
+    simulator.reset();
+    let result = simulator.run_until(100, |_, model| model.get("b") == Some(4));
+    assert_eq!(result, RunResult::Paused(25));
+
+    // abort stops the simulation until reset
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook(Box::new(ActionAt(2, HookAction::Abort)))
+        .build()
+        .unwrap();
+    simulator.reset();
+    let state = simulator.checkpoint();
+    assert_eq!(simulator.run_cycles("clk", 5).unwrap(), StopReason::Aborted);
+    assert_eq!(simulator.time(), 15);
+    assert_eq!(simulator.run(100), StopReason::Aborted);
+    assert_eq!(simulator.step(), None);
+    assert_eq!(simulator.time(), 15);
+
+    simulator.restore(&state).unwrap();
+    assert_eq!(simulator.step().unwrap().time, 5);
+    simulator.reset();
+    assert_eq!(simulator.run(10), StopReason::Completed);
+}
+
+#[test]
+fn test_breakpoint() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let breakpoint = BreakPoint::new()
+        .when_equals("b", 2)
+        .when_changes_to("b", 4)
+        .when(&["a", "b"], |x| x[0] == 1 && x[1] == 7)
+        .at_time(70);
+    let hits = breakpoint.hits();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook(Box::new(breakpoint))
+        .build()
+        .unwrap();
+    simulator.reset();
+
+    assert_eq!(simulator.run(1000), StopReason::Paused);
+    assert_eq!(simulator.time(), 15);
+    let hit = hits.lock().unwrap().last().cloned().unwrap();
+    assert_eq!(hit.condition, "b == 2");
+    assert_eq!(hit.values, vec![("b".to_string(), 2)]);
+    assert_eq!(hit.to_string(), "break at 15ns: b == 2 (b=2)");
+
+    // the condition holding at the falling edge doesn't trigger again
+    assert_eq!(simulator.run(1000), StopReason::Paused);
+    assert_eq!(simulator.time(), 35);
+    assert_eq!(
+        hits.lock().unwrap().last().unwrap().condition,
+        "b changes to 4"
+    );
+
+    assert_eq!(simulator.run(1000), StopReason::Paused);
+    assert_eq!(simulator.time(), 65);
+    let hit = hits.lock().unwrap().last().cloned().unwrap();
+    assert_eq!(hit.condition, "expression over a, b");
+    assert_eq!(hit.values, vec![("a".to_string(), 1), ("b".to_string(), 7)]);
+
+    assert_eq!(simulator.run(1000), StopReason::Paused);
+    assert_eq!(simulator.time(), 70);
+    assert_eq!(hits.lock().unwrap().last().unwrap().condition, "time >= 70");
+
+    assert_eq!(simulator.run(100), StopReason::Completed);
+    assert_eq!(hits.lock().unwrap().len(), 4);
+}
+
+#[test]
+fn test_watchpoint() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    // "FFTest.?" matches a and b
+    let watchpoint = WatchPoint::new(&["FFTest.?"]).watch("FFTest.b");
+    let events = watchpoint.events();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook(Box::new(watchpoint))
+        .build()
+        .unwrap();
+    simulator.reset();
+    simulator.run(20);
+
+    let event = |time, signal: &str, old, new| WatchEvent {
+        time,
+        signal: signal.to_string(),
+        old,
+        new,
+    };
+    assert_eq!(
+        *events.lock().unwrap(),
+        vec![
+            event(5, "a", 0, 1),
+            event(5, "b", 0, 1),
+            event(15, "a", 1, 0),
+            event(15, "b", 1, 2),
+        ]
+    );
+    assert_eq!(events.lock().unwrap()[3].to_string(), "15ns: b: 1 -> 2");
+
+    // typos are reported at the start
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook(Box::new(WatchPoint::new(&["bb"])))
+        .build()
+        .unwrap();
+    let StopReason::Failed(error) = simulator.run(20) else {
+        panic!("typo is not reported");
+    };
+    assert!(
+        error
+            .to_string()
+            .contains("watchpoint on \"bb\" matches no signal, did you mean \"b\"?")
+    );
+
+    // break on changes
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let watchpoint = WatchPoint::new(&["b"]).action(HookAction::Pause);
+    let events = watchpoint.events();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook(Box::new(watchpoint))
+        .build()
+        .unwrap();
+    simulator.reset();
+    assert_eq!(simulator.run(100), StopReason::Paused);
+    assert_eq!(simulator.time(), 5);
+    assert_eq!(simulator.run(100), StopReason::Paused);
+    assert_eq!(simulator.time(), 15);
+    assert_eq!(events.lock().unwrap().len(), 2);
+}
+
+#[test]
+fn test_schedule() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model).clock("clk", 10).build().unwrap();
+    simulator.reset();
+
+    let count = std::sync::Arc::new(std::sync::Mutex::new(0));
+    let counter = count.clone();
+    simulator.at(20, |model| model.set_reset(true));
+    simulator.at(30, |model| model.set_reset(false));
+    simulator.every(10, move |_| *counter.lock().unwrap() += 1);
+
+    simulator.advance_to(15);
+    assert_eq!(simulator.model().get("b"), Some(2));
+
+    // the stimulus is a step of its own
+    let event = simulator.step().unwrap();
+    assert_eq!((event.time, event.clock), (20, None));
+    assert_eq!(simulator.model().get("b"), Some(0));
+
+    simulator.advance_to(45);
+    assert_eq!(simulator.model().get("b"), Some(2));
+    assert_eq!(*count.lock().unwrap(), 4);
+
+    // stimuli without clocks
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::new(model, HashMap::new());
+    simulator.at(7, |model| model.input("rst", 0));
+    assert_eq!(simulator.step().unwrap().time, 7);
+    assert_eq!(simulator.step(), None);
+}
+
+#[test]
+fn test_stimulus() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let stimulus = Stimulus::from_file("tests/stimulus.csv").unwrap();
+    assert_eq!(
+        stimulus.rows(),
+        &[
+            StimulusRow {
+                time: 20,
+                signal: "rst".to_string(),
+                value: 0,
+            },
+            StimulusRow {
+                time: 30,
+                signal: "rst".to_string(),
+                value: 1,
+            },
+        ]
+    );
+
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model).clock("clk", 10).build().unwrap();
+    simulator.reset();
+    simulator.apply_stimulus(&stimulus).unwrap();
+    simulator.advance_to(20);
+    assert_eq!(simulator.model().get("b"), Some(0));
+    simulator.advance_to(45);
+    assert_eq!(simulator.model().get("b"), Some(2));
+
+    // errors
+    let stimulus = Stimulus::parse("10 b 1").unwrap();
+    assert_eq!(
+        simulator.apply_stimulus(&stimulus),
+        Err(ModelError::UnknownPort("b".to_string()))
+    );
+    assert!(matches!(
+        Stimulus::parse("10 rst"),
+        Err(ModelError::ParseFailed { cause, .. }) if cause.starts_with("line 1")
+    ));
+    assert!(Stimulus::parse("\n10 rst 0z1").is_err());
+    assert!(matches!(
+        Stimulus::from_file("tests/missing.csv"),
+        Err(ModelError::ReadFailed { .. })
+    ));
+}
+
+#[test]
+fn test_testbench() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let simulator = Simulator::builder(model).clock("clk", 10).build().unwrap();
+    let mut tb = TestBench::new(simulator);
+    tb.simulator_mut().reset();
+
+    tb.wait_posedge("clk").unwrap();
+    assert_eq!((tb.time(), tb.read("b").unwrap()), (5, 1));
+    tb.wait_negedge("clk").unwrap();
+    assert_eq!(tb.time(), 10);
+
+    tb.wait_until("b", 4, 100).unwrap();
+    assert_eq!(tb.time(), 35);
+    tb.wait_until("b", 4, 100).unwrap();
+    assert_eq!(tb.time(), 35);
+
+    tb.drive("rst", 0).unwrap();
+    assert_eq!(tb.read("b").unwrap(), 0);
+    tb.wait(2).unwrap();
+    assert_eq!(tb.time(), 37);
+    tb.drive("rst", 1).unwrap();
+
+    assert_eq!(
+        tb.wait_until("b", 2, 10),
+        Err(ModelError::Timeout {
+            condition: "b == 2".to_string(),
+            time: 45,
+        })
+    );
+    assert_eq!(tb.read("b").unwrap(), 1);
+
+    assert!(tb.drive("b", 0).is_err());
+    assert!(tb.read("c").is_err());
+    assert!(tb.wait_posedge("rst").is_err());
+
+    // no events
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut tb = TestBench::new(Simulator::new(model, HashMap::new()));
+    tb.wait(10).unwrap();
+    assert!(matches!(
+        tb.wait_until("b", 1, 10),
+        Err(ModelError::Timeout { time: 10, .. })
+    ));
+}
+
+#[test]
+fn test_testbench_expect() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let simulator = Simulator::builder(model).clock("clk", 10).build().unwrap();
+    let mut tb = TestBench::new(simulator);
+    tb.simulator_mut().reset();
+
+    tb.expect("b", 3, 5).unwrap();
+    assert_eq!(tb.time(), 25);
+    tb.expect("b", 3, 0).unwrap();
+    tb.expect_sequence("b", &[4, 5, 6]).unwrap();
+    assert_eq!(tb.time(), 55);
+
+    // タイムアウトすると確認した値の履歴を報告する
+    let Err(ModelError::ExpectFailed(failure)) = tb.expect("b", 100, 2) else {
+        panic!("expectation didn't fail");
+    };
+    assert_eq!(failure.observed, [(55, 6), (65, 7), (75, 8)]);
+    assert_eq!(
+        failure.to_string(),
+        "b != 100 within 2 cycles of clk\n  55ns: 6 (0x6)\n  65ns: 7 (0x7)\n  75ns: 8 (0x8)"
+    );
+
+    let Err(ModelError::ExpectFailed(failure)) = tb.expect_sequence("b", &[9, 11, 12]) else {
+        panic!("expectation didn't fail");
+    };
+    assert_eq!(failure.observed, [(85, 9), (95, 10)]);
+    assert_eq!(
+        failure.to_string(),
+        "b != [9, 11, 12] at edges of clk\n  85ns: 9 (0x9)\n  95ns: 10 (0xa)"
+    );
+
+    let mut tb = TestBench::new(tb.into_inner()).clock("a");
+    assert!(matches!(
+        tb.expect("b", 0, 1),
+        Err(ModelError::InvalidClock { .. })
+    ));
+}
+
+#[test]
+fn test_async_testbench() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model).clock("clk", 10).build().unwrap();
+    simulator.reset();
+    let mut tb = AsyncTestBench::new(simulator);
+
+    let log = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+    let log0 = log.clone();
+    tb.spawn(|tb| async move {
+        for _ in 0..3 {
+            tb.posedge("clk").await?;
+            log0.borrow_mut().push((tb.time(), tb.read("b")?));
+        }
+        tb.negedge("clk").await?;
+        log0.borrow_mut().push((tb.time(), tb.read("b")?));
+        Ok(())
+    });
+
+    // assert the reset in the middle of a cycle
+    let log1 = log.clone();
+    tb.spawn(|tb| async move {
+        tb.delay(32).await?;
+        tb.drive("rst", 0)?;
+        log1.borrow_mut().push((tb.time(), tb.read("b")?));
+        tb.delay(10).await?;
+        tb.drive("rst", 1)?;
+        tb.wait_until("b", 2).await?;
+        log1.borrow_mut().push((tb.time(), tb.read("b")?));
+        Ok(())
+    });
+
+    tb.run(1000).unwrap();
+    assert_eq!(
+        *log.borrow(),
+        vec![(5, 1), (15, 2), (25, 3), (30, 3), (32, 0), (55, 2)]
+    );
+    assert_eq!(tb.with_simulator(|x| x.time()), 55);
+
+    // errors
+    tb.spawn(|tb| async move { tb.posedge("rst").await });
+    assert!(matches!(tb.run(100), Err(ModelError::InvalidClock { .. })));
+    tb.spawn(|tb| async move { tb.wait_until("b", 100).await });
+    assert!(matches!(
+        tb.run(100),
+        Err(ModelError::Timeout { time: 155, .. })
+    ));
+}
+
+#[test]
+fn test_reset_for_cycles() {
+    let code = std::fs::read_to_string("tests/reset.veryl").unwrap();
+    analyze(&code);
+
+    let model = Model::new("ResetTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model).clock("clk", 10).build().unwrap();
+    simulator.reset();
+    simulator.run(40);
+    assert_eq!(simulator.model().get("a"), Some(4));
+
+    // the synchronous reset is sampled at the falling edges while held
+    simulator.reset_for_cycles(3);
+    assert_eq!(simulator.time(), 25);
+    assert_eq!(simulator.model().get("a"), Some(0));
+    assert_eq!(simulator.model().peek("srst"), Some(0));
+    assert_eq!(simulator.model().peek("arst"), Some(1));
+    simulator.run(5);
+    assert_eq!(simulator.model().get("a"), Some(1));
+    assert_eq!(simulator.model().get("b"), Some(1));
+
+    // a gated first clock has no edges to count, so the reset is not held
+    simulator.set_clock_enabled("clk", false).unwrap();
+    simulator.reset_for_cycles(3);
+    assert_eq!(simulator.time(), 0);
+    assert_eq!(simulator.model().peek("srst"), Some(0));
+}
+
+#[test]
+fn test_finish() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let build = || {
+        let model = Model::new("FFTest", HashMap::new()).unwrap();
+        let mut simulator = Simulator::builder(model).clock("clk", 10).build().unwrap();
+        simulator.reset();
+        simulator
+    };
+
+    let mut simulator = build();
+    simulator.finish_when_equals("b", 3);
+    let reason = FinishReason::Signal {
+        signal: "b".to_string(),
+        value: 3,
+    };
+    assert_eq!(simulator.run(1000), StopReason::Finished(reason.clone()));
+    assert_eq!(simulator.time(), 25);
+    assert_eq!(simulator.finish_reason(), Some(&reason));
+    // finished until reset
+    assert_eq!(simulator.run(1000), StopReason::Finished(reason.clone()));
+    assert_eq!(simulator.step(), None);
+    assert_eq!(simulator.time(), 25);
+    simulator.reset();
+    assert_eq!(simulator.finish_reason(), None);
+
+    let mut simulator = build();
+    simulator.finish_when("a and b", |model| {
+        model.get("a") == Some(0) && model.get("b") == Some(4)
+    });
+    assert_eq!(
+        simulator.run_until(1000, |_, model| model.get("b") == Some(10)),
+        RunResult::Finished(FinishReason::Condition("a and b".to_string()))
+    );
+    assert_eq!(simulator.time(), 35);
+
+    // the time stops exactly at the max time
+    let mut simulator = build();
+    simulator.finish_at(42);
+    assert_eq!(
+        simulator.run(1000),
+        StopReason::Finished(FinishReason::MaxTime(42))
+    );
+    assert_eq!(simulator.time(), 42);
+    assert_eq!(simulator.model().get("b"), Some(4));
+    let mut simulator = build();
+    simulator.finish_at(42);
+    assert_eq!(
+        simulator.advance_to(100),
+        StopReason::Finished(FinishReason::MaxTime(42))
+    );
+    assert_eq!(simulator.time(), 42);
+
+    // watchdog
+    let mut simulator = build();
+    simulator.set_watchdog(Some(std::time::Duration::from_millis(20)));
+    assert!(matches!(
+        simulator.run(u64::MAX / 2),
+        StopReason::Failed(ModelError::WatchdogTimeout { limit_ms: 20, .. })
+    ));
+    assert_eq!(simulator.run(10), StopReason::Aborted);
+}
+
+#[test]
+fn test_hook_handle() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let edges = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook(Box::new(EdgeRecorder(edges.clone())))
+        .build()
+        .unwrap();
+    let recorder = simulator.hook_ids()[0];
+    let logger = simulator.add_hook(Box::new(BufLogger::new()));
+    assert_eq!(simulator.hook_ids(), vec![recorder, logger]);
+    simulator.reset();
+    simulator.run(20);
+
+    // disabled hooks are not called
+    simulator.set_hook_enabled(recorder, false).unwrap();
+    assert!(!simulator.is_hook_enabled(recorder));
+    simulator.run(20);
+    simulator.set_hook_enabled(recorder, true).unwrap();
+    simulator.run(10);
+    assert_eq!(*edges.lock().unwrap(), vec![5, 15, 45]);
+
+    let events = simulator.with_hook_mut(logger, |x: &mut BufLogger| x.events().len());
+    assert_eq!(events, Some(6));
+    let events = simulator.with_hook_mut(logger, |x: &mut EdgeRecorder| x.0.lock().unwrap().len());
+    assert_eq!(events, None);
+
+    assert!(simulator.remove_hook(logger).is_some());
+    assert!(simulator.remove_hook(logger).is_none());
+    assert_eq!(simulator.hook_ids(), vec![recorder]);
+    assert!(matches!(
+        simulator.set_hook_enabled(logger, true),
+        Err(ModelError::HookNotFound(_))
+    ));
+}
+
+// Fails when b reaches the value
+struct FailAt(usize);
+
+impl Hook for FailAt {
+    fn post_clock(
+        &mut self,
+        _time: u64,
+        _clock_name: &str,
+        model: &Model,
+    ) -> Result<HookAction, HookError> {
+        if model.get("b") == Some(self.0) {
+            Err(HookError::Assertion(format!("b reached {}", self.0)))
+        } else {
+            Ok(HookAction::Continue)
+        }
+    }
+}
+
+#[test]
+fn test_hook_error() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook(Box::new(FailAt(2)))
+        .build()
+        .unwrap();
+    simulator.reset();
+
+    let error = ModelError::HookFailed {
+        time: 15,
+        error: HookError::Assertion("b reached 2".to_string()),
+    };
+    assert_eq!(simulator.run(100), StopReason::Failed(error.clone()));
+    assert_eq!(simulator.time(), 15);
+    assert_eq!(
+        error.to_string(),
+        "hook failed at 15ns: assertion failed: b reached 2"
+    );
+    // failed until reset
+    assert_eq!(simulator.run(100), StopReason::Failed(error.clone()));
+    assert_eq!(
+        simulator.run_until(100, |_, _| false),
+        RunResult::Failed(error)
+    );
+    simulator.reset();
+    assert_eq!(simulator.run(10), StopReason::Completed);
+
+    // I/O error of the VCD logger
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook(Box::new(VCDLoggerHook::new("tests/missing/test.vcd")))
+        .build()
+        .unwrap();
+    simulator.reset();
+    assert!(matches!(
+        simulator.run(100),
+        StopReason::Failed(ModelError::HookFailed {
+            time: 0,
+            error: HookError::Io(_),
+        })
+    ));
+}
+
+// Records the lifecycle callbacks
+struct LifecycleRecorder(std::sync::Arc<std::sync::Mutex<Vec<String>>>);
+
+impl Hook for LifecycleRecorder {
+    fn on_start(&mut self, model: &Model) -> Result<(), HookError> {
+        let b = model.get("b").unwrap();
+        self.0.lock().unwrap().push(format!("start b={b}"));
+        Ok(())
+    }
+
+    fn on_input_change(
+        &mut self,
+        time: u64,
+        name: &str,
+        old: usize,
+        new: usize,
+    ) -> Result<HookAction, HookError> {
+        let log = format!("{time}: input {name} {old} -> {new}");
+        self.0.lock().unwrap().push(log);
+        Ok(HookAction::Continue)
+    }
+
+    fn on_signal_change(
+        &mut self,
+        time: u64,
+        name: &str,
+        old: usize,
+        new: usize,
+    ) -> Result<HookAction, HookError> {
+        let log = format!("{time}: {name} {old} -> {new}");
+        self.0.lock().unwrap().push(log);
+        Ok(HookAction::Continue)
+    }
+}
+
+#[test]
+fn test_hook_lifecycle() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let log = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook(Box::new(LifecycleRecorder(log.clone())))
+        .build()
+        .unwrap();
+    simulator.reset();
+    simulator.at(12, |model| model.input("rst", 0));
+    simulator.run(15);
+    // inputs driven between steps are reported at the next step
+    simulator.model_mut().input("rst", 1);
+    simulator.run(10);
+
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec![
+            "start b=0",
+            "5: a 0 -> 1",
+            "5: b 0 -> 1",
+            "12: input rst 1 -> 0",
+            "12: a 1 -> 0",
+            "12: b 1 -> 0",
+            "15: input rst 0 -> 1",
+            "25: a 0 -> 1",
+            "25: b 0 -> 1",
+        ]
+    );
+
+    // on_start is called again after reset
+    simulator.reset();
+    simulator.run(5);
+    assert_eq!(log.lock().unwrap()[9], "start b=0");
+}
+
+// Records its name at every rising edge
+struct NameRecorder(
+    &'static str,
+    std::sync::Arc<std::sync::Mutex<Vec<&'static str>>>,
+);
+
+impl Hook for NameRecorder {
+    fn post_clock(
+        &mut self,
+        _time: u64,
+        _clock_name: &str,
+        _model: &Model,
+    ) -> Result<HookAction, HookError> {
+        self.1.lock().unwrap().push(self.0);
+        Ok(HookAction::Continue)
+    }
+}
+
+#[test]
+fn test_hook_priority() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let calls = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let recorder = |name| Box::new(NameRecorder(name, calls.clone()));
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook_with_priority(recorder("scoreboard"), 10)
+        .hook(recorder("monitor"))
+        .build()
+        .unwrap();
+    let driver = simulator.add_hook_with_priority(recorder("driver"), -10);
+    let checker = simulator.add_hook_with_priority(recorder("checker"), 10);
+    assert_eq!(simulator.hook_priority(checker), Some(10));
+
+    simulator.reset();
+    simulator.run(10);
+    assert_eq!(
+        *calls.lock().unwrap(),
+        vec!["driver", "monitor", "scoreboard", "checker"]
+    );
+
+    // hooks are moved to the end of the new priority
+    calls.lock().unwrap().clear();
+    simulator.set_hook_priority(driver, 10).unwrap();
+    simulator.run(10);
+    assert_eq!(
+        *calls.lock().unwrap(),
+        vec!["monitor", "scoreboard", "checker", "driver"]
+    );
+
+    simulator.remove_hook(driver);
+    assert!(matches!(
+        simulator.set_hook_priority(driver, 0),
+        Err(ModelError::HookNotFound(_))
+    ));
+    assert_eq!(simulator.hook_priority(driver), None);
+}
+
+#[test]
+fn test_vcd_all_signals() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let path = std::env::temp_dir().join("veryl_simulator_all_signals.vcd");
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook(Box::new(VCDLoggerHook::new(path.to_str().unwrap())))
+        .build()
+        .unwrap();
+    simulator.reset();
+    simulator.at(12, |model| model.input("rst", 0));
+    simulator.run(15);
+
+    let vcd = std::fs::read_to_string(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    let body = vcd.split("$enddefinitions $end\n").nth(1).unwrap();
+    assert!(vcd.contains("$var wire 1 ! a $end"));
+    assert!(vcd.contains("$var wire 32 \" b [31:0] $end"));
+    assert!(vcd.contains("$var wire 1 # clk $end"));
+    assert!(vcd.contains("$var wire 1 $ rst $end"));
+    assert_eq!(
+        body,
+        "#0\n$dumpvars\n0!\nb0 \"\n0#\n1$\n$end\n\
+         #5\n1!\nb1 \"\n1#\n\
+         #10\n0#\n\
+         #12\n0$\n0!\nb0 \"\n\
+         #15\n1#\n"
+    );
+}
+
+#[test]
+fn test_vcd_header() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let path = std::env::temp_dir().join("veryl_simulator_header.vcd");
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook(Box::new(VCDLoggerHook::new(path.to_str().unwrap())))
+        .build()
+        .unwrap();
+    simulator.reset();
+    simulator.run(10);
+
+    let vcd = std::fs::read_to_string(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    let lines: Vec<_> = vcd.lines().collect();
+
+    // $date is like "Fri Oct 16 09:30:00 2026"
+    assert_eq!(lines[0], "$date");
+    let date: Vec<_> = lines[1].split_whitespace().collect();
+    assert_eq!(date.len(), 5);
+    assert_eq!(date[3].len(), 8);
+    let version = format!("    Veryl Simulator {}", env!("CARGO_PKG_VERSION"));
+    assert_eq!(lines[3..6], ["$version", version.as_str(), "$end"]);
+    assert_eq!(lines[6..9], ["$timescale", "    1ns", "$end"]);
+
+    // signals are declared in the scope of the top module
+    let end = lines
+        .iter()
+        .position(|x| *x == "$enddefinitions $end")
+        .unwrap();
+    assert_eq!(
+        lines[9..end],
+        [
+            "$scope module FFTest $end",
+            "$var wire 1 ! a $end",
+            "$var wire 32 \" b [31:0] $end",
+            "$var wire 1 # clk $end",
+            "$var wire 1 $ rst $end",
+            "$upscope $end",
+        ]
+    );
+    assert_eq!(lines[end + 1..end + 3], ["#0", "$dumpvars"]);
+}
+
+#[test]
+fn test_signal_filter() {
+    let filter = SignalFilter::new(&["top.u_core.*", "!*_debug"]);
+    assert!(filter.matches("top", "u_core.state"));
+    assert!(filter.matches("top", "u_core.mem[2]"));
+    assert!(!filter.matches("top", "u_core.state_debug"));
+    assert!(!filter.matches("top", "u_bus.addr"));
+
+    // without include patterns, all signals but the excluded ones are selected
+    let filter = SignalFilter::new(&["!clk", "!r?t"]);
+    assert!(filter.matches("FFTest", "a"));
+    assert!(!filter.matches("FFTest", "clk"));
+    assert!(!filter.matches("FFTest", "rst"));
+    assert!(SignalFilter::default().matches("FFTest", "clk"));
+
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let path = std::env::temp_dir().join("veryl_simulator_filter.vcd");
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook(Box::new(
+            VCDLoggerHook::new(path.to_str().unwrap()).filter(&["FFTest.*", "!clk", "!rst"]),
+        ))
+        .build()
+        .unwrap();
+    let logger = simulator.add_hook(Box::new(BufLogger::new().filter(&["b"])));
+    simulator.reset();
+    simulator.run(20);
+
+    let vcd = std::fs::read_to_string(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    let vars: Vec<_> = vcd.lines().filter(|x| x.starts_with("$var")).collect();
+    assert_eq!(
+        vars,
+        ["$var wire 1 ! a $end", "$var wire 32 \" b [31:0] $end"]
+    );
+    assert!(!vcd.contains("#10\n"));
+
+    let names = simulator.with_hook_mut(logger, |x: &mut BufLogger| {
+        x.events()
+            .iter()
+            .all(|(_, x)| x.len() == 1 && x.contains_key("b"))
+    });
+    assert_eq!(names, Some(true));
+}
+
+#[test]
+fn test_vcd_dump_control() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let path = std::env::temp_dir().join("veryl_simulator_dump_control.vcd");
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let vcd = VCDLoggerHook::new(path.to_str().unwrap())
+        .filter(&["b"])
+        .start_at(12)
+        .stop_when_equals("b", 3);
+    let mut simulator = Simulator::builder(model).clock("clk", 10).build().unwrap();
+    let logger = simulator.add_hook(Box::new(vcd));
+    simulator.reset();
+    simulator.run(30);
+
+    // $dumpon and $dumpoff by the testbench
+    simulator.with_hook_mut(logger, |x: &mut VCDLoggerHook| x.dump_on());
+    simulator.run(20);
+    simulator.with_hook_mut(logger, |x: &mut VCDLoggerHook| x.dump_off());
+    let dumping = simulator.with_hook_mut(logger, |x: &mut VCDLoggerHook| x.is_dumping());
+    assert_eq!(dumping, Some(false));
+    simulator.run(20);
+
+    let vcd = std::fs::read_to_string(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    let body = vcd.split("$enddefinitions $end\n").nth(1).unwrap();
+    assert_eq!(
+        body,
+        "#0\n$dumpvars\nb0 !\n$end\n$dumpoff\nbx !\n$end\n\
+         #15\n$dumpon\nb10 !\n$end\n\
+         #25\n$dumpoff\nbx !\n$end\n\
+         #35\n$dumpon\nb100 !\n$end\n\
+         #45\nb101 !\n\
+         #55\n$dumpoff\nbx !\n$end\n"
+    );
+}
+
+#[test]
+fn test_vcd_gtkw() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let dir = std::env::temp_dir();
+    let vcd = dir.join("veryl_simulator_gtkw.vcd");
+    let gtkw = dir.join("veryl_simulator_gtkw.gtkw");
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook(Box::new(
+            VCDLoggerHook::new(vcd.to_str().unwrap()).gtkw(gtkw.to_str().unwrap()),
+        ))
+        .build()
+        .unwrap();
+    simulator.reset();
+    simulator.run(10);
+
+    let save = std::fs::read_to_string(&gtkw).unwrap();
+    let dumpfile = std::fs::canonicalize(&vcd).unwrap();
+    let _ = std::fs::remove_file(&vcd);
+    let _ = std::fs::remove_file(&gtkw);
+    let lines: Vec<_> = save.lines().collect();
+    assert_eq!(lines[1], format!("[dumpfile] \"{}\"", dumpfile.display()));
+    assert_eq!(
+        lines[2..],
+        [
+            "[timestart] 0",
+            "@800200",
+            "-FFTest",
+            "@28",
+            "FFTest.clk",
+            "@28",
+            "FFTest.rst",
+            "@28",
+            "FFTest.a",
+            "@22",
+            "FFTest.b[31:0]",
+            "@1000200",
+            "-FFTest",
+        ]
+    );
+}
+
+#[test]
+fn test_csv_logger() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let path = std::env::temp_dir().join("veryl_simulator_trace.csv");
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook(Box::new(
+            CsvLoggerHook::new(path.to_str().unwrap()).filter(&["a", "b", "rst"]),
+        ))
+        .build()
+        .unwrap();
+    simulator.reset();
+    simulator.at(20, |model| model.input("rst", 0));
+    simulator.run(30);
+
+    let csv = std::fs::read_to_string(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(
+        csv,
+        "time,a,b,rst\n\
+         0,0,0,1\n\
+         5,1,1,1\n\
+         15,0,2,1\n\
+         25,0,0,0\n"
+    );
+}
+
+#[test]
+fn test_json_logger() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let path = std::env::temp_dir().join("veryl_simulator_events.jsonl");
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook(Box::new(
+            JsonLoggerHook::new(path.to_str().unwrap()).filter(&["a", "rst"]),
+        ))
+        .build()
+        .unwrap();
+    simulator.reset();
+    simulator.at(12, |model| model.input("rst", 0));
+    simulator.run(20);
+
+    let jsonl = std::fs::read_to_string(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    let lines: Vec<_> = jsonl.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            r#"{"time":5,"signal":"a","old":0,"new":1,"clock":"clk"}"#,
+            r#"{"time":12,"signal":"rst","old":1,"new":0,"clock":null}"#,
+            r#"{"time":12,"signal":"a","old":1,"new":0,"clock":null}"#,
+        ]
+    );
+}
+
+#[test]
+fn test_buf_logger_query() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model).clock("clk", 10).build().unwrap();
+    let logger = simulator.add_hook(Box::new(BufLogger::new()));
+    simulator.reset();
+    simulator.at(20, |model| model.input("rst", 0));
+    simulator.run(40);
+
+    simulator.with_hook_mut(logger, |x: &mut BufLogger| {
+        assert_eq!(x.value_at("b", 0), Some(0));
+        assert_eq!(x.value_at("b", 14), Some(1));
+        assert_eq!(x.value_at("b", 15), Some(2));
+        assert_eq!(x.value_at("b", 1000), Some(0));
+        assert_eq!(x.value_at("x", 15), None);
+        assert_eq!(x.changes_of("b"), vec![(0, 0), (5, 1), (15, 2), (25, 0)]);
+        assert_eq!(x.changes_of("rst"), vec![(0, 1), (25, 0)]);
+        assert_eq!(x.last_value("a"), Some(0));
+        assert_eq!(x.last_value("x"), None);
+    });
+}
+
+#[test]
+fn test_buf_logger_export() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model).clock("clk", 10).build().unwrap();
+    let logger = simulator.add_hook(Box::new(BufLogger::new().filter(&["a", "b"])));
+    simulator.reset();
+    simulator.run(20);
+
+    let dir = std::env::temp_dir();
+    let vcd = dir.join("veryl_simulator_buf_export.vcd");
+    let csv = dir.join("veryl_simulator_buf_export.csv");
+    simulator
+        .with_hook_mut(logger, |x: &mut BufLogger| {
+            x.write_vcd(vcd.to_str().unwrap())?;
+            x.write_csv(csv.to_str().unwrap())
+        })
+        .unwrap()
+        .unwrap();
+
+    let vcd_text = std::fs::read_to_string(&vcd).unwrap();
+    let csv_text = std::fs::read_to_string(&csv).unwrap();
+    let _ = std::fs::remove_file(&vcd);
+    let _ = std::fs::remove_file(&csv);
+
+    assert!(vcd_text.contains("$scope module FFTest $end"));
+    assert!(vcd_text.contains("$var wire 1 ! a $end"));
+    assert!(vcd_text.contains("$var wire 32 \" b [31:0] $end"));
+    let body = vcd_text.split("$enddefinitions $end\n").nth(1).unwrap();
+    assert_eq!(
+        body,
+        "#0\n$dumpvars\n0!\nb0 \"\n$end\n\
+         #5\n1!\nb1 \"\n\
+         #15\n0!\nb10 \"\n"
+    );
+    assert_eq!(csv_text, "time,a,b\n0,0,0\n5,1,1\n15,0,2\n");
+}
+
+#[test]
+fn test_wave_snapshot() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model).clock("clk", 10).build().unwrap();
+    let logger = simulator.add_hook(Box::new(BufLogger::new().filter(&["a", "b"])));
+    simulator.reset();
+    simulator.run(40);
+
+    simulator.with_hook_mut(logger, |x: &mut BufLogger| {
+        assert_eq!(
+            x.snapshot(),
+            "a\n    0ns: 0x0\n    5ns: 0x1\n    15ns: 0x0\n    25ns: 0x1\n    35ns: 0x0\n\
+             b\n    0ns: 0x0\n    5ns: 0x1\n    15ns: 0x2\n    25ns: 0x3\n    35ns: 0x4\n"
+        );
+        assert_wave_snapshot!(x);
+    });
+
+    // 一致しない場合は差分を返す
+    let path = std::env::temp_dir().join("veryl_simulator_snapshot.wave");
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(check_snapshot(&path, "a\n    0ns: 0x0\n"), Ok(()));
+    assert_eq!(check_snapshot(&path, "a\n    0ns: 0x0\n"), Ok(()));
+    let diff = check_snapshot(&path, "a\n    0ns: 0x1\n").unwrap_err();
+    let _ = std::fs::remove_file(&path);
+    assert!(diff.contains("-    0ns: 0x0\n+    0ns: 0x1\n"));
+}
+
+#[test]
+fn test_buf_logger_capacity() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model).clock("clk", 10).build().unwrap();
+    let by_count = simulator.add_hook(Box::new(BufLogger::new().max_events(3)));
+    let by_time = simulator.add_hook(Box::new(BufLogger::new().max_duration(20)));
+    simulator.reset();
+    simulator.run(100);
+
+    // events at 0, 5, 15, ..., 95 are recorded
+    simulator.with_hook_mut(by_count, |x: &mut BufLogger| {
+        let times: Vec<_> = x.events().iter().map(|(x, _)| *x).collect();
+        assert_eq!(times, vec![75, 85, 95]);
+        assert_eq!(x.dropped(), 8);
+        assert_eq!(x.value_at("b", 80), Some(8));
+        assert_eq!(x.value_at("b", 50), None);
+    });
+    simulator.with_hook_mut(by_time, |x: &mut BufLogger| {
+        let times: Vec<_> = x.events().iter().map(|(x, _)| *x).collect();
+        assert_eq!(times, vec![75, 85, 95]);
+        assert_eq!(x.last_value("b"), Some(10));
+    });
+}
+
+#[test]
+fn test_buf_logger_render() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model).clock("clk", 10).build().unwrap();
+    let logger = simulator.add_hook(Box::new(
+        BufLogger::new().filter(&["a", "b", "rst"]).column_width(3),
+    ));
+    simulator.reset();
+    simulator.at(30, |model| model.input("rst", 0));
+    simulator.run(50);
+
+    let text = simulator
+        .with_hook_mut(logger, |x: &mut BufLogger| x.render())
+        .unwrap();
+    assert_eq!(
+        text,
+        "time  0  5  15 25 35 45\n\
+         a     ▁▁▁▔▔▔▁▁▁▔▔▔▁▁▁▁▁▁\n\
+         b     [0][1][2][3][0]───\n\
+         rst   ▔▔▔▔▔▔▔▔▔▔▔▔▁▁▁▁▁▁\n"
+    );
+}
+
+#[cfg(feature = "tui")]
+#[test]
+fn test_tui_hook() {
+    use ratatui::Terminal;
+    use ratatui::backend::TestBackend;
+    use veryl_simulator::TuiHook;
+
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let terminal = Terminal::new(TestBackend::new(30, 4)).unwrap();
+    let hook =
+        TuiHook::with_terminal(terminal, &["a", "b"]).refresh_interval(std::time::Duration::ZERO);
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model).clock("clk", 10).build().unwrap();
+    let tui = simulator.add_hook(Box::new(hook));
+    simulator.reset();
+    simulator.run(200);
+
+    let lines = simulator
+        .with_hook_mut(tui, |x: &mut TuiHook<TestBackend>| {
+            let buffer = x.terminal().unwrap().backend().buffer().clone();
+            (0..4)
+                .map(|y| (0..30).map(|x| buffer[(x, y)].symbol()).collect::<String>())
+                .collect::<Vec<_>>()
+        })
+        .unwrap();
+    assert_eq!(lines[0], "┌ 200ns: running  [space] pau┐");
+    assert_eq!(lines[1], "│a  ▔▔▁▁▔▔▁▁▔▔▁▁▔▔▁▁▔▔▁▁▔▔▁▁ │");
+    // values are truncated when they change every sample
+    assert_eq!(lines[2], "│b  [9[a[b[c[d[e[f[1[1[1[1[1 │");
+    assert_eq!(lines[3], "└────────────────────────────┘");
+
+    // the overview shows the whole run from the trace storage
+    let terminal = Terminal::new(TestBackend::new(30, 4)).unwrap();
+    let hook = TuiHook::with_terminal(terminal, &["a", "b"]).overview(true);
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model).clock("clk", 10).build().unwrap();
+    let tui = simulator.add_hook(Box::new(hook));
+    simulator.reset();
+    simulator.run(10000);
+
+    let (lines, samples) = simulator
+        .with_hook_mut(tui, |x: &mut TuiHook<TestBackend>| {
+            let buffer = x.terminal().unwrap().backend().buffer().clone();
+            let lines = (0..4)
+                .map(|y| (0..30).map(|x| buffer[(x, y)].symbol()).collect::<String>())
+                .collect::<Vec<_>>();
+            let trace = x.trace();
+            let samples =
+                trace.recent("b").len() + trace.summary("b").iter().map(|x| x.count).sum::<usize>();
+            (lines, samples)
+        })
+        .unwrap();
+    assert_eq!(samples, 1000);
+    assert_eq!(lines[0], "┌ 10000ns: overview, running ┐");
+    assert_eq!(lines[1], "│a  ▒▒▒▒▒▒▒▒▒▒▒▒▒▒▒▒▒▒▒▒▒▒▒▒ │");
+    // the last values of the columns, truncated since they change every column
+    assert_eq!(lines[2], "│b  [5[a[f[1[1[1[2[2[2[3[3[3 │");
+}
+
+#[test]
+fn test_vcd_compare() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let golden = std::env::temp_dir().join("veryl_simulator_golden.vcd");
+    let golden = golden.to_str().unwrap();
+    let build = |hook: Box<dyn Hook>| {
+        let model = Model::new("FFTest", HashMap::new()).unwrap();
+        let mut simulator = Simulator::builder(model)
+            .clock("clk", 10)
+            .hook(hook)
+            .build()
+            .unwrap();
+        simulator.reset();
+        simulator
+    };
+
+    let mut simulator = build(Box::new(VCDLoggerHook::new(golden)));
+    simulator.run(100);
+    drop(simulator);
+
+    // the same design matches the golden waveform
+    let mut simulator = build(Box::new(VcdCompareHook::new(golden).unwrap()));
+    assert_eq!(simulator.run(100), StopReason::Completed);
+
+    // reset asserted in the middle diverges at the next edge
+    let mut simulator = build(Box::new(VcdCompareHook::new(golden).unwrap()));
+    simulator.at(22, |model| model.input("rst", 0));
+    let mismatch = VcdMismatch {
+        time: 25,
+        signal: "a".to_string(),
+        expected: 1,
+        actual: 0,
+    };
+    assert_eq!(
+        simulator.run(100),
+        StopReason::Failed(ModelError::HookFailed {
+            time: 25,
+            error: HookError::Assertion(mismatch.to_string()),
+        })
+    );
+    assert_eq!(
+        mismatch.to_string(),
+        "mismatch at 25ns: a expected 1 but got 0"
+    );
+
+    // ignored signals are not compared
+    let mut simulator = build(Box::new(
+        VcdCompareHook::new(golden).unwrap().filter(&["clk"]),
+    ));
+    simulator.at(22, |model| model.input("rst", 0));
+    assert_eq!(simulator.run(100), StopReason::Completed);
+
+    let _ = std::fs::remove_file(golden);
+    assert!(matches!(
+        VcdCompareHook::new(golden),
+        Err(ModelError::ReadFailed { .. })
+    ));
+}
+
+#[test]
+fn test_scoreboard() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    // reference model of FFTest, b wraps at 3 to make mismatches
+    let mut a = 0;
+    let mut b = 0;
+    let reference = move |inputs: &HashMap<String, usize>| {
+        if inputs["rst"] == 0 {
+            (a, b) = (0, 0);
+        } else {
+            a ^= 1;
+            b = (b + 1) % 3;
+        }
+        HashMap::from([("a".to_string(), a), ("b".to_string(), b)])
+    };
+    let scoreboard = Scoreboard::new("clk", reference);
+    let report = scoreboard.report();
+
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook(Box::new(scoreboard))
+        .build()
+        .unwrap();
+    simulator.reset();
+    simulator.run(40);
+
+    let report = report.lock().unwrap().clone();
+    assert_eq!(report.checks, 8);
+    assert_eq!(
+        report.mismatches,
+        vec![
+            ScoreboardMismatch {
+                time: 25,
+                signal: "b".to_string(),
+                expected: 0,
+                actual: 3,
+            },
+            ScoreboardMismatch {
+                time: 35,
+                signal: "b".to_string(),
+                expected: 1,
+                actual: 4,
+            },
+        ]
+    );
+    assert!(!report.passed());
+    assert_eq!(
+        report.to_string(),
+        "8 checks, 2 mismatches\n  25ns: b expected 0 but got 3\n  35ns: b expected 1 but got 4"
+    );
+}
+
+#[test]
+fn test_assert_hook() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let asserts = AssertHook::new()
+        .clock("clk")
+        .assert("b is small", |model| model.get("b").unwrap() < 3)
+        .assert_eq_after("a stable", 2, "a", 0);
+    let report = asserts.report();
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook(Box::new(asserts))
+        .build()
+        .unwrap();
+    simulator.reset();
+    simulator.run(30);
+
+    let text = report.lock().unwrap().to_string();
+    assert_eq!(
+        text,
+        "5 checks, 2 failures\n  \
+         assertion \"b is small\" failed at 25ns\n  \
+         assertion \"a stable\" failed at 25ns"
+    );
+
+    // the run is aborted at the first failure
+    let asserts = AssertHook::new()
+        .assert_eq("b is zero", "b", 0)
+        .action(HookAction::Abort);
+    let report = asserts.report();
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook(Box::new(asserts))
+        .build()
+        .unwrap();
+    simulator.reset();
+    assert_eq!(simulator.run(100), StopReason::Aborted);
+    assert_eq!(simulator.time(), 5);
+    assert!(!report.lock().unwrap().passed());
+}
+
+#[test]
+fn test_temporal_hook() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let small = PropExpr::low("b")
+        .or(PropExpr::equals("b", 1))
+        .or(PropExpr::equals("b", 2));
+    let hook = TemporalHook::new("clk")
+        .property(Property::implies(
+            "a toggles",
+            PropExpr::high("a"),
+            1,
+            1,
+            PropExpr::low("a"),
+        ))
+        .property(Property::implies(
+            "handshake",
+            PropExpr::rose("a"),
+            1,
+            3,
+            PropExpr::equals("b", 4),
+        ))
+        .property(Property::always("b is small", small))
+        .property(Property::implies(
+            "b reaches 9",
+            PropExpr::equals("b", 1),
+            1,
+            2,
+            PropExpr::equals("b", 9),
+        ));
+    let results = hook.results();
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook(Box::new(hook))
+        .build()
+        .unwrap();
+    simulator.reset();
+    simulator.run(60);
+
+    let results = results.lock().unwrap();
+    let summary: Vec<_> = results
+        .iter()
+        .map(|x| (x.attempts, x.passes, x.failures.clone(), x.pending))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (3, 2, vec![], 1),
+            (3, 2, vec![], 1),
+            (6, 3, vec![35, 45, 55], 0),
+            (1, 0, vec![35], 0),
+        ]
+    );
+    assert_eq!(
+        results[3].to_string(),
+        "b reaches 9: 1 attempts, 0 passes, 1 failures (first at 35ns)"
+    );
+    assert_eq!(
+        Property::implies("x", PropExpr::rose("a"), 1, 3, PropExpr::high("b")).to_string(),
+        "always ($rose(a) |-> ##[1:3] b)"
+    );
+
+    // the run is aborted at the first failure
+    let hook = TemporalHook::new("clk")
+        .property(Property::implies(
+            "b reaches 9",
+            PropExpr::equals("b", 1),
+            1,
+            2,
+            PropExpr::equals("b", 9),
+        ))
+        .action(HookAction::Abort);
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook(Box::new(hook))
+        .build()
+        .unwrap();
+    simulator.reset();
+    assert_eq!(simulator.run(100), StopReason::Aborted);
+    assert_eq!(simulator.time(), 35);
+}
+
+#[test]
+fn test_assertion_failure() {
+    let code = std::fs::read_to_string("tests/assert.veryl").unwrap();
+    analyze(&code);
+
+    let mut model = Model::new("AssertTest", HashMap::new()).unwrap();
+    assert!(model.warnings().is_empty());
+    assert!(model.assertion_failures().is_empty());
+    model.input("a", 1);
+    let failures = model.assertion_failures();
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].severity, AssertSeverity::Warning);
+    assert_eq!(failures[0].to_string(), "$warning at 9:13: a is set");
+
+    // $fatal finishes the simulation
+    let model = Model::new("AssertTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model).clock("clk", 10).build().unwrap();
+    simulator.reset();
+    let reason = simulator.run(100);
+    assert_eq!(simulator.time(), 45);
+    let failures: Vec<_> = simulator
+        .model()
+        .assertion_failures()
+        .iter()
+        .map(|x| x.to_string())
+        .collect();
+    assert_eq!(
+        failures,
+        vec![
+            "$error at 18:17: b is 2 or 3",
+            "$error at 18:17: b is 2 or 3",
+            "$fatal at 21:17: b reached 4"
+        ]
+    );
+    assert_eq!(
+        reason,
+        StopReason::Finished(FinishReason::Fatal(
+            simulator.model().assertion_failures()[2].clone()
+        ))
+    );
+}
+
+#[test]
+fn test_coverage_hook() {
+    let code = std::fs::read_to_string("tests/coverage.veryl").unwrap();
+    analyze(&code);
+
+    let coverage = CoverageHook::new();
+    let report = coverage.report();
+    let model = Model::new("CoverageTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook(Box::new(coverage))
+        .build()
+        .unwrap();
+    simulator.reset();
+    simulator.run(20);
+    simulator.model_mut().input("sel", 1);
+    simulator.run(20);
+
+    // the default arm and the increment are never executed
+    let report = report.lock().unwrap();
+    assert_eq!(report.statements(), (5, 7));
+    assert_eq!(report.branches(), (5, 7));
+    assert_eq!(
+        report.to_string(),
+        "statements: 5/7 (71.4%)\n\
+         branches: 5/7 (71.4%)\n\
+         toggles: 0/19 (0.0%)\n  \
+         default at 12:13 not taken\n  \
+         statement at 12:22 not executed\n  \
+         if at 20:13 not taken\n  \
+         statement at 21:17 not executed"
+    );
+    assert_eq!(simulator.model().coverage_hits().len(), 14);
+}
+
+#[test]
+fn test_covergroup() {
+    let code = std::fs::read_to_string("tests/coverage.veryl").unwrap();
+    analyze(&code);
+
+    let group = CoverGroup::new("cg")
+        .clock("clk")
+        .coverpoint(Coverpoint::new("sel"))
+        .coverpoint(
+            Coverpoint::new("b")
+                .bin("zero", 0)
+                .range("low", 1..=3)
+                .range("high", 4..=255),
+        )
+        .cross("sel_x_b", &["sel", "b"]);
+    let report = group.report();
+    let model = Model::new("CoverageTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook(Box::new(group))
+        .build()
+        .unwrap();
+    simulator.reset();
+    simulator.run(20);
+    simulator.model_mut().input("sel", 2);
+    simulator.run(20);
+
+    // sel is 0 at 5ns and 15ns, 2 at 25ns and 35ns while b counts up
+    let report = report.lock().unwrap();
+    assert_eq!(report.samples, 4);
+    let hits = |x: &CoverpointReport| x.bins.iter().map(|x| x.hits).collect::<Vec<_>>();
+    assert_eq!(hits(&report.coverpoints[0]), vec![2, 0, 2, 0]);
+    assert_eq!(hits(&report.coverpoints[1]), vec![3, 1, 0]);
+    assert_eq!(report.crosses[0].bins.len(), 12);
+    assert_eq!(report.crosses[0].bins[6].name, "<auto[2],zero>");
+    assert_eq!(report.crosses[0].bins[6].hits, 1);
+    assert_eq!(
+        report.to_string().lines().take(4).collect::<Vec<_>>(),
+        vec![
+            "covergroup cg: 47.2% (4 samples)",
+            "  coverpoint sel: 50.0%",
+            "    auto[0]: 2",
+            "    auto[1]: 0",
+        ]
+    );
+
+    let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+    assert_eq!(json["coverpoints"][1]["bins"][1]["name"], "low");
+    assert_eq!(json["coverpoints"][1]["bins"][1]["hits"], 1);
+}
+
+#[test]
+fn test_coverage_db() {
+    let code = std::fs::read_to_string("tests/coverage.veryl").unwrap();
+    analyze(&code);
+
+    // sel selects the branches taken in each run
+    let run = |test: &str, sel: usize| {
+        let coverage = CoverageHook::new();
+        let report = coverage.report();
+        let group = CoverGroup::new("cg").coverpoint(Coverpoint::new("sel"));
+        let group_report = group.report();
+        let model = Model::new("CoverageTest", HashMap::new()).unwrap();
+        let mut simulator = Simulator::builder(model)
+            .clock("clk", 10)
+            .hook(Box::new(coverage))
+            .hook(Box::new(group))
+            .build()
+            .unwrap();
+        simulator.reset();
+        simulator.model_mut().input("sel", sel);
+        simulator.run(40);
+
+        let mut db = CoverageDb::new(test);
+        db.add_code(&report.lock().unwrap());
+        db.add_group(&group_report.lock().unwrap());
+        db
+    };
+    let first = run("first", 1);
+    let second = run("second", 2);
+    assert_eq!(first.code.toggles(), (0, 19));
+    assert_eq!(first.code.branches(), (5, 7));
+    assert_eq!(second.code.branches(), (5, 7));
+
+    let path0 = std::env::temp_dir().join("veryl_simulator_coverage_db0.json");
+    let path1 = std::env::temp_dir().join("veryl_simulator_coverage_db1.json");
+    first.save(&path0).unwrap();
+    second.save(&path1).unwrap();
+    let merged = CoverageDb::merge_files(&[&path0, &path1]).unwrap();
+    std::fs::remove_file(&path0).unwrap();
+    std::fs::remove_file(&path1).unwrap();
+
+    assert_eq!(merged.tests, vec!["first", "second"]);
+    assert_eq!(merged.code.branches(), (7, 7));
+    // b counts up in the second run only
+    assert_eq!(merged.code.toggles(), (2, 19));
+    let sel = &merged.groups[0].coverpoints[0];
+    assert_eq!(
+        sel.bins.iter().map(|x| x.hits).collect::<Vec<_>>(),
+        vec![0, 4, 4, 0]
+    );
+
+    let lcov = merged.to_lcov("tests/coverage.veryl");
+    assert!(lcov.starts_with("TN:first,second\nSF:tests/coverage.veryl\n"));
+    assert!(lcov.contains("BRDA:20,0,0,4\nBRDA:20,0,1,4\n"));
+    assert!(lcov.contains("BRF:7\nBRH:7\n"));
+    assert!(lcov.contains("DA:21,4\n"));
+    assert!(lcov.ends_with("LF:7\nLH:7\nend_of_record\n"));
+}
+
+#[test]
+fn test_regression() {
+    let code = std::fs::read_to_string("tests/coverage.veryl").unwrap();
+    let scenario = |name: &str, sel: usize| {
+        Scenario::new(name, "CoverageTest")
+            .source(&code)
+            .clock("clk", 10)
+            .time(40)
+            .stimulus(Stimulus::parse(&format!("0 sel {sel}")).unwrap())
+    };
+
+    let report = Regression::new()
+        .seed(100)
+        .jobs(2)
+        .coverage(true)
+        .scenario(scenario("count", 2).check(|x| {
+            let b = x.model().get("b").unwrap();
+            if b == 4 {
+                Ok(())
+            } else {
+                Err(format!("b is {b}"))
+            }
+        }))
+        .scenario(
+            scenario("select", 1)
+                .seed(7)
+                .setup(|_, seed| {
+                    assert_eq!(seed, 7);
+                    Ok(())
+                })
+                .check(|x| match x.model().get("a") {
+                    Some(2) => Ok(()),
+                    _ => Err("a is not 2".to_string()),
+                }),
+        )
+        .scenario(scenario("hold", 0).check(|_| Err("b is 0".to_string())))
+        .scenario(Scenario::new("missing", "CoverageTest"))
+        .run();
+
+    assert!(!report.is_success());
+    assert_eq!((report.passed(), report.failed()), (2, 2));
+    let names: Vec<_> = report.results.iter().map(|x| x.name.as_str()).collect();
+    assert_eq!(names, ["count", "select", "hold", "missing"]);
+    let seeds: Vec<_> = report.results.iter().map(|x| x.seed).collect();
+    assert_eq!(seeds, [100, 7, 102, 103]);
+    assert_eq!(report.results[0].time, 40);
+    assert_eq!(report.results[2].failures, ["b is 0"]);
+    let failures: Vec<_> = report.failures().map(|x| x.name.as_str()).collect();
+    assert_eq!(failures, ["hold", "missing"]);
+
+    // 3 つのシナリオで全ての文が実行される
+    let coverage = report.coverage.as_ref().unwrap();
+    assert_eq!(coverage.tests, ["count", "select", "hold"]);
+    assert_eq!(coverage.code.statements(), (7, 7));
+
+    let summary = report.to_string();
+    assert!(summary.contains("PASS count   seed=100 40ns"));
+    assert!(summary.contains("FAIL hold    seed=102 40ns"));
+    assert!(summary.contains("     b is 0\n"));
+    assert!(summary.contains("4 scenarios, 2 passed, 2 failed"));
+    assert!(summary.contains("coverage: statements 7/7"));
+}
+
+#[test]
+fn test_regression_processes() {
+    let code = std::fs::read_to_string("tests/coverage.veryl").unwrap();
+    let scenario = |name: &str| {
+        Scenario::new(name, "CoverageTest")
+            .source(&code)
+            .clock("clk", 10)
+            .time(40)
+            .stimulus(Stimulus::parse("0 sel 2").unwrap())
+    };
+
+    // abort は子プロセスだけを止めて、そのシナリオの失敗になる
+    let report = Regression::new()
+        .jobs(2)
+        .coverage(true)
+        .processes(true)
+        .scenario(scenario("count").check(|x| match x.model().get("b") {
+            Some(4) => Ok(()),
+            _ => Err("b is not 4".to_string()),
+        }))
+        .scenario(scenario("abort").check(|_| std::process::abort()))
+        .run();
+
+    assert_eq!((report.passed(), report.failed()), (1, 1));
+    assert_eq!(report.results[0].time, 40);
+    assert!(report.results[0].coverage.is_some());
+    assert_eq!(report.results[1].name, "abort");
+    assert!(report.results[1].failures[0].starts_with("process exited with"));
+}
+
+#[test]
+fn test_activity_hook() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let activity = ActivityHook::new();
+    let report = activity.report();
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook(Box::new(activity))
+        .build()
+        .unwrap();
+    simulator.reset();
+    simulator.run(40);
+
+    // b toggles 1 + 2 + 1 + 3 bits from 0 to 4
+    let report = report.lock().unwrap();
+    assert_eq!(report.cycles, 4);
+    assert_eq!(report.duration, 40);
+    assert_eq!(report.total_toggles(), 11);
+    let b = report.signal("b").unwrap();
+    assert_eq!((b.changes, b.toggles), (4, 7));
+    assert_eq!(
+        b.bits[..3].iter().map(|x| x.tc).collect::<Vec<_>>(),
+        vec![4, 2, 1]
+    );
+    assert_eq!(
+        report.to_string().lines().take(3).collect::<Vec<_>>(),
+        vec![
+            "11 toggles in 40ns (4 cycles)",
+            "  b: 7 toggles, 4 changes, 0.055 per bit per cycle",
+            "  a: 4 toggles, 4 changes, 1.000 per bit per cycle",
+        ]
+    );
+
+    let saif = report.to_saif();
+    assert!(saif.contains("  (DESIGN \"FFTest\")\n"));
+    assert!(saif.contains("  (DURATION 40)\n"));
+    assert!(saif.contains("      (a (T0 20) (T1 20) (TC 4))\n"));
+    assert!(saif.contains("      (b\\[2\\] (T0 35) (T1 5) (TC 1))\n"));
+}
+
+#[test]
+fn test_simulator_stats() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model).clock("clk", 10).build().unwrap();
+    simulator.reset();
+    simulator.run(100);
+    simulator.reset();
+    simulator.run(50);
+
+    // both edges are events, and the counter is incremented at rising edges
+    let stats = simulator.stats();
+    assert_eq!(stats.simulated_ns, 150);
+    assert_eq!(stats.steps, 30);
+    assert_eq!(stats.clock_events["clk"], 30);
+    assert!(stats.wall_time > std::time::Duration::ZERO);
+    assert!(stats.ns_per_second() > 0.0);
+    let hottest: Vec<_> = stats
+        .hottest
+        .iter()
+        .map(|(x, count)| (x.location.line, *count))
+        .collect();
+    assert_eq!(hottest, vec![(12, 15), (13, 15), (9, 2), (10, 2)]);
+    assert!(
+        stats
+            .to_string()
+            .contains("\n  clk: 30 events\n  statement at 12:13: 15 executions")
+    );
+}
+
+#[test]
+fn test_signal_stats_hook() {
+    let code = std::fs::read_to_string("tests/ff.veryl").unwrap();
+    analyze(&code);
+
+    let stats = SignalStatsHook::new()
+        .filter(&["a", "b"])
+        .bins(4)
+        .print(false);
+    let report = stats.report();
+    let model = Model::new("FFTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook(Box::new(stats))
+        .build()
+        .unwrap();
+    simulator.reset();
+    simulator.run(100);
+
+    // b counts from 1 to 10 at the rising edges
+    let report = report.lock().unwrap();
+    assert_eq!(report.signals.len(), 2);
+    let b = report.signal("b").unwrap();
+    assert_eq!((b.samples, b.min, b.max), (10, 1, 10));
+    assert_eq!(b.mean, 5.5);
+    assert_eq!(
+        b.histogram,
+        vec![(1..=3, 3), (4..=6, 3), (7..=9, 3), (10..=10, 1)]
+    );
+    assert_eq!(report.signal("a").unwrap().mean, 0.5);
+    assert_eq!(
+        b.to_string().lines().collect::<Vec<_>>(),
+        vec![
+            "b: min 1, max 10, mean 5.50 (10 samples)",
+            "         1..=3 #################### 3",
+            "         4..=6 #################### 3",
+            "         7..=9 #################### 3",
+            "            10 #######              1",
+        ]
+    );
+}
+
+#[test]
+fn test_handshake_checker() {
+    let code = std::fs::read_to_string("tests/handshake.veryl").unwrap();
+    analyze(&code);
+
+    let checker = HandshakeChecker::new("clk", "valid", "ready").data(&["data"]);
+    let report = checker.report();
+    let model = Model::new("HandshakeTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook(Box::new(checker))
+        .build()
+        .unwrap();
+    simulator.reset();
+
+    // each step drives the inputs sampled at the next rising edge
+    let steps = [
+        (1, 0, 0x11), // 5ns: stall
+        (1, 1, 0x11), // 15ns: accepted
+        (1, 0, 0x22), // 25ns: stall
+        (1, 0, 0x33), // 35ns: data changed
+        (0, 0, 0x33), // 45ns: valid dropped
+        (1, 1, 0x44), // 55ns: accepted
+    ];
+    for (valid, ready, data) in steps {
+        let model = simulator.model_mut();
+        model.input("valid", valid);
+        model.input("ready", ready);
+        model.input("data", data);
+        simulator.run(10);
+    }
+
+    let report = report.lock().unwrap();
+    assert_eq!(
+        report.transactions,
+        vec![
+            HandshakeTransfer {
+                time: 15,
+                data: vec![0x11]
+            },
+            HandshakeTransfer {
+                time: 55,
+                data: vec![0x44]
+            },
+        ]
+    );
+    assert_eq!(simulator.model().get("count"), Some(2));
+    assert_eq!(
+        report.to_string(),
+        "2 transactions, 2 violations\n  \
+         35ns: data changed from 34 to 51 while waiting for ready\n  \
+         45ns: valid dropped without ready"
+    );
+}
+
+#[test]
+fn test_transaction_recorder() {
+    let code = std::fs::read_to_string("tests/handshake.veryl").unwrap();
+    analyze(&code);
+
+    // a transaction starts when valid is asserted and ends when it is accepted
+    let mut start = None;
+    let recorder = TransactionRecorder::new().monitor("bus", "clk", move |time, model| {
+        if model.peek("valid")? == 0 {
+            start = None;
+            return None;
+        }
+        let begin = *start.get_or_insert(time);
+        if model.peek("ready")? == 0 {
+            return None;
+        }
+        start = None;
+        Some(Transaction::new(begin, time).field("data", model.peek("data")?))
+    });
+    let log = recorder.log();
+    let model = Model::new("HandshakeTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook(Box::new(recorder))
+        .build()
+        .unwrap();
+    simulator.reset();
+
+    let steps = [(1, 0, 0x11), (1, 1, 0x11), (0, 0, 0), (1, 1, 0x22)];
+    for (valid, ready, data) in steps {
+        let model = simulator.model_mut();
+        model.input("valid", valid);
+        model.input("ready", ready);
+        model.input("data", data);
+        simulator.run(10);
+    }
+    log.lock()
+        .unwrap()
+        .push("note", Transaction::new(0, 40).field("id", 1));
+
+    let log = log.lock().unwrap();
+    assert_eq!(
+        log.stream("bus"),
+        &[
+            Transaction::new(5, 15).field("data", 0x11),
+            Transaction::new(35, 35).field("data", 0x22),
+        ]
+    );
+    assert!(log.to_json().contains("\"data\": 17"));
+
+    let vcd = log.to_vcd();
+    assert!(vcd.contains("$var string 1 ! bus $end"));
+    assert!(vcd.contains("$var string 1 \" note $end"));
+    let changes = &vcd[vcd.find("#5\n").unwrap()..];
+    assert_eq!(
+        changes,
+        "#5\nsdata=0x11 !\n#15\ns- !\n#35\ns- !\nsdata=0x22 !\n#40\ns- \"\n"
+    );
+}
+
+#[test]
+fn test_apb_master() {
+    let code = std::fs::read_to_string("tests/apb.veryl").unwrap();
+    analyze(&code);
+
+    let master = ApbMaster::new("clk")
+        .write(0x0, 0x12)
+        .write(0x4, 0x34)
+        .read(0x0)
+        .read(0x4)
+        .read(0x8);
+    let report = master.report();
+    let model = Model::new("ApbRegs", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model).clock("clk", 10).build().unwrap();
+    let id = simulator.add_hook(Box::new(master));
+    simulator.reset();
+    simulator.run_cycles("clk", 12).unwrap();
+
+    let report = report.lock().unwrap();
+    assert_eq!(report.reads(), vec![0x12, 0x34, 0x12]);
+    assert_eq!(
+        report.transfers[0],
+        ApbTransfer {
+            time: 25,
+            write: true,
+            addr: 0x0,
+            data: 0x12,
+            error: false
+        }
+    );
+    assert!(report.transfers[4].error);
+    assert!(
+        simulator
+            .with_hook_mut(id, |x: &mut ApbMaster| x.is_idle())
+            .unwrap()
+    );
+}
+
+#[test]
+fn test_apb_slave() {
+    let code = std::fs::read_to_string("tests/apb.veryl").unwrap();
+    analyze(&code);
+
+    // the bridge connects the master to the slave through the design
+    let master = ApbMaster::new("clk").write(0x2, 0xab).read(0x2).read(0x3);
+    let slave = ApbSlave::new("clk")
+        .prefix("s_")
+        .wait_states(2)
+        .preload(0x3, 0x5a);
+    let memory = slave.memory();
+    let master_report = master.report();
+    let slave_report = slave.report();
+    let model = Model::new("ApbBridge", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook(Box::new(master))
+        .hook(Box::new(slave))
+        .build()
+        .unwrap();
+    simulator.reset();
+    simulator.run_cycles("clk", 16).unwrap();
+
+    assert_eq!(memory.lock().unwrap().get(&0x2), Some(&0xab));
+    let master_report = master_report.lock().unwrap();
+    assert_eq!(master_report.reads(), vec![0xab, 0x5a]);
+    // each transfer takes a setup, 2 wait states and the access
+    assert_eq!(
+        master_report.to_string(),
+        "3 transfers\n  \
+         45ns: write 0x2 = 0xab\n  \
+         85ns: read 0x2 -> 0xab\n  \
+         125ns: read 0x3 -> 0x5a"
+    );
+    assert_eq!(*slave_report.lock().unwrap(), *master_report);
+}
+
+#[test]
+fn test_uart_model() {
+    let code = std::fs::read_to_string("tests/uart.veryl").unwrap();
+    analyze(&code);
+
+    // 100ns per bit, 10 cycles of the clock
+    let uart = UartModel::new("clk", "rx", "tx")
+        .baud(10_000_000)
+        .send(b"Hi!");
+    let report = uart.report();
+    let model = Model::new("UartLoopback", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model).clock("clk", 10).build().unwrap();
+    let id = simulator.add_hook(Box::new(uart));
+    simulator.reset();
+    simulator.run(3100);
+    assert!(
+        simulator
+            .with_hook_mut(id, |x: &mut UartModel| x.is_idle())
+            .unwrap()
+    );
+
+    simulator
+        .with_hook_mut(id, |x: &mut UartModel| x.push(0xa5))
+        .unwrap();
+    simulator.run(1100);
+
+    let report = report.lock().unwrap();
+    assert_eq!(report.received, b"Hi!\xa5");
+    assert_eq!(report.text(), "Hi!\u{fffd}");
+    assert!(report.framing_errors.is_empty());
+}
+
+#[test]
+fn test_cosim() {
+    use std::collections::VecDeque;
+    use std::io::{BufReader, Read, Write};
+    use std::sync::{Arc, Mutex};
+    use veryl_simulator::CosimHook;
+
+    // 受け取った値をそのまま返す外部シミュレータの代わり
+    #[derive(Default)]
+    struct Pipe {
+        lines: Vec<String>,
+        replies: VecDeque<u8>,
+        partial: String, // 書きかけの行
+    }
+
+    #[derive(Clone, Default)]
+    struct Loopback(Arc<Mutex<Pipe>>);
+
+    impl Write for Loopback {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            let mut inner = self.0.lock().unwrap();
+            inner.partial.push_str(&String::from_utf8_lossy(buf));
+            while let Some(end) = inner.partial.find('\n') {
+                let line: String = inner.partial.drain(..=end).collect();
+                let line = line.trim_end().to_string();
+                if let Some(value) = line.strip_prefix("step ").and_then(|x| x.split(' ').nth(1)) {
+                    inner.replies.extend(format!("{value}\n").bytes());
+                }
+                inner.lines.push(line);
+            }
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Read for Loopback {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let mut inner = self.0.lock().unwrap();
+            let n = buf.len().min(inner.replies.len());
+            for (x, y) in buf.iter_mut().zip(inner.replies.drain(..n)) {
+                *x = y;
+            }
+            Ok(n)
+        }
+    }
+
+    let code = std::fs::read_to_string("tests/cosim.veryl").unwrap();
+    analyze(&code);
+
+    let external = Loopback::default();
+    let hook = CosimHook::from_io("clk", BufReader::new(external.clone()), external.clone())
+        .send("q")
+        .receive("d");
+    let model = Model::new("CosimTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model).clock("clk", 10).build().unwrap();
+    simulator.add_hook(Box::new(hook));
+    simulator.reset();
+    simulator.run_cycles("clk", 3).unwrap();
+    // q は外部から戻った値に 1 を加える
+    assert_eq!(simulator.model().get("q"), Some(3));
+    let lines = external.0.lock().unwrap().lines.clone();
+    assert_eq!(lines.first().map(|x| x.as_str()), Some("reset 0"));
+    assert_eq!(
+        lines
+            .iter()
+            .filter(|x| x.starts_with("step"))
+            .map(|x| x.split(' ').nth(2).unwrap())
+            .collect::<Vec<_>>(),
+        ["1", "2", "3"]
+    );
+    assert!(lines.last().unwrap().starts_with("finish"));
+
+    // 外部プロセスとして同じ動作をするシェルを起動する
+    #[cfg(unix)]
+    {
+        let mut command = std::process::Command::new("sh");
+        command.args([
+            "-c",
+            "while read cmd time value; do if [ \"$cmd\" = step ]; then echo $value; fi; done",
+        ]);
+        let hook = CosimHook::new("clk", command).send("q").receive("d");
+        let model = Model::new("CosimTest", HashMap::new()).unwrap();
+        let mut simulator = Simulator::builder(model).clock("clk", 10).build().unwrap();
+        simulator.add_hook(Box::new(hook));
+        simulator.reset();
+        simulator.run_cycles("clk", 20).unwrap();
+        assert_eq!(simulator.model().get("q"), Some(20));
+    }
+}
+
+#[cfg(feature = "server")]
+#[test]
+fn test_server() {
+    use serde_json::{Value, json};
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::{TcpListener, TcpStream};
+    use veryl_simulator::Server;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = std::thread::spawn(move || Server::new().listen(&listener));
+
+    let stream = TcpStream::connect(addr).unwrap();
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut writer = stream;
+    let mut id = 0;
+    // 応答までに受け取った通知と応答を返す
+    let mut call = |method: &str, params: Value| {
+        id += 1;
+        let request = json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+        writeln!(writer, "{request}").unwrap();
+        let mut notifications = Vec::new();
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let message: Value = serde_json::from_str(&line).unwrap();
+            if message["id"] == json!(id) {
+                return (notifications, message);
+            }
+            notifications.push(message["params"].clone());
+        }
+    };
+
+    // 読み込み前
+    let (_, response) = call("get", json!({"signal": "q"}));
+    assert_eq!(response["error"]["code"], -32000);
+
+    let source = std::fs::read_to_string("tests/cosim.veryl").unwrap();
+    let (_, response) = call(
+        "load",
+        json!({"source": source, "top": "CosimTest", "clocks": {"clk": 10}}),
+    );
+    assert_eq!(response["result"]["top"], "CosimTest");
+    assert_eq!(response["result"]["signals"].as_array().unwrap().len(), 4);
+
+    call("reset", Value::Null);
+    call("watch", json!({"signals": ["CosimTest.q"]}));
+    let (_, response) = call("watch", json!({"signals": ["qq"]}));
+    assert_eq!(response["error"]["code"], -32602);
+    assert!(
+        response["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("did you mean \"q\"?")
+    );
+    let (_, response) = call("set", json!({"port": "d", "value": 4}));
+    assert_eq!(response["result"], Value::Null);
+
+    let (changes, response) = call("run_cycles", json!({"clock": "clk", "cycles": 2}));
+    assert_eq!(response["result"]["reason"], "completed");
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0]["signal"], "q");
+    assert_eq!(changes[0]["old"], 0);
+    assert_eq!(changes[0]["new"], 5);
+
+    let (_, response) = call("get", json!({"signal": "q"}));
+    assert_eq!(response["result"], 5);
+
+    call("set", json!({"port": "d", "value": 9}));
+    let (changes, response) = call(
+        "run_until",
+        json!({"signal": "q", "value": 10, "timeout": 100}),
+    );
+    assert_eq!(response["result"]["reason"], "satisfied");
+    assert_eq!(changes.last().unwrap()["new"], 10);
+
+    let (_, response) = call(
+        "run_until",
+        json!({"signal": "q", "value": 0, "timeout": 30}),
+    );
+    assert_eq!(response["result"]["reason"], "timeout");
+
+    // エラー
+    let (_, response) = call("set", json!({"port": "q", "value": 1}));
+    assert_eq!(response["error"]["code"], -32000);
+    let (_, response) = call("set", json!({"port": "d"}));
+    assert_eq!(response["error"]["code"], -32602);
+    let (_, response) = call("unknown", Value::Null);
+    assert_eq!(response["error"]["code"], -32601);
+
+    let (_, response) = call("shutdown", Value::Null);
+    assert_eq!(response["result"], Value::Null);
+    server.join().unwrap().unwrap();
+}
+
+#[test]
+fn test_repl() {
+    use veryl_simulator::Repl;
+
+    let code = std::fs::read_to_string("tests/cosim.veryl").unwrap();
+    analyze(&code);
+    let model = Model::new("CosimTest", HashMap::new()).unwrap();
+    let simulator = Simulator::builder(model).clock("clk", 10).build().unwrap();
+
+    let history = std::env::temp_dir().join(format!("veryl_repl_{}", std::process::id()));
+    let _ = std::fs::remove_file(&history);
+    let mut repl = Repl::new(simulator).history_file(&history).unwrap();
+
+    let input = "reset\nset d 0x4\ncycles 2\nprint q\nbreak q>=7\nset d 6\nrun 1us\n\
+                 !4\ndelete 1\nrun 20ns\nprint CosimTest.?\nset q 1\nrun 1x\nfoo\nquit\nset d 1\n";
+    let mut output = Vec::new();
+    repl.run(input.as_bytes(), &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+
+    assert!(output.contains("q = 5 (0x5)"));
+    assert!(output.contains("breakpoint 1: q>=7"));
+    // ブレークポイントで停止する
+    assert!(output.contains("paused at"));
+    assert!(output.contains("breakpoint 1: break at"));
+    assert!(output.contains("q = 7 (0x7)"));
+    assert!(output.contains("d = 6 (0x6)"));
+    assert!(output.contains("error: port \"q\" is not found"));
+    assert!(output.contains("error: invalid time \"1x\""));
+    assert!(output.contains("error: unknown command \"foo\""));
+    // quit 以降は実行しない
+    assert_eq!(repl.simulator().model().peek("d"), Some(6));
+    assert_eq!(repl.simulator().model().peek("q"), Some(7));
+
+    // !4 は履歴の "print q" に置き換えられる
+    assert_eq!(repl.history()[7], "print q");
+    let saved = std::fs::read_to_string(&history).unwrap();
+    assert_eq!(saved.lines().count(), 15);
+    std::fs::remove_file(&history).unwrap();
+}
+
+#[test]
+fn test_from_metadata() {
+    let code = std::fs::read_to_string("tests/cosim.veryl").unwrap();
+    analyze(&code);
+
+    let dir = std::env::temp_dir().join(format!("veryl_simulate_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("stimulus.csv"), "0, d, 4\n100, d, 8\n").unwrap();
+
+    let mut metadata = Metadata::create_default("prj").unwrap();
+    metadata.metadata_path = dir.join("Veryl.toml");
+    metadata.simulate = toml::from_str(
+        r#"
+        top = "CosimTest"
+        clocks = {clk = 4}
+        reset_cycles = 2
+        stimulus = "stimulus.csv"
+        waveform = "wave.vcd"
+        signals = ["q"]
+        plusargs = {seed = "5"}
+        "#,
+    )
+    .unwrap();
+
+    let mut simulator = Simulator::from_metadata(&metadata).unwrap();
+    assert_eq!(simulator.clock_period("clk"), Some(4));
+    assert_eq!(simulator.plusarg("seed"), Some("5"));
+    assert_eq!(simulator.plusarg("verbose"), None);
+    simulator.reset();
+    simulator.run(50);
+    assert_eq!(simulator.model().get("q"), Some(5));
+    simulator.run(100);
+    assert_eq!(simulator.model().get("q"), Some(9));
+    drop(simulator);
+
+    let vcd = std::fs::read_to_string(dir.join("wave.vcd")).unwrap();
+    assert!(vcd.contains(" q "));
+    assert!(!vcd.contains(" d "));
+
+    // 設定の誤り
+    metadata.simulate.clocks.insert("clk2".to_string(), 4);
+    assert!(matches!(
+        Simulator::from_metadata(&metadata),
+        Err(ModelError::InvalidClock { .. })
+    ));
+    metadata.simulate.top = None;
+    assert!(matches!(
+        Simulator::from_metadata(&metadata),
+        Err(ModelError::InvalidMetadata(_))
+    ));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_spi_master_slave() {
+    let code = std::fs::read_to_string("tests/spi.veryl").unwrap();
+    analyze(&code);
+
+    for mode in 0..4 {
+        // the bridge connects the master to the slave through the design
+        let master = SpiMaster::new("clk")
+            .mode(mode)
+            .divider(2)
+            .transfer(0xa5)
+            .transfer(0x3c);
+        let slave = SpiSlave::new("clk")
+            .prefix("s_")
+            .mode(mode)
+            .respond(&[0x81, 0x7e]);
+        let master_report = master.report();
+        let slave_report = slave.report();
+        let model = Model::new("SpiBridge", HashMap::new()).unwrap();
+        let mut simulator = Simulator::builder(model)
+            .clock("clk", 10)
+            .hook(Box::new(master))
+            .hook(Box::new(slave))
+            .build()
+            .unwrap();
+        simulator.reset();
+        simulator.run_cycles("clk", 80).unwrap();
+
+        let words = |report: &SpiReport| -> Vec<_> {
+            report.transfers.iter().map(|x| (x.mosi, x.miso)).collect()
+        };
+        let expected = vec![(0xa5, 0x81), (0x3c, 0x7e)];
+        assert_eq!(
+            words(&master_report.lock().unwrap()),
+            expected,
+            "mode {mode}"
+        );
+        assert_eq!(
+            words(&slave_report.lock().unwrap()),
+            expected,
+            "mode {mode}"
+        );
+    }
+
+    // 12 bit words with the LSB first
+    let master = SpiMaster::new("clk")
+        .mode(3)
+        .word_bits(12)
+        .lsb_first()
+        .transfer(0x9a5);
+    let slave = SpiSlave::new("clk")
+        .prefix("s_")
+        .mode(3)
+        .word_bits(12)
+        .lsb_first()
+        .respond(&[0x123]);
+    let report = master.report();
+    let model = Model::new("SpiBridge", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook(Box::new(master))
+        .hook(Box::new(slave))
+        .build()
+        .unwrap();
+    simulator.reset();
+    simulator.run_cycles("clk", 40).unwrap();
+    assert_eq!(
+        report.lock().unwrap().to_string(),
+        "1 transfers\n  265ns: mosi 0x9a5, miso 0x123"
+    );
+}
+
+#[test]
+fn test_i2c_master_slave() {
+    let code = std::fs::read_to_string("tests/i2c.veryl").unwrap();
+    analyze(&code);
+
+    let bus = I2cBus::new();
+    let master = I2cMaster::new("clk")
+        .bus(&bus)
+        .write(0x50, &[0x02, 0xaa, 0xbb])
+        .write_read(0x50, &[0x02], 2)
+        .read(0x51, 1);
+    let slave = I2cSlave::new("clk", 0x50)
+        .bus(&bus)
+        .stretch(8)
+        .preload(0x04, 0xcc);
+    let master_report = master.report();
+    let slave_report = slave.report();
+    let registers = slave.registers();
+    let model = Model::new("I2cDevice", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model).clock("clk", 10).build().unwrap();
+    let id = simulator.add_hook(Box::new(master));
+    simulator.add_hook(Box::new(slave));
+    simulator.reset();
+
+    // the design stretches SCL before the first START
+    simulator.model_mut().input("hold", 1);
+    simulator.run_cycles("clk", 100).unwrap();
+    assert_eq!(simulator.model().peek("scl_i"), Some(0));
+    assert!(master_report.lock().unwrap().transfers.is_empty());
+    simulator.model_mut().input("hold", 0);
+
+    simulator.run_cycles("clk", 3000).unwrap();
+    assert!(
+        simulator
+            .with_hook_mut(id, |x: &mut I2cMaster| x.is_idle())
+            .unwrap()
+    );
+
+    let transfers: Vec<_> = master_report
+        .lock()
+        .unwrap()
+        .transfers
+        .iter()
+        .map(|x| I2cTransfer {
+            time: 0,
+            ..x.clone()
+        })
+        .collect();
+    assert_eq!(
+        transfers,
+        vec![
+            I2cTransfer {
+                time: 0,
+                addr: 0x50,
+                written: vec![0x02, 0xaa, 0xbb],
+                read: vec![],
+                acked: true,
+            },
+            I2cTransfer {
+                time: 0,
+                addr: 0x50,
+                written: vec![0x02],
+                read: vec![0xaa, 0xbb],
+                acked: true,
+            },
+            I2cTransfer {
+                time: 0,
+                addr: 0x51,
+                written: vec![],
+                read: vec![],
+                acked: false,
+            },
+        ]
+    );
+    assert_eq!(
+        *registers.lock().unwrap(),
+        BTreeMap::from([(0x02, 0xaa), (0x03, 0xbb), (0x04, 0xcc)])
+    );
+    let slave_report = slave_report.lock().unwrap();
+    assert_eq!(slave_report.transfers.len(), 2);
+    assert_eq!(slave_report.transfers[1].read, vec![0xaa, 0xbb]);
+}
+
+#[test]
+fn test_memory_model() {
+    let code = std::fs::read_to_string("tests/memory.veryl").unwrap();
+    analyze(&code);
+
+    let memory = MemoryModel::new("clk")
+        .prefix("mem_")
+        .latency(3)
+        .load_hex("tests/memory.hex")
+        .unwrap();
+    let backdoor = memory.memory();
+    let model = Model::new("MemoryPort", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook(Box::new(memory))
+        .build()
+        .unwrap();
+    simulator.reset();
+
+    // pipelined reads, the data is driven 3 edges after each request
+    let mut rdata = Vec::new();
+    for addr in [0x10, 0x11, 0x20, 0x00, 0x00, 0x00] {
+        let model = simulator.model_mut();
+        model.input("addr", addr);
+        model.input("re", (addr != 0) as usize);
+        simulator.run_cycles("clk", 1).unwrap();
+        let model = simulator.model();
+        rdata.push((model.get("rvalid").unwrap(), model.get("rdata").unwrap()));
+    }
+    assert_eq!(
+        rdata,
+        vec![
+            (0, 0),
+            (0, 0),
+            (1, 0x1234),
+            (1, 0xabcd),
+            (1, 0xffff),
+            (0, 0xffff)
+        ]
+    );
+
+    // writes from the design and the backdoor
+    let model = simulator.model_mut();
+    model.input("addr", 0x30);
+    model.input("we", 1);
+    model.input("wdata", 0x5555);
+    simulator.run_cycles("clk", 1).unwrap();
+    simulator.model_mut().input("we", 0);
+    assert_eq!(backdoor.lock().unwrap().get(&0x30), Some(&0x5555));
+
+    backdoor.lock().unwrap().insert(0x40, 0x7777);
+    simulator.model_mut().input("addr", 0x40);
+    simulator.model_mut().input("re", 1);
+    simulator.run_cycles("clk", 3).unwrap();
+    assert_eq!(simulator.model().get("rdata"), Some(0x7777));
+
+    // writes to a ROM abort the simulation
+    let rom = MemoryModel::new("clk").prefix("mem_").read_only();
+    let model = Model::new("MemoryPort", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook(Box::new(rom))
+        .build()
+        .unwrap();
+    simulator.reset();
+    simulator.model_mut().input("addr", 0x12);
+    simulator.model_mut().input("we", 1);
+    let StopReason::Failed(error) = simulator.run(20) else {
+        panic!("write to ROM must fail");
+    };
+    assert_eq!(
+        error.to_string(),
+        "hook failed at 5ns: write to read-only memory at 0x12"
+    );
+
+    let error = MemoryModel::new("clk").load_hex("tests/memory.veryl").err();
+    assert!(matches!(error, Some(ModelError::ParseFailed { .. })));
+}
+
+#[test]
+fn test_random_driver() {
+    let code = std::fs::read_to_string("tests/memory.veryl").unwrap();
+    analyze(&code);
+
+    let run = |seed: u64| {
+        let driver = RandomDriver::new("clk")
+            .seed(seed)
+            .drive("addr", Distribution::Range(0x10..=0x1f))
+            .drive("we", Distribution::Values(vec![(0, 3), (1, 1)]))
+            .drive(
+                "wdata",
+                Distribution::Ranges(vec![(0..=0, 1), (0xfff0..=0xffff, 1)]),
+            )
+            .drive("re", Distribution::Full);
+        assert_eq!(driver.current_seed(), seed);
+        let model = Model::new("MemoryPort", HashMap::new()).unwrap();
+        let mut simulator = Simulator::builder(model)
+            .clock("clk", 10)
+            .hook(Box::new(driver))
+            .build()
+            .unwrap();
+        simulator.reset();
+        let mut values = Vec::new();
+        for _ in 0..200 {
+            simulator.run_cycles("clk", 1).unwrap();
+            let model = simulator.model();
+            let value = |x: &str| model.peek(x).unwrap();
+            values.push((value("addr"), value("we"), value("wdata"), value("re")));
+        }
+        values
+    };
+
+    let values = run(42);
+    assert_eq!(values, run(42));
+    assert_ne!(values, run(43));
+
+    assert!(values.iter().all(|x| (0x10..=0x1f).contains(&x.0)));
+    assert!(values.iter().all(|x| x.2 == 0 || x.2 >= 0xfff0));
+    assert!(values.iter().all(|x| x.3 <= 1));
+    let writes = values.iter().filter(|x| x.1 == 1).count();
+    assert!((20..80).contains(&writes), "{writes} writes");
+}
+
+#[test]
+fn test_replay() {
+    let code = std::fs::read_to_string("tests/coverage.veryl").unwrap();
+    analyze(&code);
+
+    let outputs = |simulator: &mut Simulator| {
+        let mut values = Vec::new();
+        for _ in 0..50 {
+            simulator.run_cycles("clk", 1).unwrap();
+            let model = simulator.model();
+            values.push((model.get("a").unwrap(), model.get("b").unwrap()));
+        }
+        values
+    };
+
+    // 乱数と手動の入力で動かしながら記録する
+    let driver = RandomDriver::new("clk").drive("sel", Distribution::Full);
+    let model = Model::new("CoverageTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model)
+        .clock("clk", 10)
+        .hook(Box::new(driver))
+        .build()
+        .unwrap();
+    simulator.reset();
+    simulator.start_recording();
+    simulator.at(203, |model| {
+        let sel = model.peek("sel").unwrap();
+        model.input("sel", sel ^ 3);
+    });
+    let expected = outputs(&mut simulator);
+
+    let path = "tests/test_replay.csv";
+    simulator.save_replay(path).unwrap();
+    let replay = simulator.recorded_inputs().unwrap();
+    assert!(replay.rows().iter().any(|x| x.time == 203 && x.events == 0));
+    assert!(replay.rows().iter().any(|x| x.events > 0));
+
+    // 記録した入力だけで同じ結果を再現する
+    let model = Model::new("CoverageTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model).clock("clk", 10).build().unwrap();
+    simulator.reset();
+    simulator.replay(path).unwrap();
+    let actual = outputs(&mut simulator);
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(expected, actual);
+
+    assert_eq!(Replay::parse(&replay.to_csv()).unwrap(), replay);
+    let error = Replay::parse("0,0,sel,zz").err().unwrap();
+    assert_eq!(
+        error.to_string(),
+        "failed to parse \"<replay>\": line 1: invalid value \"zz\""
+    );
+    let mut simulator = Simulator::builder(Model::new("CoverageTest", HashMap::new()).unwrap())
+        .clock("clk", 10)
+        .build()
+        .unwrap();
+    let error = simulator
+        .apply_replay(&Replay::parse("0,0,a,1").unwrap())
+        .err();
+    assert!(matches!(error, Some(ModelError::UnknownPort(_))));
+}
+
+#[test]
+fn test_time_travel() {
+    let code = std::fs::read_to_string("tests/coverage.veryl").unwrap();
+    analyze(&code);
+
+    let driver = RandomDriver::new("clk")
+        .seed(1)
+        .drive("sel", Distribution::Full);
+    let model = Model::new("CoverageTest", HashMap::new()).unwrap();
+    let mut simulator = Simulator::builder(model).clock("clk", 10).build().unwrap();
+    let driver = simulator.add_hook(Box::new(driver));
+    let logger = simulator.add_hook(Box::new(BufLogger::new().filter(&["b"])));
+    assert!(matches!(
+        simulator.step_back(),
+        Err(ModelError::TimeTravelFailed(_))
+    ));
+
+    simulator.reset();
+    simulator.enable_time_travel(4);
+    simulator.at(203, |model| {
+        let sel = model.peek("sel").unwrap();
+        model.input("sel", sel ^ 3);
+    });
+    let state = |simulator: &Simulator| {
+        let model = simulator.model();
+        (
+            simulator.time(),
+            model.peek("sel").unwrap(),
+            model.get("b").unwrap(),
+        )
+    };
+    let mut trace = vec![state(&simulator)];
+    for _ in 0..60 {
+        simulator.step().unwrap();
+        trace.push(state(&simulator));
+    }
+    let events = simulator
+        .with_hook_mut(logger, |x: &mut BufLogger| x.events().len())
+        .unwrap();
+
+    // 1 ステップずつ戻る
+    for expected in trace.iter().rev().skip(1).take(12) {
+        let time = simulator.step_back().unwrap();
+        assert_eq!(time, expected.0);
+        assert_eq!(state(&simulator), *expected);
+    }
+
+    // 戻る間はフックを呼ばない
+    let logged = simulator.with_hook_mut(logger, |x: &mut BufLogger| x.events().len());
+    assert_eq!(logged, Some(events));
+
+    // 任意の時刻に戻り、記録した入力で同じ結果を再現する
+    simulator.rewind_to(42).unwrap();
+    let expected = trace.iter().rfind(|x| x.0 <= 42).unwrap();
+    assert_eq!(state(&simulator), (42, expected.1, expected.2));
+    simulator.set_hook_enabled(driver, false).unwrap();
+    for expected in trace.iter().filter(|x| x.0 > 42 && x.0 <= 240) {
+        simulator.step().unwrap();
+        assert_eq!(state(&simulator), *expected);
+    }
+
+    assert!(matches!(
+        simulator.rewind_to(1000),
+        Err(ModelError::TimeTravelFailed(_))
+    ));
+    simulator.rewind_to(0).unwrap();
+    assert_eq!(state(&simulator), trace[0]);
+    assert!(matches!(
+        simulator.step_back(),
+        Err(ModelError::TimeTravelFailed(_))
+    ));
+}
+
+#[test]
+fn test_eval_mode() {
+    let designs = [
+        ("tests/apb.veryl", "ApbRegs"),
+        ("tests/assert.veryl", "AssertTest"),
+        ("tests/behavioral.veryl", "BehavioralTest"),
+        ("tests/case.veryl", "CaseTest"),
+        ("tests/clocks.veryl", "ClocksTest"),
+        ("tests/comb.veryl", "CombTest"),
+        ("tests/compare.veryl", "CompareTest"),
+        ("tests/compound.veryl", "CompoundTest"),
+        ("tests/concat.veryl", "ConcatTest"),
+        ("tests/dpi.veryl", "DpiTest"),
+        ("tests/cond_type.veryl", "CondTypeTest"),
+        ("tests/coverage.veryl", "CoverageTest"),
+        ("tests/ff.veryl", "FFTest"),
+        ("tests/inside.veryl", "InsideTest"),
+        ("tests/latch.veryl", "LatchTest"),
+        ("tests/memory.veryl", "MemoryPort"),
+        ("tests/select.veryl", "SelectTest"),
+        ("tests/slice.veryl", "SliceTest"),
+        ("tests/unknown.veryl", "UnknownTest"),
+    ];
+
+    for (path, top) in designs {
+        let code = std::fs::read_to_string(path).unwrap();
+        analyze(&code);
+
+        // 各評価方式で同じ入力を与え、インタプリタと全信号を比較する
+        let modes = [
+            EvalMode::Compiled,
+            #[cfg(feature = "jit")]
+            EvalMode::Jit,
+        ];
+        let mut interpreted = Model::new(top, HashMap::new()).unwrap();
+        interpreted.set_eval_mode(EvalMode::Interpreted).unwrap();
+        let mut models: Vec<_> = modes
+            .iter()
+            .map(|mode| {
+                let mut model = Model::new(top, HashMap::new()).unwrap();
+                model.set_eval_mode(*mode).unwrap();
+                assert_eq!(model.eval_mode(), *mode);
+                model
+            })
+            .collect();
+
+        let clocks: Vec<_> = interpreted.clocks().iter().map(|x| x.0.clone()).collect();
+        let inputs: Vec<_> = interpreted
+            .signals()
+            .filter(|x| x.direction == Direction::Input && !clocks.contains(&x.name))
+            .collect();
+        let mut seed = 0x2545_f491_4f6c_dd1du64;
+        for cycle in 0..200 {
+            for input in &inputs {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                let value = seed as usize & ((1 << input.width.min(16)) - 1);
+                interpreted.input(&input.name, value);
+                for model in &mut models {
+                    model.input(&input.name, value);
+                }
+            }
+            for clock in &clocks {
+                interpreted.clock_rise(clock);
+                interpreted.clock_fall(clock);
+                for model in &mut models {
+                    model.clock_rise(clock);
+                    model.clock_fall(clock);
+                }
+            }
+            for (model, mode) in models.iter().zip(&modes) {
+                assert_eq!(
+                    model.snapshot(),
+                    interpreted.snapshot(),
+                    "{top} {mode:?} at cycle {cycle}"
+                );
+            }
+        }
+        for (model, mode) in models.iter().zip(&modes) {
+            assert_eq!(
+                model.coverage_hits(),
+                interpreted.coverage_hits(),
+                "{top} {mode:?}"
+            );
+            assert_eq!(
+                model.case_violations(),
+                interpreted.case_violations(),
+                "{top} {mode:?}"
+            );
+            assert_eq!(
+                model.assertion_failures(),
+                interpreted.assertion_failures(),
+                "{top} {mode:?}"
+            );
+            assert_eq!(
+                model.unknown_signals(),
+                interpreted.unknown_signals(),
+                "{top} {mode:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_emit_rust() {
+    let designs = [
+        ("tests/apb.veryl", "ApbRegs"),
+        ("tests/behavioral.veryl", "BehavioralTest"),
+        ("tests/case.veryl", "CaseTest"),
+        ("tests/clocks.veryl", "ClocksTest"),
+        ("tests/comb.veryl", "CombTest"),
+        ("tests/compare.veryl", "CompareTest"),
+        ("tests/compound.veryl", "CompoundTest"),
+        ("tests/compound.veryl", "CompoundTest"),
+        ("tests/concat.veryl", "ConcatTest"),
+        ("tests/dpi.veryl", "DpiTest"),
+        ("tests/ff.veryl", "FFTest"),
+        ("tests/inside.veryl", "InsideTest"),
+        ("tests/latch.veryl", "LatchTest"),
+        ("tests/loop.veryl", "LoopTest"),
+        ("tests/memory.veryl", "MemoryPort"),
+        ("tests/reset.veryl", "ResetTest"),
+        ("tests/select.veryl", "SelectTest"),
+        ("tests/slice.veryl", "SliceTest"),
+        ("tests/unknown.veryl", "UnknownTest"),
+    ];
+
+    // 生成したソースをまとめてコンパイルし、同じ入力を与えたモデルと全信号を比較する
+    let mut source = String::new();
+    let mut expected = String::new();
+    for (i, (path, top)) in designs.iter().enumerate() {
+        let code = std::fs::read_to_string(path).unwrap();
+        analyze(&code);
+
+        let mut model = Model::new(top, HashMap::new()).unwrap();
+        let clocks: Vec<_> = model.clocks().iter().map(|x| x.0.clone()).collect();
+        let inputs: Vec<_> = model
+            .signals()
+            .filter(|x| x.direction == Direction::Input && !clocks.contains(&x.name))
+            .collect();
+        source.push_str(&format!("mod m{i} {{\n{}}}\n", model.emit_rust()));
+
+        let mut driver = format!(
+            "    let mut m = m{i}::{top}::new();\n    m.reset();\n    let mut seed = 0x2545_f491_4f6c_dd1du64;\n    for cycle in 0..100 {{\n"
+        );
+        model.reset();
+        let mut seed = 0x2545_f491_4f6c_dd1du64;
+        for cycle in 0..100 {
+            for input in &inputs {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                let value = seed as usize & ((1 << input.width.min(16)) - 1);
+                model.input(&input.name, value);
+            }
+            for clock in &clocks {
+                model.clock_rise(clock);
+                model.clock_fall(clock);
+            }
+            let state = model.snapshot();
+            let values: BTreeMap<_, _> = state
+                .inputs
+                .iter()
+                .chain(&state.outputs)
+                .chain(&state.internals)
+                .map(|(k, v)| (k.clone(), *v))
+                .collect();
+            expected.push_str(&format!("{top} {cycle} {values:?}\n"));
+        }
+        for input in &inputs {
+            driver.push_str(&format!(
+                "        seed ^= seed << 13;\n        seed ^= seed >> 7;\n        seed ^= seed << 17;\n        m.set({:?}, seed as usize & ((1 << {}) - 1));\n",
+                input.name,
+                input.width.min(16)
+            ));
+        }
+        for clock in &clocks {
+            driver.push_str(&format!(
+                "        m.clock_rise({clock:?});\n        m.clock_fall({clock:?});\n"
+            ));
+        }
+        driver.push_str(&format!(
+            "        let values: BTreeMap<_, _> = m{i}::{top}::SIGNALS.iter().map(|x| (x.to_string(), m.get(x).unwrap())).collect();\n        println!(\"{top} {{cycle}} {{values:?}}\");\n    }}\n"
+        ));
+        source.push_str(&format!("fn run{i}() {{\n{driver}}}\n"));
+    }
+    source.push_str("use std::collections::BTreeMap;\nfn main() {\n");
+    for i in 0..designs.len() {
+        source.push_str(&format!("    run{i}();\n"));
+    }
+    source.push_str("}\n");
+
+    let dir = std::env::temp_dir().join(format!("veryl_emit_rust_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("main.rs"), &source).unwrap();
+    let rustc = std::env::var("RUSTC").unwrap_or("rustc".to_string());
+    let output = std::process::Command::new(rustc)
+        .args([
+            "--edition",
+            "2021",
+            "-D",
+            "warnings",
+            "main.rs",
+            "-o",
+            "main",
+        ])
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let output = std::process::Command::new(dir.join("main"))
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(output.status.success());
+    let actual = String::from_utf8(output.stdout).unwrap();
+    for (actual, expected) in actual.lines().zip(expected.lines()) {
+        assert_eq!(actual, expected);
+    }
+    assert_eq!(actual.lines().count(), expected.lines().count());
+}
+
+#[cfg(feature = "differential")]
+#[test]
+fn test_differential() {
+    use veryl_simulator::Differential;
+
+    let differential = Differential::new(&["tests/ff.veryl"], "FFTest")
+        .cycles(8)
+        .seed(1);
+    let sources = differential.sources().unwrap();
+    assert!(sources.design.contains("module prj_FFTest"));
+    assert!(sources.testbench.contains("prj_FFTest dut ("));
+    assert!(sources.testbench.contains("$display(\"7 b %h\", b);"));
+
+    // リファレンスのシミュレータがインストールされていれば出力を比較する
+    if std::process::Command::new("iverilog")
+        .arg("-V")
+        .output()
+        .is_ok()
+    {
+        let report = differential.run().unwrap();
+        assert_eq!(report.cycles, 8);
+        assert_eq!(report.compared + report.unknowns, 16);
+        assert!(report.is_match(), "{:?}", report.mismatches);
+    }
+}
+
+#[test]
+fn test_levelize() {
+    let code = std::fs::read_to_string("tests/latch.veryl").unwrap();
+    analyze(&code);
+
+    // assignments in reverse dependency order settle in a single pass
+    let mut model = Model::new("LatchTest", HashMap::new()).unwrap();
+    assert!(model.is_levelized());
+    model.input("en", 1);
+    let iterations = model.settle_iterations();
+    model.input("d", 5);
+    assert_eq!(model.settle_iterations(), iterations + 1);
+    assert_eq!(model.get("q"), Some(5));
+    assert_eq!(model.get("r"), Some(7));
+
+    // 組み合わせ回路のループは値が変化しなくなるまで繰り返し評価する
+    let code = std::fs::read_to_string("tests/loop.veryl").unwrap();
+    analyze(&code);
+
+    let mut model = Model::new("LoopTest", HashMap::new()).unwrap();
+    assert!(!model.is_levelized());
+    model.input("sel", 1);
+    model.input("a", 5);
+    assert_eq!(model.get("y"), Some(5));
+    model.input("sel", 0);
+    model.input("a", 7);
+    assert_eq!(model.get("x"), Some(5));
+    assert_eq!(model.get("y"), Some(5));
+}
+
+#[test]
+fn test_incremental_evaluation() {
+    let code = std::fs::read_to_string("tests/latch.veryl").unwrap();
+    analyze(&code);
+
+    let mut model = Model::new("LatchTest", HashMap::new()).unwrap();
+    model.input("en", 1);
+    model.input("d", 5);
+
+    // d の変化は q, t, r の順に伝搬する
+    let evaluated = model.evaluated_groups();
+    model.input("d", 6);
+    assert_eq!(model.evaluated_groups(), evaluated + 3);
+    assert_eq!(model.get("r"), Some(8));
+
+    // ラッチが値を保持する間は q を読み出す組を評価しない
+    model.input("en", 0);
+    let evaluated = model.evaluated_groups();
+    model.input("d", 9);
+    assert_eq!(model.evaluated_groups(), evaluated + 1);
+    assert_eq!(model.get("r"), Some(8));
+
+    // 値が変化しなければ評価しない
+    model.input("d", 9);
+    assert_eq!(model.evaluated_groups(), evaluated + 1);
+
+    // poke した値は組み合わせ回路の評価で上書きされる
+    model.poke("t", 0).unwrap();
+    assert_eq!(model.peek("t"), Some(7));
+    assert_eq!(model.get("r"), Some(8));
+}
@@ -0,0 +1,91 @@
+use crate::cmd_check::CmdCheck;
+use crate::{OptCheck, OptSimulate};
+use log::{error, info, warn};
+use miette::{IntoDiagnostic, Result, bail};
+use std::env;
+use veryl_metadata::Metadata;
+use veryl_simulator::{AssertSeverity, Direction, Simulator, StopReason, parse_time};
+
+pub struct CmdSimulate {
+    opt: OptSimulate,
+}
+
+impl CmdSimulate {
+    pub fn new(opt: OptSimulate) -> Self {
+        Self { opt }
+    }
+
+    pub fn exec(&self, metadata: &mut Metadata) -> Result<bool> {
+        // コマンドラインの指定で Veryl.toml の設定を上書きする
+        let current = env::current_dir().into_diagnostic()?;
+        let config = &mut metadata.simulate;
+        if let Some(x) = &self.opt.top {
+            config.top = Some(x.clone());
+        }
+        if let Some(x) = &self.opt.time {
+            config.time = Some(x.clone());
+        }
+        if let Some(x) = &self.opt.vcd {
+            config.waveform = Some(current.join(x));
+        }
+        if let Some(x) = &self.opt.stimulus {
+            config.stimulus = Some(current.join(x));
+        }
+
+        let Some(time) = &config.time else {
+            bail!("simulation time is not specified by --time or [simulate] in Veryl.toml");
+        };
+        let Some(duration) = parse_time(time) else {
+            bail!("simulation time \"{time}\" is not valid");
+        };
+
+        let check = CmdCheck::new(OptCheck {
+            files: self.opt.files.clone(),
+        });
+        check.exec(metadata)?;
+
+        let mut simulator = Simulator::from_metadata(metadata)?;
+        for warning in simulator.model().warnings() {
+            warn!("{warning}");
+        }
+
+        info!("Simulating module ({})", simulator.model().module_name());
+        simulator.reset();
+        let reason = simulator.run(duration);
+        let time = simulator.time();
+
+        let model = simulator.model();
+        for signal in model.signals() {
+            if signal.direction == Direction::Output {
+                info!("Output ({} = {})", signal.name, signal.value);
+            }
+        }
+
+        let mut success = true;
+        for failure in model.assertion_failures() {
+            if failure.severity == AssertSeverity::Warning {
+                warn!("{failure}");
+            } else {
+                error!("{failure}");
+                success = false;
+            }
+        }
+
+        match reason {
+            StopReason::Completed | StopReason::Paused => {
+                info!("Finished simulation ({time}ns)");
+            }
+            StopReason::Finished(x) => info!("Finished simulation ({time}ns, {x})"),
+            StopReason::Aborted => {
+                error!("Aborted simulation ({time}ns)");
+                success = false;
+            }
+            StopReason::Failed(x) => {
+                error!("Failed simulation ({time}ns, {x})");
+                success = false;
+            }
+        }
+
+        Ok(success)
+    }
+}
@@ -12,6 +12,7 @@ pub mod cmd_metadata;
 pub mod cmd_migrate;
 pub mod cmd_new;
 pub mod cmd_publish;
+pub mod cmd_simulate;
 pub mod cmd_test;
 pub mod cmd_update;
 pub mod context;
@@ -77,6 +78,7 @@ pub enum Commands {
     Metadata(OptMetadata),
     Dump(OptDump),
     Test(OptTest),
+    Simulate(OptSimulate),
 }
 
 /// Create a new project
@@ -170,6 +172,29 @@ pub struct OptTest {
     pub wave: bool,
 }
 
+/// Simulate the design with the built-in simulator
+#[derive(Args)]
+pub struct OptSimulate {
+    /// Target files
+    pub files: Vec<PathBuf>,
+
+    /// Top module, overrides the one in Veryl.toml
+    #[arg(long)]
+    pub top: Option<String>,
+
+    /// Simulation time, e.g. 100ns, 10us, overrides the one in Veryl.toml
+    #[arg(long)]
+    pub time: Option<String>,
+
+    /// Dump waveform to the VCD file, overrides the one in Veryl.toml
+    #[arg(long)]
+    pub vcd: Option<PathBuf>,
+
+    /// Stimulus file, overrides the one in Veryl.toml
+    #[arg(long)]
+    pub stimulus: Option<PathBuf>,
+}
+
 #[derive(Clone, Copy, Debug, ValueEnum)]
 pub enum SimType {
     /// Verilator
@@ -107,6 +107,7 @@ fn main() -> Result<ExitCode> {
         Commands::Metadata(x) => cmd_metadata::CmdMetadata::new(x).exec(&metadata)?,
         Commands::Dump(x) => cmd_dump::CmdDump::new(x).exec(&mut metadata)?,
         Commands::Test(x) => cmd_test::CmdTest::new(x).exec(&mut metadata)?,
+        Commands::Simulate(x) => cmd_simulate::CmdSimulate::new(x).exec(&mut metadata)?,
     };
 
     if let Some(dot_build_lock) = dot_build_lock {